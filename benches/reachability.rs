@@ -0,0 +1,114 @@
+//! Step 2 on a synthetic 50k-module graph with 1, 2, 4 and 8 threads.
+//!
+//! Run with `cargo bench --features rayon --bench reachability`.
+
+use split_chunks_algorithm::analysis;
+use split_chunks_algorithm::cache::{self, AnalysisCache};
+use split_chunks_algorithm::scc::Condensation;
+use split_chunks_algorithm::trace::DecisionTrace;
+use split_chunks_algorithm::{Dependency, JsModule, ModuleGraph, ModuleId};
+use std::time::{Duration, Instant};
+
+const MODULES: usize = 50_000;
+const ENTRIES: usize = 8;
+const RUNS: usize = 3;
+
+/// A deterministic graph shaped like a large app: every module imports a few modules
+/// defined after it, one in 40 modules also imports one lazily, and one in 100 imports
+/// a module shortly before it, which closes small import cycles.
+fn synthetic_graph() -> ModuleGraph {
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = move |bound: usize| {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        (seed % bound as u64) as usize
+    };
+
+    let mut g = ModuleGraph::new();
+    let modules: Vec<ModuleId> = (0..MODULES)
+        .map(|index| {
+            g.add_node(JsModule {
+                name: format!("src/m{}.js", index).as_str().into(),
+                size: 200 + next(5000),
+                ..Default::default()
+            })
+        })
+        .collect();
+    for index in 0..MODULES - 1 {
+        let later = |offset: usize| modules[(index + 1 + offset).min(MODULES - 1)];
+        for _ in 0..3 {
+            g.update_edge(modules[index], later(next(500)), Dependency::default());
+        }
+        if next(40) == 0 {
+            let lazy = later(next(MODULES - index));
+            g.update_edge(
+                modules[index],
+                lazy,
+                Dependency {
+                    is_async: true,
+                    ..Default::default()
+                },
+            );
+        }
+        if index > 0 && next(100) == 0 {
+            let earlier = modules[index - 1 - next(index.min(20))];
+            g.update_edge(modules[index], earlier, Dependency::default());
+        }
+    }
+    g
+}
+
+fn main() {
+    let g = synthetic_graph();
+    let entries: Vec<ModuleId> = (0..ENTRIES).map(ModuleId::new).collect();
+    let chunk_roots =
+        analysis::create_root_chunks(&g, &entries, None, &mut DecisionTrace::new(false))
+            .unwrap()
+            .chunk_roots;
+    let condensation = Condensation::new(&g);
+    let roots_fingerprint = cache::fingerprint(&g, chunk_roots.keys().copied());
+    println!(
+        "{} modules, {} imports, {} chunk roots",
+        g.node_count(),
+        g.edge_count(),
+        chunk_roots.len()
+    );
+
+    let mut expected = None;
+    let mut single_thread = Duration::ZERO;
+    for threads in [1, 2, 4, 8] {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        let mut best = Duration::MAX;
+        for _ in 0..RUNS {
+            // A fresh cache each run, so every search actually runs.
+            let cache = AnalysisCache::default();
+            let start = Instant::now();
+            let reachable = pool.install(|| {
+                analysis::reachable_from_roots(
+                    &condensation,
+                    &chunk_roots,
+                    &cache,
+                    roots_fingerprint,
+                )
+            });
+            best = best.min(start.elapsed());
+            match &expected {
+                None => expected = Some(reachable),
+                Some(expected) => assert!(*expected == reachable),
+            }
+        }
+        if threads == 1 {
+            single_thread = best;
+        }
+        println!(
+            "{} threads: {:>8.1} ms, {:.2}x",
+            threads,
+            best.as_secs_f64() * 1000.0,
+            single_thread.as_secs_f64() / best.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! Steps 1 to 4 timed one at a time on generated graphs of several sizes, each step on
+//! the output of the steps before it.
+//!
+//! Run with `cargo bench --bench steps`, or `cargo bench --bench steps -- "step 2"` for
+//! a single step.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use split_chunks_algorithm::analysis::{self, RootIndex};
+use split_chunks_algorithm::cache::{self, AnalysisCache};
+use split_chunks_algorithm::dominators;
+use split_chunks_algorithm::entries;
+use split_chunks_algorithm::graph_gen::{self, GraphGenOptions};
+use split_chunks_algorithm::passes;
+use split_chunks_algorithm::scc::Condensation;
+use split_chunks_algorithm::trace::DecisionTrace;
+use split_chunks_algorithm::SplitOptions;
+use std::collections::BTreeSet;
+
+const MODULE_COUNTS: [usize; 3] = [1_000, 5_000, 20_000];
+
+fn steps(c: &mut Criterion) {
+    // Large enough for Step 4 to merge most shared chunks.
+    let options = SplitOptions {
+        min_size: 20_000,
+        ..Default::default()
+    };
+    for module_count in MODULE_COUNTS {
+        let (g, entries) = graph_gen::generate(&GraphGenOptions {
+            module_count,
+            ..Default::default()
+        });
+        let entry_modules = entries::modules(&entries);
+        let no_trace = || DecisionTrace::new(false);
+
+        c.bench_with_input(
+            BenchmarkId::new("step 1: chunk roots", module_count),
+            &g,
+            |b, g| {
+                b.iter(|| {
+                    analysis::create_root_chunks(g, &entry_modules, None, &mut no_trace()).unwrap()
+                })
+            },
+        );
+
+        let root_chunks =
+            analysis::create_root_chunks(&g, &entry_modules, None, &mut no_trace()).unwrap();
+        c.bench_with_input(
+            BenchmarkId::new("step 2: reachability", module_count),
+            &g,
+            |b, g| {
+                b.iter(|| {
+                    let condensation = Condensation::new(g);
+                    let roots_fingerprint =
+                        cache::fingerprint(g, root_chunks.chunk_roots.keys().copied());
+                    // A fresh cache, so every search actually runs.
+                    analysis::reachable_from_roots(
+                        &condensation,
+                        &root_chunks.chunk_roots,
+                        &AnalysisCache::default(),
+                        roots_fingerprint,
+                    )
+                })
+            },
+        );
+
+        c.bench_with_input(
+            BenchmarkId::new("step 2: reachability from dominators", module_count),
+            &g,
+            |b, g| {
+                b.iter(|| {
+                    dominators::reachable_from_roots(
+                        &Condensation::new(g),
+                        &root_chunks.chunk_roots,
+                    )
+                })
+            },
+        );
+
+        let reachable_modules = analysis::reachable_from_roots(
+            &Condensation::new(&g),
+            &root_chunks.chunk_roots,
+            &AnalysisCache::default(),
+            cache::fingerprint(&g, root_chunks.chunk_roots.keys().copied()),
+        );
+        c.bench_with_input(
+            BenchmarkId::new("step 3: assignment", module_count),
+            &g,
+            |b, g| {
+                b.iter_batched_ref(
+                    || root_chunks.chunk_graph.clone(),
+                    |chunk_graph| {
+                        let reachable_chunks = analysis::parent_roots(
+                            g,
+                            chunk_graph,
+                            &root_chunks.chunk_roots,
+                            &BTreeSet::new(),
+                            &reachable_modules,
+                        );
+                        let root_index =
+                            RootIndex::new(&root_chunks.chunk_roots, &reachable_chunks);
+                        let assignments = analysis::assign_modules(
+                            g.node_count(),
+                            &reachable_modules,
+                            &root_index,
+                        );
+                        analysis::place_modules(
+                            g,
+                            chunk_graph,
+                            &root_chunks.chunk_roots,
+                            &reachable_chunks,
+                            &BTreeSet::new(),
+                            &assignments,
+                            &options,
+                            &AnalysisCache::default(),
+                            &mut no_trace(),
+                        )
+                    },
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+
+        let mut state = analysis::analyze(
+            &g,
+            &entries,
+            &options,
+            &AnalysisCache::default(),
+            &mut no_trace(),
+        )
+        .unwrap();
+        passes::prune_available_modules(&mut state, &mut no_trace());
+        passes::weigh_chunk_edges(&mut state.chunk_graph, &state.module_graph);
+        c.bench_with_input(
+            BenchmarkId::new("step 4: small chunk removal", module_count),
+            &state,
+            |b, state| {
+                b.iter_batched_ref(
+                    || state.clone(),
+                    |state| passes::merge_small_chunks(state, &options, &mut no_trace()),
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = steps
+}
+criterion_main!(benches);
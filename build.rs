@@ -0,0 +1,5 @@
+fn main() {
+    // Links the Node.js addon of the `napi` feature.
+    #[cfg(feature = "napi")]
+    napi_build::setup();
+}
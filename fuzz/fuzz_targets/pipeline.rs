@@ -0,0 +1,219 @@
+//! Splits module graphs decoded from arbitrary bytes, cycles and modules importing
+//! themselves included, with arbitrary options, and checks where the modules end up.
+//!
+//! Run with `cargo fuzz run pipeline` from the crate's directory.
+
+#![no_main]
+
+use arbitrary::{Arbitrary, Unstructured};
+use libfuzzer_sys::fuzz_target;
+use petgraph::visit::EdgeRef;
+use split_chunks_algorithm::{
+    chunk_graph::{module_stem, ChunkGraph},
+    module_graph::ModuleType,
+    options::{
+        EntrySignatures, ParallelImports, SideEffectDuplication, SmallChunkPolicy, SplitFrom,
+    },
+    Dependency, Entry, JsModule, ModuleGraph, ModuleId, SplitOptions,
+};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Keeps graphs small enough for the fuzzer to try many of them.
+const MAX_MODULES: usize = 64;
+
+#[derive(Debug)]
+struct Input {
+    g: ModuleGraph,
+    entries: Vec<Entry>,
+    options: SplitOptions,
+}
+
+impl<'a> Arbitrary<'a> for Input {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let module_count = u.int_in_range(1..=MAX_MODULES)?;
+        let mut g = ModuleGraph::new();
+        for index in 0..module_count {
+            let module_type = *u.choose(&[
+                ModuleType::Js,
+                ModuleType::Js,
+                ModuleType::Css,
+                ModuleType::Wasm,
+                ModuleType::Asset,
+            ])?;
+            g.add_node(JsModule {
+                name: format!("m{}.{}", index, module_type.extension().unwrap_or("png"))
+                    .as_str()
+                    .into(),
+                size: u.int_in_range(0..=10_000)?,
+                module_type,
+                is_external: u.ratio(1, 16)?,
+                side_effect_free: u.arbitrary()?,
+                ..Default::default()
+            });
+        }
+        for _ in 0..u.int_in_range(0..=module_count * 3)? {
+            let importer = ModuleId::new(u.choose_index(module_count)?);
+            let importee = ModuleId::new(u.choose_index(module_count)?);
+            g.add_edge(
+                importer,
+                importee,
+                match u.int_in_range(0..=7)? {
+                    0..=3 => Dependency::default(),
+                    4 | 5 => Dependency {
+                        is_async: true,
+                        ..Default::default()
+                    },
+                    6 => Dependency {
+                        is_worker: true,
+                        ..Default::default()
+                    },
+                    _ => Dependency {
+                        is_weak: true,
+                        ..Default::default()
+                    },
+                },
+            );
+        }
+        // Entries are distinct modules that are bundled, and may import each other or
+        // depend on entries listed before them.
+        let mut entries: Vec<Entry> = vec![];
+        for _ in 0..u.int_in_range(1..=module_count.min(4))? {
+            let module = ModuleId::new(u.choose_index(module_count)?);
+            if g[module].is_external || entries.iter().any(|entry| entry.module == module) {
+                continue;
+            }
+            let mut entry = Entry::from(module);
+            if u.ratio(1, 4)? {
+                let prelude = ModuleId::new(u.choose_index(module_count)?);
+                if !g[prelude].is_external {
+                    entry.prelude.push(prelude);
+                }
+            }
+            if !entries.is_empty() && u.ratio(1, 4)? {
+                let dependency = &entries[u.choose_index(entries.len())?];
+                entry
+                    .depends_on
+                    .push(module_stem(&g[dependency.module]).to_owned());
+            }
+            entry.isolated = u.ratio(1, 8)?;
+            entries.push(entry);
+        }
+        if entries.is_empty() {
+            return Err(arbitrary::Error::IncorrectFormat);
+        }
+        let small_chunk_policy = *u.choose(&[
+            SmallChunkPolicy::DuplicateIntoAll,
+            SmallChunkPolicy::MergeIntoLargestParent,
+            SmallChunkPolicy::MergeIntoSmallestParent,
+            SmallChunkPolicy::Keep,
+        ])?;
+        let options = SplitOptions {
+            min_size: u.int_in_range(0..=20_000)?,
+            max_size: if u.arbitrary()? {
+                Some(u.int_in_range(1..=50_000)?)
+            } else {
+                None
+            },
+            min_chunks: u.int_in_range(0..=4)?,
+            min_source_chunks: u.int_in_range(0..=3)?,
+            max_async_requests: u.int_in_range(1..=30)?,
+            max_initial_requests: u.int_in_range(1..=30)?,
+            max_async_depth: if u.arbitrary()? {
+                Some(u.int_in_range(0..=4)?)
+            } else {
+                None
+            },
+            runtime_chunk: u.arbitrary()?,
+            small_chunk_policies: vec![(usize::MAX, small_chunk_policy)],
+            hoist_sibling_duplicates: u.arbitrary()?,
+            preserve_entry_signatures: if u.arbitrary()? {
+                EntrySignatures::Strict
+            } else {
+                EntrySignatures::AllowExtension
+            },
+            reuse_existing_chunk: u.arbitrary()?,
+            side_effect_duplication: if u.arbitrary()? {
+                SideEffectDuplication::Refuse
+            } else {
+                SideEffectDuplication::Warn
+            },
+            parallel_imports: if u.arbitrary()? {
+                ParallelImports::PreferAsync
+            } else {
+                ParallelImports::PreferSync
+            },
+            split_from: *u.choose(&[SplitFrom::All, SplitFrom::Initial, SplitFrom::Async])?,
+            ..Default::default()
+        };
+        Ok(Input {
+            g,
+            entries,
+            options,
+        })
+    }
+}
+
+fuzz_target!(|input: Input| {
+    let Input {
+        g,
+        entries,
+        options,
+    } = input;
+    let chunk_graph = split_chunks_algorithm::split_chunks(&g, &entries, &options)
+        .expect("a graph with distinct entries of its own modules splits");
+    check_placements(&g, &entries, &chunk_graph);
+});
+
+/// Every module an entry loads is in a chunk and no other module is, no chunk is empty
+/// or holds a module twice, and every entry module is in its entry chunk.
+fn check_placements(g: &ModuleGraph, entries: &[Entry], chunk_graph: &ChunkGraph) {
+    let mut placements: BTreeMap<ModuleId, usize> = BTreeMap::new();
+    for (chunk_id, chunk) in chunk_graph.chunks() {
+        assert!(!chunk.modules.is_empty(), "chunk {:?} is empty", chunk_id);
+        for module_id in &chunk.modules {
+            *placements.entry(*module_id).or_default() += 1;
+        }
+        let mut modules = chunk.modules.clone();
+        modules.sort_unstable();
+        modules.dedup();
+        assert_eq!(
+            modules.len(),
+            chunk.modules.len(),
+            "chunk {:?} holds a module twice",
+            chunk_id
+        );
+    }
+    // Entries load their preludes and modules, and what those import, except for
+    // externals, which are loaded at runtime, and weak imports, which load nothing.
+    let mut loaded: BTreeSet<ModuleId> = BTreeSet::new();
+    let mut stack: Vec<ModuleId> = entries
+        .iter()
+        .flat_map(|entry| entry.prelude.iter().copied().chain([entry.module]))
+        .collect();
+    while let Some(module_id) = stack.pop() {
+        if g[module_id].is_external || !loaded.insert(module_id) {
+            continue;
+        }
+        for edge in g.edges(module_id) {
+            if !edge.weight().is_weak {
+                stack.push(edge.target());
+            }
+        }
+    }
+    for module_id in g.node_indices() {
+        assert_eq!(
+            placements.contains_key(&module_id),
+            loaded.contains(&module_id),
+            "{:?} is placed iff an entry loads it",
+            module_id
+        );
+    }
+    for entry in entries {
+        let (chunk_id, _) = chunk_graph.chunk_roots[&entry.module];
+        assert!(
+            chunk_graph.modules_of(chunk_id).contains(&entry.module),
+            "{:?} is not in its entry chunk",
+            entry.module
+        );
+    }
+}
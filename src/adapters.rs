@@ -0,0 +1,321 @@
+//! Reading the module graphs other bundlers dump, so a real project's graph can be split
+//! without writing a conversion script first.
+//!
+//! Each adapter turns a dump into the graph description of [`loader`], which builds and
+//! checks the graph as for a description written by hand:
+//!
+//! - [`esbuild`] reads the metafile of `esbuild --metafile=meta.json`. The entries are
+//!   the `entryPoint`s of its outputs that no dynamic import loads.
+//! - [`rollup`] reads an array of what rollup's and rolldown's `this.getModuleInfo(id)`
+//!   return for every module, e.g. collected in `generateBundle`. Module sizes are the
+//!   lengths of their `code`.
+//! - [`parcel`] reads the assets and dependencies a Parcel reporter gets from
+//!   `bundleGraph.traverse`, as `{ "assets": [...], "dependencies": [...] }`, with
+//!   the id of the asset each dependency resolved to as its `resolvedAssetId`.
+//!
+//! Module types follow the file extension, see [`module_type_of`]. Imports of modules
+//! the dump doesn't describe become imports of external modules.
+
+use crate::cache::AnalysisCache;
+use crate::entries::Entry;
+use crate::loader;
+use crate::module_graph::{ModuleGraph, ModuleType};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::error::Error;
+use std::path::Path;
+
+/// The type of the module at `path`: CSS and WebAssembly by their extension, any
+/// other file a bundler doesn't compile to JS, like an image or a font, an asset.
+pub fn module_type_of(path: &str) -> ModuleType {
+    let extension = Path::new(path.split(['?', '#']).next().unwrap_or(path))
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "css" | "scss" | "sass" | "less" => ModuleType::Css,
+        "wasm" => ModuleType::Wasm,
+        "" | "js" | "mjs" | "cjs" | "jsx" | "ts" | "mts" | "cts" | "tsx" | "json" | "vue"
+        | "svelte" => ModuleType::Js,
+        _ => ModuleType::Asset,
+    }
+}
+
+/// The graph description of [`loader`] being put together from a dump.
+#[derive(Default)]
+struct Description {
+    modules: Vec<Value>,
+    names: BTreeSet<String>,
+    edges: Vec<Value>,
+    entries: Vec<String>,
+}
+
+impl Description {
+    fn add_module(&mut self, name: &str, size: usize, is_external: bool) {
+        if self.names.insert(name.to_owned()) {
+            self.modules.push(json!({
+                "name": name,
+                "size": size,
+                "type": module_type_of(name),
+                "is_external": is_external,
+            }));
+        }
+    }
+
+    fn add_edge(&mut self, from: &str, to: &str, kind: ImportKind) {
+        self.edges.push(json!({
+            "from": from,
+            "to": to,
+            "is_async": kind == ImportKind::Async,
+            "is_worker": kind == ImportKind::Worker,
+            "is_weak": kind == ImportKind::Weak,
+        }));
+    }
+
+    /// Adds the modules that imports name but the dump didn't describe as externals.
+    fn add_missing_externals(&mut self) {
+        let missing: Vec<String> = self
+            .edges
+            .iter()
+            .filter_map(|edge| edge["to"].as_str())
+            .filter(|name| !self.names.contains(*name))
+            .map(str::to_owned)
+            .collect();
+        for name in missing {
+            self.add_module(&name, 0, true);
+        }
+    }
+
+    fn load(mut self, cache: &AnalysisCache) -> Result<(ModuleGraph, Vec<Entry>), Box<dyn Error>> {
+        self.add_missing_externals();
+        loader::from_value(
+            json!({
+                "modules": self.modules,
+                "edges": self.edges,
+                "entries": self.entries,
+            }),
+            cache,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImportKind {
+    Sync,
+    Async,
+    Worker,
+    Weak,
+}
+
+#[derive(Debug, Deserialize)]
+struct Metafile {
+    inputs: BTreeMap<String, MetafileInput>,
+    #[serde(default)]
+    outputs: BTreeMap<String, MetafileOutput>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetafileInput {
+    bytes: usize,
+    #[serde(default)]
+    imports: Vec<MetafileImport>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetafileImport {
+    path: String,
+    kind: String,
+    #[serde(default)]
+    external: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MetafileOutput {
+    entry_point: Option<String>,
+}
+
+/// Builds the module graph described by an esbuild metafile. `dynamic-import` imports
+/// are async and `require-resolve` ones, which only resolve a path, weak.
+pub fn esbuild(
+    json: &str,
+    cache: &AnalysisCache,
+) -> Result<(ModuleGraph, Vec<Entry>), Box<dyn Error>> {
+    let metafile: Metafile = serde_json::from_str(json)?;
+    let mut description = Description::default();
+    for (path, input) in &metafile.inputs {
+        description.add_module(path, input.bytes, false);
+    }
+    let mut dynamically_imported = BTreeSet::new();
+    for (path, input) in &metafile.inputs {
+        for import in &input.imports {
+            if import.external {
+                description.add_module(&import.path, 0, true);
+            }
+            let kind = match import.kind.as_str() {
+                "dynamic-import" => {
+                    dynamically_imported.insert(import.path.as_str());
+                    ImportKind::Async
+                }
+                "require-resolve" => ImportKind::Weak,
+                _ => ImportKind::Sync,
+            };
+            description.add_edge(path, &import.path, kind);
+        }
+    }
+    for output in metafile.outputs.values() {
+        if let Some(entry_point) = &output.entry_point {
+            if !dynamically_imported.contains(entry_point.as_str())
+                && !description.entries.contains(entry_point)
+            {
+                description.entries.push(entry_point.clone());
+            }
+        }
+    }
+    description.load(cache)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RollupModuleInfo {
+    id: String,
+    #[serde(default)]
+    code: Option<String>,
+    #[serde(default)]
+    is_entry: bool,
+    #[serde(default)]
+    is_external: bool,
+    #[serde(default)]
+    imported_ids: Vec<String>,
+    #[serde(default)]
+    dynamically_imported_ids: Vec<String>,
+}
+
+/// Builds the module graph described by the module infos of rollup or rolldown. Modules
+/// with `isEntry` set are the entries, in the order they are listed.
+pub fn rollup(
+    json: &str,
+    cache: &AnalysisCache,
+) -> Result<(ModuleGraph, Vec<Entry>), Box<dyn Error>> {
+    let modules: Vec<RollupModuleInfo> = serde_json::from_str(json)?;
+    let mut description = Description::default();
+    for module in &modules {
+        let size = module.code.as_ref().map_or(0, String::len);
+        description.add_module(&module.id, size, module.is_external);
+        if module.is_entry {
+            description.entries.push(module.id.clone());
+        }
+    }
+    for module in &modules {
+        for id in &module.imported_ids {
+            description.add_edge(&module.id, id, ImportKind::Sync);
+        }
+        for id in &module.dynamically_imported_ids {
+            description.add_edge(&module.id, id, ImportKind::Async);
+        }
+    }
+    description.load(cache)
+}
+
+#[derive(Debug, Deserialize)]
+struct ParcelDump {
+    assets: Vec<ParcelAsset>,
+    dependencies: Vec<ParcelDependency>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ParcelAsset {
+    id: String,
+    file_path: String,
+    #[serde(default)]
+    stats: ParcelStats,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ParcelStats {
+    #[serde(default)]
+    size: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ParcelDependency {
+    #[serde(default)]
+    source_asset_id: Option<String>,
+    #[serde(default)]
+    resolved_asset_id: Option<String>,
+    #[serde(default)]
+    priority: Option<String>,
+    #[serde(default)]
+    is_entry: bool,
+    #[serde(default)]
+    env: Option<ParcelEnvironment>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ParcelEnvironment {
+    #[serde(default)]
+    context: Option<String>,
+}
+
+/// Builds the module graph described by the assets and dependencies of a Parcel bundle
+/// graph. Modules are named by `filePath`, with `#<id>` appended for every asset of a
+/// file but the first. `lazy` dependencies are async, those running in a worker context
+/// start a worker, and entry dependencies name the entries. Dependencies that didn't
+/// resolve to an asset, like optional ones, are left out.
+pub fn parcel(
+    json: &str,
+    cache: &AnalysisCache,
+) -> Result<(ModuleGraph, Vec<Entry>), Box<dyn Error>> {
+    let dump: ParcelDump = serde_json::from_str(json)?;
+    let mut description = Description::default();
+    let mut name_of: HashMap<&str, String> = HashMap::new();
+    for asset in &dump.assets {
+        let name = if description.names.contains(&asset.file_path) {
+            format!("{}#{}", asset.file_path, asset.id)
+        } else {
+            asset.file_path.clone()
+        };
+        description.add_module(&name, asset.stats.size, false);
+        name_of.insert(&asset.id, name);
+    }
+    let name = |id: &str| {
+        name_of
+            .get(id)
+            .cloned()
+            .ok_or_else(|| format!("a dependency refers to asset {}, which is not listed", id))
+    };
+    for dependency in &dump.dependencies {
+        let Some(resolved) = &dependency.resolved_asset_id else {
+            continue;
+        };
+        let resolved = name(resolved)?;
+        let source = match &dependency.source_asset_id {
+            Some(source) => name(source)?,
+            None if dependency.is_entry => {
+                if !description.entries.contains(&resolved) {
+                    description.entries.push(resolved);
+                }
+                continue;
+            }
+            None => continue,
+        };
+        let in_worker = dependency
+            .env
+            .as_ref()
+            .and_then(|env| env.context.as_deref())
+            .is_some_and(|context| context.ends_with("worker") || context.ends_with("worklet"));
+        let kind = if in_worker {
+            ImportKind::Worker
+        } else if dependency.priority.as_deref() == Some("lazy") {
+            ImportKind::Async
+        } else {
+            ImportKind::Sync
+        };
+        description.add_edge(&source, &resolved, kind);
+    }
+    description.load(cache)
+}
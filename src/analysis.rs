@@ -0,0 +1,1136 @@
+//! Steps 1 to 3 of the algorithm: find the chunk roots, compute which modules each
+//! root reaches and place every module into a chunk.
+
+use crate::barrels::{self, BarrelReport};
+use crate::cache::{self, AnalysisCache};
+use crate::chunk_graph::{combined_name, module_stem, Chunk, ChunkGraph, ChunkId, ChunkKind};
+use crate::entries::{self, Entry};
+use crate::error::SplitChunksError;
+use crate::flags;
+use crate::module_graph::{JsModule, ModuleGraph, ModuleId, ModuleInfo, ModuleType};
+use crate::options::SplitOptions;
+use crate::pipeline;
+use crate::rules;
+use crate::scc::Condensation;
+use crate::trace::{self, DecisionTrace};
+use fixedbitset::FixedBitSet;
+use petgraph::algo::dominators::simple_fast;
+use petgraph::prelude::{DiGraph, NodeIndex};
+use petgraph::visit::{depth_first_search, Control, DfsEvent, EdgeRef};
+use petgraph::Graph;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, LinkedList, VecDeque};
+use std::sync::Arc;
+
+/// Everything the analysis produced: the module graph it ran on, the reachability
+/// index and the chunk graph with its module assignments.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "M: Serialize",
+    deserialize = "'de: 'static, M: Deserialize<'de>"
+))]
+pub struct AnalysisState<M = JsModule> {
+    /// The module graph after feature flag pruning and barrel flattening. Module ids
+    /// are the same as in the input graph.
+    pub module_graph: ModuleGraph<M>,
+    pub entries: Vec<ModuleId>,
+    /// How barrel modules were flattened before chunking.
+    pub barrels: Vec<BarrelReport>,
+    /// `(parent root, async root)` pairs: the parent root is loaded before the async root
+    /// however it is imported, see [`parent_roots`]. Also holds the `(depended-on entry, entry)`
+    /// pairs of `chunk_graph.depends_on`, since either parent is loaded first.
+    pub reachable_chunks: BTreeSet<(ModuleId, ModuleId)>,
+    /// The modules each chunk root reaches without crossing another chunk root, as a
+    /// set of module indices. Includes the roots where the search stopped, and all
+    /// modules of any import cycle it entered.
+    pub reachable_modules: BTreeMap<ModuleId, FixedBitSet>,
+    pub chunk_graph: ChunkGraph,
+    /// The chunk for each combination of chunk roots and module type, as pairs since
+    /// JSON object keys have to be strings.
+    pub chunks: Vec<(Combination, NodeIndex)>,
+    /// `(importer, importee)` pairs of the weak imports left out of `module_graph`, see
+    /// [`Dependency::is_weak`]. The importee only has a chunk if something else loads it.
+    ///
+    /// [`Dependency::is_weak`]: crate::Dependency::is_weak
+    #[serde(default)]
+    pub weak_imports: Vec<(ModuleId, ModuleId)>,
+}
+
+/// Sorted chunk roots a module is reachable from, and the module's type and layer.
+pub type Combination = (Vec<ModuleId>, ModuleType, Option<String>);
+
+/// The chunk roots a module is reachable from, which decide its chunk in Step 3.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Assignment {
+    /// Every chunk root the module is reachable from, sorted.
+    pub reachable_from: Vec<ModuleId>,
+    /// `reachable_from` without the roots whose parent roots already load the module.
+    pub roots: Vec<ModuleId>,
+}
+
+/// Chunk roots numbered in id order, so the roots reaching a module fit in a bitset and
+/// assignments are filtered with bitset intersections.
+#[derive(Debug, Clone)]
+pub struct RootIndex {
+    roots: Vec<ModuleId>,
+    /// For each root number, the numbers of its parent roots, see [`parent_roots`].
+    parents: Vec<FixedBitSet>,
+}
+
+impl RootIndex {
+    pub fn new(
+        chunk_roots: &BTreeMap<ModuleId, (ChunkId, ChunkId)>,
+        reachable_chunks: &BTreeSet<(ModuleId, ModuleId)>,
+    ) -> Self {
+        let roots: Vec<ModuleId> = chunk_roots.keys().copied().collect();
+        let mut parents = vec![FixedBitSet::with_capacity(roots.len()); roots.len()];
+        for (parent, root) in reachable_chunks {
+            if let (Ok(parent), Ok(root)) = (roots.binary_search(parent), roots.binary_search(root))
+            {
+                parents[root].insert(parent);
+            }
+        }
+        RootIndex { roots, parents }
+    }
+
+    /// The numbers of the roots whose entry in `reachable_modules` contains `module_id`.
+    pub fn reaching(
+        &self,
+        reachable_modules: &BTreeMap<ModuleId, FixedBitSet>,
+        module_id: ModuleId,
+    ) -> FixedBitSet {
+        let mut reaching = FixedBitSet::with_capacity(self.roots.len());
+        for (number, root) in self.roots.iter().enumerate() {
+            if reachable_modules[root].contains(module_id.index()) {
+                reaching.insert(number);
+            }
+        }
+        reaching
+    }
+
+    /// The assignment of a module reachable from the roots numbered in `reaching`.
+    pub fn assignment(&self, reaching: &FixedBitSet) -> Assignment {
+        // Filter out chunks when the module is reachable in a parent chunk.
+        Assignment {
+            reachable_from: reaching.ones().map(|number| self.roots[number]).collect(),
+            roots: reaching
+                .ones()
+                .filter(|number| reaching.is_disjoint(&self.parents[*number]))
+                .map(|number| self.roots[number])
+                .collect(),
+        }
+    }
+}
+
+/// The assignment of every module of a graph with `module_count` modules, by module
+/// index, from the modules each root reaches.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn assign_modules(
+    module_count: usize,
+    reachable_modules: &BTreeMap<ModuleId, FixedBitSet>,
+    root_index: &RootIndex,
+) -> Vec<Assignment> {
+    // Turn the modules reachable from each root into the roots reaching each module.
+    let mut reaching = vec![FixedBitSet::with_capacity(root_index.roots.len()); module_count];
+    for (number, reachable) in reachable_modules.values().enumerate() {
+        for module_index in reachable.ones() {
+            reaching[module_index].insert(number);
+        }
+    }
+    let assignments: Vec<Assignment> = reaching
+        .iter()
+        .map(|reaching| root_index.assignment(reaching))
+        .collect();
+    tracing::debug!(modules = module_count, "assigned modules to chunk roots");
+    assignments
+}
+
+/// What Step 1 found: a chunk graph holding only the root chunks, and the chunk and
+/// chunk group of every root.
+#[derive(Debug, Clone)]
+pub struct RootChunks {
+    pub chunk_graph: Graph<Chunk, usize>,
+    pub chunk_roots: BTreeMap<ModuleId, (ChunkId, ChunkId)>,
+}
+
+/// The `(parent root, async root)` pairs of the roots loaded before each async root
+/// however it is imported, and the `(depended-on entry, entry)` pairs of `depends_on`.
+///
+/// Step 1 meets an async import only once, along whichever path its search took first,
+/// so the parents are worked out from the `reachable_modules` of Step 2 instead: every
+/// root whose search stops at an async root imports it. What an import provides is its
+/// importer, the entries the importer depends on and, unless the importer is an entry or
+/// in a worker, the importer's own parents. Roots of another module type than the async
+/// root provide nothing. An async root's parents are what all of its imports provide,
+/// narrowed until no import changes them anymore.
+pub fn parent_roots<M: ModuleInfo>(
+    g: &ModuleGraph<M>,
+    chunk_graph: &Graph<Chunk, usize>,
+    chunk_roots: &BTreeMap<ModuleId, (ChunkId, ChunkId)>,
+    depends_on: &BTreeSet<(ModuleId, ModuleId)>,
+    reachable_modules: &BTreeMap<ModuleId, FixedBitSet>,
+) -> BTreeSet<(ModuleId, ModuleId)> {
+    // Roots by their number in `chunk_roots`, like in [`RootIndex`].
+    let roots: Vec<ModuleId> = chunk_roots.keys().copied().collect();
+    let mut root_modules = FixedBitSet::with_capacity(g.node_count());
+    root_modules.extend(roots.iter().map(|root| root.index()));
+    let number = |root: &ModuleId| roots.binary_search(root).unwrap();
+    // The roots each root's search stopped at, and the roots whose search stopped at it.
+    let mut imported = vec![vec![]; roots.len()];
+    let mut importers = vec![vec![]; roots.len()];
+    for (importer, reachable) in reachable_modules {
+        let importer = number(importer);
+        for module_index in reachable.intersection(&root_modules) {
+            let root = number(&ModuleId::new(module_index));
+            imported[importer].push(root);
+            importers[root].push(importer);
+        }
+    }
+    let kind = |root: usize| chunk_graph[chunk_roots[&roots[root]].0].kind;
+    // Entries and workers have no parents. Roots of another module type or layer do,
+    // since the async roots they import inherit them.
+    let has_parents = |root: usize| !matches!(kind(root), ChunkKind::Entry | ChunkKind::Worker);
+    // The entries each entry depends on, which are loaded before anything it imports.
+    let mut dependencies = vec![vec![]; roots.len()];
+    for (dependency, entry) in depends_on {
+        if let (Ok(dependency), Ok(entry)) =
+            (roots.binary_search(dependency), roots.binary_search(entry))
+        {
+            dependencies[entry].push(dependency);
+        }
+    }
+
+    let mut parents: Vec<Option<FixedBitSet>> = vec![None; roots.len()];
+    // Roots in chunk id order, which is the order Step 1 found them in, so most roots are
+    // narrowed after their importers.
+    let mut queue: VecDeque<usize> = (0..roots.len()).filter(|root| has_parents(*root)).collect();
+    queue
+        .make_contiguous()
+        .sort_by_key(|root| chunk_roots[&roots[*root]].0);
+    let mut queued = FixedBitSet::with_capacity(roots.len());
+    queued.extend(queue.iter().copied());
+    let mut provided = FixedBitSet::with_capacity(roots.len());
+    while let Some(root) = queue.pop_front() {
+        queued.set(root, false);
+        let module_type = g[roots[root]].module_type();
+        let mut narrowed: Option<FixedBitSet> = None;
+        for importer in &importers[root] {
+            provided.clear();
+            if g[roots[*importer]].module_type() == module_type {
+                provided.insert(*importer);
+                provided.extend(dependencies[*importer].iter().copied());
+                let (_, group_id) = chunk_roots[&roots[*importer]];
+                if has_parents(*importer) && chunk_graph[group_id].kind != ChunkKind::Worker {
+                    // An importer without parents yet is loaded after every root, so it
+                    // narrows nothing until it has some.
+                    match &parents[*importer] {
+                        Some(importer_parents) => provided.union_with(importer_parents),
+                        None => continue,
+                    }
+                }
+            }
+            match &mut narrowed {
+                Some(narrowed) => narrowed.intersect_with(&provided),
+                None => narrowed = Some(provided.clone()),
+            }
+        }
+        if narrowed.is_some() && parents[root] != narrowed {
+            parents[root] = narrowed;
+            for imported_root in &imported[root] {
+                if has_parents(*imported_root) && !queued.put(*imported_root) {
+                    queue.push_back(*imported_root);
+                }
+            }
+        }
+    }
+
+    let mut parent_roots = depends_on.clone();
+    for (root, parents) in parents.iter().enumerate() {
+        if let Some(parents) = parents.as_ref().filter(|_| kind(root) == ChunkKind::Async) {
+            parent_roots.extend(parents.ones().map(|parent| (roots[parent], roots[root])));
+        }
+    }
+    parent_roots
+}
+
+/// Checks that `entries` and every import of `g` only refer to modules of `g`, that no
+/// entry is listed twice and that no entry module or module of a prelude is external.
+pub fn validate<M: ModuleInfo>(
+    g: &ModuleGraph<M>,
+    entries: &[Entry],
+) -> Result<(), SplitChunksError> {
+    let mut seen = BTreeSet::new();
+    for entry in entries {
+        if entry.module.index() >= g.node_count() {
+            return Err(SplitChunksError::UnknownModule(entry.module));
+        }
+        if !seen.insert(entry.module) {
+            return Err(SplitChunksError::DuplicateEntry(
+                module_stem(&g[entry.module]).to_owned(),
+            ));
+        }
+        if g[entry.module].is_external() {
+            return Err(SplitChunksError::ExternalEntry(
+                g[entry.module].name().to_owned(),
+            ));
+        }
+        for module_id in &entry.prelude {
+            if module_id.index() >= g.node_count() {
+                return Err(SplitChunksError::UnknownModule(*module_id));
+            }
+            if g[*module_id].is_external() {
+                return Err(SplitChunksError::ExternalEntry(
+                    g[*module_id].name().to_owned(),
+                ));
+            }
+        }
+    }
+    for edge in g.edge_references() {
+        for module_id in edge.weight().used_reexports.iter().flatten() {
+            if module_id.index() >= g.node_count() {
+                return Err(SplitChunksError::DanglingReexport {
+                    importer: g[edge.source()].name().to_owned(),
+                    importee: g[edge.target()].name().to_owned(),
+                    module: *module_id,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Applies feature flags and barrel flattening to `g` and runs Steps 1 to 3, after
+/// checking the input with [`validate`].
+pub fn analyze<M: ModuleInfo + Clone>(
+    g: &ModuleGraph<M>,
+    entries: &[Entry],
+    options: &SplitOptions,
+    cache: &AnalysisCache,
+    trace: &mut DecisionTrace,
+) -> Result<AnalysisState<M>, SplitChunksError> {
+    analyze_with(
+        g,
+        entries,
+        options,
+        cache,
+        trace,
+        |g, condensation, chunk_roots| {
+            // The per-root DFS results only depend on the graph and the set of chunk
+            // roots, so they can be shared with other analyses of the same graph through
+            // the cache.
+            let roots_fingerprint = cache::fingerprint(g, chunk_roots.keys().copied());
+            reachable_from_roots(condensation, chunk_roots, cache, roots_fingerprint)
+        },
+    )
+}
+
+/// [`analyze`] with another way to compute Step 2. `step_2` gets the graph, its import
+/// cycles and the chunk roots, and has to return what [`reachable_from_roots`] would.
+/// Runs the first three stages of the [`pipeline`], Step 3 with the package sizes
+/// `cache` holds.
+pub fn analyze_with<M: ModuleInfo + Clone>(
+    g: &ModuleGraph<M>,
+    entries: &[Entry],
+    options: &SplitOptions,
+    cache: &AnalysisCache,
+    trace: &mut DecisionTrace,
+    step_2: impl FnOnce(
+        &ModuleGraph<M>,
+        &Condensation,
+        &BTreeMap<ModuleId, (ChunkId, ChunkId)>,
+    ) -> BTreeMap<ModuleId, FixedBitSet>,
+) -> Result<AnalysisState<M>, SplitChunksError> {
+    let roots = pipeline::create_roots(g, entries, options, trace)?;
+    let reachability = pipeline::compute_reachability(roots, step_2);
+    Ok(pipeline::assign_modules(
+        reachability,
+        options,
+        cache,
+        trace,
+    ))
+}
+
+/// The graph the algorithm runs on: `g` without the parts disabled by feature flags,
+/// the imports of external modules, weak imports or imports of a module by itself, with
+/// barrel modules flattened, with the imports of entry modules from their preludes and
+/// with one import between any two modules, see [`SplitOptions::parallel_imports`].
+/// Module ids stay the same. Also returns the `(importer, importee)` pairs of the weak
+/// imports it left out.
+pub fn prepare<M: ModuleInfo + Clone>(
+    g: &ModuleGraph<M>,
+    entries: &[Entry],
+    options: &SplitOptions,
+) -> (ModuleGraph<M>, Vec<BarrelReport>, Vec<(ModuleId, ModuleId)>) {
+    // Drop the parts of the graph that are disabled by build-time feature flags.
+    let g = flags::prune_disabled(g, &options.enabled_flags);
+
+    // Route imports of barrel modules straight to the modules they actually use, so a
+    // barrel imported everywhere doesn't make its whole subtree look shared.
+    let (mut g, barrels) = barrels::flatten_barrels(&g, options.parallel_imports);
+
+    // External modules come with whatever they import, so their imports lead nowhere.
+    // Weak imports load nothing, and neither does a module importing itself.
+    let mut weak_imports = vec![];
+    g.retain_edges(|g, edge_id| {
+        let (importer, importee) = g.edge_endpoints(edge_id).unwrap();
+        if g[importer].is_external() || importer == importee {
+            return false;
+        }
+        if g[edge_id].is_weak {
+            weak_imports.push((importer, importee));
+            return false;
+        }
+        true
+    });
+    weak_imports.sort_unstable();
+    entries::import_preludes(&mut g, entries);
+    (g, barrels, weak_imports)
+}
+
+/// Step 1: Create chunks at the explicit split points in the graph.
+///
+/// `g` has to have at most one import between two modules, like the graph
+/// [`prepare`] returns, since that import decides whether the importee is a split point.
+///
+/// Every async import starts a chunk group for its importee, whichever import the
+/// search follows first, except where the importee is already loaded wherever the
+/// importer runs, see [`loaded_at_async_imports`]. Other chunk groups importing the
+/// module synchronously reach its chunk group like that of any other chunk root.
+///
+/// With a `max_async_depth`, an async import made from a chunk group that many async
+/// imports away from its entry or worker creates no chunk group: the importee stays
+/// with its importer like a sync import would, and so does everything it imports on
+/// demand in turn, see [`SplitOptions::max_async_depth`]. Neither does an async import
+/// of a module the search already reached in a chunk group no more async imports away.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn create_root_chunks<M: ModuleInfo>(
+    g: &ModuleGraph<M>,
+    entries: &[ModuleId],
+    max_async_depth: Option<usize>,
+    trace: &mut DecisionTrace,
+) -> Result<RootChunks, SplitChunksError> {
+    // 存的是 chunk 的入口模块的 id 和对应的 chunk id组成的元组
+    let mut chunk_roots = BTreeMap::new();
+    let mut chunk_graph = Graph::new();
+
+    // Chunk ids are assigned in entry name order and then in DFS order, so identical inputs
+    // always produce identical chunk graphs no matter how the entries were listed.
+    let mut sorted_entries = entries.to_vec();
+    sorted_entries.sort_by_key(|entry| g[*entry].name());
+
+    // Create chunks for each entry.
+    for entry in &sorted_entries {
+        let chunk_id =
+            chunk_graph.add_node(Chunk::from_js_module(*entry, &g[*entry], ChunkKind::Entry));
+        chunk_roots.insert(*entry, (chunk_id, chunk_id));
+        trace.record(|| trace::Decision::ChunkRootCreated {
+            root: *entry,
+            chunk: chunk_id,
+            reason: trace::RootReason::Entry,
+        });
+    }
+
+    // Traverse the module graph and create chunks for async dependencies or other condition.
+    // This only adds the module asset of each chunk, not the subgraph.
+    // stack 的队头表示的当前 chunk 入口模块的 图索引 和其所属的 chunk 的 id
+    // stack 的 n + 1 位置的 chunk 是 n 的父 chunk ，即 chunk (n) import 了 chunk (n + 1)
+    let mut stack = LinkedList::new();
+    let mut missing_edge = None;
+    let loaded_at_async_imports = loaded_at_async_imports(g, &sorted_entries);
+    // How many async imports away the chunk group was that the search first reached each
+    // module in, with a `max_async_depth`.
+    let mut discovered_depths = vec![0; g.node_count()];
+    depth_first_search(g, sorted_entries.iter().copied(), |event| {
+        match event {
+            DfsEvent::Discover(module_idx, _) => {
+                tracing::trace!(module = module_idx.index(), "discover");
+                if max_async_depth.is_some() {
+                    discovered_depths[module_idx.index()] =
+                        async_depth(&stack, &chunk_roots, &chunk_graph);
+                }
+                // Push to the stack when a new chunk is created.
+                if let Some((_, chunk_group_id)) = chunk_roots.get(&module_idx) {
+                    // stack 的队头表示的 chunk 入口模块的 图索引 和其所属的 chunk 的 id
+                    stack.push_front((module_idx, *chunk_group_id));
+                }
+            }
+            DfsEvent::TreeEdge(importer_id, importee_id)
+            | DfsEvent::CrossForwardEdge(importer_id, importee_id)
+            | DfsEvent::BackEdge(importer_id, importee_id) => {
+                let is_tree_edge = matches!(event, DfsEvent::TreeEdge(..));
+                tracing::trace!(
+                    importer = importer_id.index(),
+                    importee = importee_id.index(),
+                    is_tree_edge,
+                    "edge"
+                );
+                // Create a new bundle as well as a new bundle group if the dependency is async.
+
+                let (edge_id, dependency) = match g.find_edge(importer_id, importee_id) {
+                    Some(edge_id) => (edge_id, &g[edge_id]),
+                    None => {
+                        missing_edge = Some(SplitChunksError::MissingEdge {
+                            importer: importer_id,
+                            importee: importee_id,
+                        });
+                        return Control::Break(());
+                    }
+                };
+                let importee_type = g[importee_id].module_type();
+                let is_async =
+                    dependency.is_async && !loaded_at_async_imports.contains(edge_id.index());
+                if dependency.is_async
+                    && !is_async
+                    && !dependency.is_worker
+                    && !g[importee_id].is_external()
+                    && !chunk_roots.contains_key(&importee_id)
+                {
+                    trace.record(|| trace::Decision::AsyncImportOfLoadedModule {
+                        module: importee_id,
+                        importer: importer_id,
+                    });
+                }
+                if g[importee_id].is_external() {
+                    // Loaded by the runtime however it is imported.
+                } else if chunk_roots.contains_key(&importee_id) {
+                    // An entry imported by a module searched before it keeps its entry
+                    // chunk, as it does when its own search comes first.
+                } else if !is_tree_edge && dependency.is_worker {
+                    // Only the import that first reaches the module starts a worker.
+                } else if dependency.is_worker {
+                    // A worker has no parents: nothing the page loaded is available in it.
+                    let chunk =
+                        Chunk::from_js_module(importee_id, &g[importee_id], ChunkKind::Worker);
+                    let chunk_id = chunk_graph.add_node(chunk);
+                    chunk_roots.insert(importee_id, (chunk_id, chunk_id));
+                    trace.record(|| trace::Decision::ChunkRootCreated {
+                        root: importee_id,
+                        chunk: chunk_id,
+                        reason: trace::RootReason::WorkerImport {
+                            importer: importer_id,
+                        },
+                    });
+                } else if is_async
+                    && max_async_depth.is_some_and(|max_async_depth| {
+                        let depth = async_depth(&stack, &chunk_roots, &chunk_graph);
+                        depth >= max_async_depth
+                            || (!is_tree_edge && depth >= discovered_depths[importee_id.index()])
+                    })
+                {
+                    // Too deep for another chunk group, so the importee stays with the
+                    // chunk group of its importer. The search already went through a
+                    // module it reached before, counting what that imports on demand in
+                    // turn from where it went through, so it can't start deeper.
+                    trace.record(|| trace::Decision::AsyncImportInlined {
+                        module: importee_id,
+                        importer: importer_id,
+                        max_async_depth: max_async_depth.unwrap(),
+                    });
+                } else if is_async {
+                    let chunk =
+                        Chunk::from_js_module(importee_id, &g[importee_id], ChunkKind::Async);
+                    let chunk_id = chunk_graph.add_node(chunk);
+                    chunk_roots.insert(importee_id, (chunk_id, chunk_id));
+                    trace.record(|| trace::Decision::ChunkRootCreated {
+                        root: importee_id,
+                        chunk: chunk_id,
+                        reason: trace::RootReason::AsyncImport {
+                            importer: importer_id,
+                        },
+                    });
+                } else if g[importer_id].module_type() != importee_type
+                    || g[importer_id].layer() != g[importee_id].layer()
+                {
+                    // A sync import of another module type, e.g. CSS imported by JS, or of
+                    // another layer starts a sibling chunk in the chunk group of the
+                    // importer, whether or not the search reached the importee before.
+                    // Step 3 adds the edge from every chunk group that reaches it.
+                    let chunk =
+                        Chunk::from_js_module(importee_id, &g[importee_id], ChunkKind::Shared);
+                    let chunk_id = chunk_graph.add_node(chunk);
+                    let (_, chunk_group_id) = stack.front().unwrap();
+                    chunk_roots.insert(importee_id, (chunk_id, *chunk_group_id));
+                    trace.record(|| trace::Decision::ChunkRootCreated {
+                        root: importee_id,
+                        chunk: chunk_id,
+                        reason: if g[importer_id].module_type() != importee_type {
+                            trace::RootReason::TypeChange {
+                                importer: importer_id,
+                            }
+                        } else {
+                            trace::RootReason::LayerChange {
+                                importer: importer_id,
+                            }
+                        },
+                    });
+                }
+            }
+            DfsEvent::Finish(finished_module_id, _) => {
+                tracing::trace!(module = finished_module_id.index(), "finish");
+                // Pop the stack when existing the asset node that created a bundle.
+                if let Some((module_id, _)) = stack.front() {
+                    if *module_id == finished_module_id {
+                        stack.pop_front();
+                    }
+                }
+            }
+        }
+        Control::Continue
+    });
+    if let Some(error) = missing_edge {
+        return Err(error);
+    }
+    // 此时 chunk_graph 中的每一个 chunk 仅包含自己的入口模块
+    tracing::debug!(roots = chunk_roots.len(), "created root chunks");
+
+    Ok(RootChunks {
+        chunk_graph,
+        chunk_roots,
+    })
+}
+
+/// The async imports, by edge index, of a module that is already loaded wherever its
+/// importer runs: the importee itself, or a module importing it synchronously, is on
+/// every path from the entries to the importer, e.g. in an import cycle. Such an
+/// import splits nothing off, as the chunk group of the importer or one of its parents
+/// has the module loaded. A worker starts a new path, as nothing the page loaded is
+/// available in it.
+fn loaded_at_async_imports<M: ModuleInfo>(g: &ModuleGraph<M>, entries: &[ModuleId]) -> FixedBitSet {
+    let mut loaded = FixedBitSet::with_capacity(g.edge_count());
+    let candidates: Vec<_> = g
+        .edge_references()
+        .filter(|edge| {
+            edge.weight().is_async && !edge.weight().is_worker && !entries.contains(&edge.target())
+        })
+        .collect();
+    if candidates.is_empty() {
+        return loaded;
+    }
+
+    // A module is on every path from the entries to an importer if it dominates the
+    // importer, with a virtual root importing every entry and every worker.
+    let mut graph = DiGraph::<(), ()>::with_capacity(g.node_count() + 1, g.edge_count());
+    for _ in g.node_indices() {
+        graph.add_node(());
+    }
+    let virtual_root = graph.add_node(());
+    for entry in entries {
+        graph.add_edge(virtual_root, *entry, ());
+    }
+    for edge in g.edge_references() {
+        if edge.weight().is_worker {
+            graph.add_edge(virtual_root, edge.target(), ());
+        } else {
+            graph.add_edge(edge.source(), edge.target(), ());
+        }
+    }
+    let dominators = simple_fast(&graph, virtual_root);
+    let imports_synchronously = |importer: ModuleId, importee: ModuleId| {
+        g.find_edge(importer, importee)
+            .is_some_and(|edge_id| !g[edge_id].is_async && !g[edge_id].is_worker)
+    };
+    for edge in candidates {
+        let importee = edge.target();
+        if dominators
+            .dominators(edge.source())
+            .is_some_and(|mut dominators| {
+                dominators.any(|dominator| {
+                    dominator == importee
+                        || (dominator != virtual_root && imports_synchronously(dominator, importee))
+                })
+            })
+        {
+            loaded.insert(edge.id().index());
+        }
+    }
+    loaded
+}
+
+/// How many async imports the chunk group at the front of `stack`, Step 1's chain of
+/// the chunk roots it is in, is away from its entry or worker.
+fn async_depth(
+    stack: &LinkedList<(ModuleId, ChunkId)>,
+    chunk_roots: &BTreeMap<ModuleId, (ChunkId, ChunkId)>,
+    chunk_graph: &Graph<Chunk, usize>,
+) -> usize {
+    stack
+        .iter()
+        // Roots of another module type or layer are in the chunk group of their importer.
+        .filter(|(root, group_id)| chunk_roots[root].0 == *group_id)
+        .take_while(|(_, group_id)| chunk_graph[*group_id].kind == ChunkKind::Async)
+        .count()
+}
+
+/// Step 2 for every chunk root, reusing the results `cache` holds for the graph
+/// identified by `roots_fingerprint`.
+///
+/// The searches only read the graph, so with the `rayon` feature they run in parallel
+/// and are collected by root afterwards.
+#[tracing::instrument(level = "debug", skip_all, fields(roots = chunk_roots.len()))]
+pub fn reachable_from_roots(
+    condensation: &Condensation,
+    chunk_roots: &BTreeMap<ModuleId, (ChunkId, ChunkId)>,
+    cache: &AnalysisCache,
+    roots_fingerprint: u64,
+) -> BTreeMap<ModuleId, FixedBitSet> {
+    let search = |root: &ModuleId| {
+        let reachable = cache.reachable_from(roots_fingerprint, *root, || {
+            reachable_from_root(condensation, *root, chunk_roots)
+        });
+        tracing::trace!(
+            root = root.index(),
+            modules = reachable.count_ones(..),
+            "searched from root"
+        );
+        (*root, (*reachable).clone())
+    };
+
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        let roots: Vec<ModuleId> = chunk_roots.keys().copied().collect();
+        roots.par_iter().map(search).collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    chunk_roots.keys().map(search).collect()
+}
+
+/// Step 2 for one root: every module reachable from `root` without crossing another
+/// chunk root, including the roots where the search stopped.
+///
+/// The search runs on the components of `condensation`, so the modules of an import
+/// cycle are reached together: the other members of the root's own cycle, and every
+/// member of a cycle the search enters. A cycle containing another chunk root is where
+/// the search stops, and only the roots in it are reached, since the rest of the cycle
+/// is theirs.
+pub fn reachable_from_root(
+    condensation: &Condensation,
+    root: ModuleId,
+    chunk_roots: &BTreeMap<ModuleId, (ChunkId, ChunkId)>,
+) -> FixedBitSet {
+    let mut reachable = FixedBitSet::with_capacity(condensation.component_of.len());
+    let start = condensation.component_of[root.index()];
+    let mut visited = FixedBitSet::with_capacity(condensation.components.len());
+    visited.insert(start);
+    let mut stack = vec![start];
+    while let Some(component) = stack.pop() {
+        let members = &condensation.components[component];
+
+        // Stop when we hit another bundle root.
+        if component != start
+            && members
+                .iter()
+                .any(|module_id| chunk_roots.contains_key(module_id))
+        {
+            for module_id in members {
+                if chunk_roots.contains_key(module_id) {
+                    reachable.insert(module_id.index());
+                }
+            }
+            continue;
+        }
+
+        // 注意这里创建的边是摊平的，是【入口模块】直接连接到可达的模块
+        // 对于依赖入口模块 A 假设有 module graph A -> B -> C
+        // 我们能得到 reachable grapg ， A -> B ， A -> C
+        for module_id in members {
+            if *module_id != root {
+                reachable.insert(module_id.index());
+            }
+        }
+        for next in &condensation.successors[component] {
+            if !visited.put(*next) {
+                stack.push(*next);
+            }
+        }
+    }
+    reachable
+}
+
+/// The chunk roots of workers, of the async imports inside workers, and the roots in
+/// their chunk groups.
+fn worker_roots(
+    chunk_graph: &Graph<Chunk, usize>,
+    chunk_roots: &BTreeMap<ModuleId, (ChunkId, ChunkId)>,
+    reachable_chunks: &BTreeSet<(ModuleId, ModuleId)>,
+) -> BTreeSet<ModuleId> {
+    let workers: BTreeSet<ModuleId> = chunk_roots
+        .iter()
+        .filter(|(_, (chunk_id, _))| chunk_graph[*chunk_id].kind == ChunkKind::Worker)
+        .map(|(root, _)| *root)
+        .collect();
+    // Step 1 records the worker as a parent of every async import inside it.
+    let worker_groups: BTreeSet<ChunkId> = reachable_chunks
+        .iter()
+        .filter(|(parent, _)| workers.contains(parent))
+        .map(|(_, root)| root)
+        .chain(&workers)
+        .map(|root| chunk_roots[root].1)
+        .collect();
+    chunk_roots
+        .iter()
+        .filter(|(_, (_, group_id))| worker_groups.contains(group_id))
+        .map(|(root, _)| *root)
+        .collect()
+}
+
+/// Step 3: Place all modules into chunks. Each module is placed into a single
+/// chunk based on the chunk entries it is reachable from. This creates a
+/// maximally code split chunk graph with no duplication.
+///
+/// `chunk_graph` holds the root chunks from Step 1 and `assignments` the assignment of
+/// every module of `g` by module index. Returns the chunk of each combination.
+///
+/// A module reachable from both the page and workers is placed twice, once by the roots
+/// of either, since workers can't load the page's chunks. Likewise a module an
+/// [`Entry::isolated`] entry module reaches is placed once for that entry alone, into its
+/// entry chunk if the types match, and once for the other roots. External modules aren't
+/// placed at all. With [`SplitOptions::reuse_existing_chunk`], shared chunks that hold
+/// the same modules as an earlier chunk are folded into it, see
+/// [`reuse_existing_chunks`]. Cache groups split by package look the package sizes up in
+/// `cache`.
+#[tracing::instrument(level = "debug", skip_all)]
+#[allow(clippy::too_many_arguments)]
+pub fn place_modules<M: ModuleInfo>(
+    g: &ModuleGraph<M>,
+    chunk_graph: &mut Graph<Chunk, usize>,
+    chunk_roots: &BTreeMap<ModuleId, (ChunkId, ChunkId)>,
+    reachable_chunks: &BTreeSet<(ModuleId, ModuleId)>,
+    isolated: &BTreeSet<ModuleId>,
+    assignments: &[Assignment],
+    options: &SplitOptions,
+    cache: &AnalysisCache,
+    trace: &mut DecisionTrace,
+) -> BTreeMap<Combination, NodeIndex> {
+    // Create a mapping from entry module ids to chunk ids.
+    // Chunk roots are registered up front since modules are visited in graph order, which
+    // may reach a module before the roots it is reachable from.
+    let mut chunks: BTreeMap<Combination, NodeIndex> = chunk_roots
+        .iter()
+        .map(|(root, (chunk_id, _))| {
+            let layer = g[*root].layer().map(str::to_owned);
+            ((vec![*root], g[*root].module_type(), layer), *chunk_id)
+        })
+        .collect();
+    // Chunks of cache groups, by the group's index in the options, the package for
+    // groups split by package, and the combination of chunk roots.
+    let mut cache_group_chunks: HashMap<(usize, Option<&str>, Combination), NodeIndex> =
+        HashMap::new();
+    let package_sizes = if options
+        .cache_groups
+        .iter()
+        .any(|group| group.split_by_package.is_some())
+    {
+        cache.package_sizes(g)
+    } else {
+        Arc::default()
+    };
+    // Manual chunks, by name, module type, layer and whether they are for workers.
+    let mut manual_chunks: HashMap<(String, ModuleType, Option<&str>, bool), NodeIndex> =
+        HashMap::new();
+    let worker_roots = worker_roots(chunk_graph, chunk_roots, reachable_chunks);
+    // Roots of the chunk groups that `split_from` leaves out of splitting.
+    let left_out_roots: BTreeSet<ModuleId> = chunk_roots
+        .iter()
+        .filter(|(_, (_, group_id))| !options.split_from.includes(chunk_graph[*group_id].kind))
+        .map(|(root, _)| *root)
+        .collect();
+    let first_placed_chunk = chunk_graph.node_count();
+
+    for module_id in g.node_indices() {
+        if g[module_id].is_external() {
+            continue;
+        }
+        let Assignment {
+            reachable_from: original_reachable,
+            roots: reachable,
+        } = &assignments[module_id.index()];
+        tracing::trace!(module = module_id.index(), roots = ?reachable, "place module");
+
+        if let Some((chunk_id, _)) = chunk_roots.get(&module_id) {
+            // If the module is a chunk root, add the chunk to every other reachable chunk group.
+            trace.record(|| trace::Decision::ModulePlaced {
+                module: module_id,
+                chunk: *chunk_id,
+                reachable_from: original_reachable.clone(),
+                filtered: trace::filtered_roots(original_reachable, reachable, reachable_chunks),
+            });
+            for a in reachable {
+                if *a != module_id {
+                    chunk_graph.add_edge(chunk_roots[a].1, *chunk_id, 0);
+                }
+            }
+        } else {
+            // Workers share no chunks with the page, so a module both reach is placed
+            // once for the page and once for the workers. Isolated entries share no
+            // chunks at all, so each gets a copy of its own, in no manual or cache group
+            // chunk either.
+            let context_of = |root: &ModuleId| {
+                (
+                    worker_roots.contains(root),
+                    isolated.contains(root).then_some(*root),
+                )
+            };
+            let mut contexts: Vec<(bool, Option<ModuleId>)> =
+                original_reachable.iter().map(context_of).collect();
+            contexts.sort_unstable();
+            contexts.dedup();
+            let resolution = rules::resolve(options, &g[module_id]);
+            let too_few_roots = |reachable: &[ModuleId]| {
+                reachable.len() > 1
+                    && reachable.len() < options.min_chunks
+                    && resolution.cache_group().is_none()
+            };
+            // Modules that would go into a shared or cache group chunk, but are reachable
+            // from a chunk group that `split_from` leaves out.
+            let left_out_of_splitting =
+                |reachable: &[ModuleId], isolated_entry: Option<ModuleId>| {
+                    (reachable.len() > 1
+                        || (resolution.cache_group().is_some() && isolated_entry.is_none()))
+                        && reachable.iter().any(|a| left_out_roots.contains(a))
+                };
+            if !resolution.matches.is_empty() {
+                trace.record(|| trace::Decision::RulesResolved {
+                    module: module_id,
+                    resolution: resolution.clone(),
+                });
+            }
+            for context @ (in_worker, isolated_entry) in contexts {
+                let in_context = |roots: &[ModuleId]| -> Vec<ModuleId> {
+                    roots
+                        .iter()
+                        .copied()
+                        .filter(|root| context_of(root) == context)
+                        .collect()
+                };
+                let (original_reachable, reachable) =
+                    (&in_context(original_reachable), &in_context(reachable));
+                if let Some(name) = resolution
+                    .manual_chunk()
+                    .filter(|_| !reachable.is_empty() && isolated_entry.is_none())
+                {
+                    // Manual chunks take the module before any automatic placement, no
+                    // matter which roots reach it. Workers get a copy of their own.
+                    let name = name.to_owned();
+                    let module_type = g[module_id].module_type();
+                    let layer = g[module_id].layer();
+                    let chunk_id = *manual_chunks
+                        .entry((name.clone(), module_type, layer, in_worker))
+                        .or_insert_with(|| {
+                            chunk_graph.add_node(Chunk {
+                                name: if in_worker {
+                                    format!("{}~worker", name)
+                                } else {
+                                    name
+                                },
+                                kind: ChunkKind::Manual,
+                                module_type,
+                                layer: layer.map(str::to_owned),
+                                ..Chunk::default()
+                            })
+                        });
+                    let bundle = &mut chunk_graph[chunk_id];
+                    bundle.modules.push(module_id);
+                    bundle.size += g[module_id].size();
+                    trace.record(|| trace::Decision::ModulePlaced {
+                        module: module_id,
+                        chunk: chunk_id,
+                        reachable_from: original_reachable.clone(),
+                        filtered: trace::filtered_roots(
+                            original_reachable,
+                            reachable,
+                            reachable_chunks,
+                        ),
+                    });
+                    for a in reachable {
+                        chunk_graph.update_edge(chunk_roots[a].1, chunk_id, 0);
+                    }
+                } else if (too_few_roots(reachable)
+                    || left_out_of_splitting(reachable, isolated_entry))
+                    && reachable.iter().all(|a| {
+                        g[*a].module_type() == g[module_id].module_type()
+                            && g[*a].layer() == g[module_id].layer()
+                    })
+                {
+                    // Shared by too few chunk roots to be worth a chunk of its own, or by
+                    // chunk groups that `split_from` leaves out, so duplicate the module
+                    // into the chunk of every root instead.
+                    let root_chunks: Vec<NodeIndex> =
+                        reachable.iter().map(|a| chunk_roots[a].0).collect();
+                    for chunk_id in &root_chunks {
+                        let bundle = &mut chunk_graph[*chunk_id];
+                        bundle.modules.push(module_id);
+                        bundle.size += g[module_id].size();
+                    }
+                    if too_few_roots(reachable) {
+                        trace.record(|| trace::Decision::ModuleDuplicated {
+                            module: module_id,
+                            chunks: root_chunks.clone(),
+                            min_chunks: options.min_chunks,
+                        });
+                    } else {
+                        trace.record(|| trace::Decision::ModuleNotSplit {
+                            module: module_id,
+                            chunks: root_chunks.clone(),
+                            split_from: options.split_from,
+                        });
+                    }
+                } else if !reachable.is_empty() {
+                    // If the asset is reachable from more than one entry, find or create
+                    // a chunk for that combination of entries, and add the asset to it.
+                    // Modules of different types or layers never share a chunk.
+                    let module_type = g[module_id].module_type();
+                    let layer = g[module_id].layer().map(str::to_owned);
+                    let combination = (reachable.clone(), module_type, layer.clone());
+                    let source_chunks = reachable
+                        .iter()
+                        .map(|a| chunk_roots[a].0)
+                        .collect::<Vec<_>>();
+                    let name_for = |prefix: &str| {
+                        let mut root_names: Vec<&str> =
+                            reachable.iter().map(|a| module_stem(&g[*a])).collect();
+                        root_names.sort_unstable();
+                        combined_name(prefix, &root_names)
+                    };
+                    // Modules of a cache group go into the group's chunk for the combination instead,
+                    // even if only one entry reaches them.
+                    let cache_group = resolution
+                        .cache_group()
+                        .filter(|_| isolated_entry.is_none())
+                        .map(|index| (index, &options.cache_groups[index]));
+                    let chunk_id = match cache_group {
+                        Some((group_index, group)) => {
+                            // Packages large enough for a chunk of their own get one per
+                            // combination, named after the group and the package.
+                            let package =
+                                cache::package_name(g[module_id].name()).filter(|package| {
+                                    group.split_by_package.is_some_and(|min_package_size| {
+                                        package_sizes[*package] >= min_package_size
+                                    })
+                                });
+                            *cache_group_chunks
+                                .entry((group_index, package, combination))
+                                .or_insert_with(|| {
+                                    let prefix = match package {
+                                        Some(package) => format!("{}~{}", group.name, package),
+                                        None => group.name.clone(),
+                                    };
+                                    chunk_graph.add_node(Chunk {
+                                        name: name_for(&prefix),
+                                        module_type,
+                                        layer,
+                                        source_bundles: source_chunks,
+                                        cache_group: Some(group.name.clone()),
+                                        ..Chunk::default()
+                                    })
+                                })
+                        }
+                        // 这里创建了共享模块的 chunk
+                        None => *chunks.entry(combination).or_insert_with(|| {
+                            chunk_graph.add_node(Chunk {
+                                name: name_for("shared"),
+                                module_type,
+                                layer,
+                                source_bundles: source_chunks,
+                                ..Chunk::default()
+                            })
+                        }),
+                    };
+
+                    let bundle = &mut chunk_graph[chunk_id];
+                    bundle.modules.push(module_id);
+                    bundle.size += g[module_id].size();
+                    trace.record(|| trace::Decision::ModulePlaced {
+                        module: module_id,
+                        chunk: chunk_id,
+                        reachable_from: original_reachable.clone(),
+                        filtered: trace::filtered_roots(
+                            original_reachable,
+                            reachable,
+                            reachable_chunks,
+                        ),
+                    });
+
+                    // Add the bundle to each reachable bundle group.
+                    for item_module_id in reachable {
+                        let item_chunk_id = chunk_roots[item_module_id].1;
+                        if item_chunk_id != chunk_id {
+                            chunk_graph.add_edge(item_chunk_id, chunk_id, 0);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    if options.reuse_existing_chunk {
+        reuse_existing_chunks(chunk_graph, &mut chunks, first_placed_chunk, trace);
+    }
+    tracing::debug!(
+        chunks = chunk_graph.node_count() - first_placed_chunk,
+        "placed modules into shared chunks"
+    );
+    chunks
+}
+
+/// Folds every shared chunk created in Step 3, from `first_placed_chunk` on, into the
+/// first chunk holding exactly the same modules of the same type, if any. The chunk
+/// groups loading the shared chunk load the existing one instead, and the shared chunk
+/// is left empty for the garbage collection after Step 3.5. Manual chunks are kept as
+/// named.
+///
+/// Since Step 3 places a module only once per chunk root combination, this happens
+/// when the page and a worker get the same copy of their modules.
+fn reuse_existing_chunks(
+    chunk_graph: &mut Graph<Chunk, usize>,
+    chunks: &mut BTreeMap<Combination, NodeIndex>,
+    first_placed_chunk: usize,
+    trace: &mut DecisionTrace,
+) {
+    let mut existing: HashMap<(ModuleType, Vec<ModuleId>), NodeIndex> = HashMap::new();
+    for chunk_id in chunk_graph.node_indices().collect::<Vec<_>>() {
+        let chunk = &chunk_graph[chunk_id];
+        let mut modules = chunk.modules.clone();
+        modules.sort_unstable();
+        let key = (chunk.module_type, modules);
+        let reusable = chunk_id.index() >= first_placed_chunk && chunk.kind == ChunkKind::Shared;
+        let existing_id = match existing.get(&key) {
+            Some(existing_id) if reusable => *existing_id,
+            _ => {
+                existing.entry(key).or_insert(chunk_id);
+                continue;
+            }
+        };
+
+        let parents: Vec<NodeIndex> = chunk_graph
+            .neighbors_directed(chunk_id, petgraph::Direction::Incoming)
+            .collect();
+        for parent in parents {
+            if parent != existing_id {
+                chunk_graph.update_edge(parent, existing_id, 0);
+            }
+        }
+        let chunk = &mut chunk_graph[chunk_id];
+        chunk.modules.clear();
+        chunk.size = 0;
+        let source_bundles = std::mem::take(&mut chunk.source_bundles);
+        // A shared chunk can be merged back into the chunks of both.
+        let existing_chunk = &mut chunk_graph[existing_id];
+        if existing_chunk.kind == ChunkKind::Shared {
+            for source_id in source_bundles {
+                if !existing_chunk.source_bundles.contains(&source_id) {
+                    existing_chunk.source_bundles.push(source_id);
+                }
+            }
+        }
+        for placed in chunks.values_mut() {
+            if *placed == chunk_id {
+                *placed = existing_id;
+            }
+        }
+        trace.record(|| trace::Decision::ChunkReused {
+            chunk: chunk_id,
+            existing: existing_id,
+        });
+    }
+}
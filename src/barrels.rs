@@ -0,0 +1,158 @@
+//! Barrel flattening: imports of re-export-only modules are routed straight to the
+//! modules the importer uses.
+
+use crate::module_graph::{Dependency, ModuleGraph, ModuleId, ModuleInfo};
+use crate::options::ParallelImports;
+use petgraph::prelude::Incoming;
+use petgraph::visit::EdgeRef;
+use serde::{Deserialize, Serialize};
+
+/// How [`flatten_barrels`] rerouted the imports of one barrel module.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BarrelReport {
+    pub barrel: ModuleId,
+    /// Importers whose edge to the barrel was replaced by edges to the used modules.
+    pub rerouted_importers: Vec<ModuleId>,
+    /// Re-exported modules that at least one rerouted importer doesn't use. These are
+    /// the modules whose placement changed because of the flattening.
+    pub narrowed_targets: Vec<ModuleId>,
+    /// Whether no importer depends on the barrel itself anymore.
+    pub bypassed: bool,
+}
+
+/// Replaces every import of a barrel module that lists its `used_reexports` with direct
+/// imports of those modules, keeping the async flag, import count and load likelihood.
+/// Imports without export metadata still go through the barrel. Only one level of
+/// barrels is flattened per import.
+///
+/// The flattened graph keeps every module at the same index, and has at most one import
+/// between two modules: imports between the same modules, whether `g` already had
+/// several or rerouting added them, are merged as `parallel_imports` says.
+pub fn flatten_barrels<M: ModuleInfo + Clone>(
+    g: &ModuleGraph<M>,
+    parallel_imports: ParallelImports,
+) -> (ModuleGraph<M>, Vec<BarrelReport>) {
+    let mut flattened = g.clone();
+    flattened.clear_edges();
+    let mut reports = vec![];
+
+    for edge in g.edge_references() {
+        let (importer, importee, dependency) = (edge.source(), edge.target(), edge.weight());
+        let used_reexports = match &dependency.used_reexports {
+            Some(used) if g[importee].reexports_only() => used,
+            _ => {
+                merge_dependency(
+                    &mut flattened,
+                    importer,
+                    importee,
+                    dependency.clone(),
+                    parallel_imports,
+                );
+                continue;
+            }
+        };
+        for target in used_reexports {
+            if !g.contains_edge(importee, *target) {
+                // Not actually re-exported by this barrel, keep the import honest.
+                continue;
+            }
+            let rerouted = Dependency {
+                used_reexports: None,
+                ..dependency.clone()
+            };
+            merge_dependency(
+                &mut flattened,
+                importer,
+                *target,
+                rerouted,
+                parallel_imports,
+            );
+        }
+        let report_index = match reports
+            .iter()
+            .position(|report: &BarrelReport| report.barrel == importee)
+        {
+            Some(index) => index,
+            None => {
+                reports.push(BarrelReport {
+                    barrel: importee,
+                    rerouted_importers: vec![],
+                    narrowed_targets: vec![],
+                    bypassed: false,
+                });
+                reports.len() - 1
+            }
+        };
+        let report = &mut reports[report_index];
+        report.rerouted_importers.push(importer);
+        for target in g.neighbors(importee) {
+            if !used_reexports.contains(&target) && !report.narrowed_targets.contains(&target) {
+                report.narrowed_targets.push(target);
+            }
+        }
+    }
+
+    for report in &mut reports {
+        report.bypassed = flattened
+            .neighbors_directed(report.barrel, Incoming)
+            .next()
+            .is_none();
+    }
+    (flattened, reports)
+}
+
+/// Adds `dependency` to `g`, merging it into an existing edge between the same modules.
+/// The merged edge is sync if either is, starts a worker only if both do, is weak only
+/// if both are, counts the import sites of both and keeps the higher priority hint and
+/// the higher frequency.
+pub fn add_dependency<M>(
+    g: &mut ModuleGraph<M>,
+    importer: ModuleId,
+    importee: ModuleId,
+    dependency: Dependency,
+) {
+    merge_dependency(
+        g,
+        importer,
+        importee,
+        dependency,
+        ParallelImports::PreferSync,
+    );
+}
+
+/// Like [`add_dependency`], except that with [`ParallelImports::PreferAsync`] the merged
+/// edge is async if either is and starts a worker if either does.
+pub fn merge_dependency<M>(
+    g: &mut ModuleGraph<M>,
+    importer: ModuleId,
+    importee: ModuleId,
+    dependency: Dependency,
+    parallel_imports: ParallelImports,
+) {
+    match g.find_edge(importer, importee) {
+        Some(edge_id) => {
+            let existing = &mut g[edge_id];
+            match parallel_imports {
+                ParallelImports::PreferSync => {
+                    existing.is_async &= dependency.is_async;
+                    existing.is_worker &= dependency.is_worker;
+                }
+                ParallelImports::PreferAsync => {
+                    existing.is_async |= dependency.is_async;
+                    existing.is_worker |= dependency.is_worker;
+                }
+            }
+            existing.is_weak &= dependency.is_weak;
+            existing.import_count += dependency.import_count;
+            existing.load_likelihood = existing.load_likelihood.max(dependency.load_likelihood);
+            existing.hint = existing.hint.max(dependency.hint);
+            existing.frequency = match (existing.frequency, dependency.frequency) {
+                (Some(a), Some(b)) => Some(a.max(b)),
+                (a, b) => a.or(b),
+            };
+        }
+        None => {
+            g.add_edge(importer, importee, dependency);
+        }
+    }
+}
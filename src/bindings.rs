@@ -0,0 +1,320 @@
+//! What the JS bindings exchange with JS, kept apart from any particular binding so
+//! every one of them accepts the same graphs and options and returns the same result.
+//!
+//! The graph is the description [`loader`] reads, or its modules and entries with the
+//! imports in flat buffers, see [`graph_from_buffers`]. The options and the result use
+//! the camel-cased names JS code expects.
+
+use crate::cache::AnalysisCache;
+use crate::chunk_graph::{ChunkGraph, ChunkId, ChunkKind};
+use crate::chunk_groups::ChunkGroups;
+use crate::entries::Entry;
+use crate::ids::{self, AssignedId, ChunkIds};
+use crate::loader;
+use crate::module_graph::{Dependency, ModuleGraph, ModuleId, ModuleType};
+use crate::options::{
+    CacheGroup, EntrySignatures, ParallelImports, SideEffectDuplication, SplitFrom, SplitOptions,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+
+/// The options of [`SplitOptions`] that can be given from JS. Every field is optional
+/// and defaults like [`SplitOptions::default`].
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct JsSplitOptions {
+    pub min_size: Option<usize>,
+    pub max_size: Option<usize>,
+    pub min_chunks: Option<usize>,
+    pub min_source_chunks: Option<usize>,
+    pub max_async_requests: Option<usize>,
+    pub max_initial_requests: Option<usize>,
+    pub max_async_depth: Option<usize>,
+    /// Puts everything under `node_modules` into vendor chunks.
+    pub vendors: bool,
+    /// Puts everything under `node_modules` into vendor chunks, with chunks of their
+    /// own for packages of at least this many bytes, see [`CacheGroup::split_by_package`].
+    pub granular_vendors: Option<usize>,
+    /// Feature flags that are switched on.
+    pub flags: Vec<String>,
+    pub runtime_chunk: bool,
+    pub runtime_size: Option<usize>,
+    pub hot_path_bias: Option<f64>,
+    pub coupling_bias: Option<f64>,
+    pub reuse_existing_chunk: bool,
+    /// Keeps small chunks whose duplication would run modules with side effects twice on
+    /// a page, see [`SideEffectDuplication::Refuse`].
+    pub refuse_side_effect_duplication: bool,
+    /// See [`SplitOptions::hoist_sibling_duplicates`].
+    pub hoist_sibling_duplicates: bool,
+    /// `"allowExtension"` or `"strict"`, see [`EntrySignatures`].
+    pub preserve_entry_signatures: Option<EntrySignatures>,
+    /// Splits a module off at an async import even if the importer also imports it
+    /// synchronously, see [`ParallelImports::PreferAsync`].
+    pub prefer_async_imports: bool,
+    /// `"initial"`, `"async"` or `"all"`, see [`SplitFrom`].
+    pub chunks: Option<SplitFrom>,
+    /// `"natural"`, `"named"` or `"deterministic"`, see [`ChunkIds`].
+    pub chunk_ids: Option<ChunkIds>,
+}
+
+impl JsSplitOptions {
+    /// The options to split with. Flags are interned in `cache`.
+    pub fn to_split_options(&self, cache: &AnalysisCache) -> SplitOptions {
+        let defaults = SplitOptions::default();
+        SplitOptions {
+            min_size: self.min_size.unwrap_or(defaults.min_size),
+            max_size: self.max_size,
+            min_chunks: self.min_chunks.unwrap_or(defaults.min_chunks),
+            min_source_chunks: self.min_source_chunks.unwrap_or(defaults.min_source_chunks),
+            max_async_requests: self
+                .max_async_requests
+                .unwrap_or(defaults.max_async_requests),
+            max_initial_requests: self
+                .max_initial_requests
+                .unwrap_or(defaults.max_initial_requests),
+            max_async_depth: self.max_async_depth,
+            cache_groups: match self.granular_vendors {
+                Some(min_package_size) => vec![CacheGroup::granular_vendors(min_package_size)],
+                None if self.vendors => vec![CacheGroup::vendors()],
+                None => vec![],
+            },
+            enabled_flags: self.flags.iter().map(|flag| cache.intern(flag)).collect(),
+            runtime_chunk: self.runtime_chunk,
+            runtime_size: self.runtime_size.unwrap_or(defaults.runtime_size),
+            hot_path_bias: self.hot_path_bias.unwrap_or(defaults.hot_path_bias),
+            coupling_bias: self.coupling_bias.unwrap_or(defaults.coupling_bias),
+            reuse_existing_chunk: self.reuse_existing_chunk,
+            side_effect_duplication: if self.refuse_side_effect_duplication {
+                SideEffectDuplication::Refuse
+            } else {
+                SideEffectDuplication::Warn
+            },
+            hoist_sibling_duplicates: self.hoist_sibling_duplicates,
+            preserve_entry_signatures: self
+                .preserve_entry_signatures
+                .unwrap_or(defaults.preserve_entry_signatures),
+            parallel_imports: if self.prefer_async_imports {
+                ParallelImports::PreferAsync
+            } else {
+                ParallelImports::PreferSync
+            },
+            split_from: self.chunks.unwrap_or(defaults.split_from),
+            chunk_ids: self.chunk_ids.unwrap_or(defaults.chunk_ids),
+            ..defaults
+        }
+    }
+}
+
+/// Bit of an import kind in [`graph_from_buffers`] for an async import.
+pub const ASYNC_IMPORT: u8 = 1;
+/// Bit of an import kind in [`graph_from_buffers`] for an import that starts a worker.
+pub const WORKER_IMPORT: u8 = 2;
+/// Bit of an import kind in [`graph_from_buffers`] for a weak import, see
+/// [`Dependency::is_weak`].
+pub const WEAK_IMPORT: u8 = 4;
+
+/// Builds a module graph from the `modules` and `entries` of a graph description and
+/// imports given as flat buffers, which JS can hand over without converting every
+/// import to an object. `edges` holds one `(importer, importee)` pair of module indices
+/// per import, and `edge_kinds`, if given, one byte per import combining
+/// [`ASYNC_IMPORT`], [`WORKER_IMPORT`] and [`WEAK_IMPORT`]. Other fields of an import
+/// take their defaults. Like [`loader`], it keeps repeated imports of a module.
+pub fn graph_from_buffers(
+    modules: serde_json::Value,
+    entries: serde_json::Value,
+    edges: &[u32],
+    edge_kinds: Option<&[u8]>,
+    cache: &AnalysisCache,
+) -> Result<(ModuleGraph, Vec<Entry>), Box<dyn Error>> {
+    let (mut g, entries) = loader::from_value(
+        serde_json::json!({ "modules": modules, "entries": entries }),
+        cache,
+    )?;
+    if !edges.len().is_multiple_of(2) {
+        return Err("the edge buffer must hold pairs of module indices".into());
+    }
+    let import_count = edges.len() / 2;
+    if let Some(edge_kinds) = edge_kinds {
+        if edge_kinds.len() != import_count {
+            return Err(format!(
+                "the edge kind buffer holds {} kinds for {} imports",
+                edge_kinds.len(),
+                import_count
+            )
+            .into());
+        }
+    }
+    let module_id = |index: u32| {
+        let index = index as usize;
+        if index < g.node_count() {
+            Ok(ModuleId::new(index))
+        } else {
+            Err(format!(
+                "an import refers to module {}, which is not listed",
+                index
+            ))
+        }
+    };
+    let mut imports = Vec::with_capacity(import_count);
+    for (number, pair) in edges.chunks_exact(2).enumerate() {
+        let kind = edge_kinds.map_or(0, |edge_kinds| edge_kinds[number]);
+        imports.push((module_id(pair[0])?, module_id(pair[1])?, kind));
+    }
+    for (importer, importee, kind) in imports {
+        let dependency = Dependency {
+            is_async: kind & ASYNC_IMPORT != 0,
+            is_worker: kind & WORKER_IMPORT != 0,
+            is_weak: kind & WEAK_IMPORT != 0,
+            ..Default::default()
+        };
+        g.add_edge(importer, importee, dependency);
+    }
+    Ok((g, entries))
+}
+
+/// Which chunks every module ended up in.
+#[derive(Debug, Serialize)]
+pub struct ChunkAssignment {
+    /// Every chunk, in id order.
+    pub chunks: Vec<AssignedChunk>,
+    /// Every chunk group, in the order of their root chunks.
+    pub groups: Vec<AssignedChunkGroup>,
+    /// Names of the chunks holding each module, by module name. Modules no entry reaches
+    /// are left out.
+    pub modules: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssignedChunk {
+    /// The chunk's id, picked by [`ids::assign`].
+    pub id: AssignedId,
+    pub name: String,
+    pub kind: ChunkKind,
+    #[serde(rename = "type")]
+    pub module_type: ModuleType,
+    /// The layer of the chunk's modules, see [`JsModule::layer`].
+    ///
+    /// [`JsModule::layer`]: crate::JsModule::layer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layer: Option<String>,
+    /// Names of the modules in the chunk.
+    pub modules: Vec<String>,
+    pub size: usize,
+    /// Names of the root chunks of the chunk groups that load the chunk.
+    pub parents: Vec<String>,
+    /// Names of the modules of every [`Chunk::concat_groups`] group.
+    ///
+    /// [`Chunk::concat_groups`]: crate::Chunk::concat_groups
+    pub concat_groups: Vec<Vec<String>>,
+}
+
+/// A chunk group, see [`ChunkGroups`].
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssignedChunkGroup {
+    /// Name of the root chunk.
+    pub name: String,
+    pub kind: ChunkKind,
+    /// Names of the chunks the group loads before its root chunk runs, in load order,
+    /// including those of the entries it depends on.
+    pub chunks: Vec<String>,
+    /// Names of the groups it imports on demand or starts as workers.
+    pub children: Vec<String>,
+}
+
+impl ChunkAssignment {
+    /// The assignment of the modules of `g` to the chunks of `chunk_graph`, with chunk ids
+    /// picked by `chunk_ids`.
+    pub fn new(chunk_graph: &ChunkGraph, g: &ModuleGraph, chunk_ids: ChunkIds) -> Self {
+        let ids = ids::assign(chunk_graph, chunk_ids);
+        let mut modules: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let chunks = chunk_graph
+            .chunks()
+            .map(|(chunk_id, chunk)| {
+                for module_id in &chunk.modules {
+                    modules
+                        .entry(g[*module_id].name.to_string())
+                        .or_default()
+                        .push(chunk.name.clone());
+                }
+                AssignedChunk {
+                    id: ids[&chunk_id].clone(),
+                    name: chunk.name.clone(),
+                    kind: chunk.kind,
+                    module_type: chunk.module_type,
+                    layer: chunk.layer.clone(),
+                    modules: chunk
+                        .modules
+                        .iter()
+                        .map(|module_id| g[*module_id].name.to_string())
+                        .collect(),
+                    size: chunk.size,
+                    parents: chunk_graph
+                        .parents(chunk_id)
+                        .iter()
+                        .map(|parent| chunk_graph.graph[*parent].name.clone())
+                        .collect(),
+                    concat_groups: chunk
+                        .concat_groups
+                        .iter()
+                        .map(|group| {
+                            group
+                                .iter()
+                                .map(|module_id| g[*module_id].name.to_string())
+                                .collect()
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+        let chunk_groups = ChunkGroups::new(chunk_graph);
+        let name_of = |chunk_id: ChunkId| chunk_graph.graph[chunk_id].name.clone();
+        let groups = chunk_groups
+            .groups()
+            .map(|(group_id, group)| AssignedChunkGroup {
+                name: name_of(group.root),
+                kind: group.kind,
+                chunks: chunk_groups
+                    .initial_chunks(group_id)
+                    .into_iter()
+                    .map(name_of)
+                    .collect(),
+                children: chunk_groups
+                    .children(group_id)
+                    .into_iter()
+                    .map(|child_id| name_of(chunk_groups.group(child_id).root))
+                    .collect(),
+            })
+            .collect();
+        ChunkAssignment {
+            chunks,
+            groups,
+            modules,
+        }
+    }
+}
+
+/// Splits the graph described by `graph` with [`crate::split_chunks`].
+pub fn split_chunks(
+    graph: serde_json::Value,
+    options: &JsSplitOptions,
+) -> Result<ChunkAssignment, Box<dyn Error>> {
+    let cache = AnalysisCache::default();
+    let (g, entries) = loader::from_value(graph, &cache)?;
+    split_loaded(&g, &entries, options, &cache)
+}
+
+/// Splits a graph built by [`graph_from_buffers`] or [`loader`] with `cache`.
+pub fn split_loaded(
+    g: &ModuleGraph,
+    entries: &[Entry],
+    options: &JsSplitOptions,
+    cache: &AnalysisCache,
+) -> Result<ChunkAssignment, Box<dyn Error>> {
+    let options = options.to_split_options(cache);
+    let chunk_graph = crate::split_chunks(g, entries, &options)?;
+    Ok(ChunkAssignment::new(&chunk_graph, g, options.chunk_ids))
+}
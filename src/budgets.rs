@@ -0,0 +1,108 @@
+//! Size budgets for the final chunks, so CI can fail a build whose entries grow past
+//! what a page should download before it runs, like webpack's `performance` hints.
+
+use crate::chunk_graph::ChunkGraph;
+use crate::chunk_groups::ChunkGroups;
+use serde::Serialize;
+use std::fmt;
+
+/// Limits [`check`] holds the chunk graph to. Unset limits are not checked.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Budgets {
+    /// Most bytes an entry may load before it runs: its initial chunks, those of the
+    /// entries it depends on and the runtime chunk.
+    pub max_initial_size: Option<usize>,
+    /// Most bytes of any single chunk.
+    pub max_chunk_size: Option<usize>,
+}
+
+/// A limit of [`Budgets`] the chunk graph exceeds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum BudgetViolation {
+    /// The initial chunks of `entry` add up to `size` bytes.
+    #[serde(rename_all = "camelCase")]
+    InitialSize {
+        entry: String,
+        size: usize,
+        budget: usize,
+        /// Names of the initial chunks, in load order.
+        chunks: Vec<String>,
+    },
+    /// `chunk` alone has `size` bytes.
+    #[serde(rename_all = "camelCase")]
+    ChunkSize {
+        chunk: String,
+        size: usize,
+        budget: usize,
+    },
+}
+
+impl fmt::Display for BudgetViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BudgetViolation::InitialSize {
+                entry,
+                size,
+                budget,
+                chunks,
+            } => write!(
+                f,
+                "entry {} loads {} bytes before it runs, over the budget of {} ({})",
+                entry,
+                size,
+                budget,
+                chunks.join(", ")
+            ),
+            BudgetViolation::ChunkSize {
+                chunk,
+                size,
+                budget,
+            } => write!(
+                f,
+                "chunk {} has {} bytes, over the budget of {}",
+                chunk, size, budget
+            ),
+        }
+    }
+}
+
+/// Every limit of `budgets` that `chunk_graph` exceeds: the entries over
+/// `max_initial_size`, in id order, then the chunks over `max_chunk_size`, in id order.
+/// Empty if the chunk graph is within budget.
+pub fn check(chunk_graph: &ChunkGraph, budgets: &Budgets) -> Vec<BudgetViolation> {
+    let mut violations = vec![];
+    if let Some(budget) = budgets.max_initial_size {
+        let chunk_groups = ChunkGroups::new(chunk_graph);
+        for (group_id, group) in chunk_groups.entry_groups() {
+            let initial = chunk_groups.initial_chunks(group_id);
+            let size = initial
+                .iter()
+                .map(|chunk_id| chunk_graph.graph[*chunk_id].size)
+                .sum();
+            if size > budget {
+                violations.push(BudgetViolation::InitialSize {
+                    entry: chunk_graph.graph[group.root].name.clone(),
+                    size,
+                    budget,
+                    chunks: initial
+                        .iter()
+                        .map(|chunk_id| chunk_graph.graph[*chunk_id].name.clone())
+                        .collect(),
+                });
+            }
+        }
+    }
+    if let Some(budget) = budgets.max_chunk_size {
+        for (_, chunk) in chunk_graph.chunks() {
+            if chunk.size > budget {
+                violations.push(BudgetViolation::ChunkSize {
+                    chunk: chunk.name.clone(),
+                    size: chunk.size,
+                    budget,
+                });
+            }
+        }
+    }
+    violations
+}
@@ -0,0 +1,169 @@
+//! A cache shared between the analyses of a batch run, e.g. one per package of a
+//! monorepo or one per build target in a CI fan-out.
+
+use crate::module_graph::{ModuleGraph, ModuleId, ModuleInfo, Symbol};
+use fixedbitset::FixedBitSet;
+use petgraph::visit::EdgeRef;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// Modules reachable from a chunk root, keyed by graph fingerprint and root.
+type ReachabilityFragments = HashMap<(u64, ModuleId), Arc<FixedBitSet>>;
+
+/// Interned names, package sizes and per-root reachability, safe to share between
+/// threads behind an `Arc`.
+///
+/// Reachability is keyed by a [`fingerprint`] of the graph it was computed on and
+/// package sizes by the names and sizes of its modules, so analyses of different graphs
+/// never see each other's results.
+
+#[derive(Debug, Default)]
+pub struct AnalysisCache {
+    names: RwLock<HashSet<&'static str>>,
+    symbols: RwLock<HashSet<Symbol>>,
+    package_sizes: RwLock<HashMap<u64, Arc<BTreeMap<String, usize>>>>,
+    reachability: RwLock<ReachabilityFragments>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+}
+
+impl AnalysisCache {
+    /// Returns the one `&'static str` equal to `name`, for flag names. Each distinct name
+    /// is leaked once for the lifetime of the process, so module names are interned as
+    /// [`symbol`](Self::symbol)s instead.
+    pub fn intern(&self, name: &str) -> &'static str {
+        if let Some(interned) = self.names.read().unwrap().get(name) {
+            return interned;
+        }
+        let mut names = self.names.write().unwrap();
+        // Another thread may have interned the name between the two locks.
+        if let Some(interned) = names.get(name) {
+            return interned;
+        }
+        let interned: &'static str = Box::leak(name.to_owned().into_boxed_str());
+        names.insert(interned);
+        interned
+    }
+
+    /// Returns the symbol sharing its string with every other symbol of `name` from this
+    /// cache. The cache keeps the string while it lives.
+    pub fn symbol(&self, name: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.read().unwrap().get(name) {
+            return symbol.clone();
+        }
+        let mut symbols = self.symbols.write().unwrap();
+        // Another thread may have interned the name between the two locks.
+        if let Some(symbol) = symbols.get(name) {
+            return symbol.clone();
+        }
+        let symbol = Symbol::from(name);
+        symbols.insert(symbol.clone());
+        symbol
+    }
+
+    /// Total module size per npm package in `g`, computed once per set of module names
+    /// and sizes.
+    pub fn package_sizes<M: ModuleInfo>(&self, g: &ModuleGraph<M>) -> Arc<BTreeMap<String, usize>> {
+        let mut hasher = DefaultHasher::new();
+        for module in g.node_weights() {
+            (module.name(), module.size()).hash(&mut hasher);
+        }
+        let key = hasher.finish();
+        if let Some(sizes) = self.package_sizes.read().unwrap().get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return sizes.clone();
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let sizes = Arc::new(package_sizes(g));
+        self.package_sizes
+            .write()
+            .unwrap()
+            .insert(key, sizes.clone());
+        sizes
+    }
+
+    /// Modules reachable from `root` in the graph identified by `fingerprint`, calling
+    /// `compute` only when no earlier analysis already stored them.
+    pub fn reachable_from(
+        &self,
+        fingerprint: u64,
+        root: ModuleId,
+        compute: impl FnOnce() -> FixedBitSet,
+    ) -> Arc<FixedBitSet> {
+        if let Some(reachable) = self.reachability.read().unwrap().get(&(fingerprint, root)) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return reachable.clone();
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let reachable = Arc::new(compute());
+        self.reachability
+            .write()
+            .unwrap()
+            .entry((fingerprint, root))
+            .or_insert(reachable)
+            .clone()
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Hash of the module names of `g` in id order and its imports in any order, plus a set
+/// of chunk roots for results that also depend on where chunks start.
+///
+/// Module order matters since cached results hold module ids. Module sizes are left
+/// out, since no result keyed by the fingerprint depends on them.
+pub fn fingerprint<M: ModuleInfo>(
+    g: &ModuleGraph<M>,
+    roots: impl Iterator<Item = ModuleId>,
+) -> u64 {
+    let modules: Vec<&str> = g.node_weights().map(|module| module.name()).collect();
+    let mut edges: Vec<(ModuleId, ModuleId, bool)> = g
+        .edge_references()
+        .map(|edge| (edge.source(), edge.target(), edge.weight().is_async))
+        .collect();
+    edges.sort_unstable();
+    let mut roots: Vec<ModuleId> = roots.collect();
+    roots.sort_unstable();
+
+    let mut hasher = DefaultHasher::new();
+    modules.hash(&mut hasher);
+    edges.hash(&mut hasher);
+    roots.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Total module size per npm package in `g`.
+pub fn package_sizes<M: ModuleInfo>(g: &ModuleGraph<M>) -> BTreeMap<String, usize> {
+    let mut sizes = BTreeMap::new();
+    for module in g.node_weights() {
+        if let Some(package) = package_name(module.name()) {
+            *sizes.entry(package.to_owned()).or_insert(0) += module.size();
+        }
+    }
+    sizes
+}
+
+/// The npm package a module path belongs to, including the scope of scoped packages.
+pub fn package_name(module_name: &str) -> Option<&str> {
+    let (_, path) = module_name.rsplit_once("node_modules/")?;
+    let mut end = path.find('/')?;
+    if path.starts_with('@') {
+        end += 1 + path[end + 1..].find('/')?;
+    }
+    Some(&path[..end])
+}
@@ -0,0 +1,413 @@
+//! The chunk graph the algorithm produces.
+
+use crate::module_graph::{ModuleId, ModuleInfo, ModuleType};
+use petgraph::prelude::{Incoming, NodeIndex};
+use petgraph::Graph;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// Chunks are identified by their index in [`ChunkGraph::graph`]. Passes that remove
+/// chunks renumber the remaining ones, so to keep track of a chunk across passes, hold
+/// on to its [`ChunkHandle`] instead.
+pub type ChunkId = NodeIndex;
+
+/// Identifies a chunk for as long as it exists, unlike its [`ChunkId`], which changes
+/// whenever [`collect_garbage`] renumbers the chunks. Handles are never given to another
+/// chunk of the same [`ChunkGraph`], so a chunk that was removed is just not found.
+///
+/// The chunks of Steps 1 to 3 get handles in the order of their ids, and every chunk a
+/// later pass adds the next one, see [`ChunkGraph::add_chunk`]. An
+/// [`IncrementalChunker`] keeps the handles of the chunks an edit leaves in place and
+/// gives new chunks handles no earlier chunk had, see [`ChunkGraph::renew`].
+///
+/// [`collect_garbage`]: crate::passes::collect_garbage
+/// [`IncrementalChunker`]: crate::incremental::IncrementalChunker
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct ChunkHandle(u32);
+
+impl ChunkHandle {
+    /// The number of the handle, unique among the handles its [`ChunkGraph`] gave out.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ChunkKind {
+    /// The root chunk of an entry, loaded by the page.
+    Entry,
+    /// The root chunk of an async import.
+    Async,
+    /// The root chunk of a web worker. The worker runs apart from the page, so the
+    /// chunks of its chunk group and of the async imports inside it only ever hold
+    /// modules for the worker, duplicating those the page uses as well.
+    Worker,
+    /// A chunk split out of one or more chunk groups, loaded alongside their root chunks.
+    #[default]
+    Shared,
+    /// A chunk named by [`SplitOptions::manual_chunks`], loaded by every chunk group that
+    /// reaches one of its modules. Never merged, split or deduplicated by the passes.
+    ///
+    /// [`SplitOptions::manual_chunks`]: crate::SplitOptions::manual_chunks
+    Manual,
+    /// The runtime shared by all entries, see [`SplitOptions::runtime_chunk`].
+    ///
+    /// [`SplitOptions::runtime_chunk`]: crate::SplitOptions::runtime_chunk
+    Runtime,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Chunk {
+    /// The root module's name without extension for root chunks. Shared chunks are named
+    /// after their cache group (or `shared`) and the roots they were split from, joined
+    /// by `~` like webpack does.
+    pub name: String,
+    pub kind: ChunkKind,
+    /// The type of all modules in the chunk.
+    pub module_type: ModuleType,
+    /// The layer of all modules in the chunk, see [`JsModule::layer`].
+    ///
+    /// [`JsModule::layer`]: crate::JsModule::layer
+    #[serde(default)]
+    pub layer: Option<String>,
+    /// The chunk's modules, in the order they run once [`passes::order_modules`] has
+    /// sorted them: after the modules of the chunk they import synchronously.
+    ///
+    /// [`passes::order_modules`]: crate::passes::order_modules
+    pub modules: Vec<ModuleId>,
+    pub size: usize,
+    /// For shared chunks, the root chunks of the chunk groups the modules were split from.
+    pub source_bundles: Vec<ChunkId>,
+    /// Name of the cache group the chunk was created for, if any.
+    pub cache_group: Option<String>,
+    /// Modules of the chunk a bundler can concatenate into one scope, like Rollup does,
+    /// see [`find_concat_groups`]. Each group starts with the module importing the
+    /// others. Modules in no group stay separate.
+    ///
+    /// [`find_concat_groups`]: crate::passes::find_concat_groups
+    #[serde(default)]
+    pub concat_groups: Vec<Vec<ModuleId>>,
+    /// See [`ChunkHandle`]. Only the [`ChunkGraph`] holding the chunk gives it one.
+    #[serde(default)]
+    pub(crate) handle: ChunkHandle,
+}
+
+impl Chunk {
+    /// The root chunk of `module`.
+    pub fn from_js_module(module_id: ModuleId, module: &impl ModuleInfo, kind: ChunkKind) -> Self {
+        Chunk {
+            name: module_stem(module).to_owned(),
+            kind,
+            module_type: module.module_type(),
+            layer: module.layer().map(str::to_owned),
+            modules: vec![module_id],
+            size: module.size(),
+            source_bundles: vec![],
+            cache_group: None,
+            concat_groups: vec![],
+            handle: ChunkHandle::default(),
+        }
+    }
+}
+
+/// Longest name [`combined_name`] returns.
+pub const MAX_NAME_LENGTH: usize = 100;
+
+/// `prefix` and `names` joined by `~` like webpack does, e.g. `shared~entry-a~entry-b`.
+/// Names longer than [`MAX_NAME_LENGTH`] are cut short and end in a hash of the full
+/// name instead, which stays the same across runs and platforms.
+pub fn combined_name(prefix: &str, names: &[&str]) -> String {
+    let name = format!("{}~{}", prefix, names.join("~"));
+    if name.len() <= MAX_NAME_LENGTH {
+        return name;
+    }
+    // FNV-1a, so the hash doesn't depend on the standard library's hasher.
+    let hash = name.bytes().fold(0x811c_9dc5_u32, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+    });
+    let mut end = MAX_NAME_LENGTH - 9;
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}~{:08x}", &name[..end], hash)
+}
+
+/// The name of `module` without the extension of its type.
+pub fn module_stem<M: ModuleInfo + ?Sized>(module: &M) -> &str {
+    let name = module.name();
+    match module.module_type().extension() {
+        Some(extension) => name
+            .strip_suffix(extension)
+            .and_then(|stem| stem.strip_suffix('.'))
+            .unwrap_or(name),
+        None => name,
+    }
+}
+
+/// Chunks with an edge from every chunk group root to each chunk it loads, weighted by
+/// the number of import sites between the two.
+///
+/// A chunk group is an entry or async chunk root together with the shared chunks it
+/// loads, including the chunks of other module types imported by the root chunk. An
+/// edge to another chunk group's root is an async import of that chunk group.
+/// [`ChunkGroups`] lists the groups as a graph of their own.
+///
+/// [`ChunkGroups`]: crate::chunk_groups::ChunkGroups
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ChunkGraph {
+    pub graph: Graph<Chunk, usize>,
+    /// The chunk and chunk group of every chunk root module. Both are the same chunk,
+    /// except for roots created where the module type changes, whose chunk belongs to the
+    /// chunk group of their importer.
+    pub chunk_roots: BTreeMap<ModuleId, (ChunkId, ChunkId)>,
+    /// `(depended-on entry, depending entry)` pairs of entry modules, see
+    /// [`Entry::depends_on`]. Closed over entries depended on through other entries.
+    ///
+    /// [`Entry::depends_on`]: crate::entries::Entry::depends_on
+    #[serde(default)]
+    pub depends_on: BTreeSet<(ModuleId, ModuleId)>,
+    /// The handle [`ChunkGraph::add_chunk`] gives the next chunk.
+    #[serde(default)]
+    next_handle: u32,
+    /// The id of the chunk with each handle, by handle, `None` for removed chunks.
+    #[serde(default)]
+    chunk_ids: Vec<Option<ChunkId>>,
+}
+
+impl ChunkGraph {
+    /// The chunk graph of the chunks of Steps 1 to 3, with handles in the order of their
+    /// ids.
+    pub fn new(
+        mut graph: Graph<Chunk, usize>,
+        chunk_roots: BTreeMap<ModuleId, (ChunkId, ChunkId)>,
+        depends_on: BTreeSet<(ModuleId, ModuleId)>,
+    ) -> Self {
+        for (index, chunk) in graph.node_weights_mut().enumerate() {
+            chunk.handle = ChunkHandle(index as u32);
+        }
+        ChunkGraph {
+            next_handle: graph.node_count() as u32,
+            chunk_ids: graph.node_indices().map(Some).collect(),
+            graph,
+            chunk_roots,
+            depends_on,
+        }
+    }
+
+    /// The chunk graph of the chunks of Steps 1 to 3 of a graph analyzed again after
+    /// `previous` was. Chunks `previous` had as well, of the same kind, name, module type,
+    /// layer and cache group, keep their handles, and the others get handles `previous`
+    /// never gave out.
+    pub fn renew(
+        mut graph: Graph<Chunk, usize>,
+        chunk_roots: BTreeMap<ModuleId, (ChunkId, ChunkId)>,
+        depends_on: BTreeSet<(ModuleId, ModuleId)>,
+        previous: &ChunkGraph,
+    ) -> Self {
+        type Key<'a> = (
+            ChunkKind,
+            &'a str,
+            ModuleType,
+            Option<&'a str>,
+            Option<&'a str>,
+        );
+        fn key(chunk: &Chunk) -> Key<'_> {
+            (
+                chunk.kind,
+                &chunk.name,
+                chunk.module_type,
+                chunk.layer.as_deref(),
+                chunk.cache_group.as_deref(),
+            )
+        }
+        // Only chunks that are alone with their key on both sides are the same chunk.
+        let mut previous_handles: HashMap<Key, Option<ChunkHandle>> = HashMap::new();
+        for chunk in previous.graph.node_weights() {
+            previous_handles
+                .entry(key(chunk))
+                .and_modify(|handle| *handle = None)
+                .or_insert(Some(chunk.handle));
+        }
+        let mut key_counts: HashMap<Key, usize> = HashMap::new();
+        for chunk in graph.node_weights() {
+            *key_counts.entry(key(chunk)).or_default() += 1;
+        }
+        let kept: Vec<Option<ChunkHandle>> = graph
+            .node_weights()
+            .map(|chunk| {
+                let key = key(chunk);
+                previous_handles
+                    .get(&key)
+                    .copied()
+                    .flatten()
+                    .filter(|_| key_counts[&key] == 1)
+            })
+            .collect();
+
+        let mut next_handle = previous.next_handle;
+        for (chunk, kept) in graph.node_weights_mut().zip(kept) {
+            chunk.handle = kept.unwrap_or_else(|| {
+                next_handle += 1;
+                ChunkHandle(next_handle - 1)
+            });
+        }
+        let mut chunk_graph = ChunkGraph {
+            graph,
+            chunk_roots,
+            depends_on,
+            next_handle,
+            chunk_ids: vec![],
+        };
+        chunk_graph.update_handles();
+        chunk_graph
+    }
+
+    /// Adds `chunk` with the next handle. Passes add chunks this way, so that no two
+    /// chunks share a handle.
+    pub fn add_chunk(&mut self, mut chunk: Chunk) -> ChunkId {
+        chunk.handle = ChunkHandle(self.next_handle);
+        self.next_handle += 1;
+        let chunk_id = self.graph.add_node(chunk);
+        self.chunk_ids.push(Some(chunk_id));
+        chunk_id
+    }
+
+    pub fn handle(&self, chunk_id: ChunkId) -> ChunkHandle {
+        self.graph[chunk_id].handle
+    }
+
+    /// The current id of the chunk with `handle`, or `None` if a pass removed it.
+    pub fn chunk_by_handle(&self, handle: ChunkHandle) -> Option<ChunkId> {
+        self.chunk_ids
+            .get(handle.index())
+            .copied()
+            .flatten()
+            .filter(|chunk_id| {
+                self.graph
+                    .node_weight(*chunk_id)
+                    .is_some_and(|chunk| chunk.handle == handle)
+            })
+    }
+
+    /// Finds every chunk by its handle again after chunks were renumbered, removed or
+    /// added to [`ChunkGraph::graph`] directly, as [`collect_garbage`] does. A chunk with
+    /// the handle of a chunk before it, or with one this graph never gave out, gets the
+    /// next handle.
+    ///
+    /// [`collect_garbage`]: crate::passes::collect_garbage
+    pub fn update_handles(&mut self) {
+        let mut chunk_ids = vec![None; self.next_handle as usize];
+        let mut unhandled = vec![];
+        for chunk_id in self.graph.node_indices() {
+            match chunk_ids.get_mut(self.graph[chunk_id].handle.index()) {
+                Some(slot @ None) => *slot = Some(chunk_id),
+                _ => unhandled.push(chunk_id),
+            }
+        }
+        for chunk_id in unhandled {
+            self.graph[chunk_id].handle = ChunkHandle(self.next_handle);
+            self.next_handle += 1;
+            chunk_ids.push(Some(chunk_id));
+        }
+        self.chunk_ids = chunk_ids;
+    }
+
+    /// Every chunk with its id, in id order.
+    pub fn chunks(&self) -> impl Iterator<Item = (ChunkId, &Chunk)> {
+        self.graph
+            .node_indices()
+            .map(move |chunk_id| (chunk_id, &self.graph[chunk_id]))
+    }
+
+    /// The root chunks of all entries, in id order.
+    pub fn entry_chunks(&self) -> impl Iterator<Item = ChunkId> + '_ {
+        self.chunks()
+            .filter(|(_, chunk)| chunk.kind == ChunkKind::Entry)
+            .map(|(chunk_id, _)| chunk_id)
+    }
+
+    /// The chunk named `name`, the first one in id order if several are.
+    pub fn chunk_by_name(&self, name: &str) -> Option<ChunkId> {
+        self.chunks()
+            .find(|(_, chunk)| chunk.name == name)
+            .map(|(chunk_id, _)| chunk_id)
+    }
+
+    pub fn chunk(&self, chunk_id: ChunkId) -> &Chunk {
+        &self.graph[chunk_id]
+    }
+
+    pub fn modules_of(&self, chunk_id: ChunkId) -> &[ModuleId] {
+        &self.graph[chunk_id].modules
+    }
+
+    /// The chunks holding `module_id`, in id order.
+    pub fn chunks_of(&self, module_id: ModuleId) -> Vec<ChunkId> {
+        self.chunks()
+            .filter(|(_, chunk)| chunk.modules.contains(&module_id))
+            .map(|(chunk_id, _)| chunk_id)
+            .collect()
+    }
+
+    /// The chunks that load `chunk_id`: the root chunks of the chunk groups that load a
+    /// shared chunk, or the root chunks that async import a root chunk. Sorted by id.
+    pub fn parents(&self, chunk_id: ChunkId) -> Vec<ChunkId> {
+        let mut parents: Vec<ChunkId> = self.graph.neighbors_directed(chunk_id, Incoming).collect();
+        parents.sort();
+        parents.dedup();
+        parents
+    }
+
+    /// Root chunks of the entry chunk groups `entry_group` depends on, in the order they
+    /// have to load: every group after the groups it depends on itself.
+    pub fn dependencies_of(&self, entry_group: ChunkId) -> Vec<ChunkId> {
+        let mut dependencies: Vec<(usize, ChunkId)> = self
+            .depends_on
+            .iter()
+            .filter(|(_, entry)| self.chunk_roots[entry].1 == entry_group)
+            .map(|(dependency, _)| {
+                // A dependency depends on fewer entries than anything depending on it.
+                let depth = self
+                    .depends_on
+                    .iter()
+                    .filter(|(_, entry)| entry == dependency)
+                    .count();
+                (depth, self.chunk_roots[dependency].1)
+            })
+            .collect();
+        dependencies.sort();
+        dependencies
+            .into_iter()
+            .map(|(_, group_id)| group_id)
+            .collect()
+    }
+
+    /// Root chunks of all chunk groups.
+    pub fn groups(&self) -> BTreeSet<NodeIndex> {
+        self.chunk_roots
+            .values()
+            .map(|(_, group_id)| *group_id)
+            .collect()
+    }
+
+    /// Modules loaded by each chunk group: the group's root chunk plus every shared chunk
+    /// the root has an edge to.
+    pub fn modules_of_groups(&self) -> HashMap<NodeIndex, HashSet<ModuleId>> {
+        let groups = self.groups();
+        groups
+            .iter()
+            .map(|group_id| {
+                let mut modules: HashSet<ModuleId> =
+                    self.graph[*group_id].modules.iter().copied().collect();
+                for child_id in self.graph.neighbors(*group_id) {
+                    if !groups.contains(&child_id) {
+                        modules.extend(self.graph[child_id].modules.iter().copied());
+                    }
+                }
+                (*group_id, modules)
+            })
+            .collect()
+    }
+}
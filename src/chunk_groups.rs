@@ -0,0 +1,140 @@
+//! Chunk groups as a layer of their own over the chunk graph.
+//!
+//! A chunk group is what an entry, an async import or a worker loads: its root chunk,
+//! holding the module it starts at, and the chunks loaded along with it. Limits like
+//! `max_async_requests` and the files a page or an `import()` fetches are about groups
+//! rather than single chunks. In the [`ChunkGraph`] a group is only implied by the
+//! edges of its root chunk; [`ChunkGroups`] lists the chunks of every group in load
+//! order and links the groups that load each other.
+
+use crate::chunk_graph::{ChunkGraph, ChunkId, ChunkKind};
+use petgraph::prelude::{Incoming, NodeIndex};
+use petgraph::Graph;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Chunk groups are identified by their index in [`ChunkGroups::graph`], which follows
+/// the ids of their root chunks.
+pub type ChunkGroupId = NodeIndex;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChunkGroup {
+    /// The chunk of the entry module, the module imported on demand or the worker.
+    pub root: ChunkId,
+    /// `Entry`, `Async` or `Worker`, the kind of the root chunk.
+    pub kind: ChunkKind,
+    /// Every chunk the group loads, in load order: the other chunks in id order, then
+    /// the root chunk, which runs once they are present.
+    pub chunks: Vec<ChunkId>,
+    /// For entry groups, the groups of the entries loaded before this one, in load
+    /// order, see [`ChunkGraph::dependencies_of`].
+    pub depends_on: Vec<ChunkGroupId>,
+}
+
+/// The chunk groups of a chunk graph, with an edge from every group to each group it
+/// imports on demand or starts as a worker. Built from the chunk graph as it is, so it
+/// has to be built again after a pass changes the chunk graph.
+#[derive(Debug, Default, Clone)]
+pub struct ChunkGroups {
+    pub graph: Graph<ChunkGroup, ()>,
+    group_of_root: BTreeMap<ChunkId, ChunkGroupId>,
+}
+
+impl ChunkGroups {
+    pub fn new(chunk_graph: &ChunkGraph) -> Self {
+        let roots = chunk_graph.groups();
+        let mut graph = Graph::new();
+        let mut group_of_root = BTreeMap::new();
+        for root in &roots {
+            let mut chunks: Vec<ChunkId> = chunk_graph
+                .graph
+                .neighbors(*root)
+                .filter(|chunk_id| !roots.contains(chunk_id))
+                .collect();
+            chunks.sort();
+            chunks.dedup();
+            chunks.push(*root);
+            let group_id = graph.add_node(ChunkGroup {
+                root: *root,
+                kind: chunk_graph.graph[*root].kind,
+                chunks,
+                depends_on: vec![],
+            });
+            group_of_root.insert(*root, group_id);
+        }
+        for (root, group_id) in &group_of_root {
+            let children: BTreeSet<ChunkGroupId> = chunk_graph
+                .graph
+                .neighbors(*root)
+                .filter(|chunk_id| chunk_id != root)
+                .filter_map(|chunk_id| group_of_root.get(&chunk_id).copied())
+                .collect();
+            for child_id in children {
+                graph.add_edge(*group_id, child_id, ());
+            }
+            graph[*group_id].depends_on = chunk_graph
+                .dependencies_of(*root)
+                .iter()
+                .map(|dependency| group_of_root[dependency])
+                .collect();
+        }
+        ChunkGroups {
+            graph,
+            group_of_root,
+        }
+    }
+
+    /// Every chunk group with its id, in id order.
+    pub fn groups(&self) -> impl Iterator<Item = (ChunkGroupId, &ChunkGroup)> {
+        self.graph
+            .node_indices()
+            .map(move |group_id| (group_id, &self.graph[group_id]))
+    }
+
+    /// The groups of all entries, in id order.
+    pub fn entry_groups(&self) -> impl Iterator<Item = (ChunkGroupId, &ChunkGroup)> {
+        self.groups()
+            .filter(|(_, group)| group.kind == ChunkKind::Entry)
+    }
+
+    pub fn group(&self, group_id: ChunkGroupId) -> &ChunkGroup {
+        &self.graph[group_id]
+    }
+
+    /// The group whose root chunk is `root`, if it is the root chunk of a group.
+    pub fn group_of_root(&self, root: ChunkId) -> Option<ChunkGroupId> {
+        self.group_of_root.get(&root).copied()
+    }
+
+    /// The groups `group_id` imports on demand or starts as workers, in id order.
+    pub fn children(&self, group_id: ChunkGroupId) -> Vec<ChunkGroupId> {
+        let mut children: Vec<ChunkGroupId> = self.graph.neighbors(group_id).collect();
+        children.sort();
+        children
+    }
+
+    /// The groups that import `group_id` on demand or start it as a worker, in id order.
+    pub fn parents(&self, group_id: ChunkGroupId) -> Vec<ChunkGroupId> {
+        let mut parents: Vec<ChunkGroupId> =
+            self.graph.neighbors_directed(group_id, Incoming).collect();
+        parents.sort();
+        parents
+    }
+
+    /// The chunks loaded before the root chunk of `group_id` runs, in load order: the
+    /// chunks of the entry groups it depends on, then its own. Chunks loaded by several
+    /// of those come once.
+    pub fn initial_chunks(&self, group_id: ChunkGroupId) -> Vec<ChunkId> {
+        let group = &self.graph[group_id];
+        let mut chunks: Vec<ChunkId> = vec![];
+        for group_id in group.depends_on.iter().chain([&group_id]) {
+            for chunk_id in &self.graph[*group_id].chunks {
+                if !chunks.contains(chunk_id) {
+                    chunks.push(*chunk_id);
+                }
+            }
+        }
+        chunks
+    }
+}
@@ -0,0 +1,486 @@
+//! Splitting a coarse graph of packages and directories instead of every module, for
+//! graphs of huge monorepos where the chunks of single modules aren't worth the time.
+//!
+//! [`split`] contracts the modules of each npm package or directory into one super-node,
+//! runs the whole algorithm on the graph of super-nodes and expands each chunk into the
+//! modules of its super-nodes. Chunk roots keep super-nodes of their own: entries, the
+//! modules of preludes and every module imported on demand, as a worker, from another
+//! module type or layer, or external. So do modules that don't agree on what decides
+//! their chunk besides the roots reaching them, like a manual chunk or the cache groups
+//! they match.
+//!
+//! A module of a super-node is reached by the chunk roots that reach the modules the
+//! rest of the graph imports the super-node through, its doors, from which it is
+//! reachable inside the super-node. Modules reachable from the same doors share a chunk
+//! in the full algorithm as well, so a super-node whose modules are all reachable from
+//! the same doors never straddles chunk boundaries. Others may: those reached from more
+//! than one chunk root in a first run on the coarse graph are refined into a super-node
+//! for each set of doors before the final run. Passes working on module sizes, like the
+//! split of [`SplitOptions::max_size`], only ever move whole super-nodes.
+
+use crate::analysis;
+use crate::cache::{package_name, AnalysisCache};
+use crate::chunk_graph::ChunkGraph;
+use crate::entries::Entry;
+use crate::error::SplitChunksError;
+use crate::module_graph::{Dependency, ModuleGraph, ModuleId, ModuleInfo, ModuleType};
+use crate::options::SplitOptions;
+use crate::passes;
+use crate::trace::DecisionTrace;
+use fixedbitset::FixedBitSet;
+use petgraph::prelude::{Incoming, Outgoing};
+use petgraph::visit::EdgeRef;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// What the modules of a super-node have in common.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Grouping {
+    /// The npm package under `node_modules`, or the directory of modules outside any.
+    #[default]
+    Package,
+    /// The directory.
+    Directory,
+}
+
+impl Grouping {
+    /// The part of `module_name` the modules of its super-node share.
+    pub fn key<'a>(&self, module_name: &'a str) -> &'a str {
+        if *self == Grouping::Package {
+            if let (Some((prefix, _)), Some(package)) = (
+                module_name.rsplit_once("node_modules/"),
+                package_name(module_name),
+            ) {
+                return &module_name[..prefix.len() + "node_modules/".len() + package.len()];
+            }
+        }
+        module_name
+            .rsplit_once('/')
+            .map_or("", |(directory, _)| directory)
+    }
+}
+
+/// A module of the coarse graph: the modules of a package or directory, or a single
+/// module. Takes its name from its first module, so cache groups and manual chunks
+/// match it like they match each of its modules.
+#[derive(Debug, Clone)]
+pub struct SuperNode {
+    pub name: String,
+    pub size: usize,
+    pub module_type: ModuleType,
+    pub is_external: bool,
+    pub side_effect_free: bool,
+    pub layer: Option<String>,
+}
+
+impl ModuleInfo for SuperNode {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn module_type(&self) -> ModuleType {
+        self.module_type
+    }
+
+    fn is_external(&self) -> bool {
+        self.is_external
+    }
+
+    fn side_effect_free(&self) -> bool {
+        self.side_effect_free
+    }
+
+    fn layer(&self) -> Option<&str> {
+        self.layer.as_deref()
+    }
+
+    fn synthetic(name: &'static str, size: usize) -> Self {
+        SuperNode {
+            name: name.to_owned(),
+            size,
+            module_type: ModuleType::Js,
+            is_external: false,
+            side_effect_free: false,
+            layer: None,
+        }
+    }
+}
+
+/// What [`split`] returns.
+#[derive(Debug, Clone)]
+pub struct CoarseSplit {
+    /// The chunks, of the modules of the graph given to [`split`]. Modules the algorithm
+    /// adds itself, like the runtime, get the ids after those of the graph.
+    pub chunk_graph: ChunkGraph,
+    /// Super-nodes of the graph the algorithm ran on.
+    pub super_nodes: usize,
+    /// Super-nodes of more than one module.
+    pub groups: usize,
+    /// Names of the first modules of the super-nodes refined before the final run.
+    pub refined: Vec<String>,
+}
+
+/// One super-node while the coarse graph is put together.
+struct Group {
+    /// Modules in the order they run: a post-order of the imports between them.
+    modules: Vec<ModuleId>,
+    /// The modules by the doors they are reachable from, in the order they run, see the
+    /// [module docs](self). A super-node of one part never straddles chunk boundaries.
+    parts: Vec<Vec<ModuleId>>,
+}
+
+/// Splits `g` like [`crate::split_chunks`], with modules contracted into super-nodes by
+/// `grouping`. Fails like [`crate::split_chunks`] does.
+#[tracing::instrument(level = "debug", skip_all, fields(modules = g.node_count()))]
+pub fn split<M: ModuleInfo + Clone>(
+    g: &ModuleGraph<M>,
+    entries: &[Entry],
+    options: &SplitOptions,
+    grouping: Grouping,
+) -> Result<CoarseSplit, SplitChunksError> {
+    analysis::validate(g, entries)?;
+    let (g, _, _) = analysis::prepare(g, entries, options);
+    let reachable = reachable_modules(&g, entries);
+    let mut super_of = group_modules(&g, entries, options, grouping, &reachable);
+    let groups = build_groups(&g, &super_of, &reachable);
+
+    let cache = AnalysisCache::default();
+    let mut trace = DecisionTrace::new(false);
+    let mut refined = vec![];
+    if groups.iter().any(|group| group.parts.len() > 1) {
+        let (coarse, coarse_entries) = coarse_graph(&g, entries, &super_of, &groups);
+        let state = analysis::analyze(&coarse, &coarse_entries, options, &cache, &mut trace)?;
+        let mut roots_reaching = vec![0usize; groups.len()];
+        for (root, modules) in &state.reachable_modules {
+            roots_reaching[root.index()] += 1;
+            for index in modules.ones() {
+                roots_reaching[index] += 1;
+            }
+        }
+        let mut super_nodes = groups.len();
+        for (index, group) in groups.iter().enumerate() {
+            if group.parts.len() > 1 && roots_reaching[index] > 1 {
+                refined.push(g[group.modules[0]].name().to_owned());
+                for part in &group.parts[1..] {
+                    for module_id in part {
+                        super_of[module_id.index()] = Some(super_nodes);
+                    }
+                    super_nodes += 1;
+                }
+            }
+        }
+    }
+    let groups = if refined.is_empty() {
+        groups
+    } else {
+        renumber(&mut super_of);
+        build_groups(&g, &super_of, &reachable)
+    };
+
+    let (coarse, coarse_entries) = coarse_graph(&g, entries, &super_of, &groups);
+    let mut state = analysis::analyze(&coarse, &coarse_entries, options, &cache, &mut trace)?;
+    passes::optimize(&mut state, options, &mut trace);
+    tracing::debug!(
+        super_nodes = groups.len(),
+        refined = refined.len(),
+        "split the coarse graph"
+    );
+    Ok(CoarseSplit {
+        chunk_graph: expand(state.chunk_graph, &groups, g.node_count()),
+        super_nodes: groups.len(),
+        groups: groups
+            .iter()
+            .filter(|group| group.modules.len() > 1)
+            .count(),
+        refined,
+    })
+}
+
+/// The modules Step 1 reaches from `entries` in `g`, which has to be prepared.
+fn reachable_modules<M>(g: &ModuleGraph<M>, entries: &[Entry]) -> Vec<bool> {
+    let mut reachable = vec![false; g.node_count()];
+    let mut stack: Vec<ModuleId> = entries.iter().map(|entry| entry.module).collect();
+    while let Some(module_id) = stack.pop() {
+        if !std::mem::replace(&mut reachable[module_id.index()], true) {
+            stack.extend(g.neighbors_directed(module_id, Outgoing));
+        }
+    }
+    reachable
+}
+
+/// The super-node of every reachable module of `g`, numbered by their first modules.
+fn group_modules<M: ModuleInfo>(
+    g: &ModuleGraph<M>,
+    entries: &[Entry],
+    options: &SplitOptions,
+    grouping: Grouping,
+    reachable: &[bool],
+) -> Vec<Option<usize>> {
+    let mut singletons = HashSet::new();
+    for entry in entries {
+        singletons.insert(entry.module);
+        singletons.extend(&entry.prelude);
+    }
+    for edge in g.edge_references() {
+        let (importer, importee) = (&g[edge.source()], &g[edge.target()]);
+        if edge.weight().is_async
+            || edge.weight().is_worker
+            || importer.module_type() != importee.module_type()
+            || importer.layer() != importee.layer()
+        {
+            singletons.insert(edge.target());
+        }
+    }
+
+    let mut super_of = vec![None; g.node_count()];
+    let mut super_nodes = 0;
+    let mut by_key = BTreeMap::new();
+    for module_id in g.node_indices() {
+        let module = &g[module_id];
+        if !reachable[module_id.index()] {
+            continue;
+        }
+        if singletons.contains(&module_id) || module.is_external() {
+            super_of[module_id.index()] = Some(super_nodes);
+            super_nodes += 1;
+            continue;
+        }
+        let cache_groups: Vec<bool> = options
+            .cache_groups
+            .iter()
+            .map(|group| group.matches(module.name()))
+            .collect();
+        let key = (
+            grouping.key(module.name()),
+            module.module_type(),
+            module.layer(),
+            module.side_effect_free(),
+            options.manual_chunk_of(module),
+            cache_groups,
+        );
+        let super_node = *by_key.entry(key).or_insert_with(|| {
+            super_nodes += 1;
+            super_nodes - 1
+        });
+        super_of[module_id.index()] = Some(super_node);
+    }
+    super_of
+}
+
+/// Numbers the super-nodes of `super_of` by their first modules again.
+fn renumber(super_of: &mut [Option<usize>]) {
+    let mut numbers = HashMap::new();
+    for super_node in super_of.iter_mut().flatten() {
+        let next = numbers.len();
+        *super_node = *numbers.entry(*super_node).or_insert(next);
+    }
+}
+
+/// The modules of every super-node of `super_of` and its parts.
+///
+/// No super-node depends on what another gives, so with the `rayon` feature they are
+/// built in parallel.
+fn build_groups<M: ModuleInfo>(
+    g: &ModuleGraph<M>,
+    super_of: &[Option<usize>],
+    reachable: &[bool],
+) -> Vec<Group> {
+    let mut members: Vec<Vec<ModuleId>> = vec![];
+    for module_id in g.node_indices() {
+        if let Some(super_node) = super_of[module_id.index()] {
+            if super_node == members.len() {
+                members.push(vec![]);
+            }
+            members[super_node].push(module_id);
+        }
+    }
+    let build = |(super_node, modules): (usize, &Vec<ModuleId>)| {
+        build_group(g, super_of, reachable, super_node, modules)
+    };
+
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        members.par_iter().enumerate().map(build).collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    members.iter().enumerate().map(build).collect()
+}
+
+fn build_group<M: ModuleInfo>(
+    g: &ModuleGraph<M>,
+    super_of: &[Option<usize>],
+    reachable: &[bool],
+    super_node: usize,
+    modules: &[ModuleId],
+) -> Group {
+    if modules.len() == 1 {
+        return Group {
+            modules: modules.to_vec(),
+            parts: vec![modules.to_vec()],
+        };
+    }
+    let inside = |module_id: ModuleId| super_of[module_id.index()] == Some(super_node);
+    let doors: Vec<ModuleId> = modules
+        .iter()
+        .copied()
+        .filter(|module_id| {
+            g.neighbors_directed(*module_id, Incoming)
+                .any(|importer| reachable[importer.index()] && !inside(importer))
+        })
+        .collect();
+
+    // The doors each module is reachable from.
+    let mut doors_of: HashMap<ModuleId, FixedBitSet> = modules
+        .iter()
+        .map(|module_id| (*module_id, FixedBitSet::with_capacity(doors.len())))
+        .collect();
+    for (door_index, door) in doors.iter().enumerate() {
+        doors_of.get_mut(door).unwrap().insert(door_index);
+        let mut stack = vec![*door];
+        while let Some(module_id) = stack.pop() {
+            for next in g.neighbors_directed(module_id, Outgoing) {
+                if inside(next) && !doors_of.get_mut(&next).unwrap().put(door_index) {
+                    stack.push(next);
+                }
+            }
+        }
+    }
+
+    // Imports run before their importer, so the modules run in post-order.
+    let mut order = Vec::with_capacity(modules.len());
+    let mut seen = HashSet::new();
+    for start in doors.iter().chain(modules) {
+        if !seen.insert(*start) {
+            continue;
+        }
+        let mut stack = vec![(*start, false)];
+        while let Some((module_id, finished)) = stack.pop() {
+            if finished {
+                order.push(module_id);
+                continue;
+            }
+            stack.push((module_id, true));
+            for next in g.neighbors_directed(module_id, Outgoing) {
+                if inside(next) && seen.insert(next) {
+                    stack.push((next, false));
+                }
+            }
+        }
+    }
+
+    let mut parts: Vec<Vec<ModuleId>> = vec![];
+    let mut part_of: HashMap<&FixedBitSet, usize> = HashMap::new();
+    for module_id in &order {
+        let part = *part_of.entry(&doors_of[module_id]).or_insert_with(|| {
+            parts.push(vec![]);
+            parts.len() - 1
+        });
+        parts[part].push(*module_id);
+    }
+    Group {
+        modules: order,
+        parts,
+    }
+}
+
+/// The graph of the super-nodes of `super_of` and the entries of `entries` in it. Of
+/// the imports between two super-nodes, the first of each kind is kept.
+fn coarse_graph<M: ModuleInfo>(
+    g: &ModuleGraph<M>,
+    entries: &[Entry],
+    super_of: &[Option<usize>],
+    groups: &[Group],
+) -> (ModuleGraph<SuperNode>, Vec<Entry>) {
+    let mut coarse = ModuleGraph::with_capacity(groups.len(), groups.len());
+    for group in groups {
+        let first = &g[group.modules[0]];
+        coarse.add_node(SuperNode {
+            name: first.name().to_owned(),
+            size: group
+                .modules
+                .iter()
+                .map(|module_id| g[*module_id].size())
+                .sum(),
+            module_type: first.module_type(),
+            is_external: first.is_external(),
+            side_effect_free: first.side_effect_free(),
+            layer: first.layer().map(str::to_owned),
+        });
+    }
+    let super_node = |module_id: ModuleId| ModuleId::new(super_of[module_id.index()].unwrap());
+    let mut kept = HashSet::new();
+    for edge in g.edge_references() {
+        let (Some(importer), Some(importee)) = (
+            super_of[edge.source().index()],
+            super_of[edge.target().index()],
+        ) else {
+            continue;
+        };
+        let dependency = edge.weight();
+        if importer == importee
+            || !kept.insert((
+                importer,
+                importee,
+                dependency.is_async,
+                dependency.is_worker,
+            ))
+        {
+            continue;
+        }
+        coarse.add_edge(
+            ModuleId::new(importer),
+            ModuleId::new(importee),
+            Dependency {
+                used_reexports: None,
+                condition: None,
+                ..dependency.clone()
+            },
+        );
+    }
+    let entries = entries
+        .iter()
+        .map(|entry| Entry {
+            module: super_node(entry.module),
+            prelude: entry.prelude.iter().copied().map(super_node).collect(),
+            ..entry.clone()
+        })
+        .collect();
+    (coarse, entries)
+}
+
+/// `chunk_graph` of the coarse graph with the modules of `groups`. Super-nodes past
+/// those of `groups`, which the algorithm added, become the modules after the
+/// `module_count` of the original graph.
+fn expand(mut chunk_graph: ChunkGraph, groups: &[Group], module_count: usize) -> ChunkGraph {
+    let modules = |super_node: &ModuleId| -> Vec<ModuleId> {
+        match groups.get(super_node.index()) {
+            Some(group) => group.modules.clone(),
+            None => vec![ModuleId::new(
+                module_count + super_node.index() - groups.len(),
+            )],
+        }
+    };
+    for chunk in chunk_graph.graph.node_weights_mut() {
+        chunk.modules = chunk.modules.iter().flat_map(modules).collect();
+        for concat_group in &mut chunk.concat_groups {
+            *concat_group = concat_group.iter().flat_map(modules).collect();
+        }
+    }
+    // Chunk roots and entries are super-nodes of one module.
+    let first = |super_node: &ModuleId| modules(super_node)[0];
+    chunk_graph.chunk_roots = chunk_graph
+        .chunk_roots
+        .iter()
+        .map(|(root, chunks)| (first(root), *chunks))
+        .collect();
+    chunk_graph.depends_on = chunk_graph
+        .depends_on
+        .iter()
+        .map(|(depended_on, entry)| (first(depended_on), first(entry)))
+        .collect();
+    chunk_graph
+}
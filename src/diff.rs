@@ -0,0 +1,346 @@
+//! Structural differences between two module graphs, e.g. from two builds, and between
+//! the chunks two builds split them into.
+
+use crate::ids::AssignedId;
+use crate::module_graph::{ModuleGraph, ModuleId, Symbol};
+use crate::output::{Stats, StatsChunk};
+use petgraph::visit::EdgeRef;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizeChange {
+    pub module: Symbol,
+    pub old_size: usize,
+    pub new_size: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EdgeChange {
+    pub importer: Symbol,
+    pub importee: Symbol,
+    /// Whether the import is async in the graph it exists in. For edges whose async
+    /// flag flipped, this is the flag in the new graph.
+    pub is_async: bool,
+}
+
+/// How `new` differs from `old`, by module name since module ids of two graphs don't
+/// correspond. Modules sharing a name, which [`lint`] warns about, are compared as one
+/// module. Every list is sorted by module name.
+///
+/// [`lint`]: crate::lint
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModuleGraphDiff {
+    pub added_modules: Vec<Symbol>,
+    pub removed_modules: Vec<Symbol>,
+    pub changed_sizes: Vec<SizeChange>,
+    pub added_edges: Vec<EdgeChange>,
+    pub removed_edges: Vec<EdgeChange>,
+    /// Imports present in both graphs that switched between sync and async.
+    pub async_changes: Vec<EdgeChange>,
+}
+
+impl ModuleGraphDiff {
+    pub fn is_empty(&self) -> bool {
+        *self == ModuleGraphDiff::default()
+    }
+}
+
+pub fn diff_module_graphs(old: &ModuleGraph, new: &ModuleGraph) -> ModuleGraphDiff {
+    let mut diff = ModuleGraphDiff::default();
+    let old_by_name = modules_by_name(old);
+    let new_by_name = modules_by_name(new);
+
+    for module in new.node_weights() {
+        let old_size = match old_by_name.get(&*module.name) {
+            Some(old_id) => old[*old_id].size,
+            None => {
+                diff.added_modules.push(module.name.clone());
+                continue;
+            }
+        };
+        if old_size != module.size {
+            diff.changed_sizes.push(SizeChange {
+                module: module.name.clone(),
+                old_size,
+                new_size: module.size,
+            });
+        }
+    }
+    diff.removed_modules = old
+        .node_weights()
+        .map(|module| module.name.clone())
+        .filter(|name| !new_by_name.contains_key(&**name))
+        .collect();
+
+    let old_imports = imports_by_name(old);
+    let new_imports = imports_by_name(new);
+    for (&(importer, importee), &is_async) in &new_imports {
+        let change = EdgeChange {
+            importer: importer.clone(),
+            importee: importee.clone(),
+            is_async,
+        };
+        match old_imports.get(&(importer, importee)) {
+            None => diff.added_edges.push(change),
+            Some(&is_old_async) if is_old_async != is_async => diff.async_changes.push(change),
+            Some(_) => {}
+        }
+    }
+    for (&(importer, importee), &is_async) in &old_imports {
+        if !new_imports.contains_key(&(importer, importee)) {
+            diff.removed_edges.push(EdgeChange {
+                importer: importer.clone(),
+                importee: importee.clone(),
+                is_async,
+            });
+        }
+    }
+
+    diff.added_modules.sort_unstable();
+    diff.removed_modules.sort_unstable();
+    diff.changed_sizes.sort_by(|a, b| a.module.cmp(&b.module));
+    for edges in [
+        &mut diff.added_edges,
+        &mut diff.removed_edges,
+        &mut diff.async_changes,
+    ] {
+        edges.sort_by(|a, b| (&a.importer, &a.importee).cmp(&(&b.importer, &b.importee)));
+    }
+    diff
+}
+
+fn modules_by_name(g: &ModuleGraph) -> HashMap<&str, ModuleId> {
+    g.node_indices()
+        .map(|module_id| (&*g[module_id].name, module_id))
+        .collect()
+}
+
+/// Whether the imports between each pair of module names are async, merged like
+/// `add_dependency` would.
+fn imports_by_name(g: &ModuleGraph) -> BTreeMap<(&Symbol, &Symbol), bool> {
+    let mut imports: BTreeMap<(&Symbol, &Symbol), bool> = BTreeMap::new();
+    for edge in g.edge_references() {
+        let is_async = edge.weight().is_async;
+        imports
+            .entry((&g[edge.source()].name, &g[edge.target()].name))
+            .and_modify(|merged| *merged = *merged && is_async)
+            .or_insert(is_async);
+    }
+    imports
+}
+
+impl fmt::Display for EdgeChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let arrow = if self.is_async { "-->" } else { "->" };
+        write!(f, "{} {} {}", self.importer, arrow, self.importee)
+    }
+}
+
+impl fmt::Display for ModuleGraphDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "no changes");
+        }
+        for module_id in &self.added_modules {
+            writeln!(f, "+ module {}", module_id)?;
+        }
+        for module_id in &self.removed_modules {
+            writeln!(f, "- module {}", module_id)?;
+        }
+        for change in &self.changed_sizes {
+            writeln!(
+                f,
+                "~ module {} {} -> {} bytes",
+                change.module, change.old_size, change.new_size
+            )?;
+        }
+        for change in &self.added_edges {
+            writeln!(f, "+ import {}", change)?;
+        }
+        for change in &self.removed_edges {
+            writeln!(f, "- import {}", change)?;
+        }
+        for change in &self.async_changes {
+            writeln!(
+                f,
+                "~ import {} is now {}",
+                change,
+                if change.is_async { "async" } else { "sync" }
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// How a chunk present in both builds changed. Chunks, modules and parents are named
+/// like in [`ChunkGraphDiff`], and every list is sorted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChunkChange {
+    pub chunk: String,
+    pub old_size: usize,
+    pub new_size: usize,
+    pub added_modules: Vec<String>,
+    pub removed_modules: Vec<String>,
+    /// Roots of the chunk groups that load the chunk now but didn't before.
+    pub added_parents: Vec<String>,
+    pub removed_parents: Vec<String>,
+    /// Whether the content hash changed, so browsers that cached the chunk fetch it
+    /// again. A chunk can change its hash without changing its modules, e.g. the chunk
+    /// holding the runtime when a chunk it loads on demand changes.
+    pub hash_changed: bool,
+}
+
+impl ChunkChange {
+    /// The change in bytes, negative if the chunk shrank.
+    pub fn size_delta(&self) -> i64 {
+        self.new_size as i64 - self.old_size as i64
+    }
+}
+
+/// How the chunks of the stats document `new` differ from those of `old`, see
+/// [`diff_chunk_graphs`]. Chunks are told apart by their file names, since chunk ids
+/// may differ between builds and the JS and CSS chunks of a root share a name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChunkGraphDiff {
+    pub added_chunks: Vec<String>,
+    pub removed_chunks: Vec<String>,
+    /// Chunks of both builds whose modules, size, parents or hash differ, by file name.
+    pub changed_chunks: Vec<ChunkChange>,
+}
+
+impl ChunkGraphDiff {
+    pub fn is_empty(&self) -> bool {
+        *self == ChunkGraphDiff::default()
+    }
+}
+
+/// The chunks `new` added, removed and changed compared to `old`, for two stats
+/// documents written by [`crate::output::to_json`], e.g. before and after upgrading a
+/// dependency.
+pub fn diff_chunk_graphs(old: &Stats, new: &Stats) -> ChunkGraphDiff {
+    let old_chunks = chunks_by_file(old);
+    let new_chunks = chunks_by_file(new);
+    let old_files = files_by_id(old);
+    let new_files = files_by_id(new);
+
+    let mut diff = ChunkGraphDiff {
+        added_chunks: new_chunks
+            .keys()
+            .filter(|file| !old_chunks.contains_key(*file))
+            .map(|file| file.to_string())
+            .collect(),
+        removed_chunks: old_chunks
+            .keys()
+            .filter(|file| !new_chunks.contains_key(*file))
+            .map(|file| file.to_string())
+            .collect(),
+        ..Default::default()
+    };
+    for (file, new_chunk) in &new_chunks {
+        let Some(old_chunk) = old_chunks.get(file) else {
+            continue;
+        };
+        let modules = |chunk: &StatsChunk| -> BTreeSet<String> {
+            chunk
+                .modules
+                .iter()
+                .map(|module| module.name.clone())
+                .collect()
+        };
+        let parents = |chunk: &StatsChunk, files: &HashMap<&AssignedId, &str>| {
+            chunk
+                .parents
+                .iter()
+                .filter_map(|parent| files.get(parent).map(|file| file.to_string()))
+                .collect::<BTreeSet<String>>()
+        };
+        let (old_modules, new_modules) = (modules(old_chunk), modules(new_chunk));
+        let (old_parents, new_parents) = (
+            parents(old_chunk, &old_files),
+            parents(new_chunk, &new_files),
+        );
+        let change = ChunkChange {
+            chunk: file.to_string(),
+            old_size: old_chunk.size,
+            new_size: new_chunk.size,
+            added_modules: new_modules.difference(&old_modules).cloned().collect(),
+            removed_modules: old_modules.difference(&new_modules).cloned().collect(),
+            added_parents: new_parents.difference(&old_parents).cloned().collect(),
+            removed_parents: old_parents.difference(&new_parents).cloned().collect(),
+            hash_changed: old_chunk.hash != new_chunk.hash,
+        };
+        if change.old_size != change.new_size
+            || change.hash_changed
+            || !change.added_modules.is_empty()
+            || !change.removed_modules.is_empty()
+            || !change.added_parents.is_empty()
+            || !change.removed_parents.is_empty()
+        {
+            diff.changed_chunks.push(change);
+        }
+    }
+    diff
+}
+
+fn chunks_by_file(stats: &Stats) -> BTreeMap<&str, &StatsChunk> {
+    stats
+        .chunks
+        .iter()
+        .filter_map(|chunk| Some((chunk.files.first()?.as_str(), chunk)))
+        .collect()
+}
+
+fn files_by_id(stats: &Stats) -> HashMap<&AssignedId, &str> {
+    stats
+        .chunks
+        .iter()
+        .filter_map(|chunk| Some((&chunk.id, chunk.files.first()?.as_str())))
+        .collect()
+}
+
+impl fmt::Display for ChunkGraphDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "no changes");
+        }
+        for chunk in &self.added_chunks {
+            writeln!(f, "+ chunk {}", chunk)?;
+        }
+        for chunk in &self.removed_chunks {
+            writeln!(f, "- chunk {}", chunk)?;
+        }
+        for change in &self.changed_chunks {
+            write!(f, "~ chunk {}", change.chunk)?;
+            if change.old_size != change.new_size {
+                write!(
+                    f,
+                    " {} -> {} bytes ({:+})",
+                    change.old_size,
+                    change.new_size,
+                    change.size_delta()
+                )?;
+            }
+            if change.hash_changed {
+                write!(f, ", new hash")?;
+            }
+            writeln!(f)?;
+            for module in &change.added_modules {
+                writeln!(f, "  + module {}", module)?;
+            }
+            for module in &change.removed_modules {
+                writeln!(f, "  - module {}", module)?;
+            }
+            for parent in &change.added_parents {
+                writeln!(f, "  + parent {}", parent)?;
+            }
+            for parent in &change.removed_parents {
+                writeln!(f, "  - parent {}", parent)?;
+            }
+        }
+        Ok(())
+    }
+}
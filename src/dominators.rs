@@ -0,0 +1,160 @@
+//! Step 2 from a dominator tree instead of a search from every chunk root.
+//!
+//! The search of [`analysis::reachable_from_roots`] visits the part of the graph below
+//! each root once per root, which adds up on large graphs with many async imports. A
+//! module whose every path from the chunk roots runs through the same module, its
+//! immediate dominator, is reachable from exactly the roots its dominator is
+//! reachable from. Only modules right below a point where the paths of several roots
+//! join are computed from their importers, so the whole graph is walked once.
+
+use crate::analysis::{self, AnalysisState};
+use crate::cache::AnalysisCache;
+use crate::chunk_graph::ChunkId;
+use crate::entries::Entry;
+use crate::error::SplitChunksError;
+use crate::module_graph::{ModuleGraph, ModuleId, ModuleInfo};
+use crate::options::SplitOptions;
+use crate::scc::Condensation;
+use crate::trace::DecisionTrace;
+use fixedbitset::FixedBitSet;
+use petgraph::algo::dominators::simple_fast;
+use petgraph::prelude::{DiGraph, Incoming, NodeIndex};
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+/// Step 2 for every chunk root, with the same result as
+/// [`analysis::reachable_from_roots`].
+///
+/// Runs on the components of `condensation`. A component holding chunk roots is both
+/// where the searches of those roots start and where the searches of other roots stop,
+/// so it gets two nodes in the graph the dominators are computed on: one its own roots
+/// start from, and one the imports from other components lead to, which is a dead end.
+/// A virtual node leads to every start node, which makes it the one root the dominator
+/// tree needs.
+pub fn reachable_from_roots(
+    condensation: &Condensation,
+    chunk_roots: &BTreeMap<ModuleId, (ChunkId, ChunkId)>,
+) -> BTreeMap<ModuleId, FixedBitSet> {
+    let roots: Vec<ModuleId> = chunk_roots.keys().copied().collect();
+    let component_count = condensation.components.len();
+
+    // The roots of each component, as root numbers.
+    let mut own_roots = vec![FixedBitSet::with_capacity(roots.len()); component_count];
+    for (number, root) in roots.iter().enumerate() {
+        own_roots[condensation.component_of[root.index()]].insert(number);
+    }
+    let own_roots: Vec<Rc<FixedBitSet>> = own_roots.into_iter().map(Rc::new).collect();
+
+    // Node `c` is component `c`, the start node of components with roots. The dead-end
+    // node of a component with roots follows, and the virtual root comes last.
+    let mut graph = DiGraph::<(), ()>::with_capacity(component_count * 2 + 1, 0);
+    for _ in 0..component_count {
+        graph.add_node(());
+    }
+    let dead_end: Vec<Option<NodeIndex>> = own_roots
+        .iter()
+        .map(|own| (!own.is_clear()).then(|| graph.add_node(())))
+        .collect();
+    let virtual_root = graph.add_node(());
+    for (component, (own, successors)) in own_roots.iter().zip(&condensation.successors).enumerate()
+    {
+        if !own.is_clear() {
+            graph.add_edge(virtual_root, NodeIndex::new(component), ());
+        }
+        for next in successors {
+            let target = dead_end[*next].unwrap_or(NodeIndex::new(*next));
+            graph.add_edge(NodeIndex::new(component), target, ());
+        }
+    }
+    let dominators = simple_fast(&graph, virtual_root);
+
+    // The roots whose search reaches each node, shared with the node's immediate
+    // dominator where it has one other than the virtual root.
+    let mut reaching: Vec<Option<Rc<FixedBitSet>>> = vec![None; graph.node_count()];
+    // The roots whose search continues from a node: the roots of a component for its
+    // start node, nobody for a dead end, and the roots reaching any other node.
+    let continuing = |reaching: &[Option<Rc<FixedBitSet>>], node: NodeIndex| {
+        let component = node.index();
+        if component < component_count && !own_roots[component].is_clear() {
+            Some(own_roots[component].clone())
+        } else {
+            reaching[node.index()].clone()
+        }
+    };
+    // Tarjan's algorithm lists the components in reverse topological order, so every
+    // node comes after all nodes leading to it.
+    for component in (0..component_count).rev() {
+        let nodes = dead_end[component].into_iter().chain(
+            own_roots[component]
+                .is_clear()
+                .then(|| NodeIndex::new(component)),
+        );
+        for node in nodes {
+            reaching[node.index()] = match dominators.immediate_dominator(node) {
+                None => None,
+                Some(dominator) if dominator != virtual_root => continuing(&reaching, dominator),
+                Some(_) => {
+                    let mut union = FixedBitSet::with_capacity(roots.len());
+                    for importer in graph.neighbors_directed(node, Incoming) {
+                        if let Some(reaching) = continuing(&reaching, importer) {
+                            union.union_with(&reaching);
+                        }
+                    }
+                    Some(Rc::new(union))
+                }
+            };
+        }
+    }
+
+    let mut reachable_modules: Vec<FixedBitSet> =
+        vec![FixedBitSet::with_capacity(condensation.component_of.len()); roots.len()];
+    for (component, members) in condensation.components.iter().enumerate() {
+        // A root's own component is reached by its search, except for the root itself.
+        for number in own_roots[component].ones() {
+            for module_id in members {
+                if *module_id != roots[number] {
+                    reachable_modules[number].insert(module_id.index());
+                }
+            }
+        }
+        // Other roots reach only the roots of a component with roots, the rest of it is
+        // theirs.
+        let (node, reached): (_, Vec<usize>) = match dead_end[component] {
+            Some(dead_end) => (
+                dead_end,
+                own_roots[component]
+                    .ones()
+                    .map(|number| roots[number].index())
+                    .collect(),
+            ),
+            None => (
+                NodeIndex::new(component),
+                members.iter().map(|module_id| module_id.index()).collect(),
+            ),
+        };
+        for number in reaching[node.index()]
+            .iter()
+            .flat_map(|reaching| reaching.ones())
+        {
+            reachable_modules[number].extend(reached.iter().copied());
+        }
+    }
+    roots.into_iter().zip(reachable_modules).collect()
+}
+
+/// Runs [`analysis::analyze_with`] with [`reachable_from_roots`] as Step 2.
+pub fn analyze<M: ModuleInfo + Clone>(
+    g: &ModuleGraph<M>,
+    entries: &[Entry],
+    options: &SplitOptions,
+    trace: &mut DecisionTrace,
+) -> Result<AnalysisState<M>, SplitChunksError> {
+    analysis::analyze_with(
+        g,
+        entries,
+        options,
+        &AnalysisCache::default(),
+        trace,
+        |_, condensation, chunk_roots| reachable_from_roots(condensation, chunk_roots),
+    )
+}
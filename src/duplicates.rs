@@ -0,0 +1,239 @@
+//! Modules a page downloads more than once, and the options that would have spared it.
+//!
+//! Step 4 duplicates small shared chunks into every chunk they were split from, and
+//! modules shared by fewer than `min_chunks` chunk roots stay in the chunk of each root.
+//! That costs nothing while the copies end up on different pages, but an entry that
+//! later loads an async chunk holding a copy of a module it already has downloads the
+//! module twice. [`duplicates`] measures those bytes for every entry, and splits the
+//! graph again with lower `min_size` and `min_chunks` to find the closest options that
+//! would have avoided them.
+
+use crate::analysis::{self, AnalysisState};
+use crate::cache::AnalysisCache;
+use crate::chunk_graph::{ChunkGraph, ChunkId, ChunkKind};
+use crate::chunk_groups::{ChunkGroupId, ChunkGroups};
+use crate::entries::Entry;
+use crate::error::SplitChunksError;
+use crate::module_graph::{ModuleGraph, ModuleId, ModuleInfo};
+use crate::options::SplitOptions;
+use crate::passes;
+use crate::trace::DecisionTrace;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// What [`duplicates`] found.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicationReport {
+    /// Every entry, in id order, with the modules it may download more than once.
+    pub pages: Vec<PageDuplication>,
+    /// The bytes of all pages together.
+    pub wasted_bytes: usize,
+    /// The options closest to those given that download no module twice on any page,
+    /// `None` if no module is or if lowering `min_size` and `min_chunks` doesn't help.
+    pub suggestion: Option<Suggestion>,
+}
+
+/// The modules of the chunks an entry may load on its page that several of them hold:
+/// the chunks of its own chunk group, of the entries it depends on and of every async
+/// import it leads to. Workers run apart from the page, so their chunks don't count.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PageDuplication {
+    /// Name of the entry chunk.
+    pub entry: String,
+    /// Bytes of the copies beyond the first.
+    pub wasted_bytes: usize,
+    /// The duplicated modules, by wasted bytes, most first.
+    pub modules: Vec<DuplicatedModule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicatedModule {
+    pub name: String,
+    pub size: usize,
+    /// Names of the chunks of the page holding the module.
+    pub chunks: Vec<String>,
+}
+
+impl DuplicatedModule {
+    pub fn wasted_bytes(&self) -> usize {
+        self.size * (self.chunks.len() - 1)
+    }
+}
+
+/// Options [`duplicates`] found to download no module twice on any page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Suggestion {
+    pub min_size: usize,
+    pub min_chunks: usize,
+}
+
+/// The modules each page of `chunk_graph` downloads more than once, whose modules are
+/// those of `g`.
+pub fn page_duplication<M: ModuleInfo>(
+    chunk_graph: &ChunkGraph,
+    g: &ModuleGraph<M>,
+) -> Vec<PageDuplication> {
+    let chunk_groups = ChunkGroups::new(chunk_graph);
+    chunk_groups
+        .entry_groups()
+        .map(|(group_id, group)| {
+            let mut chunks_of_module: BTreeMap<ModuleId, Vec<String>> = BTreeMap::new();
+            for chunk_id in page_chunks(&chunk_groups, group_id) {
+                let chunk = &chunk_graph.graph[chunk_id];
+                for module_id in &chunk.modules {
+                    chunks_of_module
+                        .entry(*module_id)
+                        .or_default()
+                        .push(chunk.name.clone());
+                }
+            }
+            let mut modules: Vec<DuplicatedModule> = chunks_of_module
+                .into_iter()
+                .filter(|(_, chunks)| chunks.len() > 1)
+                .map(|(module_id, chunks)| DuplicatedModule {
+                    name: g[module_id].name().to_owned(),
+                    size: g[module_id].size(),
+                    chunks,
+                })
+                .collect();
+            modules.sort_by(|a, b| {
+                b.wasted_bytes()
+                    .cmp(&a.wasted_bytes())
+                    .then_with(|| a.name.cmp(&b.name))
+            });
+            PageDuplication {
+                entry: chunk_graph.graph[group.root].name.clone(),
+                wasted_bytes: modules.iter().map(DuplicatedModule::wasted_bytes).sum(),
+                modules,
+            }
+        })
+        .collect()
+}
+
+/// The chunks the page of the entry group `entry` may load, in id order.
+fn page_chunks(chunk_groups: &ChunkGroups, entry: ChunkGroupId) -> BTreeSet<ChunkId> {
+    let mut chunks: BTreeSet<ChunkId> = chunk_groups.initial_chunks(entry).into_iter().collect();
+    let mut visited = BTreeSet::from([entry]);
+    let mut stack = vec![entry];
+    while let Some(group_id) = stack.pop() {
+        for child_id in chunk_groups.children(group_id) {
+            let child = chunk_groups.group(child_id);
+            if child.kind != ChunkKind::Worker && visited.insert(child_id) {
+                chunks.extend(&child.chunks);
+                stack.push(child_id);
+            }
+        }
+    }
+    chunks
+}
+
+/// Splits `g` like [`crate::split_chunks`] and reports the modules each page downloads
+/// more than once. If any does, tries every `min_chunks` from the given one down to 1,
+/// and for each every `min_size` from the given one down to 0 at which Step 4 keeps
+/// another small chunk, and suggests the first that downloads no module twice.
+pub fn duplicates<M: ModuleInfo + Clone>(
+    g: &ModuleGraph<M>,
+    entries: &[Entry],
+    options: &SplitOptions,
+) -> Result<DuplicationReport, SplitChunksError> {
+    let cache = AnalysisCache::default();
+    let analyze = |options: &SplitOptions| {
+        analysis::analyze(g, entries, options, &cache, &mut DecisionTrace::new(false))
+    };
+    let finish = |state: &AnalysisState<M>, options: &SplitOptions| {
+        let mut state = state.clone();
+        passes::optimize(&mut state, options, &mut DecisionTrace::new(false));
+        state
+    };
+    let wasted_bytes = |state: &AnalysisState<M>| {
+        page_duplication(&state.chunk_graph, &state.module_graph)
+            .iter()
+            .map(|page| page.wasted_bytes)
+            .sum::<usize>()
+    };
+
+    let state = finish(&analyze(options)?, options);
+    let pages = page_duplication(&state.chunk_graph, &state.module_graph);
+    let report_wasted_bytes = pages.iter().map(|page| page.wasted_bytes).sum();
+    let mut suggestion = None;
+    if report_wasted_bytes > 0 {
+        'search: for min_chunks in (1..=options.min_chunks).rev() {
+            let options = SplitOptions {
+                min_chunks,
+                ..options.clone()
+            };
+            let assigned = analyze(&options)?;
+            for min_size in min_size_candidates(&assigned, options.min_size) {
+                let options = SplitOptions {
+                    min_size,
+                    ..options.clone()
+                };
+                if wasted_bytes(&finish(&assigned, &options)) == 0 {
+                    suggestion = Some(Suggestion {
+                        min_size,
+                        min_chunks,
+                    });
+                    break 'search;
+                }
+            }
+        }
+    }
+    Ok(DuplicationReport {
+        pages,
+        wasted_bytes: report_wasted_bytes,
+        suggestion,
+    })
+}
+
+/// `min_size` and, largest first, the minimum sizes below it at which Step 4 keeps
+/// another shared chunk of `state`, which is after Step 3.
+fn min_size_candidates<M: ModuleInfo + Clone>(
+    state: &AnalysisState<M>,
+    min_size: usize,
+) -> Vec<usize> {
+    let mut pruned = state.clone();
+    passes::prune_available_modules(&mut pruned, &mut DecisionTrace::new(false));
+    let candidates: BTreeSet<usize> = pruned
+        .chunk_graph
+        .chunks()
+        .filter(|(_, chunk)| !chunk.source_bundles.is_empty() && chunk.size < min_size)
+        .map(|(_, chunk)| chunk.size)
+        .chain([min_size])
+        .collect();
+    candidates.into_iter().rev().collect()
+}
+
+impl fmt::Display for DuplicationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} bytes downloaded more than once", self.wasted_bytes)?;
+        for page in self.pages.iter().filter(|page| page.wasted_bytes > 0) {
+            writeln!(f, "\n{} wastes {} bytes", page.entry, page.wasted_bytes)?;
+            for module in &page.modules {
+                writeln!(
+                    f,
+                    "{:>10}  {} ({})",
+                    module.wasted_bytes(),
+                    module.name,
+                    module.chunks.join(", ")
+                )?;
+            }
+        }
+        match self.suggestion {
+            Some(suggestion) => writeln!(
+                f,
+                "\nmin_size {} and min_chunks {} would download every module once",
+                suggestion.min_size, suggestion.min_chunks
+            ),
+            None if self.wasted_bytes > 0 => writeln!(
+                f,
+                "\nno lower min_size or min_chunks downloads every module once"
+            ),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,266 @@
+//! The files a build writes, so a host bundler only has to render the code of each
+//! chunk into the file planned for it.
+//!
+//! File names come from templates like webpack's `output.filename`, with the
+//! placeholders `[name]`, `[id]`, `[contenthash]` (or its first digits, as in
+//! `[contenthash:8]`) and `[ext]`. Entry chunks use [`EmitOptions::entry_file_name`]
+//! and every other chunk [`EmitOptions::chunk_file_name`]. The plan also lists the
+//! files each entry's page has to reference, in the order to inject them into its HTML.
+
+use crate::analysis::AnalysisState;
+use crate::chunk_graph::{ChunkId, ChunkKind};
+use crate::chunk_groups::ChunkGroups;
+use crate::hashing::{self, ModuleHasher};
+use crate::html;
+use crate::ids::{self, AssignedId, ChunkIds};
+use crate::module_graph::{ModuleInfo, ModuleType};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// A file name template, see the [module docs](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileNameTemplate {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Name,
+    Id,
+    /// The first that many hex digits of the content hash, all 16 for `None`.
+    ContentHash(Option<usize>),
+    Ext,
+}
+
+impl FromStr for FileNameTemplate {
+    type Err = EmitError;
+
+    fn from_str(template: &str) -> Result<Self, EmitError> {
+        let mut parts = vec![];
+        let mut rest = template;
+        while let Some(start) = rest.find('[') {
+            let end = rest[start..]
+                .find(']')
+                .map(|end| start + end)
+                .ok_or_else(|| EmitError::UnknownPlaceholder(rest[start..].to_owned()))?;
+            if start > 0 {
+                parts.push(Part::Text(rest[..start].to_owned()));
+            }
+            let placeholder = &rest[start + 1..end];
+            parts.push(match placeholder.split_once(':') {
+                None if placeholder == "name" => Part::Name,
+                None if placeholder == "id" => Part::Id,
+                None if placeholder == "contenthash" => Part::ContentHash(None),
+                None if placeholder == "ext" => Part::Ext,
+                Some(("contenthash", digits)) => match digits.parse() {
+                    Ok(digits @ 1..=16) => Part::ContentHash(Some(digits)),
+                    _ => return Err(EmitError::UnknownPlaceholder(rest[start..=end].to_owned())),
+                },
+                _ => return Err(EmitError::UnknownPlaceholder(rest[start..=end].to_owned())),
+            });
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.to_owned()));
+        }
+        Ok(FileNameTemplate { parts })
+    }
+}
+
+impl FileNameTemplate {
+    /// The file name of a chunk named `name` with `id`, `content_hash` and the file
+    /// extension `ext`. A dot right before an empty `[ext]` is left out, so asset chunks
+    /// of files without extension get none.
+    pub fn render(&self, name: &str, id: &AssignedId, content_hash: &str, ext: &str) -> String {
+        let mut file_name = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => file_name.push_str(text),
+                Part::Name => file_name.push_str(name),
+                Part::Id => file_name.push_str(&id.to_string()),
+                Part::ContentHash(digits) => {
+                    file_name.push_str(&content_hash[..digits.unwrap_or(content_hash.len())])
+                }
+                Part::Ext if ext.is_empty() => {
+                    if file_name.ends_with('.') {
+                        file_name.pop();
+                    }
+                }
+                Part::Ext => file_name.push_str(ext),
+            }
+        }
+        file_name
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EmitOptions {
+    /// Template of the file names of entry chunks, like webpack's `output.filename`.
+    pub entry_file_name: FileNameTemplate,
+    /// Template of the file names of every other chunk, like `output.chunkFilename`.
+    pub chunk_file_name: FileNameTemplate,
+    /// How the `[id]` of every chunk is picked.
+    pub chunk_ids: ChunkIds,
+}
+
+impl Default for EmitOptions {
+    fn default() -> Self {
+        EmitOptions {
+            entry_file_name: "[name].[contenthash:8].[ext]".parse().unwrap(),
+            chunk_file_name: "[name].[contenthash:8].[ext]".parse().unwrap(),
+            chunk_ids: ChunkIds::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EmitError {
+    /// A template holds a placeholder other than those of the [module docs](self), or
+    /// a `[` without `]`.
+    UnknownPlaceholder(String),
+    /// The templates give two chunks the same file name.
+    SameFileName {
+        file_name: String,
+        chunks: [String; 2],
+    },
+}
+
+impl fmt::Display for EmitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmitError::UnknownPlaceholder(placeholder) => {
+                write!(f, "unknown file name placeholder {}", placeholder)
+            }
+            EmitError::SameFileName { file_name, chunks } => write!(
+                f,
+                "chunks {} and {} would both be written to {}",
+                chunks[0], chunks[1], file_name
+            ),
+        }
+    }
+}
+
+impl Error for EmitError {}
+
+/// What [`emit_plan`] returns.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmitPlan {
+    /// A file for every chunk, in chunk id order.
+    pub files: Vec<PlannedFile>,
+    /// The pages of the entries, in the order of their entry chunks.
+    pub entries: Vec<EntryPage>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedFile {
+    pub file_name: String,
+    pub chunk: String,
+    pub id: AssignedId,
+    pub kind: ChunkKind,
+    pub module_type: ModuleType,
+    /// The chunk's content hash, see [`hashing::chunk_hashes`].
+    pub content_hash: String,
+    /// Names of the modules to render into the file, in the order they run.
+    pub modules: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryPage {
+    /// Name of the entry chunk.
+    pub entry: String,
+    /// The files the page references, in the order to inject them: stylesheets first,
+    /// then scripts, each in load order. Wasm and asset files are fetched by the
+    /// scripts.
+    pub files: Vec<String>,
+    /// The tags loading them, with preload hints for likely async imports, see
+    /// [`html::entry_html`].
+    pub html: String,
+}
+
+/// The files of `state` after every pass has run, named by `options`, with content
+/// hashes of the modules `hasher` hashes. Fails if two chunks would get the same file.
+pub fn emit_plan<M: ModuleInfo>(
+    state: &AnalysisState<M>,
+    options: &EmitOptions,
+    hasher: &dyn ModuleHasher,
+) -> Result<EmitPlan, EmitError> {
+    let g = &state.module_graph;
+    let chunk_graph = &state.chunk_graph;
+    let ids = ids::assign(chunk_graph, options.chunk_ids);
+    let hashes = hashing::chunk_hashes(chunk_graph, g, hasher);
+
+    let mut file_names: BTreeMap<ChunkId, String> = BTreeMap::new();
+    let mut chunk_of_file: BTreeMap<String, &str> = BTreeMap::new();
+    let mut files = vec![];
+    for (chunk_id, chunk) in chunk_graph.chunks() {
+        let (name, ext) = match chunk.module_type.extension() {
+            Some(ext) => (chunk.name.as_str(), ext),
+            // Asset chunks are named after their file, extension included.
+            None => chunk
+                .name
+                .rsplit_once('.')
+                .filter(|(_, ext)| !ext.contains('/'))
+                .unwrap_or((&chunk.name, "")),
+        };
+        let template = if chunk.kind == ChunkKind::Entry {
+            &options.entry_file_name
+        } else {
+            &options.chunk_file_name
+        };
+        let content_hash = hashing::to_hex(hashes[&chunk_id]);
+        let file_name = template.render(name, &ids[&chunk_id], &content_hash, ext);
+        if let Some(other) = chunk_of_file.insert(file_name.clone(), &chunk.name) {
+            return Err(EmitError::SameFileName {
+                file_name,
+                chunks: [other.to_owned(), chunk.name.clone()],
+            });
+        }
+        file_names.insert(chunk_id, file_name.clone());
+        files.push(PlannedFile {
+            file_name,
+            chunk: chunk.name.clone(),
+            id: ids[&chunk_id].clone(),
+            kind: chunk.kind,
+            module_type: chunk.module_type,
+            content_hash,
+            modules: chunk
+                .modules
+                .iter()
+                .map(|module_id| g[*module_id].name().to_owned())
+                .collect(),
+        });
+    }
+
+    let chunk_groups = ChunkGroups::new(chunk_graph);
+    let hints = html::import_hints(state);
+    let file_name = |chunk_id: ChunkId| file_names[&chunk_id].clone();
+    let entries = chunk_groups
+        .entry_groups()
+        .map(|(group_id, group)| {
+            let initial = chunk_groups.initial_chunks(group_id);
+            let of_type = |module_type: ModuleType| {
+                initial
+                    .iter()
+                    .filter(move |chunk_id| {
+                        chunk_graph.graph[**chunk_id].module_type == module_type
+                    })
+                    .map(|chunk_id| file_name(*chunk_id))
+            };
+            EntryPage {
+                entry: chunk_graph.graph[group.root].name.clone(),
+                files: of_type(ModuleType::Css)
+                    .chain(of_type(ModuleType::Js))
+                    .collect(),
+                html: html::entry_html_with(chunk_graph, &hints, group.root, &file_name),
+            }
+        })
+        .collect();
+    Ok(EmitPlan { files, entries })
+}
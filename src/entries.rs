@@ -0,0 +1,102 @@
+//! Entry descriptors, like the objects of webpack's `entry` option.
+//!
+//! An entry is named after its entry chunk, the entry module's name without extension.
+//! An entry that depends on other entries is only ever loaded after them, so it reuses
+//! their chunks instead of duplicating or sharing the modules they already provide.
+//!
+//! An entry can run further modules before its entry module, like the leading modules
+//! of an array in webpack's `entry`. They are placed as if the entry module imported
+//! them, so they end up in the entry chunk unless other chunk groups load them too.
+
+use crate::barrels::add_dependency;
+use crate::chunk_graph::module_stem;
+use crate::error::SplitChunksError;
+use crate::module_graph::{Dependency, ModuleGraph, ModuleId, ModuleInfo};
+use std::collections::{BTreeSet, HashMap};
+
+/// The name of an entry, which is also the name of its entry chunk.
+pub type EntryName = String;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub module: ModuleId,
+    /// Modules that run before `module`, in order, like `polyfill.js` in
+    /// `entry: ['polyfill.js', 'index.js']`.
+    pub prelude: Vec<ModuleId>,
+    /// Entries loaded before this one, like webpack's `dependOn`.
+    pub depends_on: Vec<EntryName>,
+    /// Keeps every module the entry chunk would hold in it, copying the modules other
+    /// chunk roots reach too instead of sharing them, e.g. for a widget embedded in
+    /// pages that run none of the other entries.
+    pub isolated: bool,
+}
+
+impl From<ModuleId> for Entry {
+    fn from(module: ModuleId) -> Self {
+        Entry {
+            module,
+            prelude: vec![],
+            depends_on: vec![],
+            isolated: false,
+        }
+    }
+}
+
+/// The entry modules of `entries`.
+pub fn modules(entries: &[Entry]) -> Vec<ModuleId> {
+    entries.iter().map(|entry| entry.module).collect()
+}
+
+/// The entry modules of the [`Entry::isolated`] entries of `entries`.
+pub fn isolated(entries: &[Entry]) -> BTreeSet<ModuleId> {
+    entries
+        .iter()
+        .filter(|entry| entry.isolated)
+        .map(|entry| entry.module)
+        .collect()
+}
+
+/// Adds an import from every entry module of `entries` to the modules of its prelude,
+/// so Step 1 finds them in the entry chunk.
+pub fn import_preludes<M>(g: &mut ModuleGraph<M>, entries: &[Entry]) {
+    for entry in entries {
+        for module_id in entry.prelude.iter().filter(|id| **id != entry.module) {
+            add_dependency(g, entry.module, *module_id, Dependency::default());
+        }
+    }
+}
+
+/// `(depended-on entry, depending entry)` pairs, including the entries depended on
+/// through other entries.
+///
+/// Fails if an entry depends on a name that is not one of `entries`, or if the
+/// dependencies form a cycle.
+pub fn depends_on<M: ModuleInfo>(
+    g: &ModuleGraph<M>,
+    entries: &[Entry],
+) -> Result<BTreeSet<(ModuleId, ModuleId)>, SplitChunksError> {
+    let name_of = |module_id: ModuleId| module_stem(&g[module_id]);
+    let by_name: HashMap<&str, &Entry> = entries
+        .iter()
+        .map(|entry| (name_of(entry.module), entry))
+        .collect();
+    let mut pairs = BTreeSet::new();
+    for entry in entries {
+        let mut stack: Vec<&str> = entry.depends_on.iter().map(String::as_str).collect();
+        while let Some(name) = stack.pop() {
+            let dependency = by_name
+                .get(name)
+                .ok_or_else(|| SplitChunksError::UnknownEntry {
+                    entry: name_of(entry.module).to_owned(),
+                    dependency: name.to_owned(),
+                })?;
+            if dependency.module == entry.module {
+                return Err(SplitChunksError::CyclicEntry(name.to_owned()));
+            }
+            if pairs.insert((dependency.module, entry.module)) {
+                stack.extend(dependency.depends_on.iter().map(String::as_str));
+            }
+        }
+    }
+    Ok(pairs)
+}
@@ -0,0 +1,71 @@
+//! Errors for inputs the algorithm can't split: entries and imports that refer to
+//! modules outside the graph, and entries that depend on each other in a cycle.
+
+use crate::module_graph::ModuleId;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SplitChunksError {
+    /// An entry, or a module passed to an edit, is not a module of the graph.
+    UnknownModule(ModuleId),
+    /// The same module is listed as an entry more than once.
+    DuplicateEntry(String),
+    /// An entry, or a module of its prelude, is an external module, which has no chunk
+    /// to start.
+    ExternalEntry(String),
+    /// An entry depends on a name that is not one of the entries.
+    UnknownEntry { entry: String, dependency: String },
+    /// An entry depends on itself, directly or through other entries.
+    CyclicEntry(String),
+    /// An import lists a used re-export that is not a module of the graph.
+    DanglingReexport {
+        importer: String,
+        importee: String,
+        module: ModuleId,
+    },
+    /// The DFS of Step 1 followed an import that the graph doesn't have.
+    MissingEdge {
+        importer: ModuleId,
+        importee: ModuleId,
+    },
+}
+
+impl fmt::Display for SplitChunksError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SplitChunksError::UnknownModule(module_id) => {
+                write!(f, "module {} is not in the graph", module_id.index())
+            }
+            SplitChunksError::DuplicateEntry(entry) => {
+                write!(f, "entry {} is listed more than once", entry)
+            }
+            SplitChunksError::ExternalEntry(entry) => {
+                write!(f, "entry {} is an external module", entry)
+            }
+            SplitChunksError::UnknownEntry { entry, dependency } => {
+                write!(f, "entry {} depends on unknown entry {}", entry, dependency)
+            }
+            SplitChunksError::CyclicEntry(entry) => write!(f, "entry {} depends on itself", entry),
+            SplitChunksError::DanglingReexport {
+                importer,
+                importee,
+                module,
+            } => write!(
+                f,
+                "import of {} by {} uses re-export {}, which is not in the graph",
+                importee,
+                importer,
+                module.index()
+            ),
+            SplitChunksError::MissingEdge { importer, importee } => write!(
+                f,
+                "no import from module {} to module {}",
+                importer.index(),
+                importee.index()
+            ),
+        }
+    }
+}
+
+impl Error for SplitChunksError {}
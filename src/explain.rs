@@ -0,0 +1,170 @@
+//! Why a module ended up in the chunks it did, for debugging surprising assignments
+//! without reading the whole [`DecisionTrace`].
+//!
+//! [`explain`] runs the algorithm with the trace enabled and collects what concerns one
+//! module: the chunk roots it is reachable from, those Step 3 left out because a parent
+//! chunk group already loads it, the placement rules matching it, what Step 3 did with
+//! it, and the chunks holding it after Step 3, after Step 4 and in the end.
+
+use crate::analysis::{AnalysisState, Assignment, RootIndex};
+use crate::cache::AnalysisCache;
+use crate::entries::Entry;
+use crate::error::SplitChunksError;
+use crate::module_graph::{ModuleGraph, ModuleId, ModuleInfo};
+use crate::options::SplitOptions;
+use crate::pipeline::{Pipeline, Stage};
+use crate::rules::{self, Resolution};
+use crate::trace::{self, Decision, DecisionTrace, FilteredRoot};
+use std::cell::RefCell;
+
+/// What happened to one module, see [`explain`]. Chunks are given by name, since the
+/// passes renumber them; the names are the ones the chunks had at the time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explanation {
+    pub module: ModuleId,
+    /// The chunk roots the module is reachable from, and those Step 3 placed it by.
+    pub assignment: Assignment,
+    /// The roots of `assignment.reachable_from` Step 3 left out, with the parent root
+    /// each one was available from.
+    pub filtered: Vec<FilteredRoot>,
+    /// The manual chunk and cache groups matching the module, see [`rules::resolve`].
+    pub resolution: Resolution,
+    /// What Step 3 did with the module: a [`Decision::ModulePlaced`] for every chunk it
+    /// was placed in, or a [`Decision::ModuleDuplicated`] if it was too little shared.
+    pub placement: Vec<Decision>,
+    /// The chunks holding the module after Step 3.
+    pub placed_in: Vec<String>,
+    /// The chunks holding the module after Step 4 removed the small chunks.
+    pub after_small_chunks: Vec<String>,
+    /// The chunks holding the module in the end.
+    pub chunks: Vec<String>,
+}
+
+impl Explanation {
+    /// Whether Step 4 copied the module into more chunks than Step 3 placed it in,
+    /// because a chunk holding it was too small.
+    pub fn duplicated_by_size_pass(&self) -> bool {
+        self.after_small_chunks.len() > self.placed_in.len()
+    }
+
+    /// The explanation as lines of text, with module ids resolved to names in `g`.
+    pub fn describe<M: ModuleInfo>(&self, g: &ModuleGraph<M>) -> Vec<String> {
+        let name = g[self.module].name();
+        let module_names = |modules: &[ModuleId]| {
+            modules
+                .iter()
+                .map(|module_id| g[*module_id].name())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut lines = vec![];
+        if self.assignment.reachable_from.is_empty() {
+            lines.push(format!("{} is not reachable from any chunk root", name));
+        } else {
+            lines.push(format!(
+                "{} is reachable from {}",
+                name,
+                module_names(&self.assignment.reachable_from)
+            ));
+        }
+        for filtered in &self.filtered {
+            lines.push(format!(
+                "{} left out, since {} already loads {}",
+                g[filtered.root].name(),
+                g[filtered.available_from].name(),
+                name
+            ));
+        }
+        match self.resolution.winner() {
+            Some(winner) => lines.push(format!("claimed by {}", winner)),
+            None => lines.push("no manual chunk or cache group matches it".to_owned()),
+        }
+        for outranked in self.resolution.outranked() {
+            lines.push(format!("outranked: {}", outranked));
+        }
+        for decision in &self.placement {
+            lines.push(decision.describe(g));
+        }
+        lines.push(format!("after Step 3 in {}", self.placed_in.join(", ")));
+        if self.duplicated_by_size_pass() {
+            lines.push(format!(
+                "duplicated by Step 4 into {}",
+                self.after_small_chunks.join(", ")
+            ));
+        } else if self.after_small_chunks != self.placed_in {
+            lines.push(format!(
+                "moved by Step 4 into {}",
+                self.after_small_chunks.join(", ")
+            ));
+        }
+        lines.push(format!("ends up in {}", self.chunks.join(", ")));
+        lines
+    }
+}
+
+/// Splits `g` like [`split_chunks`] and explains where the module named `module_name`
+/// went, `None` if `g` has no module of that name.
+///
+/// [`split_chunks`]: crate::split_chunks
+pub fn explain<M: ModuleInfo + Clone>(
+    g: &ModuleGraph<M>,
+    entries: &[Entry],
+    options: &SplitOptions,
+    module_name: &str,
+) -> Result<Option<Explanation>, SplitChunksError> {
+    let Some(module_id) = g
+        .node_indices()
+        .find(|module_id| g[*module_id].name() == module_name)
+    else {
+        return Ok(None);
+    };
+    let chunk_names = |state: &AnalysisState<M>| -> Vec<String> {
+        state
+            .chunk_graph
+            .chunks_of(module_id)
+            .into_iter()
+            .map(|chunk_id| state.chunk_graph.chunk(chunk_id).name.clone())
+            .collect()
+    };
+    let placed_in = RefCell::new(vec![]);
+    let after_small_chunks = RefCell::new(vec![]);
+    let mut trace = DecisionTrace::new(true);
+    let state = Pipeline::new()
+        .after(Stage::AssignModules, |state, _, _| {
+            *placed_in.borrow_mut() = chunk_names(state);
+        })
+        .after(Stage::PruneSmallChunks, |state, _, _| {
+            *after_small_chunks.borrow_mut() = chunk_names(state);
+        })
+        .run(g, entries, options, &AnalysisCache::default(), &mut trace)?;
+
+    let root_index = RootIndex::new(&state.chunk_graph.chunk_roots, &state.reachable_chunks);
+    let assignment =
+        root_index.assignment(&root_index.reaching(&state.reachable_modules, module_id));
+    let filtered = trace::filtered_roots(
+        &assignment.reachable_from,
+        &assignment.roots,
+        &state.reachable_chunks,
+    );
+    let placement = trace
+        .decisions()
+        .iter()
+        .filter(|decision| match decision {
+            Decision::ModulePlaced { module, .. } | Decision::ModuleDuplicated { module, .. } => {
+                *module == module_id
+            }
+            _ => false,
+        })
+        .cloned()
+        .collect();
+    Ok(Some(Explanation {
+        module: module_id,
+        assignment,
+        filtered,
+        resolution: rules::resolve(options, &g[module_id]),
+        placement,
+        placed_in: placed_in.into_inner(),
+        after_small_chunks: after_small_chunks.into_inner(),
+        chunks: chunk_names(&state),
+    }))
+}
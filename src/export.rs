@@ -0,0 +1,128 @@
+//! Writing the module graph and the chunk graph as Graphviz DOT or Mermaid flowcharts
+//! with readable labels: module names, chunk names and sizes, and async edges dashed.
+
+use crate::chunk_graph::ChunkGraph;
+use crate::module_graph::ModuleGraph;
+use petgraph::visit::EdgeRef;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Dot,
+    Mermaid,
+}
+
+impl Format {
+    /// Mermaid for `.mmd` files, DOT for anything else.
+    pub fn of_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(extension) if extension == "mmd" => Format::Mermaid,
+            _ => Format::Dot,
+        }
+    }
+}
+
+/// A graph reduced to what the exporters print.
+struct Drawing {
+    nodes: Vec<String>,
+    /// `(from, to, dashed)`.
+    edges: Vec<(usize, usize, bool)>,
+}
+
+impl Drawing {
+    fn render(&self, format: Format) -> String {
+        let mut out = String::new();
+        match format {
+            Format::Dot => {
+                out.push_str("digraph {\n");
+                for (index, label) in self.nodes.iter().enumerate() {
+                    out.push_str(&format!(
+                        "    {} [label=\"{}\"]\n",
+                        index,
+                        label
+                            .replace('\\', "\\\\")
+                            .replace('"', "\\\"")
+                            .replace('\n', "\\n")
+                    ));
+                }
+                for (from, to, dashed) in &self.edges {
+                    let style = if *dashed { " [style=dashed]" } else { "" };
+                    out.push_str(&format!("    {} -> {}{}\n", from, to, style));
+                }
+                out.push_str("}\n");
+            }
+            Format::Mermaid => {
+                out.push_str("flowchart TD\n");
+                for (index, label) in self.nodes.iter().enumerate() {
+                    out.push_str(&format!(
+                        "    n{}[\"{}\"]\n",
+                        index,
+                        label.replace('"', "#quot;").replace('\n', "<br/>")
+                    ));
+                }
+                for (from, to, dashed) in &self.edges {
+                    let arrow = if *dashed { "-.->" } else { "-->" };
+                    out.push_str(&format!("    n{} {} n{}\n", from, arrow, to));
+                }
+            }
+        }
+        out
+    }
+}
+
+/// `g` with a node per module labeled with its name and size.
+pub fn module_graph(g: &ModuleGraph, format: Format) -> String {
+    Drawing {
+        nodes: g
+            .node_weights()
+            .map(|module| format!("{}\n{} bytes", module.name, module.size))
+            .collect(),
+        edges: g
+            .edge_references()
+            .map(|edge| {
+                (
+                    edge.source().index(),
+                    edge.target().index(),
+                    edge.weight().is_async,
+                )
+            })
+            .collect(),
+    }
+    .render(format)
+}
+
+/// `chunk_graph` with a node per chunk labeled with its name, kind and size. Edges to
+/// the roots of other chunk groups are async imports.
+pub fn chunk_graph(chunk_graph: &ChunkGraph, format: Format) -> String {
+    let groups = chunk_graph.groups();
+    Drawing {
+        nodes: chunk_graph
+            .chunks()
+            .map(|(_, chunk)| format!("{} ({:?})\n{} bytes", chunk.name, chunk.kind, chunk.size))
+            .collect(),
+        edges: chunk_graph
+            .graph
+            .edge_references()
+            .map(|edge| {
+                (
+                    edge.source().index(),
+                    edge.target().index(),
+                    groups.contains(&edge.target()),
+                )
+            })
+            .collect(),
+    }
+    .render(format)
+}
+
+/// Writes [`module_graph`] to `path` in the format its extension asks for.
+pub fn write_module_graph(g: &ModuleGraph, path: &str) -> io::Result<()> {
+    fs::write(path, module_graph(g, Format::of_path(Path::new(path))))
+}
+
+/// Writes [`chunk_graph`] to `path` in the format its extension asks for.
+pub fn write_chunk_graph(graph: &ChunkGraph, path: &str) -> io::Result<()> {
+    fs::write(path, chunk_graph(graph, Format::of_path(Path::new(path))))
+}
@@ -0,0 +1,119 @@
+//! Build-time feature flags that remove modules and imports before chunking.
+
+use crate::module_graph::{ModuleGraph, ModuleId, ModuleInfo};
+use petgraph::visit::Dfs;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Holds when `flag` is enabled (or disabled, if `enabled` is false).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FlagCondition {
+    pub flag: &'static str,
+    pub enabled: bool,
+}
+
+impl FlagCondition {
+    pub fn holds(&self, enabled_flags: &HashSet<&'static str>) -> bool {
+        enabled_flags.contains(self.flag) == self.enabled
+    }
+}
+
+/// Bytes reachable from the entries with one flag switched on and off, all other
+/// flags as configured.
+#[derive(Debug)]
+pub struct FlagImpact {
+    pub flag: &'static str,
+    pub enabled: bool,
+    pub bytes_when_enabled: usize,
+    pub bytes_when_disabled: usize,
+}
+
+/// Copy of `g` without the imports whose condition doesn't hold, or whose importer or
+/// importee is a module whose condition doesn't hold. Disabled modules stay in the graph
+/// without any imports so that module ids remain valid.
+pub fn prune_disabled<M: ModuleInfo + Clone>(
+    g: &ModuleGraph<M>,
+    enabled_flags: &HashSet<&'static str>,
+) -> ModuleGraph<M> {
+    let mut pruned = g.clone();
+    pruned.retain_edges(|pruned, edge_id| {
+        let (importer, importee) = pruned.edge_endpoints(edge_id).unwrap();
+        let edge_enabled = match &pruned[edge_id].condition {
+            Some(condition) => condition.holds(enabled_flags),
+            None => true,
+        };
+        edge_enabled
+            && module_enabled(&pruned, importer, enabled_flags)
+            && module_enabled(&pruned, importee, enabled_flags)
+    });
+    pruned
+}
+
+fn module_enabled<M: ModuleInfo>(
+    g: &ModuleGraph<M>,
+    module_id: ModuleId,
+    enabled_flags: &HashSet<&'static str>,
+) -> bool {
+    match &g[module_id].condition() {
+        Some(condition) => condition.holds(enabled_flags),
+        None => true,
+    }
+}
+
+/// Size impact of every flag referenced by a module or import condition, sorted by flag.
+pub fn flag_impacts<M: ModuleInfo + Clone>(
+    g: &ModuleGraph<M>,
+    entries: &[ModuleId],
+    enabled_flags: &HashSet<&'static str>,
+) -> Vec<FlagImpact> {
+    let mut flags: Vec<&'static str> = g
+        .edge_weights()
+        .filter_map(|dependency| dependency.condition)
+        .chain(g.node_weights().filter_map(|module| module.condition()))
+        .map(|condition| condition.flag)
+        .collect();
+    flags.sort_unstable();
+    flags.dedup();
+
+    flags
+        .into_iter()
+        .map(|flag| {
+            let mut with_flag = enabled_flags.clone();
+            with_flag.insert(flag);
+            let mut without_flag = enabled_flags.clone();
+            without_flag.remove(flag);
+            FlagImpact {
+                flag,
+                enabled: enabled_flags.contains(flag),
+                bytes_when_enabled: reachable_bytes(
+                    &prune_disabled(g, &with_flag),
+                    entries,
+                    &with_flag,
+                ),
+                bytes_when_disabled: reachable_bytes(
+                    &prune_disabled(g, &without_flag),
+                    entries,
+                    &without_flag,
+                ),
+            }
+        })
+        .collect()
+}
+
+fn reachable_bytes<M: ModuleInfo>(
+    g: &ModuleGraph<M>,
+    entries: &[ModuleId],
+    enabled_flags: &HashSet<&'static str>,
+) -> usize {
+    let mut seen = HashSet::new();
+    for entry in entries
+        .iter()
+        .filter(|entry| module_enabled(g, **entry, enabled_flags))
+    {
+        let mut dfs = Dfs::new(g, *entry);
+        while let Some(module_id) = dfs.next(g) {
+            seen.insert(module_id);
+        }
+    }
+    seen.into_iter().map(|module_id| g[module_id].size()).sum()
+}
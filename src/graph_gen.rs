@@ -0,0 +1,139 @@
+//! Synthetic module graphs for benchmarks and stress tests, so the algorithm can run on
+//! graphs the size of large applications without real project data.
+//!
+//! A graph is made of app modules, the first of which are the entries, followed by
+//! shared modules that app modules all over the graph import, like utilities or
+//! packages. Imports mostly go to modules shortly after the importer, so the graph is
+//! acyclic and loosely layered the way real applications are. The same options and
+//! seed always give the same graph.
+
+use crate::entries::Entry;
+use crate::module_graph::{Dependency, JsModule, ModuleGraph, ModuleId};
+use fixedbitset::FixedBitSet;
+
+/// How far after the importer an app module's imports reach, in modules.
+const IMPORT_WINDOW: usize = 500;
+
+#[derive(Debug, Clone)]
+pub struct GraphGenOptions {
+    /// Picks the graph among all graphs with these options.
+    pub seed: u64,
+    pub module_count: usize,
+    /// Number of entries, taken from the first modules.
+    pub entry_count: usize,
+    /// Average number of imports per module.
+    pub average_fan_out: f64,
+    /// Probability of an import being async.
+    pub async_probability: f64,
+    /// Fraction of the modules that are shared modules. Half the imports of app
+    /// modules go to a shared module.
+    pub shared_ratio: f64,
+}
+
+impl Default for GraphGenOptions {
+    fn default() -> Self {
+        GraphGenOptions {
+            seed: 0,
+            module_count: 10_000,
+            entry_count: 4,
+            average_fan_out: 3.0,
+            async_probability: 0.025,
+            shared_ratio: 0.1,
+        }
+    }
+}
+
+/// Generates a graph and its entries. App modules are named `src/m<n>.js` and shared
+/// modules `src/shared/m<n>.js`; the names are leaked for the rest of the process.
+///
+/// Every app module is reachable from an entry. A shared module may not be imported by
+/// anything if the graph is small.
+pub fn generate(options: &GraphGenOptions) -> (ModuleGraph, Vec<Entry>) {
+    assert!(
+        options.entry_count > 0 && options.entry_count <= options.module_count,
+        "a graph needs between one entry and one entry per module"
+    );
+    let mut rng = SplitMix64(options.seed);
+    let shared_count = ((options.module_count as f64 * options.shared_ratio).round() as usize)
+        .min(options.module_count - options.entry_count);
+    let app_count = options.module_count - shared_count;
+
+    let mut g = ModuleGraph::new();
+    for index in 0..options.module_count {
+        let name = if index < app_count {
+            format!("src/m{}.js", index)
+        } else {
+            format!("src/shared/m{}.js", index - app_count)
+        };
+        g.add_node(JsModule {
+            name: name.as_str().into(),
+            size: 200 + rng.below(5000),
+            ..Default::default()
+        });
+    }
+
+    let dependency = |rng: &mut SplitMix64| Dependency {
+        is_async: rng.chance(options.async_probability),
+        ..Default::default()
+    };
+    let mut imported = FixedBitSet::with_capacity(options.module_count);
+    for index in 0..options.module_count {
+        let first = index + 1;
+        let end = if index < app_count {
+            app_count
+        } else {
+            options.module_count
+        };
+        let fan_out = options.average_fan_out.floor() as usize
+            + usize::from(rng.chance(options.average_fan_out.fract()));
+        for _ in 0..fan_out {
+            let importee = if index < app_count && shared_count > 0 && rng.chance(0.5) {
+                app_count + rng.below(shared_count)
+            } else if first < end {
+                first + rng.below((end - first).min(IMPORT_WINDOW))
+            } else {
+                continue;
+            };
+            imported.insert(importee);
+            let dependency = dependency(&mut rng);
+            g.update_edge(ModuleId::new(index), ModuleId::new(importee), dependency);
+        }
+    }
+    // App modules nothing imports yet are imported by one of the app modules shortly
+    // before them, so every app module is reachable from the entries.
+    for index in options.entry_count..app_count {
+        if !imported.contains(index) {
+            let importer = index - 1 - rng.below(index.min(IMPORT_WINDOW));
+            let dependency = dependency(&mut rng);
+            g.update_edge(ModuleId::new(importer), ModuleId::new(index), dependency);
+        }
+    }
+
+    let entries = (0..options.entry_count)
+        .map(|index| ModuleId::new(index).into())
+        .collect();
+    (g, entries)
+}
+
+/// SplitMix64, a small generator that gives the same numbers on every platform and
+/// release for a seed.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number below `bound`, which must not be zero.
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        ((self.next() >> 11) as f64 / (1u64 << 53) as f64) < probability
+    }
+}
@@ -0,0 +1,179 @@
+//! Rules rewriting a module graph once it is built, for what-if experiments on how it
+//! splits without editing the graph's description.
+//!
+//! [`GraphRules::apply`] runs them in order: aliases first, so the other rules see the
+//! modules imports resolve to, then ignored imports, then async boundaries. Like
+//! [`flags::prune_disabled`], it keeps every module in the graph, so module ids stay
+//! valid: an aliased module just loses its imports and is imported no more.
+//!
+//! [`flags::prune_disabled`]: crate::flags::prune_disabled
+
+use crate::entries::Entry;
+use crate::module_graph::{Dependency, ModuleGraph, ModuleId, ModuleInfo};
+use petgraph::visit::EdgeRef;
+use std::collections::HashMap;
+use std::error::Error;
+
+/// Imports whose importer and importee names contain the given texts. A missing text
+/// matches any module.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ImportPattern {
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+impl ImportPattern {
+    /// Imports of modules whose name contains `text`.
+    pub fn to(text: &str) -> Self {
+        ImportPattern {
+            from: None,
+            to: Some(text.to_owned()),
+        }
+    }
+
+    pub fn matches(&self, importer: &str, importee: &str) -> bool {
+        let contains = |text: &Option<String>, name: &str| {
+            text.as_ref()
+                .is_none_or(|text| name.contains(text.as_str()))
+        };
+        contains(&self.from, importer) && contains(&self.to, importee)
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct GraphRules {
+    /// `(alias, module)` pairs of module names: imports of `alias`, entries starting at
+    /// it and preludes running it resolve to `module` instead, like webpack's
+    /// `resolve.alias`. An alias may name another alias.
+    pub aliases: Vec<(String, String)>,
+    /// Imports to leave out, e.g. of modules only a development build loads.
+    pub ignored_imports: Vec<ImportPattern>,
+    /// Modules whose name contains any of these texts are imported on demand, even where
+    /// they are imported synchronously, so each becomes the root of a chunk group of its
+    /// own. Imports that start a worker or are weak stay as they are.
+    pub async_boundaries: Vec<String>,
+}
+
+/// What [`GraphRules::apply`] changed.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GraphRulesReport {
+    /// `(alias, module)` pairs of the aliased modules.
+    pub aliased: Vec<(ModuleId, ModuleId)>,
+    /// Imports left out, by importer and importee.
+    pub ignored_imports: Vec<(ModuleId, ModuleId)>,
+    /// Sync imports made async, by importer and importee.
+    pub async_imports: Vec<(ModuleId, ModuleId)>,
+}
+
+impl GraphRules {
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+            && self.ignored_imports.is_empty()
+            && self.async_boundaries.is_empty()
+    }
+
+    /// Rewrites `g` and `entries` by the rules. Fails if an alias names a module `g`
+    /// doesn't have, or if aliases form a cycle. Repeated imports of a module stay in
+    /// the order they were added, for [`analysis::prepare`] to combine.
+    ///
+    /// [`analysis::prepare`]: crate::analysis::prepare
+    pub fn apply<M: ModuleInfo>(
+        &self,
+        g: &mut ModuleGraph<M>,
+        entries: &mut [Entry],
+    ) -> Result<GraphRulesReport, Box<dyn Error>> {
+        let mut report = GraphRulesReport::default();
+        if self.is_empty() {
+            return Ok(report);
+        }
+        let module_by_name: HashMap<&str, ModuleId> = g
+            .node_indices()
+            .map(|module_id| (g[module_id].name(), module_id))
+            .collect();
+        let lookup = |name: &str| {
+            module_by_name
+                .get(name)
+                .copied()
+                .ok_or_else(|| format!("unknown module {} in an alias", name))
+        };
+        let mut alias_of: HashMap<ModuleId, ModuleId> = HashMap::new();
+        for (alias, module) in &self.aliases {
+            alias_of.insert(lookup(alias)?, lookup(module)?);
+        }
+        let mut resolved: HashMap<ModuleId, ModuleId> = HashMap::new();
+        for alias in alias_of.keys() {
+            let mut module_id = *alias;
+            let mut hops = 0;
+            while let Some(next) = alias_of.get(&module_id) {
+                module_id = *next;
+                hops += 1;
+                if hops > alias_of.len() {
+                    return Err(format!("the aliases of {} form a cycle", g[*alias].name()).into());
+                }
+            }
+            resolved.insert(*alias, module_id);
+        }
+        let resolve = |module_id: ModuleId| resolved.get(&module_id).copied().unwrap_or(module_id);
+        report.aliased = resolved
+            .iter()
+            .map(|(alias, module)| (*alias, *module))
+            .collect();
+        report.aliased.sort_unstable();
+
+        let is_async_boundary = |module_id: ModuleId| {
+            let name = g[module_id].name();
+            self.async_boundaries
+                .iter()
+                .any(|text| name.contains(text.as_str()))
+        };
+        let mut imports: Vec<(ModuleId, ModuleId, Dependency)> = vec![];
+        for edge in g.edge_references() {
+            // An aliased module is replaced wherever it is imported, so what it imports
+            // itself is never loaded.
+            if resolved.contains_key(&edge.source()) {
+                continue;
+            }
+            let (importer, importee) = (edge.source(), resolve(edge.target()));
+            if self
+                .ignored_imports
+                .iter()
+                .any(|pattern| pattern.matches(g[importer].name(), g[importee].name()))
+            {
+                report.ignored_imports.push((importer, importee));
+                continue;
+            }
+            let mut dependency = edge.weight().clone();
+            if let Some(used_reexports) = &mut dependency.used_reexports {
+                for module_id in used_reexports {
+                    *module_id = resolve(*module_id);
+                }
+            }
+            if !dependency.is_async
+                && !dependency.is_worker
+                && !dependency.is_weak
+                && is_async_boundary(importee)
+            {
+                dependency.is_async = true;
+                report.async_imports.push((importer, importee));
+            }
+            imports.push((importer, importee, dependency));
+        }
+        g.clear_edges();
+        for (importer, importee, dependency) in imports {
+            g.add_edge(importer, importee, dependency);
+        }
+        for entry in entries {
+            entry.module = resolve(entry.module);
+            for module_id in &mut entry.prelude {
+                *module_id = resolve(*module_id);
+            }
+        }
+        tracing::debug!(
+            aliased = report.aliased.len(),
+            ignored_imports = report.ignored_imports.len(),
+            async_imports = report.async_imports.len(),
+            "applied graph rules"
+        );
+        Ok(report)
+    }
+}
@@ -0,0 +1,119 @@
+//! Content hashes of chunks for long-term caching, like webpack's `[contenthash]`.
+//!
+//! A chunk's hash covers the modules in it. The chunk holding the runtime, which is the
+//! runtime chunk if there is one and every entry chunk otherwise, knows the file names
+//! of all chunks it may load on demand, so its hash also covers theirs: a changed async
+//! chunk changes the hash of the runtime as well, and of no other chunk.
+
+use crate::chunk_graph::{ChunkGraph, ChunkId, ChunkKind};
+use crate::module_graph::{ModuleGraph, ModuleId, ModuleInfo};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Hashes the content of a module. Bundlers that have the module sources can hash those
+/// instead of [`NameAndSize`].
+pub trait ModuleHasher {
+    fn hash_module(&self, module_id: ModuleId, module: &dyn ModuleInfo) -> u64;
+}
+
+/// Hashes the name and size of a module, which is all the module graph knows about its
+/// content.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NameAndSize;
+
+impl ModuleHasher for NameAndSize {
+    fn hash_module(&self, _: ModuleId, module: &dyn ModuleInfo) -> u64 {
+        let mut hash = Fnv::default();
+        hash.write(module.name().as_bytes());
+        hash.write(&(module.size() as u64).to_le_bytes());
+        hash.0
+    }
+}
+
+/// FNV-1a, which unlike the standard library's hasher gives the same hashes on every
+/// platform and release.
+pub(crate) struct Fnv(pub(crate) u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv {
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+/// The hash of every chunk of `chunk_graph`, whose modules are those of `g`.
+pub fn chunk_hashes<M: ModuleInfo>(
+    chunk_graph: &ChunkGraph,
+    g: &ModuleGraph<M>,
+    hasher: &dyn ModuleHasher,
+) -> BTreeMap<ChunkId, u64> {
+    let mut hashes: BTreeMap<ChunkId, u64> = chunk_graph
+        .chunks()
+        .map(|(chunk_id, chunk)| {
+            let mut modules: Vec<ModuleId> = chunk.modules.clone();
+            modules.sort_by_key(|module_id| g[*module_id].name());
+            let mut hash = Fnv::default();
+            for module_id in modules {
+                let module_hash = hasher.hash_module(module_id, &g[module_id]);
+                hash.write(&module_hash.to_le_bytes());
+            }
+            (chunk_id, hash.0)
+        })
+        .collect();
+
+    let has_runtime_chunk = chunk_graph
+        .chunks()
+        .any(|(_, chunk)| chunk.kind == ChunkKind::Runtime);
+    let runtime_holders: Vec<(ChunkId, Vec<ChunkId>)> = if has_runtime_chunk {
+        chunk_graph
+            .chunks()
+            .filter(|(_, chunk)| chunk.kind == ChunkKind::Runtime)
+            .map(|(chunk_id, _)| (chunk_id, chunk_graph.parents(chunk_id)))
+            .collect()
+    } else {
+        chunk_graph
+            .entry_chunks()
+            .map(|entry_group| (entry_group, vec![entry_group]))
+            .collect()
+    };
+    let content = hashes.clone();
+    for (holder, entry_groups) in runtime_holders {
+        let mut hash = Fnv::default();
+        hash.write(&content[&holder].to_le_bytes());
+        for chunk_id in loaded_on_demand(chunk_graph, &entry_groups) {
+            hash.write(&content[&chunk_id].to_le_bytes());
+        }
+        hashes.insert(holder, hash.0);
+    }
+    hashes
+}
+
+/// Every chunk the chunk groups of `entry_groups` may load after they started: the
+/// async chunk groups they import, directly or through other async chunk groups, and
+/// the chunks those load.
+fn loaded_on_demand(chunk_graph: &ChunkGraph, entry_groups: &[ChunkId]) -> BTreeSet<ChunkId> {
+    let groups = chunk_graph.groups();
+    let mut stack: Vec<ChunkId> = entry_groups
+        .iter()
+        .flat_map(|entry_group| chunk_graph.graph.neighbors(*entry_group))
+        .filter(|chunk_id| groups.contains(chunk_id) && !entry_groups.contains(chunk_id))
+        .collect();
+    let mut loaded = BTreeSet::new();
+    while let Some(chunk_id) = stack.pop() {
+        if loaded.insert(chunk_id) {
+            stack.extend(chunk_graph.graph.neighbors(chunk_id));
+        }
+    }
+    loaded
+}
+
+/// `hash` as the 16 hex digits used in file names.
+pub fn to_hex(hash: u64) -> String {
+    format!("{:016x}", hash)
+}
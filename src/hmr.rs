@@ -0,0 +1,91 @@
+//! What a hot module replacement runtime has to reload when a module changes.
+//!
+//! Step 2 records the modules each chunk root reaches; [`HmrIndex`] turns that around
+//! into the chunk roots reaching each module, and adds the chunks holding each module
+//! in the final chunk graph. A changed module's [`HmrBoundary`] lists the chunks to
+//! invalidate and the nearest chunk roots above it, the roots whose search reached it
+//! without crossing another root. An update stays within one chunk if a single chunk
+//! holds the module; otherwise the runtime had better reload.
+
+use crate::analysis::AnalysisState;
+use crate::chunk_graph::ChunkId;
+use crate::module_graph::ModuleId;
+use fixedbitset::FixedBitSet;
+use serde::Serialize;
+
+/// The chunks and chunk roots of one changed module, see [`HmrIndex::boundary`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HmrBoundary {
+    pub module: ModuleId,
+    /// The chunks holding the module, which have to be invalidated, in id order.
+    pub chunks: Vec<ChunkId>,
+    /// The nearest chunk roots above the module, in id order. A chunk root is its own
+    /// nearest root.
+    pub roots: Vec<ModuleId>,
+}
+
+impl HmrBoundary {
+    /// Whether a hot update of the module replaces a single chunk. Modules duplicated
+    /// into several chunks, and modules no chunk holds, need a reload.
+    pub fn stays_in_one_chunk(&self) -> bool {
+        self.chunks.len() == 1
+    }
+}
+
+/// The chunk roots reaching every module and the chunks holding it, for looking up the
+/// boundary of any changed module without searching the graph. Built from the final
+/// state, so it has to be built again after the chunks change.
+#[derive(Debug, Clone)]
+pub struct HmrIndex {
+    /// Every chunk root, in id order.
+    roots: Vec<ModuleId>,
+    /// For each module index, the numbers of the roots in `roots` reaching it.
+    reaching: Vec<FixedBitSet>,
+    /// For each module index, the chunks holding it, in id order.
+    chunks: Vec<Vec<ChunkId>>,
+}
+
+impl HmrIndex {
+    pub fn new<M>(state: &AnalysisState<M>) -> Self {
+        let module_count = state.module_graph.node_count();
+        let roots: Vec<ModuleId> = state.reachable_modules.keys().copied().collect();
+        let mut reaching = vec![FixedBitSet::with_capacity(roots.len()); module_count];
+        for (number, reachable) in state.reachable_modules.values().enumerate() {
+            for module_index in reachable.ones() {
+                reaching[module_index].insert(number);
+            }
+        }
+        let mut chunks = vec![vec![]; module_count];
+        for (chunk_id, chunk) in state.chunk_graph.chunks() {
+            for module_id in &chunk.modules {
+                let holding: &mut Vec<ChunkId> = &mut chunks[module_id.index()];
+                if holding.last() != Some(&chunk_id) {
+                    holding.push(chunk_id);
+                }
+            }
+        }
+        HmrIndex {
+            roots,
+            reaching,
+            chunks,
+        }
+    }
+
+    /// The boundary of a change to `module_id`.
+    pub fn boundary(&self, module_id: ModuleId) -> HmrBoundary {
+        let roots = if self.roots.binary_search(&module_id).is_ok() {
+            vec![module_id]
+        } else {
+            self.reaching[module_id.index()]
+                .ones()
+                .map(|number| self.roots[number])
+                .collect()
+        };
+        HmrBoundary {
+            module: module_id,
+            chunks: self.chunks[module_id.index()].clone(),
+            roots,
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! HTML snippets that load the initial chunks of an entry.
+
+use crate::analysis::AnalysisState;
+use crate::chunk_graph::{ChunkGraph, ChunkKind};
+use crate::chunk_groups::ChunkGroups;
+use crate::module_graph::{ImportHint, ModuleInfo, ModuleType};
+use petgraph::prelude::NodeIndex;
+use petgraph::visit::EdgeRef;
+use std::collections::BTreeMap;
+
+/// The strongest [`ImportHint`] among the async imports of each chunk group root made
+/// by modules an entry runs, by the root chunks of the entry and of the async group.
+pub fn import_hints<M: ModuleInfo>(
+    state: &AnalysisState<M>,
+) -> BTreeMap<(NodeIndex, NodeIndex), ImportHint> {
+    let g = &state.module_graph;
+    let chunk_roots = &state.chunk_graph.chunk_roots;
+    let mut hints: BTreeMap<(NodeIndex, NodeIndex), ImportHint> = BTreeMap::new();
+    for edge in g.edge_references() {
+        let dependency = edge.weight();
+        let (Some(hint), true, false) =
+            (dependency.hint, dependency.is_async, dependency.is_worker)
+        else {
+            continue;
+        };
+        let group = match chunk_roots.get(&edge.target()) {
+            Some((chunk_id, group_id)) if chunk_id == group_id => *group_id,
+            _ => continue,
+        };
+        let running = state.entries.iter().filter(|entry| {
+            **entry == edge.source()
+                || state.reachable_modules[*entry].contains(edge.source().index())
+        });
+        for entry in running {
+            let entry_group = chunk_roots[entry].1;
+            let strongest = hints.entry((entry_group, group)).or_insert(hint);
+            *strongest = (*strongest).max(hint);
+        }
+    }
+    hints
+}
+
+/// File name of a chunk: its name with the extension of its module type.
+pub fn chunk_file_name(chunk_graph: &ChunkGraph, chunk_id: NodeIndex) -> String {
+    let chunk = &chunk_graph.graph[chunk_id];
+    match chunk.module_type.extension() {
+        Some(extension) => format!("{}.{}", chunk.name, extension),
+        None => chunk.name.clone(),
+    }
+}
+
+/// Stylesheet links and script tags for the initial chunks of `entry_group`, the root
+/// chunk of an entry, plus preload and prefetch links for the chunks of the async chunk
+/// groups the entry imports with a hint, see [`import_hints`]. Wasm and asset chunks are
+/// fetched by the scripts and get no tags.
+pub fn entry_html(
+    chunk_graph: &ChunkGraph,
+    hints: &BTreeMap<(NodeIndex, NodeIndex), ImportHint>,
+    entry_group: NodeIndex,
+) -> String {
+    entry_html_with(chunk_graph, hints, entry_group, &|chunk_id| {
+        chunk_file_name(chunk_graph, chunk_id)
+    })
+}
+
+/// [`entry_html`] with the files of the chunks named by `file_name`, like the planned
+/// files of [`emit_plan`].
+///
+/// [`emit_plan`]: crate::emit::emit_plan
+pub fn entry_html_with(
+    chunk_graph: &ChunkGraph,
+    hints: &BTreeMap<(NodeIndex, NodeIndex), ImportHint>,
+    entry_group: NodeIndex,
+    file_name: &dyn Fn(NodeIndex) -> String,
+) -> String {
+    let chunk_groups = ChunkGroups::new(chunk_graph);
+    let entry_root = entry_group;
+    let entry_group = chunk_groups.group_of_root(entry_group).unwrap();
+    let initial = chunk_groups.initial_chunks(entry_group);
+
+    // Chunks of preloaded imports, then those of prefetched ones not preloaded anyway.
+    let mut hinted: Vec<(NodeIndex, ImportHint)> = vec![];
+    for hint in [ImportHint::Preload, ImportHint::Prefetch] {
+        let groups = hints
+            .iter()
+            .filter(|((entry, _), group_hint)| *entry == entry_root && **group_hint == hint)
+            .filter_map(|((_, root), _)| chunk_groups.group_of_root(*root))
+            // Workers fetch their chunks into a module map of their own.
+            .filter(|group_id| chunk_groups.group(*group_id).kind != ChunkKind::Worker);
+        for group_id in groups {
+            for chunk_id in chunk_groups.initial_chunks(group_id) {
+                if !initial.contains(&chunk_id)
+                    && !hinted.iter().any(|(hinted, _)| *hinted == chunk_id)
+                {
+                    hinted.push((chunk_id, hint));
+                }
+            }
+        }
+    }
+
+    let module_type = |chunk_id: &NodeIndex| chunk_graph.graph[*chunk_id].module_type;
+    let mut html = String::new();
+    for (chunk_id, hint) in &hinted {
+        let href = escape(&file_name(*chunk_id));
+        match (hint, module_type(chunk_id)) {
+            (_, ModuleType::Wasm | ModuleType::Asset) => {}
+            (ImportHint::Prefetch, _) => {
+                html.push_str(&format!("<link rel=\"prefetch\" href=\"{}\">\n", href))
+            }
+            (ImportHint::Preload, ModuleType::Js) => {
+                html.push_str(&format!("<link rel=\"modulepreload\" href=\"{}\">\n", href))
+            }
+            (ImportHint::Preload, ModuleType::Css) => html.push_str(&format!(
+                "<link rel=\"preload\" as=\"style\" href=\"{}\">\n",
+                href
+            )),
+        }
+    }
+    // Stylesheets first so they load in parallel with the scripts.
+    for chunk_id in initial
+        .iter()
+        .filter(|chunk_id| module_type(chunk_id) == ModuleType::Css)
+    {
+        html.push_str(&format!(
+            "<link rel=\"stylesheet\" href=\"{}\">\n",
+            escape(&file_name(*chunk_id))
+        ));
+    }
+    for chunk_id in initial
+        .iter()
+        .filter(|chunk_id| module_type(chunk_id) == ModuleType::Js)
+    {
+        html.push_str(&format!(
+            "<script type=\"module\" src=\"{}\"></script>\n",
+            escape(&file_name(*chunk_id))
+        ));
+    }
+    html
+}
+
+/// `text` with the characters HTML gives a meaning escaped, for element content and
+/// quoted attribute values.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,115 @@
+//! The ids chunks are referred to by in the output, like webpack's
+//! `optimization.chunkIds`.
+//!
+//! Chunk ids of the chunk graph are indices that shift whenever a chunk is added or
+//! removed anywhere, and with them every file referring to later chunks. Named and
+//! deterministic ids only depend on the chunk itself, so unrelated changes to the graph
+//! leave them, and the long-term caching of the files holding them, alone.
+
+use crate::chunk_graph::{ChunkGraph, ChunkId};
+use crate::hashing::Fnv;
+use crate::html;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// How [`assign`] picks the id of every chunk.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChunkIds {
+    /// Numbers counting up in chunk id order.
+    #[default]
+    Natural,
+    /// The chunk's name, or its file name if an earlier chunk has the same name, like
+    /// the JS and CSS chunks of the same root, followed by a counter if that is taken
+    /// too.
+    Named,
+    /// A number hashed from the chunk's file name, with at least 3 digits and enough
+    /// room that few chunks collide. A chunk that collides with an earlier one by name
+    /// hashes again with a counter appended.
+    Deterministic,
+}
+
+/// The id of a chunk in the output: a number, or a name for [`ChunkIds::Named`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AssignedId {
+    Number(u64),
+    Name(String),
+}
+
+impl fmt::Display for AssignedId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssignedId::Number(number) => write!(f, "{}", number),
+            AssignedId::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// The id of every chunk of `chunk_graph` picked by `strategy`, all different.
+pub fn assign(chunk_graph: &ChunkGraph, strategy: ChunkIds) -> BTreeMap<ChunkId, AssignedId> {
+    match strategy {
+        ChunkIds::Natural => chunk_graph
+            .chunks()
+            .enumerate()
+            .map(|(number, (chunk_id, _))| (chunk_id, AssignedId::Number(number as u64)))
+            .collect(),
+        ChunkIds::Named => {
+            let mut used = BTreeSet::new();
+            chunk_graph
+                .chunks()
+                .map(|(chunk_id, chunk)| {
+                    let file_name = html::chunk_file_name(chunk_graph, chunk_id);
+                    let mut name = chunk.name.clone();
+                    let mut counter = 1;
+                    while !used.insert(name.clone()) {
+                        name = if counter == 1 {
+                            file_name.clone()
+                        } else {
+                            format!("{}~{}", file_name, counter)
+                        };
+                        counter += 1;
+                    }
+                    (chunk_id, AssignedId::Name(name))
+                })
+                .collect()
+        }
+        ChunkIds::Deterministic => deterministic_ids(chunk_graph),
+    }
+}
+
+/// Ids hashed from the file names of the chunks into a range of at least 1000 and ten
+/// times the number of chunks, rounded up to a power of 10 like webpack does. Chunks
+/// are hashed in file name order, so a collision only moves the later chunk.
+fn deterministic_ids(chunk_graph: &ChunkGraph) -> BTreeMap<ChunkId, AssignedId> {
+    let mut chunks: Vec<(String, ChunkId)> = chunk_graph
+        .chunks()
+        .map(|(chunk_id, _)| (html::chunk_file_name(chunk_graph, chunk_id), chunk_id))
+        .collect();
+    chunks.sort();
+    let mut range: u64 = 1000;
+    while range < chunks.len() as u64 * 10 {
+        range *= 10;
+    }
+
+    let mut used = BTreeSet::new();
+    let mut ids = BTreeMap::new();
+    for (file_name, chunk_id) in chunks {
+        let mut salt = 0u64;
+        let id = loop {
+            let mut hash = Fnv::default();
+            hash.write(file_name.as_bytes());
+            if salt > 0 {
+                hash.write(&salt.to_le_bytes());
+            }
+            let id = hash.0 % range;
+            if used.insert(id) {
+                break id;
+            }
+            salt += 1;
+        };
+        ids.insert(chunk_id, AssignedId::Number(id));
+    }
+    ids
+}
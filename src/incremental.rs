@@ -0,0 +1,323 @@
+//! Re-chunking after small edits to the module graph, for watch mode.
+//!
+//! Step 1 is a single DFS and reruns on every edit. Step 2 only searches again from the
+//! chunk roots that can reach an edited module, and Step 3 only reassigns the modules
+//! whose reachable roots changed before placing every module again.
+
+use crate::analysis::{self, AnalysisState, Assignment, RootChunks, RootIndex};
+use crate::cache::AnalysisCache;
+use crate::chunk_graph::{module_stem, ChunkGraph};
+use crate::entries::{self, Entry};
+use crate::error::SplitChunksError;
+use crate::module_graph::{Dependency, JsModule, ModuleGraph, ModuleId};
+use crate::options::SplitOptions;
+use crate::passes;
+use crate::scc::Condensation;
+use crate::trace::DecisionTrace;
+use fixedbitset::FixedBitSet;
+use petgraph::prelude::{Incoming, NodeIndex, Outgoing};
+use petgraph::visit::EdgeRef;
+use std::collections::BTreeSet;
+
+/// What an edit made the chunker recompute.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct UpdateReport {
+    /// Chunk roots searched again in Step 2.
+    pub recomputed_roots: Vec<ModuleId>,
+    /// Modules whose assignment was recomputed in Step 3.
+    pub reassigned_modules: Vec<ModuleId>,
+}
+
+/// Keeps the results of Steps 1 to 3 for a module graph and updates them as the graph
+/// is edited.
+///
+/// Removed modules stay in the graph without imports, so module ids remain valid. They
+/// are no longer reachable and not placed in any chunk.
+pub struct IncrementalChunker {
+    /// The module graph as edited, before feature flags and barrel flattening.
+    source: ModuleGraph,
+    entries: Vec<Entry>,
+    options: SplitOptions,
+    state: AnalysisState,
+    /// The import cycles of `state.module_graph`.
+    condensation: Condensation,
+    /// The assignment of every module, by module index.
+    assignments: Vec<Assignment>,
+    /// The package sizes of Step 3, reused by edits that change no module size. Holds
+    /// nothing else, so it doesn't grow with the searches of every edit.
+    cache: AnalysisCache,
+}
+
+impl IncrementalChunker {
+    /// Runs Steps 1 to 3 on `g` from scratch.
+    pub fn new(
+        g: &ModuleGraph,
+        entries: &[Entry],
+        options: SplitOptions,
+    ) -> Result<Self, SplitChunksError> {
+        let state = analysis::analyze(
+            g,
+            entries,
+            &options,
+            &AnalysisCache::default(),
+            &mut DecisionTrace::new(false),
+        )?;
+        let assignments = analysis::assign_modules(
+            state.module_graph.node_count(),
+            &state.reachable_modules,
+            &RootIndex::new(&state.chunk_graph.chunk_roots, &state.reachable_chunks),
+        );
+        Ok(IncrementalChunker {
+            source: g.clone(),
+            entries: entries.to_vec(),
+            options,
+            condensation: Condensation::new(&state.module_graph),
+            state,
+            assignments,
+            cache: AnalysisCache::default(),
+        })
+    }
+
+    /// The results of Steps 1 to 3 for the current graph.
+    pub fn state(&self) -> &AnalysisState {
+        &self.state
+    }
+
+    /// The final chunk graph: the current state after every pass.
+    pub fn chunk_graph(&self) -> ChunkGraph {
+        let mut state = self.state.clone();
+        passes::optimize(&mut state, &self.options, &mut DecisionTrace::new(false));
+        state.chunk_graph
+    }
+
+    /// Adds a module without any imports. Nothing imports it yet, so no chunk changes.
+    pub fn add_module(&mut self, module: JsModule) -> ModuleId {
+        let module_id = self.source.add_node(module.clone());
+        self.state.module_graph.add_node(module);
+        self.condensation.add_isolated(module_id);
+        for reachable in self.state.reachable_modules.values_mut() {
+            reachable.grow(self.source.node_count());
+        }
+        self.assignments.push(Assignment::default());
+        module_id
+    }
+
+    /// Removes every import from and to `module_id` and drops it from the entries, their
+    /// preludes and the entries other entries depend on.
+    pub fn remove_module(&mut self, module_id: ModuleId) -> Result<UpdateReport, SplitChunksError> {
+        self.check_module(module_id)?;
+        while let Some(edge_id) = self.source.first_edge(module_id, Outgoing) {
+            self.source.remove_edge(edge_id);
+        }
+        while let Some(edge_id) = self.source.first_edge(module_id, Incoming) {
+            self.source.remove_edge(edge_id);
+        }
+        let name = module_stem(&self.source[module_id]).to_owned();
+        self.entries.retain(|entry| entry.module != module_id);
+        for entry in &mut self.entries {
+            entry.prelude.retain(|prelude| *prelude != module_id);
+            entry.depends_on.retain(|dependency| *dependency != name);
+        }
+        self.rechunk()
+    }
+
+    /// Sets the import from `importer` to `importee`, replacing every import between
+    /// them, or removes them all if `dependency` is `None`.
+    pub fn update_edge(
+        &mut self,
+        importer: ModuleId,
+        importee: ModuleId,
+        dependency: Option<Dependency>,
+    ) -> Result<UpdateReport, SplitChunksError> {
+        self.check_module(importer)?;
+        self.check_module(importee)?;
+        for module_id in dependency
+            .iter()
+            .flat_map(|dependency| dependency.used_reexports.iter().flatten())
+        {
+            if self.check_module(*module_id).is_err() {
+                return Err(SplitChunksError::DanglingReexport {
+                    importer: self.source[importer].name.to_string(),
+                    importee: self.source[importee].name.to_string(),
+                    module: *module_id,
+                });
+            }
+        }
+        let mut parallel: Vec<_> = self
+            .source
+            .edges_connecting(importer, importee)
+            .map(|edge| edge.id())
+            .collect();
+        parallel.sort_unstable();
+        // Removing an edge moves the last one into its place, so the highest go first.
+        for edge_id in parallel.iter().skip(1).rev() {
+            self.source.remove_edge(*edge_id);
+        }
+        match (parallel.first().copied(), dependency) {
+            (Some(edge_id), Some(dependency)) => self.source[edge_id] = dependency,
+            (Some(edge_id), None) => {
+                self.source.remove_edge(edge_id);
+            }
+            (None, Some(dependency)) => {
+                self.source.add_edge(importer, importee, dependency);
+            }
+            (None, None) => {}
+        }
+        self.rechunk()
+    }
+
+    fn check_module(&self, module_id: ModuleId) -> Result<(), SplitChunksError> {
+        if module_id.index() < self.source.node_count() {
+            Ok(())
+        } else {
+            Err(SplitChunksError::UnknownModule(module_id))
+        }
+    }
+
+    fn rechunk(&mut self) -> Result<UpdateReport, SplitChunksError> {
+        let mut report = UpdateReport::default();
+        let mut trace = DecisionTrace::new(false);
+        let (g, barrels, weak_imports) =
+            analysis::prepare(&self.source, &self.entries, &self.options);
+        let depends_on = entries::depends_on(&g, &self.entries)?;
+        let RootChunks {
+            mut chunk_graph,
+            chunk_roots,
+        } = analysis::create_root_chunks(
+            &g,
+            &entries::modules(&self.entries),
+            self.options.max_async_depth,
+            &mut trace,
+        )?;
+        let old_roots: BTreeSet<ModuleId> =
+            self.state.chunk_graph.chunk_roots.keys().copied().collect();
+
+        // Modules that became or stopped being chunk roots, and modules whose imports
+        // changed. A root has to search again if it reaches any of them.
+        let status_changed: BTreeSet<ModuleId> = chunk_roots
+            .keys()
+            .filter(|root| !old_roots.contains(root))
+            .chain(
+                old_roots
+                    .iter()
+                    .filter(|root| !chunk_roots.contains_key(root)),
+            )
+            .copied()
+            .collect();
+        let module_count = g.node_count();
+        let mut changed = FixedBitSet::with_capacity(module_count);
+        for module_id in g.node_indices() {
+            if imports(&g, module_id) != imports(&self.state.module_graph, module_id) {
+                changed.insert(module_id.index());
+            }
+        }
+        // Joining or leaving an import cycle changes what the search reaches through the
+        // cycle, even for modules whose own imports stayed the same.
+        let condensation = Condensation::new(&g);
+        for members in &condensation.components {
+            if self.condensation.members_with(members[0]) != members.as_slice() {
+                changed.extend(members.iter().map(|module_id| module_id.index()));
+            }
+        }
+        for module_id in &status_changed {
+            changed.insert(module_id.index());
+        }
+        // The search of another root reaches only the roots of a cycle with roots, so a
+        // change anywhere in the cycle counts for all of it.
+        for members in &condensation.components {
+            if members.len() > 1
+                && members
+                    .iter()
+                    .any(|module_id| changed.contains(module_id.index()))
+            {
+                changed.extend(members.iter().map(|module_id| module_id.index()));
+            }
+        }
+
+        let mut dirty = status_changed.clone();
+        for root in &old_roots {
+            if !chunk_roots.contains_key(root) {
+                let reachable = self.state.reachable_modules.remove(root).unwrap();
+                dirty.extend(reachable.ones().map(NodeIndex::new));
+            }
+        }
+        for root in chunk_roots.keys() {
+            if let Some(old_reachable) = self.state.reachable_modules.get(root) {
+                if !changed.contains(root.index()) && old_reachable.is_disjoint(&changed) {
+                    continue;
+                }
+            }
+            report.recomputed_roots.push(*root);
+            let reachable = analysis::reachable_from_root(&condensation, *root, &chunk_roots);
+            let old_reachable = self
+                .state
+                .reachable_modules
+                .insert(*root, reachable.clone())
+                .unwrap_or_default();
+            dirty.extend(
+                old_reachable
+                    .symmetric_difference(&reachable)
+                    .map(NodeIndex::new),
+            );
+        }
+
+        let reachable_chunks = analysis::parent_roots(
+            &g,
+            &chunk_graph,
+            &chunk_roots,
+            &depends_on,
+            &self.state.reachable_modules,
+        );
+        // A new or dropped `(parent, async root)` pair changes the filter of every module
+        // the async root reaches.
+        for (_, async_root) in reachable_chunks.symmetric_difference(&self.state.reachable_chunks) {
+            if let Some(reachable) = self.state.reachable_modules.get(async_root) {
+                dirty.extend(reachable.ones().map(NodeIndex::new));
+            }
+        }
+
+        let root_index = RootIndex::new(&chunk_roots, &reachable_chunks);
+        for module_id in &dirty {
+            let reaching = root_index.reaching(&self.state.reachable_modules, *module_id);
+            self.assignments[module_id.index()] = root_index.assignment(&reaching);
+        }
+        report.reassigned_modules = dirty.into_iter().collect();
+
+        let chunks = analysis::place_modules(
+            &g,
+            &mut chunk_graph,
+            &chunk_roots,
+            &reachable_chunks,
+            &entries::isolated(&self.entries),
+            &self.assignments,
+            &self.options,
+            &self.cache,
+            &mut trace,
+        );
+        self.state.module_graph = g;
+        self.condensation = condensation;
+        self.state.entries = entries::modules(&self.entries);
+        self.state.barrels = barrels;
+        self.state.weak_imports = weak_imports;
+        self.state.reachable_chunks = reachable_chunks;
+        self.state.chunk_graph = ChunkGraph::renew(
+            chunk_graph,
+            chunk_roots,
+            depends_on,
+            &self.state.chunk_graph,
+        );
+        self.state.chunks = chunks.into_iter().collect();
+        Ok(report)
+    }
+}
+
+/// The modules `module_id` imports and whether each import is async or starts a worker,
+/// which is all of an import that Steps 1 and 2 look at.
+fn imports(g: &ModuleGraph, module_id: ModuleId) -> BTreeSet<(ModuleId, bool, bool)> {
+    g.edges(module_id)
+        .map(|edge| {
+            let dependency = edge.weight();
+            (edge.target(), dependency.is_async, dependency.is_worker)
+        })
+        .collect()
+}
@@ -0,0 +1,89 @@
+//! Splits a module graph into chunks the way webpack's `optimization.splitChunks`
+//! does, following the algorithm from <https://github.com/devongovett/bundler-algorithm>.
+//!
+//! [`split_chunks`] runs the whole algorithm and [`split_chunks_with`] runs any other
+//! [`ChunkingStrategy`]. The modules below expose the individual
+//! steps and passes for hosts that want to inspect or extend them, and a [`Pipeline`]
+//! runs them with passes of a host in between.
+
+extern crate petgraph;
+
+pub mod adapters;
+pub mod analysis;
+pub mod barrels;
+pub mod bindings;
+pub mod budgets;
+pub mod cache;
+pub mod chunk_graph;
+pub mod chunk_groups;
+pub mod coarsen;
+pub mod diff;
+pub mod dominators;
+pub mod duplicates;
+pub mod emit;
+pub mod entries;
+pub mod error;
+pub mod explain;
+pub mod export;
+pub mod flags;
+pub mod graph_gen;
+pub mod graph_rules;
+pub mod hashing;
+pub mod hmr;
+pub mod html;
+pub mod ids;
+pub mod incremental;
+pub mod lint;
+pub mod loader;
+pub mod manifest;
+pub mod module_graph;
+#[cfg(feature = "napi")]
+pub mod napi;
+pub mod options;
+pub mod output;
+pub mod pareto;
+pub mod passes;
+pub mod pipeline;
+pub mod report;
+pub mod rules;
+pub mod scc;
+pub mod serve;
+pub mod snapshot;
+pub mod stats;
+pub mod strategy;
+pub mod trace;
+pub mod verify;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod waterfall;
+
+pub use chunk_graph::{Chunk, ChunkGraph, ChunkHandle, ChunkId, ChunkKind};
+pub use chunk_groups::{ChunkGroup, ChunkGroupId, ChunkGroups};
+pub use entries::{Entry, EntryName};
+pub use error::SplitChunksError;
+pub use module_graph::{
+    Dependency, ImportHint, JsModule, ModuleGraph, ModuleId, ModuleInfo, ModuleType, Symbol,
+};
+pub use options::SplitOptions;
+pub use pipeline::Pipeline;
+pub use strategy::{ChunkingStrategy, CoarseningStrategy, DominatorStrategy, ReachabilityStrategy};
+
+/// Splits `graph` into chunks, starting one entry chunk at each of `entries`. Fails if
+/// the entries or imports refer to modules outside `graph`, see [`analysis::validate`].
+pub fn split_chunks<M: ModuleInfo + Clone>(
+    graph: &ModuleGraph<M>,
+    entries: &[Entry],
+    options: &SplitOptions,
+) -> Result<ChunkGraph, SplitChunksError> {
+    split_chunks_with(&ReachabilityStrategy, graph, entries, options)
+}
+
+/// Splits `graph` into chunks with `strategy` instead of the algorithm of this crate.
+pub fn split_chunks_with<M>(
+    strategy: &dyn ChunkingStrategy<M>,
+    graph: &ModuleGraph<M>,
+    entries: &[Entry],
+    options: &SplitOptions,
+) -> Result<ChunkGraph, SplitChunksError> {
+    strategy.split(graph, entries, options)
+}
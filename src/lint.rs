@@ -0,0 +1,250 @@
+//! Checks a module graph for mistakes before it is split, so a host learns that its
+//! graph is off instead of puzzling over the chunks it splits into.
+//!
+//! [`analysis::validate`] only rejects what the algorithm can't split at all. [`check`]
+//! also reports what it splits without complaint but hardly means what the host
+//! wanted: modules no entry reaches, which end up in no chunk, async imports of entry
+//! modules, modules of no size and modules sharing a name, whose chunks can't be told
+//! apart by name.
+//!
+//! [`analysis::validate`]: crate::analysis::validate
+
+use crate::entries::Entry;
+use crate::module_graph::{ModuleGraph, ModuleId, ModuleInfo};
+use fixedbitset::FixedBitSet;
+use petgraph::visit::EdgeRef;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// How bad a [`Lint`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    /// Splits fine, but may not be what was meant.
+    Info,
+    /// Splits into chunks that are likely not what was meant.
+    Warning,
+    /// Fails to split, see [`analysis::validate`].
+    ///
+    /// [`analysis::validate`]: crate::analysis::validate
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// Something [`check`] found in a module graph.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Lint {
+    /// An entry, a module of its prelude or a used re-export of an import refers to
+    /// module `module`, which is not in the graph. `referrer` is the entry or importer.
+    #[serde(rename_all = "camelCase")]
+    DanglingReference { referrer: String, module: usize },
+    /// Several modules are named `name`. Their chunks may be named alike, and the
+    /// modules can't be told apart in a graph description or a report.
+    #[serde(rename_all = "camelCase")]
+    DuplicateName { name: String, modules: Vec<usize> },
+    /// `importer` imports the entry module `entry` on demand, so the entry module runs
+    /// again as the root of an async chunk group, or its entry chunk becomes one.
+    #[serde(rename_all = "camelCase")]
+    AsyncImportOfEntry { importer: String, entry: String },
+    /// No entry reaches `module`, so it ends up in no chunk.
+    #[serde(rename_all = "camelCase")]
+    Orphan { module: String },
+    /// `module` has no size, so it adds nothing to the size of its chunks.
+    #[serde(rename_all = "camelCase")]
+    ZeroSize { module: String },
+}
+
+impl Lint {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Lint::DanglingReference { .. } => Severity::Error,
+            Lint::DuplicateName { .. } | Lint::AsyncImportOfEntry { .. } | Lint::Orphan { .. } => {
+                Severity::Warning
+            }
+            Lint::ZeroSize { .. } => Severity::Info,
+        }
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Lint::DanglingReference { referrer, module } => write!(
+                f,
+                "{} refers to module {}, which is not in the graph",
+                referrer, module
+            ),
+            Lint::DuplicateName { name, modules } => write!(
+                f,
+                "{} modules are named {} ({})",
+                modules.len(),
+                name,
+                modules
+                    .iter()
+                    .map(|module| module.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Lint::AsyncImportOfEntry { importer, entry } => write!(
+                f,
+                "{} imports the entry module {} on demand",
+                importer, entry
+            ),
+            Lint::Orphan { module } => write!(f, "no entry reaches {}", module),
+            Lint::ZeroSize { module } => write!(f, "{} has no size", module),
+        }
+    }
+}
+
+/// A [`Lint`] with its severity.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostic {
+    pub severity: Severity,
+    #[serde(flatten)]
+    pub lint: Lint,
+}
+
+impl From<Lint> for Diagnostic {
+    fn from(lint: Lint) -> Self {
+        Diagnostic {
+            severity: lint.severity(),
+            lint,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.lint)
+    }
+}
+
+/// Everything wrong with `g` and `entries`, the most severe first, then in the order
+/// of the modules, entries and imports they are about. Empty if nothing is.
+pub fn check<M: ModuleInfo>(g: &ModuleGraph<M>, entries: &[Entry]) -> Vec<Diagnostic> {
+    let mut lints = vec![];
+    let in_graph = |module_id: &ModuleId| module_id.index() < g.node_count();
+
+    for entry in entries {
+        let referrer = if in_graph(&entry.module) {
+            format!("entry {}", g[entry.module].name())
+        } else {
+            format!("entry {}", entry.module.index())
+        };
+        for module_id in [&entry.module].into_iter().chain(&entry.prelude) {
+            if !in_graph(module_id) {
+                lints.push(Lint::DanglingReference {
+                    referrer: referrer.clone(),
+                    module: module_id.index(),
+                });
+            }
+        }
+    }
+    for edge in g.edge_references() {
+        for module_id in edge.weight().used_reexports.iter().flatten() {
+            if !in_graph(module_id) {
+                lints.push(Lint::DanglingReference {
+                    referrer: g[edge.source()].name().to_owned(),
+                    module: module_id.index(),
+                });
+            }
+        }
+    }
+
+    let mut modules_by_name: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for module_id in g.node_indices() {
+        modules_by_name
+            .entry(g[module_id].name())
+            .or_default()
+            .push(module_id.index());
+    }
+    for (name, modules) in modules_by_name {
+        if modules.len() > 1 {
+            lints.push(Lint::DuplicateName {
+                name: name.to_owned(),
+                modules,
+            });
+        }
+    }
+
+    let mut entry_modules = FixedBitSet::with_capacity(g.node_count());
+    for entry in entries.iter().filter(|entry| in_graph(&entry.module)) {
+        entry_modules.insert(entry.module.index());
+    }
+    for edge in g.edge_references() {
+        let dependency = edge.weight();
+        if dependency.is_async
+            && !dependency.is_weak
+            && !dependency.is_worker
+            && entry_modules.contains(edge.target().index())
+        {
+            lints.push(Lint::AsyncImportOfEntry {
+                importer: g[edge.source()].name().to_owned(),
+                entry: g[edge.target()].name().to_owned(),
+            });
+        }
+    }
+
+    // Weak imports never load their importee, so they don't count as reaching it.
+    let mut reached = FixedBitSet::with_capacity(g.node_count());
+    let mut stack: Vec<ModuleId> = entries
+        .iter()
+        .flat_map(|entry| [&entry.module].into_iter().chain(&entry.prelude))
+        .copied()
+        .filter(in_graph)
+        .collect();
+    while let Some(module_id) = stack.pop() {
+        if reached.put(module_id.index()) {
+            continue;
+        }
+        for edge in g.edges(module_id) {
+            if !edge.weight().is_weak && !reached.contains(edge.target().index()) {
+                stack.push(edge.target());
+            }
+        }
+    }
+    // External modules are never bundled, so neither matters for them.
+    for module_id in g
+        .node_indices()
+        .filter(|module_id| !g[*module_id].is_external())
+    {
+        if !reached.contains(module_id.index()) {
+            lints.push(Lint::Orphan {
+                module: g[module_id].name().to_owned(),
+            });
+        }
+    }
+    for module_id in g
+        .node_indices()
+        .filter(|module_id| !g[*module_id].is_external())
+    {
+        if g[module_id].size() == 0 {
+            lints.push(Lint::ZeroSize {
+                module: g[module_id].name().to_owned(),
+            });
+        }
+    }
+
+    let mut diagnostics: Vec<Diagnostic> = lints.into_iter().map(Diagnostic::from).collect();
+    diagnostics.sort_by_key(|diagnostic| std::cmp::Reverse(diagnostic.severity));
+    diagnostics
+}
+
+/// Whether any of `diagnostics` keeps the graph from being split.
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == Severity::Error)
+}
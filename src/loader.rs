@@ -0,0 +1,390 @@
+//! Loading a module graph from a JSON description, so the algorithm can run on the
+//! graph of a real application.
+//!
+//! The format lists modules by name, imports between module names and the entries:
+//!
+//! ```json
+//! {
+//!   "modules": [{ "name": "entry.js", "size": 1000 }, { "name": "lazy.js", "size": 500 }],
+//!   "edges": [{ "from": "entry.js", "to": "lazy.js", "is_async": true }],
+//!   "entries": ["entry.js"]
+//! }
+//! ```
+//!
+//! An entry can also be an object naming the entries it depends on, by their module
+//! names without extension: `{ "name": "admin.js", "dependOn": ["entry"] }`, and with
+//! `"isolated": true` one that shares no chunks, see [`Entry::isolated`]. An entry,
+//! or the `name` of such an object, can be an array of modules, like
+//! `["polyfill.js", "index.js"]`; the last one is the entry module, and the ones before it
+//! run first in the same entry chunk.
+//!
+//! Every other field of [`JsModule`] and [`Dependency`] is optional and defaults like
+//! the struct does, so `"is_external": true` marks an external module,
+//! `"side_effect_free": true` a module that may safely run twice and
+//! `"is_worker": true` an import that starts a worker, `"is_weak": true` one that only
+//! uses the module if something else loaded it. A module's `type` is one of `js`,
+//! `css`, `wasm` or `asset`, its `layer` names the build variant it belongs to, and an
+//! import's `hint` is `preload` or `prefetch`.
+//!
+//! Graphs with millions of modules are better described one record per line, in files
+//! ending in `.ndjson` or `.jsonl`, which [`parse_records`] reads as they stream in
+//! instead of parsing the whole description first. Each line is a module, an import or
+//! an entry in the format above, tagged with its `kind`:
+//!
+//! ```json
+//! { "kind": "module", "name": "entry.js", "size": 1000 }
+//! { "kind": "module", "name": "lazy.js", "size": 500 }
+//! { "kind": "edge", "from": "entry.js", "to": "lazy.js", "is_async": true }
+//! { "kind": "entry", "name": "entry.js" }
+//! ```
+//!
+//! A module has to come before the lines naming it. Empty lines are skipped.
+
+use crate::cache::AnalysisCache;
+use crate::entries::{self, Entry};
+use crate::flags::FlagCondition;
+use crate::module_graph::{
+    Dependency, ImportHint, JsModule, ModuleGraph, ModuleId, ModuleType, Symbol,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+#[derive(Debug, Deserialize)]
+struct GraphFile {
+    modules: Vec<ModuleEntry>,
+    #[serde(default)]
+    edges: Vec<EdgeEntry>,
+    entries: Vec<EntryDescriptor>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum EntryDescriptor {
+    Name(EntryModules),
+    WithDependencies {
+        name: EntryModules,
+        #[serde(default, rename = "dependOn")]
+        depend_on: Vec<String>,
+        #[serde(default)]
+        isolated: bool,
+    },
+}
+
+/// A line of the newline-delimited format.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum Record {
+    Module(ModuleEntry),
+    Edge(EdgeEntry),
+    Entry {
+        name: EntryModules,
+        #[serde(default, rename = "dependOn")]
+        depend_on: Vec<String>,
+        #[serde(default)]
+        isolated: bool,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum EntryModules {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Debug, Deserialize)]
+struct ModuleEntry {
+    name: String,
+    size: usize,
+    #[serde(default, rename = "type")]
+    module_type: ModuleType,
+    #[serde(default)]
+    reexports_only: bool,
+    #[serde(default)]
+    is_external: bool,
+    #[serde(default)]
+    side_effect_free: bool,
+    #[serde(default)]
+    condition: Option<ConditionEntry>,
+    #[serde(default)]
+    layer: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EdgeEntry {
+    from: String,
+    to: String,
+    #[serde(default)]
+    is_async: bool,
+    #[serde(default)]
+    is_worker: bool,
+    #[serde(default)]
+    is_weak: bool,
+    #[serde(default = "default_import_count")]
+    import_count: usize,
+    #[serde(default = "default_load_likelihood")]
+    load_likelihood: f64,
+    /// Names of the re-exported modules the importer uses.
+    #[serde(default)]
+    used_reexports: Option<Vec<String>>,
+    #[serde(default)]
+    condition: Option<ConditionEntry>,
+    #[serde(default)]
+    hint: Option<ImportHint>,
+    #[serde(default)]
+    frequency: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConditionEntry {
+    flag: String,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+}
+
+fn default_import_count() -> usize {
+    Dependency::default().import_count
+}
+
+fn default_load_likelihood() -> f64 {
+    Dependency::default().load_likelihood
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Reads the graph description at `path`, with [`parse_records`] if it is
+/// newline-delimited and [`parse`] otherwise.
+pub fn load(
+    path: &str,
+    cache: &AnalysisCache,
+) -> Result<(ModuleGraph, Vec<Entry>), Box<dyn Error>> {
+    if is_newline_delimited(path) {
+        parse_records(BufReader::new(fs::File::open(path)?), cache)
+    } else {
+        parse(&fs::read_to_string(path)?, cache)
+    }
+}
+
+/// Like [`load`], reading the file through a memory map instead of copying it, which
+/// saves a copy of a description of several gigabytes.
+pub fn load_mapped(
+    path: &str,
+    cache: &AnalysisCache,
+) -> Result<(ModuleGraph, Vec<Entry>), Box<dyn Error>> {
+    let file = fs::File::open(path)?;
+    // SAFETY: the map is only read while this function runs. A file truncated in the
+    // meantime by another process makes reading it fault, like with any memory map.
+    let map = unsafe { memmap2::Mmap::map(&file)? };
+    if is_newline_delimited(path) {
+        parse_records(&map[..], cache)
+    } else {
+        build(serde_json::from_slice(&map)?, cache)
+    }
+}
+
+fn is_newline_delimited(path: &str) -> bool {
+    matches!(
+        Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str()),
+        Some("ndjson" | "jsonl")
+    )
+}
+
+/// Builds the module graph and its entries from the newline-delimited format, adding
+/// every module, import and entry as soon as its line is read. Errors name the line.
+pub fn parse_records(
+    mut reader: impl BufRead,
+    cache: &AnalysisCache,
+) -> Result<(ModuleGraph, Vec<Entry>), Box<dyn Error>> {
+    let mut builder = GraphBuilder::new(cache);
+    let mut line = Vec::new();
+    let mut line_number = 0;
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        line_number += 1;
+        let text = line.trim_ascii();
+        if text.is_empty() {
+            continue;
+        }
+        let added = match serde_json::from_slice(text) {
+            Ok(Record::Module(module)) => builder.add_module(module),
+            Ok(Record::Edge(edge)) => builder.add_edge(edge),
+            Ok(Record::Entry {
+                name,
+                depend_on,
+                isolated,
+            }) => builder.add_entry(EntryDescriptor::WithDependencies {
+                name,
+                depend_on,
+                isolated,
+            }),
+            Err(error) => Err(error.to_string()),
+        };
+        added.map_err(|error| format!("line {}: {}", line_number, error))?;
+    }
+    builder.finish()
+}
+
+/// Builds the module graph and its entries from a JSON graph description. Module
+/// names and flags are interned in `cache`; modules get ids in the order they are
+/// listed. Repeated edges between the same two modules are kept, for
+/// [`analysis::prepare`] to merge as [`SplitOptions::parallel_imports`] says.
+///
+/// [`analysis::prepare`]: crate::analysis::prepare
+/// [`SplitOptions::parallel_imports`]: crate::SplitOptions::parallel_imports
+pub fn parse(
+    json: &str,
+    cache: &AnalysisCache,
+) -> Result<(ModuleGraph, Vec<Entry>), Box<dyn Error>> {
+    build(serde_json::from_str(json)?, cache)
+}
+
+/// Like [`parse`], for a graph description that was already parsed, e.g. an object
+/// handed over from JS.
+pub fn from_value(
+    value: serde_json::Value,
+    cache: &AnalysisCache,
+) -> Result<(ModuleGraph, Vec<Entry>), Box<dyn Error>> {
+    build(serde_json::from_value(value)?, cache)
+}
+
+fn build(
+    file: GraphFile,
+    cache: &AnalysisCache,
+) -> Result<(ModuleGraph, Vec<Entry>), Box<dyn Error>> {
+    let mut builder = GraphBuilder::new(cache);
+    for module in file.modules {
+        builder.add_module(module)?;
+    }
+    for edge in file.edges {
+        builder.add_edge(edge)?;
+    }
+    for entry in file.entries {
+        builder.add_entry(entry)?;
+    }
+    builder.finish()
+}
+
+/// Builds the module graph one module, import or entry at a time, so a graph can be
+/// loaded while its description is still being read. Modules have to be added before
+/// the imports and entries naming them.
+struct GraphBuilder<'a> {
+    cache: &'a AnalysisCache,
+    g: ModuleGraph,
+    module_by_name: HashMap<Symbol, ModuleId>,
+    entries: Vec<Entry>,
+}
+
+impl<'a> GraphBuilder<'a> {
+    fn new(cache: &'a AnalysisCache) -> Self {
+        GraphBuilder {
+            cache,
+            g: ModuleGraph::new(),
+            module_by_name: HashMap::new(),
+            entries: vec![],
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Result<ModuleId, String> {
+        self.module_by_name
+            .get(name)
+            .copied()
+            .ok_or_else(|| format!("unknown module {}", name))
+    }
+
+    fn condition(&self, entry: Option<ConditionEntry>) -> Option<FlagCondition> {
+        entry.map(|entry| FlagCondition {
+            flag: self.cache.intern(&entry.flag),
+            enabled: entry.enabled,
+        })
+    }
+
+    fn add_module(&mut self, module: ModuleEntry) -> Result<(), String> {
+        let name = self.cache.symbol(&module.name);
+        if self.module_by_name.contains_key(&name) {
+            return Err(format!("module {} is listed more than once", name));
+        }
+        let module_id = self.g.add_node(JsModule {
+            name: name.clone(),
+            size: module.size,
+            module_type: module.module_type,
+            reexports_only: module.reexports_only,
+            condition: self.condition(module.condition),
+            is_external: module.is_external,
+            side_effect_free: module.side_effect_free,
+            layer: module.layer.map(|layer| self.cache.symbol(&layer)),
+        });
+        self.module_by_name.insert(name, module_id);
+        Ok(())
+    }
+
+    fn add_edge(&mut self, edge: EdgeEntry) -> Result<(), String> {
+        let used_reexports = match edge.used_reexports {
+            Some(names) => Some(
+                names
+                    .iter()
+                    .map(|name| self.lookup(name))
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            None => None,
+        };
+        let dependency = Dependency {
+            is_async: edge.is_async,
+            is_worker: edge.is_worker,
+            is_weak: edge.is_weak,
+            import_count: edge.import_count,
+            load_likelihood: edge.load_likelihood,
+            used_reexports,
+            condition: self.condition(edge.condition),
+            hint: edge.hint,
+            frequency: edge.frequency,
+        };
+        let (from, to) = (self.lookup(&edge.from)?, self.lookup(&edge.to)?);
+        self.g.add_edge(from, to, dependency);
+        Ok(())
+    }
+
+    fn add_entry(&mut self, entry: EntryDescriptor) -> Result<(), String> {
+        let (name, depends_on, isolated) = match entry {
+            EntryDescriptor::Name(name) => (name, vec![], false),
+            EntryDescriptor::WithDependencies {
+                name,
+                depend_on,
+                isolated,
+            } => (name, depend_on, isolated),
+        };
+        let (module, prelude) = match name {
+            EntryModules::One(name) => (self.lookup(&name)?, vec![]),
+            EntryModules::Many(mut names) => {
+                let module = names.pop().ok_or("an entry lists no modules")?;
+                let prelude = names
+                    .iter()
+                    .map(|name| self.lookup(name))
+                    .collect::<Result<_, _>>()?;
+                (self.lookup(&module)?, prelude)
+            }
+        };
+        self.entries.push(Entry {
+            module,
+            prelude,
+            depends_on,
+            isolated,
+        });
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(ModuleGraph, Vec<Entry>), Box<dyn Error>> {
+        entries::depends_on(&self.g, &self.entries)?;
+        Ok((self.g, self.entries))
+    }
+}
@@ -1,330 +1,1004 @@
-#![feature(hash_drain_filter)]
-#![feature(drain_filter)]
-
 extern crate petgraph;
 
-use petgraph::data::Build;
-use petgraph::dot::Dot;
-use petgraph::prelude::{Incoming, NodeIndex};
-use petgraph::visit::{depth_first_search, Control, DfsEvent};
-use petgraph::Graph;
-use std::collections::{HashMap, HashSet, LinkedList};
-
-#[derive(Debug, PartialEq, Eq, Hash)]
-struct JsModule {
-    name: ModuleId,
-    size: usize,
-}
-
-#[derive(Debug)]
-struct Dependency {
-    is_async: bool,
-}
-
-#[derive(Debug, Default)]
-struct Chunk {
-    module_ids: Vec<ModuleId>,
-    size: usize,
-    source_bundles: Vec<NodeIndex>,
-}
+use split_chunks_algorithm::analysis::AnalysisState;
+use split_chunks_algorithm::budgets::{self, Budgets};
+use split_chunks_algorithm::cache::AnalysisCache;
+use split_chunks_algorithm::export::{self, Format};
+use split_chunks_algorithm::flags::{self, FlagCondition};
+use split_chunks_algorithm::graph_rules::{GraphRules, ImportPattern};
+use split_chunks_algorithm::hashing::NameAndSize;
+use split_chunks_algorithm::ids::ChunkIds;
+use split_chunks_algorithm::options::{
+    CacheGroup, EntrySignatures, ManualChunks, ParallelImports, SideEffectDuplication,
+    SmallChunkPolicy, SplitFrom,
+};
+use split_chunks_algorithm::pipeline::Stage;
+use split_chunks_algorithm::trace::DecisionTrace;
+use split_chunks_algorithm::{
+    adapters, analysis, diff, duplicates, emit, entries, explain, html, lint, loader, manifest,
+    output, pareto, passes, report, serve, snapshot, stats, waterfall, ChunkGroups, ChunkKind,
+    Dependency, Entry, JsModule, ModuleGraph, ModuleId, ModuleType, Pipeline, SplitOptions,
+};
+use std::collections::{BTreeSet, HashSet};
+use std::sync::Arc;
+use tracing_subscriber::EnvFilter;
+
+const USAGE: &str = "\
+usage: split-chunks <command> [graph.json] [options]
+
+commands:
+  analyze   split the graph and print the HTML of each entry and the chunks
+  dot       print the final chunk graph in Graphviz DOT format, or as a Mermaid
+            flowchart with --mermaid
+  stats     print chunk counts, sizes and the initial requests of each entry
+  check     print the size budgets the chunks exceed and fail if there are any
+  serve     explore the chunk graph and the module graph in the browser at
+            http://localhost:8000, or the port given with --port
+  lint      print orphans, async imports of entries, modules of no size and
+            modules sharing a name, and fail if the graph can't be split
+  diff      compare two stats documents written by analyze --stats-json:
+            split-chunks diff <old.json> <new.json>
+  explain   print which chunk roots reach a module, which rule placed it and
+            whether removing small chunks duplicated it:
+            split-chunks explain <graph.json> <module name>
+  help      print this message
+
+Without a graph file, a small built-in demo graph is split. See the `loader` module
+for the JSON format, and for the newline-delimited format of .ndjson and .jsonl
+files. With --mmap the graph file is memory-mapped instead of read. With --from
+esbuild, rollup or parcel it is read as the metafile of esbuild, the module infos
+of rollup or rolldown or the bundle graph of Parcel, see the `adapters` module.
+
+options:
+  --min-size <bytes>               smallest shared chunk worth a request
+  --max-size <bytes>               split chunks larger than this
+  --min-chunks <count>             chunk roots a module needs to be shared
+  --min-source-chunks <count>      chunks a shared chunk needs to be split from
+  --max-async-requests <count>     chunks an async chunk group may load
+  --max-initial-requests <count>   chunks an entry may load
+  --max-async-depth <depth>        async imports deep chunk groups may nest, keeping
+                                   deeper async imports with their importer
+  --vendors                        put node_modules into vendor chunks
+  --granular-vendors <bytes>       put node_modules into vendor chunks, with
+                                   chunks of their own for packages of at
+                                   least <bytes>
+  --flag <name>                    enable a feature flag, may be repeated
+  --ignore-import <text>           leave out imports of modules whose name
+                                   contains <text>, may be repeated
+  --alias <alias>=<module>         resolve imports of the module <alias> to
+                                   <module>, may be repeated
+  --async-boundary <text>          import modules whose name contains <text>
+                                   on demand, may be repeated
+  --runtime-chunk                  move the runtime into a chunk shared by all entries
+  --runtime-size <bytes>           size of the runtime
+  --manual-chunk <name>=<text>     force modules whose name contains <text> into
+                                   the chunk <name>, may be repeated
+  --manual-chunk-priority <n>      let cache groups with a higher priority claim
+                                   the modules of manual chunks
+  --hot-path-bias <power>          how much more min_size chunks of frequently
+                                   loaded chunk groups need, 1 by default
+  --coupling-bias <power>          how much more min_size chunks imported from
+                                   many places in each chunk group need, 0 by
+                                   default
+  --small-chunk-policy <policy>[:<bytes>]
+                                   remove chunks under min_size by duplicating
+                                   them (duplicate), merging them into the
+                                   largest-parent or smallest-parent chunk
+                                   their chunk groups load, or keep them; for
+                                   chunks under <bytes> if given, may be
+                                   repeated
+  --max-initial-size <bytes>       budget for the bytes an entry loads before it runs
+  --max-chunk-size <bytes>         budget for the bytes of any chunk
+  --reuse-existing-chunk           reuse a chunk with the same modules instead of
+                                   creating a shared chunk
+  --refuse-side-effect-duplication keep chunks under min_size whose duplication
+                                   would run modules with side effects twice on
+                                   one page
+  --hoist-sibling-duplicates       after removing small chunks, move modules that
+                                   async imports of the same chunk group each got
+                                   a copy of into one shared chunk
+  --strict-entry-signatures        keep only the entry module in entry chunks
+                                   other chunks import from, moving their other
+                                   modules into a shared chunk
+  --prefer-async-imports           split modules off at an async import even if
+                                   the importer also imports them synchronously
+  --chunks <chunks>                split shared modules out of initial chunks,
+                                   async chunks or all of them (the default)
+  --verify-passes                  check the chunk graph after every pass
+
+analyze options:
+  --module-graph <path>   write the analyzed module graph, as Mermaid if the path
+                          ends in .mmd and as DOT otherwise
+  --chunk-graph <path>    write the final chunk graph, in the same formats
+  --stats-json <path>     write the chunks as a webpack stats document
+  --chunk-ids <ids>       id the chunks of the stats document by natural
+                          order, named or deterministic hashes of their names
+  --manifest <path>       write the files each entry and each dynamic import loads
+  --loader-table <path>   write the chunks each dynamic import of every chunk
+                          loads, by the ids of --chunk-ids, as compact JSON
+  --emit-plan <path>      write the file of every chunk and the files each entry's
+                          HTML references, in injection order
+  --file-name <template>  name the files of entry chunks by <template>, with
+                          [name], [id], [contenthash], [contenthash:<n>] and
+                          [ext], [name].[contenthash:8].[ext] by default
+  --chunk-file-name <template>
+                          name the files of every other chunk by <template>
+  --html-report <path>    write a treemap of the chunks and their modules as HTML
+  --trace                 print every placement decision
+  --verbose               log what every step of the algorithm and every pass
+                          does to stderr;
+                          RUST_LOG=split_chunks=trace logs every module
+  --save-state <path>     save the analysis state after Step 3
+  --resume <path>         continue from a saved analysis state
+  --warm-cache <path>     reuse the analysis state cached at <path> if the graph
+                          and options are unchanged, and cache it there if not
+  --compare <path>        diff the module graph against a saved analysis state
+  --pareto-csv <path>     write the min_size trade-offs as CSV
+
+stats options:
+  --report                list the size of every chunk, the duplicated modules
+                          and the largest modules instead
+  --top <count>           largest modules to list, 10 by default
+  --waterfall             list the rounds of requests, requests and bytes each
+                          async import and worker waits for instead
+  --duplicates            list the modules each entry's page may download more
+                          than once, and the min_size and min_chunks that
+                          would download each once, instead
+  --json                  print the report as JSON instead
+
+check options:
+  --json                  print the exceeded budgets as JSON
+
+lint options:
+  --json                  print the diagnostics as JSON
+
+serve options:
+  --port <port>           port to listen on, 8000 by default
+
+diff options:
+  --json                  print the changed chunks as JSON
+";
+
+/// Flags that take no value.
+const SWITCHES: [&str; 16] = [
+    "--mmap",
+    "--trace",
+    "--verbose",
+    "--vendors",
+    "--mermaid",
+    "--runtime-chunk",
+    "--reuse-existing-chunk",
+    "--refuse-side-effect-duplication",
+    "--hoist-sibling-duplicates",
+    "--strict-entry-signatures",
+    "--prefer-async-imports",
+    "--verify-passes",
+    "--report",
+    "--waterfall",
+    "--duplicates",
+    "--json",
+];
 
-impl Chunk {
-    fn from_js_module(module_id: ModuleId, module: &JsModule) -> Self {
-        Chunk {
-            module_ids: vec![module_id],
-            size: module.size,
-            source_bundles: vec![],
+fn main() {
+    init_logging();
+    let command = std::env::args().nth(1).unwrap_or_default();
+    match command.as_str() {
+        "analyze" => analyze(),
+        "dot" => dot(),
+        "stats" => stats(),
+        "check" => check(),
+        "lint" => lint(),
+        "serve" => serve(),
+        "diff" => diff(),
+        "explain" => explain(),
+        "help" | "--help" | "-h" => print!("{}", USAGE),
+        _ => {
+            eprint!("{}", USAGE);
+            std::process::exit(2);
         }
     }
 }
 
-fn main() {
-    let (g, entries, module_by_id) = build_graph();
-    println!("{:?}", Dot::new(&g));
-
-    // 存的是 chunk 的入口模块的 id 和对应的 chunk id组成的元组
-    let mut chunk_roots = HashMap::new();
-    let mut reachable_chunks = HashSet::new();
-    let mut chunk_graph = Graph::new();
-
-    // Step 1: Create chunks at the explicit split points in the graph.
-    // Create chunks for each entry.
-    for entry in &entries {
-        let chunk_id = chunk_graph.add_node(Chunk::from_js_module(*entry, &module_by_id[*entry]));
-        chunk_roots.insert(*entry, (chunk_id, chunk_id));
-    }
+/// Logs the spans and events of the library to stderr, filtered by `RUST_LOG`, or at
+/// the debug level with `--verbose`. Without either only warnings are logged.
+fn init_logging() {
+    let verbose = std::env::args().any(|arg| arg == "--verbose");
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+        EnvFilter::new(if verbose {
+            "split_chunks_algorithm=debug"
+        } else {
+            "warn"
+        })
+    });
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .init();
+}
 
-    // Traverse the module graph and create chunks for async dependencies or other condition.
-    // This only adds the module asset of each chunk, not the subgraph.
-    // stack 的队头表示的当前 chunk 入口模块的 图索引 和其所属的 chunk 的 id
-    // stack 的 n + 1 位置的 chunk 是 n 的父 chunk ，即 chunk (n) import 了 chunk (n + 1)
-    let mut stack = LinkedList::new();
-    depth_first_search(&g, entries, |event| {
-        match event {
-            DfsEvent::Discover(module_idx, _) => {
-                // println!("Discover {:?}", module_idx);
-                // Push to the stack when a new chunk is created.
-                if let Some((_, chunk_group_id)) = chunk_roots.get(&module_idx) {
-                    // stack 的队头表示的 chunk 入口模块的 图索引 和其所属的 chunk 的 id
-                    stack.push_front((module_idx, *chunk_group_id));
+/// `analyze`: splits the graph and prints the HTML of each entry and the final chunks,
+/// and with `--verbose` what every pass did to stderr.
+fn analyze() {
+    // Shared by every analysis in this process. Cheap to clone into worker threads.
+    let cache = Arc::new(AnalysisCache::default());
+
+    // Opt-in record of every placement decision, printed at the end with `--trace`.
+    let mut trace = DecisionTrace::new(std::env::args().any(|arg| arg == "--trace"));
+
+    // What every step found goes to stderr, and only with `--verbose`.
+    let verbose = std::env::args().any(|arg| arg == "--verbose");
+
+    let options = split_options(&cache);
+
+    // Either analyze the graph from scratch or pick up where a previous run saved its state.
+    let mut state = match arg_value("--resume") {
+        Some(path) => snapshot::load(&path)
+            .unwrap_or_else(|error| fail(format!("failed to load {}: {}", path, error))),
+        None => {
+            let (g, entries) = load_graph(&cache);
+            if verbose {
+                for (package, size) in cache.package_sizes(&g).iter() {
+                    eprintln!("package {} {}", package, size);
                 }
-            }
-            DfsEvent::TreeEdge(importer_id, importee_id) => {
-                // println!("TreeEdge from {:?} to {:?}", importer_id, importee_id);
-                // Create a new bundle as well as a new bundle group if the dependency is async.
-
-                let dependency = &g[(importer_id, importee_id)];
-                if dependency.is_async {
-                    let chunk = Chunk::from_js_module(importee_id, &module_by_id[importee_id]);
-                    let chunk_id = chunk_graph.add_node(chunk);
-                    chunk_roots.insert(importee_id, (chunk_id, chunk_id));
-
-                    // Walk up the stack until we hit a different asset type
-                    // and mark each this bundle as reachable from every parent bundle.
-                    for (chunk_entry_module_idx, _) in &stack {
-                        reachable_chunks.insert((*chunk_entry_module_idx, importee_id));
-                    }
+                for impact in
+                    flags::flag_impacts(&g, &entries::modules(&entries), &options.enabled_flags)
+                {
+                    eprintln!(
+                        "flag {} ({}): {} bytes when enabled, {} bytes when disabled",
+                        impact.flag,
+                        if impact.enabled {
+                            "enabled"
+                        } else {
+                            "disabled"
+                        },
+                        impact.bytes_when_enabled,
+                        impact.bytes_when_disabled
+                    );
                 }
             }
-            DfsEvent::Finish(finished_module_id, _) => {
-                // println!("Finish {:?}", finished_module_id);
-                // Pop the stack when existing the asset node that created a bundle.
-                if let Some((module_id, _)) = stack.front() {
-                    if *module_id == finished_module_id {
-                        stack.pop_front();
+            let state = match arg_value("--warm-cache") {
+                Some(path) => {
+                    let (state, warm_start) =
+                        snapshot::analyze_warm(&g, &entries, &options, &cache, &mut trace, &path)
+                            .unwrap_or_else(|error| fail(error.to_string()));
+                    if verbose {
+                        eprintln!("warm cache {:?}", warm_start);
                     }
+                    state
                 }
+                None => analysis::analyze(&g, &entries, &options, &cache, &mut trace)
+                    .unwrap_or_else(|error| fail(error.to_string())),
+            };
+            if verbose {
+                eprintln!("barrels {:?}", state.barrels);
+                let stats = cache.stats();
+                eprintln!("analysis cache hits {} misses {}", stats.hits, stats.misses);
             }
-            _ => {}
+            state
         }
-    });
-    // chunk_roots
-    println!("chunk_roots roots {:#?}", chunk_roots);
-    // reachable 存储着 entry chunk module 到各个 chunk entry module 之间的边，不存在说明对应模块不可达
-    println!("reachable_chunks {:?}", reachable_chunks);
-    println!("initial chunk graph {:?}", Dot::new(&chunk_graph));
-    // 此时 chunk_graph 中的每一个 chunk 仅包含自己的入口模块
-
-    // Step 2: Determine reachability for every module from each chunk root.
-    // This is later used to determine which chunk to place each module in.
-    let mut reachable_modules = HashSet::new();
-
-    for (root_which_is_node_idx_of_chunks_entry_module, _) in &chunk_roots {
-        depth_first_search(
-            &g,
-            Some(*root_which_is_node_idx_of_chunks_entry_module),
-            |event| {
-                if let DfsEvent::Discover(node_idx_of_visiting_module, _) = &event {
-                    if node_idx_of_visiting_module == root_which_is_node_idx_of_chunks_entry_module
-                    {
-                        return Control::Continue;
-                    }
+    };
+    if let Some(path) = arg_value("--save-state") {
+        snapshot::save(&state, &path)
+            .unwrap_or_else(|error| fail(format!("failed to save {}: {}", path, error)));
+    }
+    if let Some(path) = arg_value("--compare") {
+        let previous = snapshot::load(&path)
+            .unwrap_or_else(|error| fail(format!("failed to load {}: {}", path, error)));
+        let graph_diff = diff::diff_module_graphs(&previous.module_graph, &state.module_graph);
+        println!("module graph changes since {}:\n{}", path, graph_diff);
+    }
 
-                    // 注意这里创建的边是摊平的，是【入口模块】直接连接到可达的模块
-                    // 对于依赖入口模块 A 假设有 module graph A -> B -> C
-                    // 我们能得到 reachable grapg ， A -> B ， A -> C
-                    reachable_modules.insert((
-                        *root_which_is_node_idx_of_chunks_entry_module,
-                        *node_idx_of_visiting_module,
-                    ));
-
-                    // Stop when we hit another bundle root.
-                    if chunk_roots.contains_key(*node_idx_of_visiting_module) {
-                        return Control::<()>::Prune;
-                    }
-                }
-                Control::Continue
-            },
-        );
+    // Run every pass after Step 3. With `--pareto-csv`, sweep the minimum size over every
+    // point where Step 4 could decide differently once the chunk edges are weighed, and
+    // write the trade-offs that are not strictly worse than another configuration.
+    let mut pipeline = Pipeline::new();
+    if let Some(path) = arg_value("--pareto-csv") {
+        pipeline = pipeline.after(Stage::PruneAvailableModules, move |state, options, _| {
+            let front = pareto::pareto_front(&pareto::sweep_min_size(state, options));
+            std::fs::write(&path, pareto::to_csv(&front))
+                .unwrap_or_else(|error| fail(format!("failed to write {}: {}", path, error)));
+        });
+    }
+    let report = pipeline.optimize(&mut state, &options, &mut trace);
+    if verbose {
+        eprintln!("passes {:?}", report);
+    }
+
+    let g = &state.module_graph;
+    let chunk_graph = &state.chunk_graph;
+    if let Some(path) = arg_value("--module-graph") {
+        export::write_module_graph(g, &path)
+            .unwrap_or_else(|error| fail(format!("failed to write {}: {}", path, error)));
+    }
+    if let Some(path) = arg_value("--chunk-graph") {
+        export::write_chunk_graph(chunk_graph, &path)
+            .unwrap_or_else(|error| fail(format!("failed to write {}: {}", path, error)));
+    }
+    if let Some(path) = arg_value("--stats-json") {
+        std::fs::write(&path, output::to_json(chunk_graph, g, options.chunk_ids))
+            .unwrap_or_else(|error| fail(format!("failed to write {}: {}", path, error)));
+    }
+    if let Some(path) = arg_value("--manifest") {
+        std::fs::write(&path, manifest::to_json(&state))
+            .unwrap_or_else(|error| fail(format!("failed to write {}: {}", path, error)));
+    }
+    if let Some(path) = arg_value("--loader-table") {
+        std::fs::write(
+            &path,
+            manifest::loader_table_json(&state, options.chunk_ids),
+        )
+        .unwrap_or_else(|error| fail(format!("failed to write {}: {}", path, error)));
+    }
+    if let Some(path) = arg_value("--emit-plan") {
+        let template = |flag: &str| {
+            arg_value(flag).map(|template| {
+                template
+                    .parse()
+                    .unwrap_or_else(|error: emit::EmitError| fail(format!("{}: {}", flag, error)))
+            })
+        };
+        let defaults = emit::EmitOptions::default();
+        let emit_options = emit::EmitOptions {
+            entry_file_name: template("--file-name").unwrap_or(defaults.entry_file_name),
+            chunk_file_name: template("--chunk-file-name").unwrap_or(defaults.chunk_file_name),
+            chunk_ids: options.chunk_ids,
+        };
+        let plan = emit::emit_plan(&state, &emit_options, &NameAndSize)
+            .unwrap_or_else(|error| fail(error.to_string()));
+        std::fs::write(&path, serde_json::to_string_pretty(&plan).unwrap())
+            .unwrap_or_else(|error| fail(format!("failed to write {}: {}", path, error)));
+    }
+    if let Some(path) = arg_value("--html-report") {
+        std::fs::write(&path, report::treemap_html(chunk_graph, g))
+            .unwrap_or_else(|error| fail(format!("failed to write {}: {}", path, error)));
+    }
+
+    let hints = html::import_hints(&state);
+    for entry in &state.entries {
+        let entry_group = chunk_graph.chunk_roots[entry].1;
+        println!("{}", html::entry_html(chunk_graph, &hints, entry_group));
     }
 
-    let reachable_module_graph = petgraph::graphmap::DiGraphMap::<&'static str, ()>::from_edges(&reachable_modules);
+    for decision in trace.decisions() {
+        println!("trace: {}", decision.describe(g));
+    }
+
+    for (chunk_id, chunk) in chunk_graph.chunks() {
+        println!(
+            "{:?} {} ({:?}, parents {:?}) {} {}",
+            chunk_id,
+            chunk.name,
+            chunk.kind,
+            chunk_graph.parents(chunk_id),
+            chunk_graph
+                .modules_of(chunk_id)
+                .iter()
+                .map(|n| &*g[*n].name)
+                .collect::<Vec<&str>>()
+                .join(", "),
+            chunk.size
+        )
+    }
+}
+
+/// `dot`: prints the final chunk graph in Graphviz DOT format or, with `--mermaid`, as a
+/// Mermaid flowchart.
+fn dot() {
+    let state = split();
+    let format = if std::env::args().any(|arg| arg == "--mermaid") {
+        Format::Mermaid
+    } else {
+        Format::Dot
+    };
+    print!("{}", export::chunk_graph(&state.chunk_graph, format));
+}
+
+/// `serve`: answers the page exploring the split graph until interrupted.
+fn serve() {
+    let state = split();
+    let port = arg_value("--port").unwrap_or_else(|| "8000".to_owned());
+    let listener = std::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+        .unwrap_or_else(|error| fail(format!("failed to listen on port {}: {}", port, error)));
+    println!("exploring the chunks at http://localhost:{}", port);
+    let graph = serve::ExplorerGraph::new(&state.chunk_graph, &state.module_graph);
+    serve::serve(&listener, &graph).unwrap_or_else(|error| fail(error.to_string()));
+}
+
+/// `stats`: prints how many chunks of each kind there are, how many bytes they add up
+/// to and what each entry loads up front.
+fn stats() {
+    let has_switch = |switch: &str| std::env::args().any(|arg| arg == switch);
+    if has_switch("--duplicates") {
+        let cache = AnalysisCache::default();
+        let options = split_options(&cache);
+        let (g, entries) = load_graph(&cache);
+        let report = duplicates::duplicates(&g, &entries, &options)
+            .unwrap_or_else(|error| fail(error.to_string()));
+        if has_switch("--json") {
+            println!("{}", serde_json::to_string_pretty(&report).unwrap());
+        } else {
+            print!("{}", report);
+        }
+        return;
+    }
+    let state = split();
+    let g = &state.module_graph;
+    let chunk_graph = &state.chunk_graph;
+
+    if has_switch("--waterfall") {
+        let waterfalls = waterfall::waterfalls(chunk_graph);
+        if has_switch("--json") {
+            println!("{}", serde_json::to_string_pretty(&waterfalls).unwrap());
+        } else {
+            for waterfall in &waterfalls {
+                println!("{}", waterfall);
+            }
+        }
+        return;
+    }
+    if has_switch("--report") || has_switch("--json") {
+        let top = match arg_value("--top") {
+            Some(value) => value
+                .parse()
+                .unwrap_or_else(|_| fail(format!("--top expects a number, got {}", value))),
+            None => 10,
+        };
+        let report = stats::size_report(chunk_graph, g, top);
+        if has_switch("--json") {
+            println!("{}", report.to_json());
+        } else {
+            print!("{}", report);
+        }
+        return;
+    }
+
+    let count = |kind: ChunkKind| {
+        chunk_graph
+            .chunks()
+            .filter(|(_, chunk)| chunk.kind == kind)
+            .count()
+    };
     println!(
-        "reachable_module_graph {:?}",
-        Dot::new(&reachable_module_graph)
+        "chunks {} ({} entry, {} async, {} worker, {} shared, {} manual, {} runtime)",
+        chunk_graph.chunks().count(),
+        count(ChunkKind::Entry),
+        count(ChunkKind::Async),
+        count(ChunkKind::Worker),
+        count(ChunkKind::Shared),
+        count(ChunkKind::Manual),
+        count(ChunkKind::Runtime)
     );
 
-    // Step 3: Place all modules into chunks. Each module is placed into a single
-    // chunk based on the chunk entries it is reachable from. This creates a
-    // maximally code split chunk graph with no duplication.
-
-    // Create a mapping from entry module ids to chunk ids.
-    let mut chunks: HashMap<Vec<ModuleId>, NodeIndex> = HashMap::new();
-
-    for module_id in g.nodes() {
-        // Find chunk entries reachable from the module.
-        let reachable: Vec<&'static str> = reachable_module_graph
-            .neighbors_directed(module_id, Incoming)
-            .collect();
-        println!("original reachable: {:?} for {:?}", reachable, module_id);
-        // Filter out chunks when the module is reachable in a parent chunk.
-        let reachable: Vec<&'static str> = reachable
-            .iter()
-            .cloned()
-            .filter(|b| {
-                (&reachable)
-                    .into_iter()
-                    .all(|a| !reachable_chunks.contains(&(*a, *b)))
-            })
-            .collect();
+    let total: usize = chunk_graph.chunks().map(|(_, chunk)| chunk.size).sum();
+    let unique: usize = chunk_graph
+        .chunks()
+        .flat_map(|(_, chunk)| chunk.modules.iter().copied())
+        .collect::<BTreeSet<ModuleId>>()
+        .iter()
+        .map(|module_id| g[*module_id].size)
+        .sum();
+    println!("size {} bytes, {} duplicated", total, total - unique);
+
+    let chunk_groups = ChunkGroups::new(chunk_graph);
+    for entry in &state.entries {
+        let entry_group = chunk_graph.chunk_roots[entry].1;
+        let initial = chunk_groups.initial_chunks(chunk_groups.group_of_root(entry_group).unwrap());
+        println!(
+            "entry {}: {} initial requests, {} bytes",
+            chunk_graph.graph[entry_group].name,
+            initial.len(),
+            initial
+                .iter()
+                .map(|chunk_id| chunk_graph.graph[*chunk_id].size)
+                .sum::<usize>()
+        );
+    }
+}
 
-        println!("filtered reachable: {:?}", reachable);
+/// `check`: splits the graph and prints every budget of `--max-initial-size` and
+/// `--max-chunk-size` it exceeds, exiting with status 1 if there are any.
+fn check() {
+    let state = split();
+    let violations = budgets::check(&state.chunk_graph, &budgets());
+    if std::env::args().any(|arg| arg == "--json") {
+        println!("{}", serde_json::to_string_pretty(&violations).unwrap());
+    } else if violations.is_empty() {
+        println!("within budget");
+    }
+    if !violations.is_empty() {
+        for violation in &violations {
+            eprintln!("{}", violation);
+        }
+        std::process::exit(1);
+    }
+}
 
-        if let Some((chunk_id, _)) = chunk_roots.get(&module_id) {
-            // If the module is a chunk root, add the chunk to every other reachable chunk group.
-            chunks.entry(vec![module_id]).or_insert(*chunk_id);
-            for a in &reachable {
-                if *a != module_id {
-                    chunk_graph.add_edge(chunk_roots[a].1, *chunk_id, 0);
-                }
-            }
-        } else if reachable.len() > 0 {
-            // If the asset is reachable from more than one entry, find or create
-            // a chunk for that combination of entries, and add the asset to it.
-            // 这段代码依赖了chunk的【入口模块】先于普通模块被遍历到，否则在 chunks 里面取值的时候会取不到 panic
-            let source_chunks = reachable.iter().map(|a| chunks[&vec![*a]]).collect::<Vec<_>>();
-            // 这里创建了共享模块的 chunk
-            let chunk_id = chunks.entry(reachable.clone()).or_insert_with(|| {
-                let mut bundle = Chunk::default();
-                bundle.source_bundles = source_chunks;
-                chunk_graph.add_node(bundle)
-            });
-
-            let bundle = &mut chunk_graph[*chunk_id];
-            bundle.module_ids.push(module_id);
-            bundle.size += module_by_id[module_id].size;
-
-            // Add the bundle to each reachable bundle group.
-            for item_module_id in reachable {
-                let item_chunk_id = chunk_roots[&item_module_id].1;
-                if item_chunk_id != *chunk_id {
-                    chunk_graph.add_edge(item_chunk_id, *chunk_id, 0);
+/// `lint`: prints what is wrong with the graph, and fails if it can't be split.
+fn lint() {
+    let (g, entries) = load_graph(&AnalysisCache::default());
+    let diagnostics = lint::check(&g, &entries);
+    if std::env::args().any(|arg| arg == "--json") {
+        println!("{}", serde_json::to_string_pretty(&diagnostics).unwrap());
+    } else if diagnostics.is_empty() {
+        println!("no problems found");
+    } else {
+        for diagnostic in &diagnostics {
+            println!("{}", diagnostic);
+        }
+    }
+    if lint::has_errors(&diagnostics) {
+        std::process::exit(1);
+    }
+}
+
+/// `diff`: prints the chunks added, removed and changed between two stats documents.
+fn diff() {
+    let paths = positional_args();
+    let [old, new] = paths.as_slice() else {
+        fail("diff needs the paths of two stats documents".to_owned());
+    };
+    let load = |path: &String| -> output::Stats {
+        let json = std::fs::read_to_string(path)
+            .unwrap_or_else(|error| fail(format!("failed to read {}: {}", path, error)));
+        serde_json::from_str(&json)
+            .unwrap_or_else(|error| fail(format!("failed to parse {}: {}", path, error)))
+    };
+    let chunk_diff = diff::diff_chunk_graphs(&load(old), &load(new));
+    if std::env::args().any(|arg| arg == "--json") {
+        println!("{}", serde_json::to_string_pretty(&chunk_diff).unwrap());
+    } else {
+        print!("{}", chunk_diff);
+    }
+}
+
+/// `explain`: prints why the module named on the command line ended up in its chunks.
+fn explain() {
+    let paths = positional_args();
+    let [_, module_name] = paths.as_slice() else {
+        fail("explain needs the path of a graph and the name of a module".to_owned());
+    };
+    let cache = AnalysisCache::default();
+    let options = split_options(&cache);
+    let (g, entries) = load_graph(&cache);
+    let explanation = explain::explain(&g, &entries, &options, module_name)
+        .unwrap_or_else(|error| fail(error.to_string()))
+        .unwrap_or_else(|| fail(format!("the graph has no module named {}", module_name)));
+    for line in explanation.describe(&g) {
+        println!("{}", line);
+    }
+}
+
+/// Loads the graph and runs the whole algorithm on it without reporting anything.
+fn split() -> AnalysisState {
+    let cache = AnalysisCache::default();
+    let options = split_options(&cache);
+    let mut trace = DecisionTrace::new(false);
+    let (g, entries) = load_graph(&cache);
+    let mut state = analysis::analyze(&g, &entries, &options, &cache, &mut trace)
+        .unwrap_or_else(|error| fail(error.to_string()));
+    passes::optimize(&mut state, &options, &mut trace);
+    state
+}
+
+/// The graph file given on the command line, or the demo graph without one, rewritten
+/// by the rules of `--ignore-import`, `--alias` and `--async-boundary`.
+fn load_graph(cache: &AnalysisCache) -> (ModuleGraph, Vec<Entry>) {
+    let (mut g, mut entries) = match graph_path() {
+        Some(path) => {
+            let loaded = match arg_value("--from").as_deref() {
+                Some(format) => load_dump(&path, format, cache),
+                None if std::env::args().any(|arg| arg == "--mmap") => {
+                    loader::load_mapped(&path, cache)
                 }
-            }
+                None => loader::load(&path, cache),
+            };
+            loaded.unwrap_or_else(|error| fail(format!("failed to load {}: {}", path, error)))
         }
+        None => build_graph(),
+    };
+    graph_rules()
+        .apply(&mut g, &mut entries)
+        .unwrap_or_else(|error| fail(error.to_string()));
+    (g, entries)
+}
+
+/// The rules passed as `--ignore-import <text>`, `--alias <alias>=<module>` and
+/// `--async-boundary <text>`, each of which may be repeated.
+fn graph_rules() -> GraphRules {
+    let args: Vec<String> = std::env::args().collect();
+    let values = |flag: &str| -> Vec<String> {
+        args.windows(2)
+            .filter(|pair| pair[0] == flag)
+            .map(|pair| pair[1].clone())
+            .collect()
+    };
+    GraphRules {
+        aliases: values("--alias")
+            .into_iter()
+            .map(|alias| match alias.split_once('=') {
+                Some((alias, module)) => (alias.to_owned(), module.to_owned()),
+                None => fail(format!("--alias expects <alias>=<module>, got {}", alias)),
+            })
+            .collect(),
+        ignored_imports: values("--ignore-import")
+            .iter()
+            .map(|text| ImportPattern::to(text))
+            .collect(),
+        async_boundaries: values("--async-boundary"),
     }
+}
 
-    println!("chunk_graph in step3: {:#?}", Dot::new(&chunk_graph));
+/// Reads the graph file a bundler of `--from <format>` dumped.
+fn load_dump(
+    path: &str,
+    format: &str,
+    cache: &AnalysisCache,
+) -> Result<(ModuleGraph, Vec<Entry>), Box<dyn std::error::Error>> {
+    let convert = match format {
+        "esbuild" => adapters::esbuild,
+        "rollup" => adapters::rollup,
+        "parcel" => adapters::parcel,
+        _ => fail(format!(
+            "--from expects esbuild, rollup or parcel, got {}",
+            format
+        )),
+    };
+    convert(&std::fs::read_to_string(path)?, cache)
+}
 
-    // // Step 4: Remove shared bundles that are smaller than the minimum size,
-    // // and add the assets to the original source bundles they were referenced from.
-    // // This may result in duplication of assets in multiple bundles.
-    // for bundle_id in chunk_graph.node_indices() {
-    //     let bundle = &chunk_graph[bundle_id];
-    //     if bundle.source_bundles.len() > 0 && bundle.size < 10 {
-    //         remove_bundle(&g, &mut chunk_graph, bundle_id);
-    //     }
-    // }
+/// Defaults overridden by `--min-size`, `--max-size`, `--min-chunks`, `--min-source-chunks`,
+/// `--max-async-requests`, `--max-initial-requests`, `--max-async-depth`, `--vendors`, `--granular-vendors`,
+/// `--flag`, `--runtime-chunk`, `--runtime-size`, `--manual-chunk`,
+/// `--manual-chunk-priority`, `--hot-path-bias`, `--coupling-bias`,
+/// `--small-chunk-policy`, `--reuse-existing-chunk`, `--hoist-sibling-duplicates`,
+/// `--strict-entry-signatures`, `--prefer-async-imports`, `--chunks`, `--max-initial-size`, `--max-chunk-size`
+/// and `--verify-passes`.
+fn split_options(cache: &AnalysisCache) -> SplitOptions {
+    let defaults = SplitOptions::default();
+    let number = |flag: &str, default: usize| match arg_value(flag) {
+        Some(value) => value
+            .parse()
+            .unwrap_or_else(|_| fail(format!("{} expects a number, got {}", flag, value))),
+        None => default,
+    };
+    SplitOptions {
+        min_size: number("--min-size", defaults.min_size),
+        max_size: arg_value("--max-size").map(|_| number("--max-size", 0)),
+        min_chunks: number("--min-chunks", defaults.min_chunks),
+        min_source_chunks: number("--min-source-chunks", defaults.min_source_chunks),
+        max_async_requests: number("--max-async-requests", defaults.max_async_requests),
+        max_initial_requests: number("--max-initial-requests", defaults.max_initial_requests),
+        max_async_depth: arg_value("--max-async-depth").map(|_| number("--max-async-depth", 0)),
+        cache_groups: if arg_value("--granular-vendors").is_some() {
+            vec![CacheGroup::granular_vendors(number(
+                "--granular-vendors",
+                0,
+            ))]
+        } else if std::env::args().any(|arg| arg == "--vendors") {
+            vec![CacheGroup::vendors()]
+        } else {
+            vec![]
+        },
+        enabled_flags: enabled_flags(cache),
+        runtime_chunk: std::env::args().any(|arg| arg == "--runtime-chunk"),
+        runtime_size: number("--runtime-size", defaults.runtime_size),
+        manual_chunks: manual_chunks(),
+        hot_path_bias: match arg_value("--hot-path-bias") {
+            Some(value) => value.parse().unwrap_or_else(|_| {
+                fail(format!("--hot-path-bias expects a number, got {}", value))
+            }),
+            None => defaults.hot_path_bias,
+        },
+        coupling_bias: match arg_value("--coupling-bias") {
+            Some(value) => value.parse().unwrap_or_else(|_| {
+                fail(format!("--coupling-bias expects a number, got {}", value))
+            }),
+            None => defaults.coupling_bias,
+        },
+        small_chunk_policies: small_chunk_policies(),
+        reuse_existing_chunk: std::env::args().any(|arg| arg == "--reuse-existing-chunk"),
+        side_effect_duplication: if std::env::args()
+            .any(|arg| arg == "--refuse-side-effect-duplication")
+        {
+            SideEffectDuplication::Refuse
+        } else {
+            SideEffectDuplication::Warn
+        },
+        hoist_sibling_duplicates: std::env::args().any(|arg| arg == "--hoist-sibling-duplicates"),
+        preserve_entry_signatures: if std::env::args().any(|arg| arg == "--strict-entry-signatures")
+        {
+            EntrySignatures::Strict
+        } else {
+            EntrySignatures::AllowExtension
+        },
+        parallel_imports: if std::env::args().any(|arg| arg == "--prefer-async-imports") {
+            ParallelImports::PreferAsync
+        } else {
+            ParallelImports::PreferSync
+        },
+        split_from: match arg_value("--chunks").as_deref() {
+            Some("all") | None => SplitFrom::All,
+            Some("initial") => SplitFrom::Initial,
+            Some("async") => SplitFrom::Async,
+            Some(chunks) => fail(format!(
+                "--chunks expects initial, async or all, got {}",
+                chunks
+            )),
+        },
+        budgets: budgets(),
+        verify_passes: std::env::args().any(|arg| arg == "--verify-passes"),
+        chunk_names: None,
+        chunk_ids: match arg_value("--chunk-ids").as_deref() {
+            Some("natural") | None => ChunkIds::Natural,
+            Some("named") => ChunkIds::Named,
+            Some("deterministic") => ChunkIds::Deterministic,
+            Some(ids) => fail(format!(
+                "--chunk-ids expects natural, named or deterministic, got {}",
+                ids
+            )),
+        },
+    }
+}
 
-    println!("chunk graph {:?}", Dot::new(&chunk_graph));
+/// Manual chunks passed as `--manual-chunk <name>=<text>`, possibly repeated. The first
+/// matching one wins. `--manual-chunk-priority` lets cache groups of a higher priority
+/// claim their modules.
+fn manual_chunks() -> Option<ManualChunks> {
+    let args: Vec<String> = std::env::args().collect();
+    let rules: Vec<(String, String)> = args
+        .windows(2)
+        .filter(|pair| pair[0] == "--manual-chunk")
+        .map(|pair| match pair[1].split_once('=') {
+            Some((name, text)) => (name.to_owned(), text.to_owned()),
+            None => fail(format!(
+                "--manual-chunk expects <name>=<text>, got {}",
+                pair[1]
+            )),
+        })
+        .collect();
+    if rules.is_empty() {
+        return None;
+    }
+    let manual_chunks = ManualChunks::new(move |module| {
+        rules
+            .iter()
+            .find(|(_, text)| module.name().contains(text.as_str()))
+            .map(|(name, _)| name.clone())
+    });
+    Some(match arg_value("--manual-chunk-priority") {
+        Some(value) => manual_chunks.with_priority(value.parse().unwrap_or_else(|_| {
+            fail(format!(
+                "--manual-chunk-priority expects a number, got {}",
+                value
+            ))
+        })),
+        None => manual_chunks,
+    })
+}
 
-    for bundle_id in chunk_graph.node_indices() {
-        let chunk = &chunk_graph[bundle_id];
-        println!(
-            "{:?} {} {}",
-            bundle_id,
-            chunk
-                .module_ids
-                .iter()
-                .map(|n| module_by_id[*n].name)
-                .collect::<Vec<&str>>()
-                .join(", "),
-            chunk.size
-        )
+/// Feature flags passed as `--flag <name>`, possibly repeated.
+fn enabled_flags(cache: &AnalysisCache) -> HashSet<&'static str> {
+    let args: Vec<String> = std::env::args().collect();
+    args.windows(2)
+        .filter(|pair| pair[0] == "--flag")
+        .map(|pair| cache.intern(&pair[1]))
+        .collect()
+}
+
+/// Budgets passed as `--max-initial-size <bytes>` and `--max-chunk-size <bytes>`.
+fn budgets() -> Budgets {
+    let number = |flag: &str| {
+        arg_value(flag).map(|value| {
+            value
+                .parse()
+                .unwrap_or_else(|_| fail(format!("{} expects a number, got {}", flag, value)))
+        })
+    };
+    Budgets {
+        max_initial_size: number("--max-initial-size"),
+        max_chunk_size: number("--max-chunk-size"),
+    }
+}
+
+/// Policies passed as `--small-chunk-policy <policy>[:<bytes>]`, possibly repeated. A
+/// policy without a size applies to every small chunk.
+fn small_chunk_policies() -> Vec<(usize, SmallChunkPolicy)> {
+    let args: Vec<String> = std::env::args().collect();
+    args.windows(2)
+        .filter(|pair| pair[0] == "--small-chunk-policy")
+        .map(|pair| {
+            let (policy, below) = match pair[1].split_once(':') {
+                Some((policy, below)) => match below.parse() {
+                    Ok(below) => (policy, below),
+                    Err(_) => fail(format!(
+                        "--small-chunk-policy expects a size in bytes after :, got {}",
+                        below
+                    )),
+                },
+                None => (pair[1].as_str(), usize::MAX),
+            };
+            let policy = match policy {
+                "duplicate" => SmallChunkPolicy::DuplicateIntoAll,
+                "largest-parent" => SmallChunkPolicy::MergeIntoLargestParent,
+                "smallest-parent" => SmallChunkPolicy::MergeIntoSmallestParent,
+                "keep" => SmallChunkPolicy::Keep,
+                _ => fail(format!(
+                    "--small-chunk-policy expects duplicate, largest-parent, smallest-parent or keep, got {}",
+                    policy
+                )),
+            };
+            (below, policy)
+        })
+        .collect()
+}
+
+/// Value passed as `<flag> <value>` on the command line, if any.
+fn arg_value(flag: &str) -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != flag);
+    args.next()?;
+    args.next()
+}
+
+/// The first argument after the command that is neither a flag nor the value of one.
+fn graph_path() -> Option<String> {
+    positional_args().into_iter().next()
+}
+
+/// The arguments after the command that are neither flags nor the values of one.
+fn positional_args() -> Vec<String> {
+    let mut positional = vec![];
+    let mut args = std::env::args().skip(2);
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            positional.push(arg);
+        } else if !SWITCHES.contains(&arg.as_str()) {
+            args.next();
+        }
     }
+    positional
+}
+
+/// Prints `message` and exits with a failure status.
+fn fail(message: String) -> ! {
+    eprintln!("split-chunks: {}", message);
+    std::process::exit(1);
 }
 
-// fn remove_bundle(
-//     asset_graph: &Graph<JsModule, Dependency>,
-//     bundle_graph: &mut Graph<Chunk, i32>,
-//     bundle_id: NodeIndex,
-// ) {
-//     let bundle = bundle_graph.remove_node(bundle_id).unwrap();
-//     for asset_id in &bundle.module_ids {
-//         for source_bundle_id in &bundle.source_bundles {
-//             let bundle = &mut bundle_graph[*source_bundle_id];
-//             bundle.module_ids.push(*asset_id);
-//             bundle.size += asset_graph[*asset_id].size;
-//         }
-//     }
-// }
-
-type ModuleId = &'static str;
-
-type ModuleGraph = petgraph::graphmap::DiGraphMap<ModuleId, Dependency>;
-
-fn build_graph() -> (ModuleGraph, Vec<ModuleId>, HashMap<ModuleId, JsModule>) {
-    let mut module_by_id = HashMap::new();
+fn build_graph() -> (ModuleGraph, Vec<Entry>) {
     let mut g = ModuleGraph::new();
     let mut entries = Vec::new();
 
-    module_by_id.insert(
-        "entry-a.js",
-        JsModule {
-            name: "entry-a.js",
-            size: 1000,
+    let entry_a_js = g.add_node(JsModule {
+        name: "entry-a.js".into(),
+        size: 1000,
+        ..Default::default()
+    });
+    let entry_b_js = g.add_node(JsModule {
+        name: "entry-b.js".into(),
+        size: 1000,
+        ..Default::default()
+    });
+    let a_js = g.add_node(JsModule {
+        name: "a.js".into(),
+        size: 1000,
+        ..Default::default()
+    });
+    let b_js = g.add_node(JsModule {
+        name: "b.js".into(),
+        size: 1000,
+        ..Default::default()
+    });
+    let shared_js = g.add_node(JsModule {
+        name: "shared.js".into(),
+        size: 1000,
+        ..Default::default()
+    });
+    let asynced_a_js = g.add_node(JsModule {
+        name: "asynced_a.js".into(),
+        size: 1000,
+        ..Default::default()
+    });
+    let utils_index_js = g.add_node(JsModule {
+        name: "utils/index.js".into(),
+        size: 100,
+        reexports_only: true,
+        ..Default::default()
+    });
+    let utils_format_js = g.add_node(JsModule {
+        name: "utils/format.js".into(),
+        size: 1000,
+        ..Default::default()
+    });
+    let utils_parse_js = g.add_node(JsModule {
+        name: "utils/parse.js".into(),
+        size: 1000,
+        ..Default::default()
+    });
+    let debug_panel_js = g.add_node(JsModule {
+        name: "debug-panel.js".into(),
+        size: 5000,
+        condition: Some(FlagCondition {
+            flag: "debug",
+            enabled: true,
+        }),
+        ..Default::default()
+    });
+    let lodash_js = g.add_node(JsModule {
+        name: "node_modules/lodash/lodash.js".into(),
+        size: 5000,
+        ..Default::default()
+    });
+    let styles_a_css = g.add_node(JsModule {
+        name: "styles/a.css".into(),
+        size: 500,
+        module_type: ModuleType::Css,
+        ..Default::default()
+    });
+
+    g.add_edge(entry_a_js, a_js, Dependency::default());
+    g.add_edge(
+        entry_a_js,
+        asynced_a_js,
+        Dependency {
+            is_async: true,
+            load_likelihood: 0.3,
+            ..Default::default()
         },
     );
-
-    module_by_id.insert(
-        "entry-b.js",
-        JsModule {
-            name: "entry-b.js",
-            size: 1000,
+    g.add_edge(
+        entry_a_js,
+        shared_js,
+        Dependency {
+            import_count: 3,
+            ..Default::default()
         },
     );
-
-    module_by_id.insert(
-        "a.js",
-        JsModule {
-            name: "a.js",
-            size: 1000,
+    g.add_edge(entry_b_js, b_js, Dependency::default());
+    g.add_edge(
+        entry_b_js,
+        asynced_a_js,
+        Dependency {
+            is_async: true,
+            ..Default::default()
         },
     );
-    module_by_id.insert(
-        "b.js",
-        JsModule {
-            name: "b.js",
-            size: 1000,
+    g.add_edge(entry_b_js, shared_js, Dependency::default());
+
+    g.add_edge(
+        a_js,
+        utils_index_js,
+        Dependency {
+            used_reexports: Some(vec![utils_format_js]),
+            ..Default::default()
         },
     );
-
-    module_by_id.insert(
-        "shared.js",
-        JsModule {
-            name: "shared.js",
-            size: 1000,
+    g.add_edge(
+        b_js,
+        utils_index_js,
+        Dependency {
+            used_reexports: Some(vec![utils_parse_js]),
+            ..Default::default()
         },
     );
-
-    module_by_id.insert(
-        "asynced_a.js",
-        JsModule {
-            name: "asynced_a.js",
-            size: 1000,
+    g.add_edge(utils_index_js, utils_format_js, Dependency::default());
+    g.add_edge(utils_index_js, utils_parse_js, Dependency::default());
+    g.add_edge(shared_js, lodash_js, Dependency::default());
+    g.add_edge(
+        entry_b_js,
+        debug_panel_js,
+        Dependency {
+            condition: Some(FlagCondition {
+                flag: "debug",
+                enabled: true,
+            }),
+            ..Default::default()
         },
     );
 
-    let entry_a_js = g.add_node("entry-a.js");
-
-    let entry_b_js = g.add_node("entry-b.js");
-
-    let a_js = g.add_node("a.js");
-    let b_js = g.add_node("b.js");
-
-    let shared_js = g.add_node("shared.js");
-
-    let asynced_a_js = g.add_node("asynced_a.js");
-
-    g.add_edge(entry_a_js, a_js, Dependency { is_async: false });
-    g.add_edge(entry_a_js, asynced_a_js, Dependency { is_async: true });
-    g.add_edge(entry_a_js, shared_js, Dependency { is_async: false });
-    g.add_edge(entry_b_js, b_js, Dependency { is_async: false });
-    // g.add_edge(entry_b_js, asynced_a_js, Dependency { is_async: true });
-    g.add_edge(entry_b_js, shared_js, Dependency { is_async: false });
+    g.add_edge(a_js, styles_a_css, Dependency::default());
 
-    entries.push(entry_a_js);
-    entries.push(entry_b_js);
+    entries.push(Entry::from(entry_a_js));
+    entries.push(Entry::from(entry_b_js));
 
-    return (g, entries, module_by_id);
+    (g, entries)
 }
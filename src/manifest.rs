@@ -0,0 +1,343 @@
+//! The loading manifest: the files each entry loads up front and the files each
+//! dynamic import fetches, for runtimes and servers that preload chunks themselves.
+//!
+//! The files of async imports with a [`ImportHint`] are also listed by entry, so a
+//! server can add `<link rel="preload">` and `<link rel="prefetch">` tags to its pages,
+//! and the external modules of each file, which it has to provide. The weak imports of
+//! each file whose modules no chunk holds are listed too, since the runtime can't
+//! resolve them.
+//!
+//! The [`LoaderTable`] holds the same imports by the chunk of the importer, compact
+//! enough to ship with a browser runtime that loads chunks by id.
+
+use crate::analysis::AnalysisState;
+use crate::chunk_graph::ChunkId;
+use crate::chunk_groups::ChunkGroups;
+use crate::html;
+use crate::ids::{self, AssignedId, ChunkIds};
+use crate::module_graph::ImportHint;
+use crate::module_graph::{Dependency, ModuleId, ModuleInfo};
+use petgraph::visit::EdgeRef;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Debug, Serialize)]
+pub struct Manifest {
+    /// Files each entry loads before it runs, in load order, by the name of its entry
+    /// chunk.
+    pub entries: BTreeMap<String, Vec<String>>,
+    /// Every dynamic import of a chunk group root and every worker started, by importer
+    /// and importee name.
+    pub imports: Vec<ManifestImport>,
+    /// Files fetched by the preloaded imports of modules each entry runs, by entry.
+    pub preload: BTreeMap<String, Vec<String>>,
+    /// Files fetched by the prefetched imports of modules each entry runs, by entry.
+    /// Files the entry also preloads are left out.
+    pub prefetch: BTreeMap<String, Vec<String>>,
+    /// External modules imported by the modules of each file, which the runtime has to
+    /// provide before the file runs.
+    pub externals: BTreeMap<String, Vec<String>>,
+    /// Modules weakly imported by the modules of each file that no chunk holds, so the
+    /// weak imports find nothing to use, see [`Dependency::is_weak`].
+    ///
+    /// [`Dependency::is_weak`]: crate::Dependency::is_weak
+    pub unresolved_weak_imports: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ManifestImport {
+    pub importer: String,
+    pub importee: String,
+    /// Files the import fetches, in load order. Files that every chunk group running
+    /// the importer has already loaded are left out.
+    pub files: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<ImportHint>,
+}
+
+/// Builds the manifest of `state` after every pass has run.
+pub fn manifest<M: ModuleInfo>(state: &AnalysisState<M>) -> Manifest {
+    let g = &state.module_graph;
+    let chunk_graph = &state.chunk_graph;
+    let chunk_groups = ChunkGroups::new(chunk_graph);
+    let file_names = |chunks: &[ChunkId]| -> Vec<String> {
+        chunks
+            .iter()
+            .map(|chunk_id| html::chunk_file_name(chunk_graph, *chunk_id))
+            .collect()
+    };
+
+    let entries = chunk_groups
+        .entry_groups()
+        .map(|(group_id, group)| {
+            let initial = chunk_groups.initial_chunks(group_id);
+            (
+                chunk_graph.graph[group.root].name.clone(),
+                file_names(&initial),
+            )
+        })
+        .collect();
+
+    let mut imports = vec![];
+    let mut hinted: BTreeMap<(ImportHint, String), BTreeSet<String>> = BTreeMap::new();
+    for edge in g.edge_references() {
+        let (importer, importee) = (edge.source(), edge.target());
+        let dependency = edge.weight();
+        // Roots whose chunk group runs the importer.
+        let running: Vec<ModuleId> = state
+            .reachable_modules
+            .iter()
+            .filter(|(root, reachable)| **root == importer || reachable.contains(importer.index()))
+            .map(|(root, _)| *root)
+            .collect();
+        let Some(fetched) = fetched_chunks(state, &chunk_groups, importee, dependency, &running)
+        else {
+            continue;
+        };
+        let files = file_names(&fetched);
+        let hint = dependency.hint;
+        if let Some(hint) = hint {
+            for root in running.iter().filter(|root| state.entries.contains(root)) {
+                let entry_name = chunk_graph.graph[chunk_graph.chunk_roots[root].1]
+                    .name
+                    .clone();
+                hinted
+                    .entry((hint, entry_name))
+                    .or_default()
+                    .extend(files.iter().cloned());
+            }
+        }
+        imports.push(ManifestImport {
+            importer: g[importer].name().to_owned(),
+            importee: g[importee].name().to_owned(),
+            files,
+            hint,
+        });
+    }
+    imports.sort_by(|a, b| (&a.importer, &a.importee).cmp(&(&b.importer, &b.importee)));
+
+    let mut preload = BTreeMap::new();
+    let mut prefetch = BTreeMap::new();
+    for ((hint, entry_name), files) in &hinted {
+        match hint {
+            ImportHint::Preload => {
+                preload.insert(entry_name.clone(), files.iter().cloned().collect());
+            }
+            ImportHint::Prefetch => {
+                let preloaded = hinted.get(&(ImportHint::Preload, entry_name.clone()));
+                let files: Vec<String> = files
+                    .iter()
+                    .filter(|file| !preloaded.is_some_and(|preloaded| preloaded.contains(*file)))
+                    .cloned()
+                    .collect();
+                if !files.is_empty() {
+                    prefetch.insert(entry_name.clone(), files);
+                }
+            }
+        }
+    }
+
+    let mut externals = BTreeMap::new();
+    for (chunk_id, chunk) in chunk_graph.chunks() {
+        let imported: BTreeSet<&str> = chunk
+            .modules
+            .iter()
+            .flat_map(|module_id| g.neighbors(*module_id))
+            .filter(|module_id| g[*module_id].is_external())
+            .map(|module_id| g[module_id].name())
+            .collect();
+        if !imported.is_empty() {
+            externals.insert(
+                html::chunk_file_name(chunk_graph, chunk_id),
+                imported.into_iter().map(str::to_owned).collect(),
+            );
+        }
+    }
+
+    let placed: BTreeSet<ModuleId> = chunk_graph
+        .chunks()
+        .flat_map(|(_, chunk)| chunk.modules.iter().copied())
+        .collect();
+    let mut unresolved_weak_imports = BTreeMap::new();
+    for (chunk_id, chunk) in chunk_graph.chunks() {
+        let unresolved: BTreeSet<&str> = state
+            .weak_imports
+            .iter()
+            .filter(|(importer, importee)| {
+                chunk.modules.contains(importer)
+                    && !placed.contains(importee)
+                    && !g[*importee].is_external()
+            })
+            .map(|(_, importee)| g[*importee].name())
+            .collect();
+        if !unresolved.is_empty() {
+            unresolved_weak_imports.insert(
+                html::chunk_file_name(chunk_graph, chunk_id),
+                unresolved.into_iter().map(str::to_owned).collect(),
+            );
+        }
+    }
+
+    Manifest {
+        entries,
+        imports,
+        preload,
+        prefetch,
+        externals,
+        unresolved_weak_imports,
+    }
+}
+
+/// The chunks loaded whenever the chunk group of `root` runs: its own chunks and those
+/// of the parent roots Step 1 recorded for it, which Step 3 already relies on.
+fn loaded_with<M>(
+    state: &AnalysisState<M>,
+    chunk_groups: &ChunkGroups,
+    root: ModuleId,
+) -> BTreeSet<ChunkId> {
+    let chunk_graph = &state.chunk_graph;
+    let chunks_of_group = |root: &ModuleId| -> BTreeSet<ChunkId> {
+        let group_id = chunk_groups
+            .group_of_root(chunk_graph.chunk_roots[root].1)
+            .unwrap();
+        chunk_groups.initial_chunks(group_id).into_iter().collect()
+    };
+    let mut loaded = chunks_of_group(&root);
+    for (parent, _) in state
+        .reachable_chunks
+        .iter()
+        .filter(|(_, child)| *child == root)
+    {
+        loaded.extend(chunks_of_group(parent));
+    }
+    loaded
+}
+
+/// The chunks an import of `importee` fetches, in load order, if it is a dynamic import
+/// of a chunk group root or starts a worker. Chunks the chunk groups of every root in
+/// `running` have already loaded are left out. `None` for other imports, and if no root
+/// runs the importer.
+fn fetched_chunks<M>(
+    state: &AnalysisState<M>,
+    chunk_groups: &ChunkGroups,
+    importee: ModuleId,
+    dependency: &Dependency,
+    running: &[ModuleId],
+) -> Option<Vec<ChunkId>> {
+    let importee_group = match state.chunk_graph.chunk_roots.get(&importee) {
+        Some((chunk_id, group_id))
+            if (dependency.is_async || dependency.is_worker) && chunk_id == group_id =>
+        {
+            chunk_groups.group_of_root(*group_id).unwrap()
+        }
+        _ => return None,
+    };
+    if running.is_empty() {
+        return None;
+    }
+    // A worker loads all of its chunks itself.
+    let already_loaded = if dependency.is_worker {
+        BTreeSet::new()
+    } else {
+        running
+            .iter()
+            .map(|root| loaded_with(state, chunk_groups, *root))
+            .reduce(|loaded, other| loaded.intersection(&other).copied().collect())
+            .unwrap()
+    };
+    Some(
+        chunk_groups
+            .initial_chunks(importee_group)
+            .into_iter()
+            .filter(|chunk_id| !already_loaded.contains(chunk_id))
+            .collect(),
+    )
+}
+
+/// What a browser runtime needs to resolve the dynamic imports of every chunk with
+/// `__loadChunk(id)`, see [`loader_table`]. Chunks are given by the ids [`ids::assign`]
+/// picked, modules by name.
+#[derive(Debug, Serialize)]
+pub struct LoaderTable {
+    /// The file of every chunk, by chunk id.
+    pub files: BTreeMap<AssignedId, String>,
+    /// By the id of a chunk, the dynamic imports and workers of its modules: by importer
+    /// and import target, the chunks to load before the target runs, in load order.
+    /// Chunks every chunk group loading the chunk has already loaded are left out.
+    pub chunks: BTreeMap<AssignedId, BTreeMap<String, BTreeMap<String, Vec<AssignedId>>>>,
+}
+
+/// Builds the loader table of `state` after every pass has run, with chunk ids picked
+/// by `chunk_ids`. Unlike the [`Manifest`], which lists every import once for all chunk
+/// groups running the importer, each copy of a duplicated importer gets the chunks the
+/// groups loading its own chunk still miss.
+pub fn loader_table<M: ModuleInfo>(state: &AnalysisState<M>, chunk_ids: ChunkIds) -> LoaderTable {
+    let g = &state.module_graph;
+    let chunk_graph = &state.chunk_graph;
+    let chunk_groups = ChunkGroups::new(chunk_graph);
+    let ids = ids::assign(chunk_graph, chunk_ids);
+
+    // Roots whose chunk group loads each chunk. Roots created where the module type
+    // changes have no group of their own.
+    let mut running: BTreeMap<ChunkId, Vec<ModuleId>> = BTreeMap::new();
+    for (root, (chunk_id, group_id)) in &chunk_graph.chunk_roots {
+        if chunk_id != group_id {
+            continue;
+        }
+        let group = chunk_groups.group(chunk_groups.group_of_root(*group_id).unwrap());
+        for chunk_id in &group.chunks {
+            running.entry(*chunk_id).or_default().push(*root);
+        }
+    }
+
+    let mut chunks = BTreeMap::new();
+    for (chunk_id, chunk) in chunk_graph.chunks() {
+        let running = running.get(&chunk_id).map_or(&[][..], Vec::as_slice);
+        let mut imports: BTreeMap<String, BTreeMap<String, Vec<AssignedId>>> = BTreeMap::new();
+        for module_id in &chunk.modules {
+            for edge in g.edges(*module_id) {
+                let Some(fetched) =
+                    fetched_chunks(state, &chunk_groups, edge.target(), edge.weight(), running)
+                else {
+                    continue;
+                };
+                imports
+                    .entry(g[*module_id].name().to_owned())
+                    .or_default()
+                    .insert(
+                        g[edge.target()].name().to_owned(),
+                        fetched
+                            .iter()
+                            .map(|chunk_id| ids[chunk_id].clone())
+                            .collect(),
+                    );
+            }
+        }
+        if !imports.is_empty() {
+            chunks.insert(ids[&chunk_id].clone(), imports);
+        }
+    }
+
+    LoaderTable {
+        files: chunk_graph
+            .chunks()
+            .map(|(chunk_id, _)| {
+                (
+                    ids[&chunk_id].clone(),
+                    html::chunk_file_name(chunk_graph, chunk_id),
+                )
+            })
+            .collect(),
+        chunks,
+    }
+}
+
+/// [`loader_table`] serialized as compact JSON, for embedding in a runtime.
+pub fn loader_table_json<M: ModuleInfo>(state: &AnalysisState<M>, chunk_ids: ChunkIds) -> String {
+    serde_json::to_string(&loader_table(state, chunk_ids)).unwrap()
+}
+
+/// [`manifest`] serialized as pretty-printed JSON.
+pub fn to_json<M: ModuleInfo>(state: &AnalysisState<M>) -> String {
+    serde_json::to_string_pretty(&manifest(state)).unwrap()
+}
@@ -0,0 +1,315 @@
+//! The module graph the algorithm takes as input.
+
+use crate::flags::FlagCondition;
+use petgraph::prelude::NodeIndex;
+use petgraph::Graph;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Borrow;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// Modules are identified by their index in the [`ModuleGraph`].
+pub type ModuleId = NodeIndex;
+
+/// The name of a module. Cloning it shares the string instead of copying it, and names
+/// interned by [`AnalysisCache::symbol`] share one string per distinct name across
+/// every graph built with the cache, which matters for graphs of millions of modules
+/// whose paths repeat the same directories. The string is freed with its last symbol.
+///
+/// [`AnalysisCache::symbol`]: crate::cache::AnalysisCache::symbol
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(Arc<str>);
+
+impl Symbol {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether both symbols share the same string, as those interned by the same cache
+    /// do when they are equal.
+    pub fn ptr_eq(&self, other: &Symbol) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// A symbol of its own for `name`, not shared with equal names unless cloned.
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Symbol(name.into())
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for Symbol {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Serialize for Symbol {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Symbol {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name: std::borrow::Cow<'de, str> = Deserialize::deserialize(deserializer)?;
+        Ok(Symbol::from(&*name))
+    }
+}
+
+/// Modules connected by an edge from each importer to each module it imports. The
+/// algorithm runs on any module type implementing [`ModuleInfo`].
+pub type ModuleGraph<M = JsModule> = Graph<M, Dependency>;
+
+/// What the algorithm reads of a module. Implemented by [`JsModule`], and by the module
+/// type of a host that wants to split its own graph without converting it first, e.g.
+/// to keep source maps or package information next to each module. Modules are read
+/// from several threads at once with the `rayon` feature, so they have to be `Sync`.
+pub trait ModuleInfo: Sync {
+    /// The module's path, which chunks are named after and cache groups match.
+    fn name(&self) -> &str;
+
+    fn size(&self) -> usize;
+
+    /// What kind of file the module is. Chunks only ever contain modules of one type.
+    fn module_type(&self) -> ModuleType {
+        ModuleType::Js
+    }
+
+    /// The module only re-exports other modules (a barrel file such as `index.js`).
+    fn reexports_only(&self) -> bool {
+        false
+    }
+
+    /// The module only exists in builds where this condition holds.
+    fn condition(&self) -> Option<FlagCondition> {
+        None
+    }
+
+    /// The module is provided at runtime instead of bundled, see
+    /// [`JsModule::is_external`].
+    fn is_external(&self) -> bool {
+        false
+    }
+
+    /// The build variant the module belongs to, see [`JsModule::layer`].
+    fn layer(&self) -> Option<&str> {
+        None
+    }
+
+    /// Running the module twice does the same as running it once, see
+    /// [`JsModule::side_effect_free`].
+    fn side_effect_free(&self) -> bool {
+        false
+    }
+
+    /// A module the algorithm adds to the graph itself, like the runtime of
+    /// [`SplitOptions::runtime_chunk`], of which only the name and size are known.
+    ///
+    /// [`SplitOptions::runtime_chunk`]: crate::SplitOptions::runtime_chunk
+    fn synthetic(name: &'static str, size: usize) -> Self
+    where
+        Self: Sized;
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(bound(deserialize = "'de: 'static"))]
+pub struct JsModule {
+    pub name: Symbol,
+    pub size: usize,
+    /// What kind of file the module is. Chunks only ever contain modules of one type.
+    #[serde(default, rename = "type")]
+    pub module_type: ModuleType,
+    /// The module only re-exports other modules (a barrel file such as `index.js`).
+    pub reexports_only: bool,
+    /// The module only exists in builds where this condition holds.
+    pub condition: Option<FlagCondition>,
+    /// The module is provided at runtime instead of bundled, like webpack's `externals`.
+    /// It is never placed in a chunk, and its imports are ignored.
+    #[serde(default)]
+    pub is_external: bool,
+    /// Running the module has no effect beyond its exports, like modules of a package
+    /// with `"sideEffects": false`. Only such modules can be duplicated into chunks
+    /// that run on the same page without changing what the page does, since each copy
+    /// runs on its own.
+    #[serde(default)]
+    pub side_effect_free: bool,
+    /// The build variant the module belongs to, like a webpack layer, e.g. `ssr` or
+    /// `client`. Modules of different layers never share a chunk, like modules of
+    /// different types, so a module built for two layers is two modules of the graph.
+    #[serde(default)]
+    pub layer: Option<Symbol>,
+}
+
+impl ModuleInfo for JsModule {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn module_type(&self) -> ModuleType {
+        self.module_type
+    }
+
+    fn reexports_only(&self) -> bool {
+        self.reexports_only
+    }
+
+    fn condition(&self) -> Option<FlagCondition> {
+        self.condition
+    }
+
+    fn is_external(&self) -> bool {
+        self.is_external
+    }
+
+    fn side_effect_free(&self) -> bool {
+        self.side_effect_free
+    }
+
+    fn layer(&self) -> Option<&str> {
+        self.layer.as_deref()
+    }
+
+    fn synthetic(name: &'static str, size: usize) -> Self {
+        JsModule {
+            name: name.into(),
+            size,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ModuleType {
+    #[default]
+    Js,
+    Css,
+    Wasm,
+    /// Any other file, such as an image or a font, emitted as is.
+    Asset,
+}
+
+impl ModuleType {
+    /// File extension of the chunks of this type. Asset chunks keep the name of their
+    /// root module, extension included.
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            ModuleType::Js => Some("js"),
+            ModuleType::Css => Some("css"),
+            ModuleType::Wasm => Some("wasm"),
+            ModuleType::Asset => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound(deserialize = "'de: 'static"))]
+pub struct Dependency {
+    pub is_async: bool,
+    /// The import starts a web worker running the importee, like
+    /// `new Worker(new URL("./worker.js", import.meta.url))`. The worker is a chunk root
+    /// whose chunks are never shared with the page.
+    #[serde(default)]
+    pub is_worker: bool,
+    /// The import never loads the importee, like webpack's weak imports
+    /// (`require.resolveWeak`, `import(/* webpackMode: "weak" */ ...)`): it only uses
+    /// the importee if something else already loaded it. Weak imports are left out of
+    /// the graph the algorithm runs on, so they neither place the importee nor make it
+    /// reachable, and are kept in [`AnalysisState::weak_imports`] instead.
+    ///
+    /// [`AnalysisState::weak_imports`]: crate::analysis::AnalysisState::weak_imports
+    #[serde(default)]
+    pub is_weak: bool,
+    /// Number of distinct import sites in the importer that reference the importee.
+    pub import_count: usize,
+    /// Probability that an async import is executed once its importer has loaded.
+    /// Sync imports always load with their importer and ignore this.
+    pub load_likelihood: f64,
+    /// For imports of a barrel module, the re-exported modules the importer actually uses.
+    /// `None` means unknown, in which case the whole barrel is assumed to be used.
+    pub used_reexports: Option<Vec<ModuleId>>,
+    /// The import only exists in builds where this condition holds.
+    pub condition: Option<FlagCondition>,
+    /// For async imports, whether the browser should fetch the imported chunks ahead of
+    /// time, like webpack's `webpackPreload` and `webpackPrefetch` comments.
+    #[serde(default)]
+    pub hint: Option<ImportHint>,
+    /// How often an async or worker import is taken relative to its importer's chunk
+    /// group, e.g. from analytics about which routes are visited most. Above 1 for an
+    /// import that is hotter than its importer, as for a route most sessions go on to.
+    /// Chunk groups are as hot as their hottest import path, see
+    /// [`SplitOptions::hot_path_bias`]. `None` counts as 1; sync imports ignore it.
+    ///
+    /// [`SplitOptions::hot_path_bias`]: crate::SplitOptions::hot_path_bias
+    #[serde(default)]
+    pub frequency: Option<f64>,
+}
+
+/// How early an async import's chunks are fetched, ordered by priority.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportHint {
+    /// Fetched while the browser is idle, for imports likely needed on a later
+    /// navigation: `<link rel="prefetch">`.
+    Prefetch,
+    /// Fetched in parallel with the importer's chunks, for imports needed right away:
+    /// `<link rel="preload">`.
+    Preload,
+}
+
+impl Default for Dependency {
+    fn default() -> Self {
+        Dependency {
+            is_async: false,
+            is_worker: false,
+            is_weak: false,
+            import_count: 1,
+            load_likelihood: 1.0,
+            used_reexports: None,
+            condition: None,
+            hint: None,
+            frequency: None,
+        }
+    }
+}
@@ -0,0 +1,49 @@
+//! Node.js bindings through N-API, so a bundler plugin can hand chunking to native code.
+//! Built with the `napi` feature, e.g. by `napi build --features napi`.
+
+use crate::bindings::{self, JsSplitOptions};
+use crate::cache::AnalysisCache;
+use napi::bindgen_prelude::{Object, Uint32Array, Uint8Array};
+use napi::{Error, Result};
+use napi_derive::napi;
+
+/// `splitChunks(graph, options)` from Node.js.
+///
+/// `graph` has the `modules` and `entries` of a graph description in the format of
+/// [`crate::loader`]. Its imports are an `edges` `Uint32Array` of `(importer, importee)`
+/// module index pairs and an optional `edgeKinds` `Uint8Array`, see
+/// [`bindings::graph_from_buffers`]; both are read in place rather than copied.
+/// `options` is an optional object of [`JsSplitOptions`]. Returns the
+/// [`bindings::ChunkAssignment`] and throws if the graph is invalid.
+#[napi(js_name = "splitChunks")]
+pub fn split_chunks(
+    graph: Object,
+    options: Option<serde_json::Value>,
+) -> Result<serde_json::Value> {
+    let field = |name: &str| {
+        graph
+            .get::<_, serde_json::Value>(name)?
+            .ok_or_else(|| Error::from_reason(format!("the graph has no {}", name)))
+    };
+    let modules = field("modules")?;
+    let entries = field("entries")?;
+    let edges: Uint32Array = graph
+        .get("edges")?
+        .ok_or_else(|| Error::from_reason("the graph has no edges"))?;
+    let edge_kinds: Option<Uint8Array> = graph.get("edgeKinds")?;
+    let options: JsSplitOptions = match options {
+        Some(options) => serde_json::from_value(options).map_err(invalid)?,
+        None => JsSplitOptions::default(),
+    };
+
+    let cache = AnalysisCache::default();
+    let (g, entries) =
+        bindings::graph_from_buffers(modules, entries, &edges, edge_kinds.as_deref(), &cache)
+            .map_err(invalid)?;
+    let assignment = bindings::split_loaded(&g, &entries, &options, &cache).map_err(invalid)?;
+    serde_json::to_value(assignment).map_err(invalid)
+}
+
+fn invalid(error: impl ToString) -> Error {
+    Error::from_reason(error.to_string())
+}
@@ -0,0 +1,409 @@
+//! Options that tune how the module graph is split, modelled on webpack's
+//! `optimization.splitChunks`.
+
+use crate::budgets::Budgets;
+use crate::chunk_graph::{Chunk, ChunkKind};
+use crate::ids::ChunkIds;
+use crate::module_graph::ModuleInfo;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+
+#[derive(Debug, Clone)]
+pub struct SplitOptions {
+    /// Shared chunks smaller than this are merged back into the chunks they were split
+    /// from in Step 4.
+    pub min_size: usize,
+    /// Shared chunks split from fewer source chunks than this are removed in Step 4
+    /// whatever their size, since a shared chunk that a single chunk loads costs a
+    /// request and saves nothing. A chunk can be left with one source chunk once the
+    /// others were emptied by earlier passes. Cache group chunks are not affected.
+    pub min_source_chunks: usize,
+    /// Chunks larger than this are split into several parts. `None` leaves chunks
+    /// unbounded.
+    pub max_size: Option<usize>,
+    /// Minimum number of chunk roots a module has to be reachable from before Step 3
+    /// hoists it into a shared chunk. Modules shared by fewer roots are duplicated into
+    /// the chunk of each root. Cache groups are not affected.
+    pub min_chunks: usize,
+    /// Maximum number of chunks an async chunk group may load in parallel.
+    pub max_async_requests: usize,
+    /// Maximum number of chunks an entry may load before it runs.
+    pub max_initial_requests: usize,
+    /// How deep async chunk groups nest below an entry or worker, limiting the chains of
+    /// requests one after another that nested dynamic imports cause. Step 1 creates no
+    /// chunk group for an async import made from a group this many async imports deep,
+    /// and the importee stays in the chunks of its importer's group instead. `None`
+    /// nests them as deep as the imports go.
+    pub max_async_depth: Option<usize>,
+    /// Groups of modules that get chunks of their own, e.g. for vendor code. Checked
+    /// before the generic shared chunk placement of Step 3.
+    pub cache_groups: Vec<CacheGroup>,
+    /// Build-time feature flags that are switched on. Modules and imports whose
+    /// condition doesn't hold are pruned before chunking.
+    pub enabled_flags: HashSet<&'static str>,
+    /// Moves the runtime out of the entry chunks into a single chunk loaded by every
+    /// entry, like webpack's `optimization.runtimeChunk: 'single'`. Otherwise the runtime
+    /// stays inlined in each entry chunk, where it isn't modeled.
+    pub runtime_chunk: bool,
+    /// Size of the synthetic runtime module placed in the runtime chunk.
+    pub runtime_size: usize,
+    /// Forces modules into chunks named by a callback, like Rollup's `manualChunks`.
+    /// Wins over the cache groups a module matches unless one has a higher priority,
+    /// see [`rules::resolve`].
+    ///
+    /// [`rules::resolve`]: crate::rules::resolve
+    pub manual_chunks: Option<ManualChunks>,
+    /// How strongly the minimum size of Step 4 follows the heat of the chunk groups a
+    /// shared chunk was split from, from the [`Dependency::frequency`] of their imports:
+    /// it is scaled by the heat to this power. With 1, a chunk of groups loaded twice as
+    /// often as the entries needs twice the size to stay, keeping hot paths in fewer
+    /// chunks, and one of groups loaded a tenth as often a tenth of it. 0 ignores
+    /// frequencies.
+    ///
+    /// [`Dependency::frequency`]: crate::Dependency::frequency
+    pub hot_path_bias: f64,
+    /// How strongly the minimum size of Step 4 follows the coupling of a shared chunk,
+    /// the average number of import sites from each chunk group loading it into it, see
+    /// [`passes::coupling_of`]: it is scaled by the coupling to this power. With 1, a
+    /// chunk imported from three places in each group needs three times the size to
+    /// stay, keeping strongly coupled modules next to their importers. 0, the default,
+    /// ignores coupling.
+    ///
+    /// [`passes::coupling_of`]: crate::passes::coupling_of
+    pub coupling_bias: f64,
+    /// How Step 4 removes a shared chunk smaller than `min_size`, by the chunk's size:
+    /// each policy applies to chunks smaller than its size, and the one with the
+    /// smallest size the chunk is under wins. Chunks under none of them, and all chunks
+    /// if this is empty, are duplicated into every source chunk.
+    pub small_chunk_policies: Vec<(usize, SmallChunkPolicy)>,
+    /// Reuses an existing chunk for a shared or cache group chunk of Step 3 whose modules
+    /// are exactly the existing chunk's, instead of emitting the same modules twice, like
+    /// webpack's `reuseExistingChunk`. The chunk groups of both then load the existing
+    /// chunk.
+    pub reuse_existing_chunk: bool,
+    /// What Step 4 does when duplicating a small chunk would put a module with side
+    /// effects into several chunks that can run on the same page, where each copy runs
+    /// again. Duplicating into chunks that never share a page, like those of a worker and
+    /// of the page starting it, is always fine.
+    pub side_effect_duplication: SideEffectDuplication,
+    /// After Step 4, moves the modules that several async chunk groups imported by the
+    /// same chunk group each hold a copy of into one shared chunk, if they add up to
+    /// `min_size`, see [`passes::hoist_sibling_duplicates`].
+    ///
+    /// [`passes::hoist_sibling_duplicates`]: crate::passes::hoist_sibling_duplicates
+    pub hoist_sibling_duplicates: bool,
+    /// Whether an entry chunk may hold modules that other chunks import from it, which
+    /// it then exports besides its entry module, like Rollup's `preserveEntrySignatures`,
+    /// see [`passes::add_entry_facades`].
+    ///
+    /// [`passes::add_entry_facades`]: crate::passes::add_entry_facades
+    pub preserve_entry_signatures: EntrySignatures,
+    /// Whether a module imported by the same module both synchronously and on demand,
+    /// e.g. by a re-export and an `import()`, is split off at that import.
+    pub parallel_imports: ParallelImports,
+    /// Which chunk groups Step 3 splits shared chunks and cache group chunks out of, like
+    /// webpack's `splitChunks.chunks`.
+    pub split_from: SplitFrom,
+    /// Size limits the final chunks are checked against by [`budgets::check`]. They
+    /// don't change how the graph is split.
+    ///
+    /// [`budgets::check`]: crate::budgets::check
+    pub budgets: Budgets,
+    /// Renames chunks once every pass has run, e.g. to add a prefix or to shorten the
+    /// names of shared chunks.
+    pub chunk_names: Option<ChunkNames>,
+    /// How the chunks are numbered or named in the output, see [`ids::assign`]. It
+    /// doesn't change how the graph is split.
+    ///
+    /// [`ids::assign`]: crate::ids::assign
+    pub chunk_ids: ChunkIds,
+    /// Checks the chunk graph for broken invariants after every pass in release builds
+    /// too, and panics at the first pass that breaks one, see [`verify::check_pass`].
+    /// Debug builds always check.
+    ///
+    /// [`verify::check_pass`]: crate::verify::check_pass
+    pub verify_passes: bool,
+}
+
+impl Default for SplitOptions {
+    fn default() -> Self {
+        SplitOptions {
+            min_size: 10,
+            min_source_chunks: 2,
+            max_size: None,
+            min_chunks: 1,
+            max_async_requests: 30,
+            max_initial_requests: 30,
+            max_async_depth: None,
+            cache_groups: vec![],
+            enabled_flags: HashSet::new(),
+            runtime_chunk: false,
+            runtime_size: 1000,
+            manual_chunks: None,
+            hot_path_bias: 1.0,
+            coupling_bias: 0.0,
+            small_chunk_policies: vec![],
+            reuse_existing_chunk: false,
+            side_effect_duplication: SideEffectDuplication::Warn,
+            hoist_sibling_duplicates: false,
+            preserve_entry_signatures: EntrySignatures::AllowExtension,
+            parallel_imports: ParallelImports::PreferSync,
+            split_from: SplitFrom::All,
+            budgets: Budgets::default(),
+            chunk_names: None,
+            chunk_ids: ChunkIds::Natural,
+            verify_passes: false,
+        }
+    }
+}
+
+impl SplitOptions {
+    /// The cache group `module_name` belongs to, with its index in `cache_groups`. When
+    /// several groups match, the one with the highest priority wins, then the one listed
+    /// first.
+    pub fn cache_group_of(&self, module_name: &str) -> Option<(usize, &CacheGroup)> {
+        self.cache_groups
+            .iter()
+            .enumerate()
+            .filter(|(_, group)| group.matches(module_name))
+            .fold(
+                None,
+                |best: Option<(usize, &CacheGroup)>, (index, group)| match best {
+                    Some((_, best_group)) if best_group.priority >= group.priority => best,
+                    _ => Some((index, group)),
+                },
+            )
+    }
+
+    /// The name of the manual chunk `module` is forced into, if any.
+    pub fn manual_chunk_of(&self, module: &dyn ModuleInfo) -> Option<String> {
+        self.manual_chunks.as_ref()?.chunk_of(module)
+    }
+
+    /// The policy Step 4 removes a small chunk of `size` bytes with, see
+    /// [`SplitOptions::small_chunk_policies`].
+    pub fn small_chunk_policy(&self, size: usize) -> SmallChunkPolicy {
+        self.small_chunk_policies
+            .iter()
+            .filter(|(below, _)| size < *below)
+            .min_by_key(|(below, _)| *below)
+            .map_or(SmallChunkPolicy::DuplicateIntoAll, |(_, policy)| *policy)
+    }
+
+    /// The cache group `chunk` was created for, if any.
+    pub fn cache_group_of_chunk(&self, chunk: &Chunk) -> Option<&CacheGroup> {
+        let name = chunk.cache_group.as_ref()?;
+        self.cache_groups.iter().find(|group| group.name == *name)
+    }
+}
+
+/// What Step 4 does with a shared chunk smaller than the minimum size.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SmallChunkPolicy {
+    /// Adds the chunk's modules to every source chunk it was split from. Saves the
+    /// request, at the cost of downloading the modules once per source chunk.
+    #[default]
+    DuplicateIntoAll,
+    /// Moves the chunk's modules into the largest chunk that every chunk group loading
+    /// it loads anyway: the root chunk of its only chunk group, or a shared chunk of the
+    /// same groups. Nothing is duplicated and no request added. Chunks without such a
+    /// parent are duplicated into every source chunk.
+    MergeIntoLargestParent,
+    /// Like `MergeIntoLargestParent`, into the smallest such chunk, so chunks stay
+    /// evenly sized.
+    MergeIntoSmallestParent,
+    /// Keeps the chunk despite its size.
+    Keep,
+}
+
+/// How Step 4 treats a small chunk whose duplication would run a module with side
+/// effects twice on one page, see [`JsModule::side_effect_free`].
+///
+/// [`JsModule::side_effect_free`]: crate::JsModule::side_effect_free
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SideEffectDuplication {
+    /// Duplicates the chunk anyway and records a
+    /// [`Decision::SideEffectsDuplicated`](crate::trace::Decision::SideEffectsDuplicated).
+    #[default]
+    Warn,
+    /// Keeps the chunk despite its size and records a
+    /// [`Decision::DuplicationRefused`](crate::trace::Decision::DuplicationRefused).
+    Refuse,
+}
+
+/// What an entry chunk exports, see [`SplitOptions::preserve_entry_signatures`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EntrySignatures {
+    /// The exports of its entry module, and of any module of the chunk that modules of
+    /// other chunks import, like Rollup's `allow-extension`.
+    #[default]
+    AllowExtension,
+    /// Only the exports of its entry module, like Rollup's `strict`. An entry chunk
+    /// other chunks import from keeps only its entry module, as a facade, and its other
+    /// modules move into a shared chunk it loads.
+    Strict,
+}
+
+/// How the imports between the same two modules combine into the one import the
+/// algorithm runs on, see [`analysis::prepare`].
+///
+/// [`analysis::prepare`]: crate::analysis::prepare
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParallelImports {
+    /// The import is async or starts a worker only if all of them do, since a sync import
+    /// loads the module up front anyway, like [`add_dependency`].
+    ///
+    /// [`add_dependency`]: crate::barrels::add_dependency
+    #[default]
+    PreferSync,
+    /// The import is async if any of them is, and starts a worker if any of them does,
+    /// so the module keeps a chunk group of its own that the importer's chunk group
+    /// loads with it.
+    PreferAsync,
+}
+
+/// The chunk groups shared modules are split out of, see [`SplitOptions::split_from`].
+/// A module reachable from a chunk group that is left out stays in the chunk of every
+/// root reaching it, like a module shared by fewer than `min_chunks` roots.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SplitFrom {
+    /// Only entries, leaving the chunks of async imports and workers as they are.
+    Initial,
+    /// Only async imports and workers, leaving what entries load up front as it is.
+    Async,
+    #[default]
+    All,
+}
+
+impl SplitFrom {
+    /// Whether modules are split out of chunk groups whose root chunk is of `kind`.
+    pub fn includes(self, kind: ChunkKind) -> bool {
+        match self {
+            SplitFrom::Initial => kind == ChunkKind::Entry,
+            SplitFrom::Async => kind != ChunkKind::Entry,
+            SplitFrom::All => true,
+        }
+    }
+}
+
+/// Modules whose name contains `test` are placed into dedicated chunks of this group,
+/// one per combination of chunk roots they are reachable from, instead of the generic
+/// shared chunks. Like webpack's `splitChunks.cacheGroups`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheGroup {
+    /// Prefix of the file names of the group's chunks.
+    pub name: String,
+    pub test: String,
+    /// Groups with higher priority win when a module matches several, and win over
+    /// [`SplitOptions::manual_chunks`] if higher than [`ManualChunks::priority`].
+    pub priority: i32,
+    /// Overrides [`SplitOptions::min_size`] for the group's chunks in Step 4.
+    pub min_size: Option<usize>,
+    /// Keep the group's chunks no matter their size or the request limits.
+    pub enforce: bool,
+    /// Gives every npm package whose modules add up to at least this many bytes in the
+    /// graph chunks of its own within the group, like Next.js's `granularChunks`, so
+    /// upgrading one large package leaves the chunks of the others cached. Smaller
+    /// packages and modules outside `node_modules` share the group's chunks.
+    pub split_by_package: Option<usize>,
+}
+
+impl CacheGroup {
+    /// Webpack's `defaultVendors` group: everything under `node_modules`.
+    pub fn vendors() -> Self {
+        CacheGroup {
+            name: "vendors".to_owned(),
+            test: "node_modules/".to_owned(),
+            priority: -10,
+            min_size: None,
+            enforce: false,
+            split_by_package: None,
+        }
+    }
+
+    /// [`CacheGroup::vendors`] with a chunk for every package of at least
+    /// `min_package_size` bytes, see [`CacheGroup::split_by_package`].
+    pub fn granular_vendors(min_package_size: usize) -> Self {
+        CacheGroup {
+            split_by_package: Some(min_package_size),
+            ..CacheGroup::vendors()
+        }
+    }
+
+    pub fn matches(&self, module_name: &str) -> bool {
+        module_name.contains(&self.test)
+    }
+}
+
+/// A callback naming the chunk a module is forced into, or `None` to leave the module
+/// to the automatic placement. Modules given the same name share a chunk, one per
+/// module type. Chunk roots keep their own chunks.
+#[derive(Clone)]
+pub struct ManualChunks {
+    chunk_of: Arc<ManualChunkOf>,
+    priority: i32,
+}
+
+type ManualChunkOf = dyn Fn(&dyn ModuleInfo) -> Option<String> + Send + Sync;
+
+impl ManualChunks {
+    /// Manual chunks with the highest priority, which no cache group outranks.
+    pub fn new(
+        chunk_of: impl Fn(&dyn ModuleInfo) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        ManualChunks {
+            chunk_of: Arc::new(chunk_of),
+            priority: i32::MAX,
+        }
+    }
+
+    /// Lets cache groups with a higher [`CacheGroup::priority`] than `priority` claim
+    /// the modules they match instead.
+    pub fn with_priority(self, priority: i32) -> Self {
+        ManualChunks { priority, ..self }
+    }
+
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    pub fn chunk_of(&self, module: &dyn ModuleInfo) -> Option<String> {
+        (self.chunk_of)(module)
+    }
+}
+
+impl fmt::Debug for ManualChunks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ManualChunks(..)")
+    }
+}
+
+/// A callback giving a chunk its final name from the chunk, with its default name, and
+/// its modules in the order of `chunk.modules`. `None` keeps the default name. Names
+/// should stay unique, since they become file names.
+#[derive(Clone)]
+pub struct ChunkNames(Arc<ChunkNameOf>);
+
+type ChunkNameOf = dyn Fn(&Chunk, &[&dyn ModuleInfo]) -> Option<String> + Send + Sync;
+
+impl ChunkNames {
+    pub fn new(
+        name_of: impl Fn(&Chunk, &[&dyn ModuleInfo]) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        ChunkNames(Arc::new(name_of))
+    }
+
+    pub fn name_of(&self, chunk: &Chunk, modules: &[&dyn ModuleInfo]) -> Option<String> {
+        (self.0)(chunk, modules)
+    }
+}
+
+impl fmt::Debug for ChunkNames {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ChunkNames(..)")
+    }
+}
@@ -0,0 +1,174 @@
+//! The chunk graph as a webpack stats document, so existing visualizers such as
+//! webpack-bundle-analyzer and Statoscope can display the result.
+//!
+//! Only the parts of the format those tools read are filled in. Chunk ids are picked by
+//! [`ids::assign`] and module ids are their indices in the module graph.
+//!
+//! A document written earlier can be read back with `serde_json`, e.g. to compare the
+//! chunks of two builds with [`diff::diff_chunk_graphs`].
+//!
+//! [`diff::diff_chunk_graphs`]: crate::diff::diff_chunk_graphs
+
+use crate::chunk_graph::{ChunkGraph, ChunkKind};
+use crate::chunk_groups::ChunkGroups;
+use crate::hashing::{self, NameAndSize};
+use crate::html;
+use crate::ids::{self, AssignedId, ChunkIds};
+use crate::module_graph::{ModuleGraph, ModuleId, ModuleInfo};
+use petgraph::prelude::NodeIndex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Stats {
+    pub assets: Vec<StatsAsset>,
+    pub chunks: Vec<StatsChunk>,
+    pub modules: Vec<StatsModule>,
+    /// Initial chunks of each entry, by the name of its entry chunk.
+    pub entrypoints: BTreeMap<String, StatsEntrypoint>,
+}
+
+/// The file emitted for a chunk.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsAsset {
+    pub name: String,
+    pub size: usize,
+    pub chunks: Vec<AssignedId>,
+    pub chunk_names: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatsChunk {
+    pub id: AssignedId,
+    pub names: Vec<String>,
+    pub files: Vec<String>,
+    pub size: usize,
+    /// The chunk's content hash, see [`hashing::chunk_hashes`].
+    pub hash: String,
+    /// Whether the chunk is the root chunk of an entry.
+    pub entry: bool,
+    /// Whether the chunk is loaded up front by an entry rather than by an async import.
+    pub initial: bool,
+    /// Roots of the chunk groups that load the chunk.
+    pub parents: Vec<AssignedId>,
+    /// For chunk group roots, the chunks the group loads, including the roots of async
+    /// chunk groups.
+    pub children: Vec<AssignedId>,
+    pub modules: Vec<StatsModule>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsModule {
+    pub id: usize,
+    pub name: String,
+    pub size: usize,
+    /// Every chunk the module was placed in, more than one if it is duplicated.
+    pub chunks: Vec<AssignedId>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatsEntrypoint {
+    pub chunks: Vec<AssignedId>,
+    pub assets: Vec<StatsEntrypointAsset>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatsEntrypointAsset {
+    pub name: String,
+}
+
+/// Builds the stats document of `chunk_graph`, whose modules are those of `g`, with
+/// chunk ids picked by `chunk_ids`.
+pub fn stats<M: ModuleInfo>(
+    chunk_graph: &ChunkGraph,
+    g: &ModuleGraph<M>,
+    chunk_ids: ChunkIds,
+) -> Stats {
+    let chunk_groups = ChunkGroups::new(chunk_graph);
+    let hashes = hashing::chunk_hashes(chunk_graph, g, &NameAndSize);
+    let ids = ids::assign(chunk_graph, chunk_ids);
+    let id_of = |chunk_id: &NodeIndex| ids[chunk_id].clone();
+
+    let mut chunks_of_module: BTreeMap<ModuleId, Vec<AssignedId>> = BTreeMap::new();
+    for (chunk_id, chunk) in chunk_graph.chunks() {
+        for module_id in &chunk.modules {
+            chunks_of_module
+                .entry(*module_id)
+                .or_default()
+                .push(id_of(&chunk_id));
+        }
+    }
+    let stats_module = |module_id: &ModuleId| StatsModule {
+        id: module_id.index(),
+        name: g[*module_id].name().to_owned(),
+        size: g[*module_id].size(),
+        chunks: chunks_of_module[module_id].clone(),
+    };
+
+    let initial: BTreeSet<NodeIndex> = chunk_groups
+        .entry_groups()
+        .flat_map(|(group_id, _)| chunk_groups.initial_chunks(group_id))
+        .collect();
+
+    let mut assets = vec![];
+    let mut chunks = vec![];
+    for (chunk_id, chunk) in chunk_graph.chunks() {
+        let file_name = html::chunk_file_name(chunk_graph, chunk_id);
+        assets.push(StatsAsset {
+            name: file_name.clone(),
+            size: chunk.size,
+            chunks: vec![id_of(&chunk_id)],
+            chunk_names: vec![chunk.name.clone()],
+        });
+
+        let children: BTreeSet<NodeIndex> = chunk_graph.graph.neighbors(chunk_id).collect();
+        chunks.push(StatsChunk {
+            id: id_of(&chunk_id),
+            names: vec![chunk.name.clone()],
+            files: vec![file_name],
+            size: chunk.size,
+            hash: hashing::to_hex(hashes[&chunk_id]),
+            entry: chunk.kind == ChunkKind::Entry,
+            initial: initial.contains(&chunk_id),
+            parents: chunk_graph.parents(chunk_id).iter().map(id_of).collect(),
+            children: children.iter().map(id_of).collect(),
+            modules: chunk.modules.iter().map(stats_module).collect(),
+        });
+    }
+
+    let modules = chunks_of_module.keys().map(stats_module).collect();
+
+    let entrypoints = chunk_groups
+        .entry_groups()
+        .map(|(group_id, group)| {
+            let initial = chunk_groups.initial_chunks(group_id);
+            let entrypoint = StatsEntrypoint {
+                chunks: initial.iter().map(id_of).collect(),
+                assets: initial
+                    .iter()
+                    .map(|chunk_id| StatsEntrypointAsset {
+                        name: html::chunk_file_name(chunk_graph, *chunk_id),
+                    })
+                    .collect(),
+            };
+            (chunk_graph.graph[group.root].name.clone(), entrypoint)
+        })
+        .collect();
+
+    Stats {
+        assets,
+        chunks,
+        modules,
+        entrypoints,
+    }
+}
+
+/// [`stats`] serialized as pretty-printed JSON.
+pub fn to_json<M: ModuleInfo>(
+    chunk_graph: &ChunkGraph,
+    g: &ModuleGraph<M>,
+    chunk_ids: ChunkIds,
+) -> String {
+    serde_json::to_string_pretty(&stats(chunk_graph, g, chunk_ids)).unwrap()
+}
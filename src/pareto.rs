@@ -0,0 +1,197 @@
+//! Pareto-front selection over the configurations explored by a parameter sweep.
+
+use crate::analysis::AnalysisState;
+use crate::module_graph::ModuleId;
+use crate::module_graph::ModuleInfo;
+use crate::options::{SmallChunkPolicy, SplitOptions};
+use crate::passes;
+use crate::pipeline::{Pipeline, Stage};
+use crate::trace::DecisionTrace;
+use petgraph::prelude::NodeIndex;
+use std::collections::{HashMap, HashSet};
+
+/// The cost of the chunk graph produced by one configuration. Lower is better for
+/// every metric.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SweepPoint {
+    /// Minimum shared chunk size used in Step 4.
+    pub min_size: usize,
+    /// Bytes loaded by all entries before any async import runs.
+    pub initial_bytes: usize,
+    /// Chunks requested by all chunk groups together.
+    pub request_count: usize,
+    /// Bytes of modules that ended up in more than one chunk.
+    pub duplication_bytes: usize,
+}
+
+impl SweepPoint {
+    fn costs(&self) -> [usize; 3] {
+        [
+            self.initial_bytes,
+            self.request_count,
+            self.duplication_bytes,
+        ]
+    }
+
+    /// Whether `self` is at least as good as `other` everywhere and better somewhere.
+    pub fn dominates(&self, other: &SweepPoint) -> bool {
+        let (ours, theirs) = (self.costs(), other.costs());
+        ours.iter().zip(&theirs).all(|(a, b)| a <= b) && ours != theirs
+    }
+}
+
+/// Every point that no other point dominates, in sweep order. Of points with equal
+/// costs only the one with the smallest `min_size` is kept.
+pub fn pareto_front(points: &[SweepPoint]) -> Vec<SweepPoint> {
+    let mut smallest: HashMap<[usize; 3], usize> = HashMap::new();
+    for point in points {
+        let min_size = smallest.entry(point.costs()).or_insert(point.min_size);
+        *min_size = (*min_size).min(point.min_size);
+    }
+    let mut kept = HashSet::new();
+    points
+        .iter()
+        .filter(|point| smallest[&point.costs()] == point.min_size)
+        .filter(|point| kept.insert(point.costs()))
+        .filter(|point| !points.iter().any(|other| other.dominates(point)))
+        .cloned()
+        .collect()
+}
+
+/// Renders points as CSV with a header row, ready for plotting.
+pub fn to_csv(points: &[SweepPoint]) -> String {
+    let mut csv = String::from("min_size,initial_bytes,request_count,duplication_bytes\n");
+    for point in points {
+        csv.push_str(&format!(
+            "{},{},{},{}\n",
+            point.min_size, point.initial_bytes, point.request_count, point.duplication_bytes
+        ));
+    }
+    csv
+}
+
+/// Runs Step 4 and every later pass on a copy of `state` for every minimum size at
+/// which Step 4 may decide differently, including `options.min_size`, and measures each
+/// result. Expects the passes before Step 4 to have run already.
+pub fn sweep_min_size<M: ModuleInfo + Clone>(
+    state: &AnalysisState<M>,
+    options: &SplitOptions,
+) -> Vec<SweepPoint> {
+    min_size_candidates(state, options)
+        .into_iter()
+        .map(|min_size| {
+            let mut candidate = state.clone();
+            let options = SplitOptions {
+                min_size,
+                ..options.clone()
+            };
+            Pipeline::default().optimize_from(
+                Stage::PruneSmallChunks,
+                &mut candidate,
+                &options,
+                &mut DecisionTrace::new(false),
+            );
+            measure(&candidate, min_size)
+        })
+        .collect()
+}
+
+/// Minimum sizes at which Step 4 may change its mind about some shared bundle: the
+/// smallest at which the bundle's expected size falls under its scaled minimum size,
+/// see [`passes::expected_size_and_scale`]. Bundles Step 4 keeps or removes whatever
+/// the minimum size, like those of enforced cache groups, with a minimum size of their
+/// own or split from too few chunks, add none.
+fn min_size_candidates<M: ModuleInfo>(
+    state: &AnalysisState<M>,
+    options: &SplitOptions,
+) -> Vec<usize> {
+    let chunk_graph = &state.chunk_graph.graph;
+    let likelihoods = passes::group_likelihoods(
+        &state.module_graph,
+        &state.chunk_graph,
+        &state.entries,
+        &state.reachable_modules,
+    );
+    let heats = passes::group_heats(
+        &state.module_graph,
+        &state.chunk_graph,
+        &state.entries,
+        &state.reachable_modules,
+    );
+    let mut candidates = vec![0, options.min_size];
+    for bundle_id in chunk_graph.node_indices() {
+        let bundle = &chunk_graph[bundle_id];
+        let cache_group = options.cache_group_of_chunk(bundle);
+        if bundle.source_bundles.is_empty()
+            || bundle.modules.is_empty()
+            || !passes::fits_source_bundles(chunk_graph, bundle_id)
+            || cache_group.is_some_and(|group| group.enforce || group.min_size.is_some())
+            || passes::has_few_source_chunks(bundle, options)
+            || options.small_chunk_policy(bundle.size) == SmallChunkPolicy::Keep
+        {
+            continue;
+        }
+        let (expected_size, scale) =
+            passes::expected_size_and_scale(chunk_graph, bundle_id, &likelihoods, &heats, options);
+        if !(scale > 0.0 && scale.is_finite()) {
+            continue;
+        }
+        // The scaled minimum size is rounded, so step to the first that exceeds it.
+        let is_small = |min_size: usize| (min_size as f64 * scale).round() > expected_size;
+        let mut min_size = (expected_size / scale) as usize;
+        while min_size > 0 && is_small(min_size - 1) {
+            min_size -= 1;
+        }
+        while !is_small(min_size) {
+            min_size += 1;
+        }
+        candidates.push(min_size);
+    }
+    candidates.sort_unstable();
+    candidates.dedup();
+    candidates
+}
+
+/// Measures the cost of the chunk graph of `state`, produced with `min_size`. Empty
+/// chunks are ignored.
+pub fn measure<M: ModuleInfo>(state: &AnalysisState<M>, min_size: usize) -> SweepPoint {
+    let chunk_graph = &state.chunk_graph.graph;
+    let groups = state.chunk_graph.groups();
+    let chunks_of_group = |group_id: NodeIndex| {
+        Some(group_id)
+            .into_iter()
+            .chain(
+                chunk_graph
+                    .neighbors(group_id)
+                    .filter(|chunk_id| !groups.contains(chunk_id)),
+            )
+            .filter(|chunk_id| !chunk_graph[*chunk_id].modules.is_empty())
+            .collect::<HashSet<NodeIndex>>()
+    };
+
+    let initial_bytes = state
+        .entries
+        .iter()
+        .flat_map(|entry| chunks_of_group(state.chunk_graph.chunk_roots[entry].1))
+        .map(|chunk_id| chunk_graph[chunk_id].size)
+        .sum();
+    let request_count = groups
+        .iter()
+        .map(|group_id| chunks_of_group(*group_id).len())
+        .sum();
+    let total_bytes: usize = chunk_graph.node_weights().map(|chunk| chunk.size).sum();
+    let unique_bytes: usize = chunk_graph
+        .node_weights()
+        .flat_map(|chunk| chunk.modules.iter().copied())
+        .collect::<HashSet<ModuleId>>()
+        .into_iter()
+        .map(|module_id| state.module_graph[module_id].size())
+        .sum();
+
+    SweepPoint {
+        min_size,
+        initial_bytes,
+        request_count,
+        duplication_bytes: total_bytes - unique_bytes,
+    }
+}
@@ -0,0 +1,1492 @@
+//! Passes that run on the chunk graph after Step 3: removing available modules,
+//! garbage collection, edge weights, Step 4, splitting oversized chunks and the runtime
+//! chunk.
+
+use crate::analysis::{AnalysisState, Combination};
+use crate::chunk_graph::{combined_name, Chunk, ChunkGraph, ChunkKind};
+use crate::chunk_groups::ChunkGroups;
+use crate::module_graph::{Dependency, ModuleGraph, ModuleId, ModuleInfo};
+use crate::options::{EntrySignatures, SideEffectDuplication, SmallChunkPolicy, SplitOptions};
+use crate::pipeline::Pipeline;
+use crate::trace::{self, DecisionTrace};
+use fixedbitset::FixedBitSet;
+use petgraph::prelude::{Incoming, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::Graph;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// What [`optimize`] changed.
+#[derive(Debug, Default)]
+pub struct OptimizeReport {
+    /// `(chunk, module)` pairs removed because every parent chunk group loads the module.
+    pub removed_available: Vec<(NodeIndex, ModuleId)>,
+    pub gc_after_available: GcSummary,
+    /// Shared chunks merged back into their source chunks in Step 4, by their index
+    /// before the following garbage collection.
+    pub removed_small: Vec<NodeIndex>,
+    pub gc_after_small: GcSummary,
+    /// Shared chunks that modules duplicated across sibling async chunks were hoisted
+    /// into, if `hoist_sibling_duplicates` is set.
+    pub hoisted: Vec<NodeIndex>,
+    /// Chunks larger than `max_size`, with the chunks their extra parts were moved to.
+    pub split_large: Vec<(NodeIndex, Vec<NodeIndex>)>,
+    /// Shared chunks merged back into their source chunks to respect the request
+    /// limits, by their index before the following garbage collection.
+    pub merged_for_requests: Vec<NodeIndex>,
+    pub gc_after_requests: GcSummary,
+    /// Entry chunks turned into facades, with the shared chunks their other modules
+    /// went to, if `preserve_entry_signatures` is `Strict`.
+    pub facades: Vec<(NodeIndex, NodeIndex)>,
+    /// The chunk the runtime was moved into, if `runtime_chunk` is set.
+    pub runtime_chunk: Option<NodeIndex>,
+    /// Shared chunks left with fewer than `min_source_chunks` source chunks by the
+    /// passes after Step 4 and merged back into them, by their index before the garbage
+    /// collection that followed each pass.
+    pub removed_few_sources: Vec<NodeIndex>,
+}
+
+/// Runs every pass after Step 3 in order: Step 3.5, edge weighing, Step 4, hoisting
+/// the modules sibling async chunks duplicate, the request limits, the `max_size`
+/// split, the entry facades, the runtime chunk extraction and the execution order of
+/// the modules, with garbage collection after each pass that empties chunks. Chunks are
+/// renamed last. Every pass is followed by [`verify::check_pass`]. This is
+/// [`Pipeline::optimize`] without passes of a host, see [`Stage`] for the order.
+///
+/// Like in webpack, `min_size` takes precedence over `max_size`, which takes precedence
+/// over the request limits: the split runs last and may add requests again.
+///
+/// [`verify::check_pass`]: crate::verify::check_pass
+/// [`Stage`]: crate::pipeline::Stage
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn optimize<M: ModuleInfo>(
+    state: &mut AnalysisState<M>,
+    options: &SplitOptions,
+    trace: &mut DecisionTrace,
+) -> OptimizeReport {
+    Pipeline::default().optimize(state, options, trace)
+}
+
+/// Step 3.5: Removes modules that every parent chunk group has already loaded, then
+/// collects the chunks that became empty.
+///
+/// The filter in Step 3 only leaves a module out of an async root that one of the root's
+/// parents reaches, so a module each chunk group importing the root loads from another
+/// parent is still carried.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn prune_available_modules<M: ModuleInfo>(
+    state: &mut AnalysisState<M>,
+    trace: &mut DecisionTrace,
+) -> (Vec<(NodeIndex, ModuleId)>, GcSummary) {
+    let removed =
+        remove_available_modules(&mut state.chunk_graph, &state.entries, &state.module_graph);
+    for (chunk_id, module_id) in &removed {
+        trace.record(|| trace::Decision::ModuleRemovedAsAvailable {
+            module: *module_id,
+            chunk: *chunk_id,
+        });
+    }
+    tracing::debug!(modules = removed.len(), "removed available modules");
+    let gc_summary = collect_garbage(&mut state.chunk_graph, &mut state.chunks);
+    (removed, gc_summary)
+}
+
+/// Step 4 with `options.min_size`, followed by garbage collection. Expects the chunk
+/// edges to be weighed already.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn merge_small_chunks<M: ModuleInfo>(
+    state: &mut AnalysisState<M>,
+    options: &SplitOptions,
+    trace: &mut DecisionTrace,
+) -> (Vec<NodeIndex>, GcSummary) {
+    let likelihoods = group_likelihoods(
+        &state.module_graph,
+        &state.chunk_graph,
+        &state.entries,
+        &state.reachable_modules,
+    );
+    let heats = group_heats(
+        &state.module_graph,
+        &state.chunk_graph,
+        &state.entries,
+        &state.reachable_modules,
+    );
+    let groups = state.chunk_graph.groups();
+    let removed = remove_small_bundles(
+        &mut state.chunk_graph.graph,
+        &groups,
+        &state.module_graph,
+        &likelihoods,
+        &heats,
+        options,
+        trace,
+    );
+    tracing::debug!(chunks = removed.len(), "removed small chunks");
+    let gc_summary = collect_garbage(&mut state.chunk_graph, &mut state.chunks);
+    (removed, gc_summary)
+}
+
+/// Moves the modules that several async chunk groups imported by the same chunk group
+/// each hold a copy of into one shared chunk all of them load, if
+/// `options.hoist_sibling_duplicates` is set. Returns the chunks the modules went to.
+///
+/// Step 3 only shares a module between the chunk roots that reach it, so modules
+/// reached by different combinations of sibling roots end up in different shared
+/// chunks, which Step 4 duplicates back into the siblings when each is under
+/// `min_size`. Together they may be worth a request: the copies of every module held by
+/// at least two siblings of a parent are hoisted if they add up to `min_size`, at the
+/// cost of siblings loading some modules they don't need. Siblings are the root chunks
+/// of async chunk groups, by module type and layer. The modules go into a shared chunk
+/// of the same type and layer loaded by exactly the siblings holding them, if there is
+/// one.
+pub fn hoist_sibling_duplicates<M: ModuleInfo>(
+    state: &mut AnalysisState<M>,
+    options: &SplitOptions,
+    trace: &mut DecisionTrace,
+) -> Vec<NodeIndex> {
+    if !options.hoist_sibling_duplicates {
+        return vec![];
+    }
+    let g = &state.module_graph;
+    let chunk_graph = &mut state.chunk_graph;
+    let groups = chunk_graph.groups();
+    let mut hoisted = vec![];
+    for parent_id in &groups {
+        let mut siblings: BTreeMap<_, BTreeSet<NodeIndex>> = BTreeMap::new();
+        for child_id in chunk_graph.graph.neighbors(*parent_id) {
+            let child = &chunk_graph.graph[child_id];
+            if child.kind == ChunkKind::Async && groups.contains(&child_id) {
+                siblings
+                    .entry((child.module_type, child.layer.clone()))
+                    .or_default()
+                    .insert(child_id);
+            }
+        }
+        for ((module_type, layer), siblings) in siblings {
+            let mut holders: BTreeMap<ModuleId, Vec<NodeIndex>> = BTreeMap::new();
+            for sibling_id in &siblings {
+                for module_id in &chunk_graph.graph[*sibling_id].modules {
+                    if !chunk_graph.chunk_roots.contains_key(module_id) {
+                        holders.entry(*module_id).or_default().push(*sibling_id);
+                    }
+                }
+            }
+            holders.retain(|_, holders| holders.len() > 1);
+            let size: usize = holders.keys().map(|module_id| g[*module_id].size()).sum();
+            if holders.is_empty() || size < options.min_size {
+                continue;
+            }
+            let modules: Vec<ModuleId> = holders.keys().copied().collect();
+            let from: BTreeSet<NodeIndex> = holders.into_values().flatten().collect();
+            let graph = &mut chunk_graph.graph;
+            for sibling_id in &from {
+                let sibling = &mut graph[*sibling_id];
+                sibling
+                    .modules
+                    .retain(|module_id| !modules.contains(module_id));
+                sibling.size = sibling
+                    .modules
+                    .iter()
+                    .map(|module_id| g[*module_id].size())
+                    .sum();
+            }
+            let existing = graph.node_indices().find(|chunk_id| {
+                let chunk = &graph[*chunk_id];
+                chunk.kind == ChunkKind::Shared
+                    && chunk.cache_group.is_none()
+                    && chunk.module_type == module_type
+                    && chunk.layer == layer
+                    && graph
+                        .neighbors_directed(*chunk_id, Incoming)
+                        .collect::<BTreeSet<_>>()
+                        == from
+            });
+            let chunk_id = existing.unwrap_or_else(|| {
+                let mut names: Vec<&str> = from
+                    .iter()
+                    .map(|sibling_id| chunk_graph.graph[*sibling_id].name.as_str())
+                    .collect();
+                names.sort_unstable();
+                let chunk = Chunk {
+                    name: combined_name("shared", &names),
+                    module_type,
+                    layer: layer.clone(),
+                    source_bundles: from.iter().copied().collect(),
+                    ..Chunk::default()
+                };
+                let chunk_id = chunk_graph.add_chunk(chunk);
+                for sibling_id in &from {
+                    chunk_graph.graph.add_edge(*sibling_id, chunk_id, 0);
+                }
+                chunk_id
+            });
+            let chunk = &mut chunk_graph.graph[chunk_id];
+            for module_id in &modules {
+                if !chunk.modules.contains(module_id) {
+                    chunk.modules.push(*module_id);
+                    chunk.size += g[*module_id].size();
+                }
+            }
+            trace.record(|| trace::Decision::DuplicatesHoisted {
+                chunk: chunk_id,
+                modules,
+                from: from.into_iter().collect(),
+            });
+            hoisted.push(chunk_id);
+        }
+    }
+    if !hoisted.is_empty() {
+        weigh_chunk_edges(&mut state.chunk_graph, &state.module_graph);
+    }
+    tracing::debug!(chunks = hoisted.len(), "hoisted sibling duplicates");
+    hoisted
+}
+
+/// Merges shared chunks back into their source chunks until every entry loads at most
+/// `options.max_initial_requests` chunks and every async chunk group at most
+/// `options.max_async_requests`, then collects the emptied chunks.
+///
+/// The smallest shared chunk of a group over its limit is merged first, so the fewest
+/// bytes get duplicated. The group's root chunk counts as a request too. Chunks of
+/// enforced cache groups are never merged, so a group may stay over its limit.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn limit_requests<M: ModuleInfo>(
+    state: &mut AnalysisState<M>,
+    options: &SplitOptions,
+    trace: &mut DecisionTrace,
+) -> (Vec<NodeIndex>, GcSummary) {
+    // Merging a chunk only moves modules, so the chunks of every group stay the same,
+    // except that merged chunks are left empty until the garbage collection below.
+    let chunk_groups = ChunkGroups::new(&state.chunk_graph);
+    let mut merged = vec![];
+    for (_, group) in chunk_groups.groups() {
+        let max_requests = if group.kind == ChunkKind::Entry {
+            options.max_initial_requests
+        } else {
+            options.max_async_requests
+        };
+        loop {
+            let chunk_graph = &state.chunk_graph.graph;
+            let shared: Vec<NodeIndex> = group
+                .chunks
+                .iter()
+                .copied()
+                .filter(|chunk_id| {
+                    *chunk_id != group.root && !chunk_graph[*chunk_id].modules.is_empty()
+                })
+                .collect();
+            let requests = shared.len() + 1;
+            if requests <= max_requests {
+                break;
+            }
+            let smallest = shared
+                .into_iter()
+                .filter(|chunk_id| {
+                    let chunk = &chunk_graph[*chunk_id];
+                    !chunk.source_bundles.is_empty()
+                        && fits_source_bundles(chunk_graph, *chunk_id)
+                        && !matches!(options.cache_group_of_chunk(chunk), Some(group) if group.enforce)
+                })
+                .min_by_key(|chunk_id| (chunk_graph[*chunk_id].size, *chunk_id));
+            let chunk_id = match smallest {
+                Some(chunk_id) => chunk_id,
+                None => break,
+            };
+            trace.record(|| trace::Decision::ChunkMergedForRequests {
+                chunk: chunk_id,
+                group: group.root,
+                requests,
+                max_requests,
+            });
+            remove_bundle(&state.module_graph, &mut state.chunk_graph.graph, chunk_id);
+            merged.push(chunk_id);
+        }
+    }
+
+    tracing::debug!(
+        chunks = merged.len(),
+        "merged chunks for the request limits"
+    );
+    let gc_summary = collect_garbage(&mut state.chunk_graph, &mut state.chunks);
+    (merged, gc_summary)
+}
+
+/// Merges the shared chunks split from fewer than `options.min_source_chunks` chunks
+/// back into their source chunks, as Step 4 does, then collects the emptied chunks.
+/// Runs after the passes following Step 4 that merge or add chunks, so a shared chunk
+/// they leave with a single source chunk doesn't cost a request for nothing.
+///
+/// With strict entry signatures, a shared chunk loaded by entry chunks alone holds the
+/// modules of an entry facade, see [`add_entry_facades`], and stays.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn merge_chunks_of_few_sources<M: ModuleInfo>(
+    state: &mut AnalysisState<M>,
+    options: &SplitOptions,
+    trace: &mut DecisionTrace,
+) -> (Vec<NodeIndex>, GcSummary) {
+    let chunk_graph = &state.chunk_graph.graph;
+    let merged: Vec<NodeIndex> = chunk_graph
+        .node_indices()
+        .filter(|chunk_id| {
+            let chunk = &chunk_graph[*chunk_id];
+            let holds_facade_modules = options.preserve_entry_signatures == EntrySignatures::Strict
+                && chunk
+                    .source_bundles
+                    .iter()
+                    .all(|source_id| chunk_graph[*source_id].kind == ChunkKind::Entry);
+            has_few_source_chunks(chunk, options)
+                && !chunk.modules.is_empty()
+                && fits_source_bundles(chunk_graph, *chunk_id)
+                && !holds_facade_modules
+        })
+        .collect();
+    if merged.is_empty() {
+        return (merged, GcSummary::default());
+    }
+    for chunk_id in &merged {
+        trace.record(|| trace::Decision::ChunkRemovedForFewSources {
+            chunk: *chunk_id,
+            source_chunks: state.chunk_graph.graph[*chunk_id].source_bundles.len(),
+            min_source_chunks: options.min_source_chunks,
+        });
+        remove_bundle(&state.module_graph, &mut state.chunk_graph.graph, *chunk_id);
+    }
+    tracing::debug!(
+        chunks = merged.len(),
+        "merged chunks of too few source chunks"
+    );
+    let gc_summary = collect_garbage(&mut state.chunk_graph, &mut state.chunks);
+    (merged, gc_summary)
+}
+
+/// Whether `chunk` is a shared chunk split from fewer than `options.min_source_chunks`
+/// chunks. Cache group chunks never are.
+pub fn has_few_source_chunks(chunk: &Chunk, options: &SplitOptions) -> bool {
+    chunk.cache_group.is_none()
+        && !chunk.source_bundles.is_empty()
+        && chunk.source_bundles.len() < options.min_source_chunks
+}
+
+/// Splits the chunks larger than `options.max_size`, if set, and weighs the edges of
+/// the new parts.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn enforce_max_size<M: ModuleInfo>(
+    state: &mut AnalysisState<M>,
+    options: &SplitOptions,
+    trace: &mut DecisionTrace,
+) -> Vec<(NodeIndex, Vec<NodeIndex>)> {
+    let max_size = match options.max_size {
+        Some(max_size) => max_size,
+        None => return vec![],
+    };
+    let splits = split_large_chunks(&mut state.chunk_graph, &state.module_graph, max_size, trace);
+    weigh_chunk_edges(&mut state.chunk_graph, &state.module_graph);
+    tracing::debug!(chunks = splits.len(), "split large chunks");
+    splits
+}
+
+/// Turns every entry chunk holding modules that modules of other chunks import into a
+/// facade, if `options.preserve_entry_signatures` is `Strict`: the entry chunk keeps
+/// only its entry module, and its other modules move into a new shared chunk of the
+/// entry's chunk group, which the other chunks import from instead. The entry chunk
+/// then exports what its entry module does and nothing else. Returns every facade
+/// with the chunk its modules went to.
+///
+/// Other chunks import from an entry chunk when they are loaded after it, like the
+/// chunks of an async import or of an entry depending on it, and find a module they
+/// need in it.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn add_entry_facades<M: ModuleInfo>(
+    state: &mut AnalysisState<M>,
+    options: &SplitOptions,
+    trace: &mut DecisionTrace,
+) -> Vec<(NodeIndex, NodeIndex)> {
+    if options.preserve_entry_signatures != EntrySignatures::Strict {
+        return vec![];
+    }
+    let g = &state.module_graph;
+    let chunk_graph = &mut state.chunk_graph;
+    let mut chunks_of_module: HashMap<ModuleId, Vec<NodeIndex>> = HashMap::new();
+    for (chunk_id, chunk) in chunk_graph.chunks() {
+        for module_id in &chunk.modules {
+            chunks_of_module
+                .entry(*module_id)
+                .or_default()
+                .push(chunk_id);
+        }
+    }
+    // Whether a copy of an importer of `module_id` in another chunk than `chunk_id`
+    // has no copy of the module at hand.
+    let imported_from_elsewhere = |chunks_of_module: &HashMap<ModuleId, Vec<NodeIndex>>,
+                                   module_id: ModuleId,
+                                   chunk_id: NodeIndex| {
+        g.edges_directed(module_id, Incoming)
+            .filter(|edge| !edge.weight().is_weak)
+            .flat_map(|edge| chunks_of_module.get(&edge.source()).into_iter().flatten())
+            .any(|importer_chunk| {
+                *importer_chunk != chunk_id
+                    && !chunks_of_module[&module_id].contains(importer_chunk)
+            })
+    };
+
+    // Moving the modules of one entry chunk out can leave the entry module of another
+    // chunk group without the copy it imported from, so the entry chunks are checked
+    // again until none turns into a facade.
+    let mut facades = vec![];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for entry_chunk in chunk_graph.entry_chunks().collect::<Vec<_>>() {
+            if facades.iter().any(|(facade, _)| *facade == entry_chunk) {
+                continue;
+            }
+            let chunk = &chunk_graph.graph[entry_chunk];
+            let Some(root) = chunk.modules.iter().copied().find(|module_id| {
+                chunk_graph
+                    .chunk_roots
+                    .get(module_id)
+                    .is_some_and(|(chunk_id, _)| *chunk_id == entry_chunk)
+            }) else {
+                continue;
+            };
+            let modules: Vec<ModuleId> = chunk
+                .modules
+                .iter()
+                .copied()
+                .filter(|module_id| *module_id != root)
+                .collect();
+            if !modules.iter().any(|module_id| {
+                imported_from_elsewhere(&chunks_of_module, *module_id, entry_chunk)
+            }) {
+                continue;
+            }
+            let shared = Chunk {
+                name: combined_name("shared", &[&chunk.name]),
+                module_type: chunk.module_type,
+                layer: chunk.layer.clone(),
+                size: modules.iter().map(|module_id| g[*module_id].size()).sum(),
+                modules,
+                source_bundles: vec![entry_chunk],
+                ..Chunk::default()
+            };
+            let facade = &mut chunk_graph.graph[entry_chunk];
+            facade.modules = vec![root];
+            facade.size = g[root].size();
+            let shared_id = chunk_graph.add_chunk(shared);
+            chunk_graph.graph.add_edge(entry_chunk, shared_id, 0);
+            for module_id in &chunk_graph.graph[shared_id].modules {
+                for chunk_id in chunks_of_module.get_mut(module_id).unwrap() {
+                    if *chunk_id == entry_chunk {
+                        *chunk_id = shared_id;
+                    }
+                }
+            }
+            trace.record(|| trace::Decision::FacadeCreated {
+                facade: entry_chunk,
+                chunk: shared_id,
+            });
+            facades.push((entry_chunk, shared_id));
+            changed = true;
+        }
+    }
+    if !facades.is_empty() {
+        weigh_chunk_edges(&mut state.chunk_graph, &state.module_graph);
+    }
+    tracing::debug!(facades = facades.len(), "added entry facades");
+    facades
+}
+
+/// Name of the synthetic runtime module and of the runtime chunk.
+pub const RUNTIME: &str = "runtime";
+
+/// Adds the runtime to the module graph as a synthetic module and places it in a chunk
+/// of its own, loaded by the chunk group of every entry. Returns the runtime chunk, or
+/// `None` if `runtime_chunk` is not set.
+///
+/// Runs after every other pass, so the runtime chunk is never merged or split and
+/// doesn't count against `max_initial_requests`, which webpack doesn't count it
+/// against either.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn extract_runtime_chunk<M: ModuleInfo>(
+    state: &mut AnalysisState<M>,
+    options: &SplitOptions,
+) -> Option<NodeIndex> {
+    if !options.runtime_chunk {
+        return None;
+    }
+    let entry_groups: Vec<NodeIndex> = state.chunk_graph.entry_chunks().collect();
+    let runtime = state
+        .module_graph
+        .add_node(M::synthetic(RUNTIME, options.runtime_size));
+    let chunk_graph = &mut state.chunk_graph;
+    let runtime_chunk = chunk_graph.add_chunk(Chunk {
+        name: RUNTIME.to_owned(),
+        kind: ChunkKind::Runtime,
+        modules: vec![runtime],
+        size: options.runtime_size,
+        ..Default::default()
+    });
+    for entry_group in entry_groups {
+        chunk_graph.graph.add_edge(entry_group, runtime_chunk, 1);
+    }
+    Some(runtime_chunk)
+}
+
+/// Sorts the modules of every chunk into the order they run: each module after the
+/// modules of the same chunk it imports synchronously, like the post-order of the
+/// imports a bundler emits a chunk in. Async and worker imports load other chunks, so
+/// they don't order anything.
+///
+/// Imports carry no source order, so ties are broken by module id: the search starts
+/// at the modules no other module of the chunk imports, then at any module left, which
+/// only happens for import cycles, and follows imports in id order. A cycle thus runs
+/// from the module imported by the member the search reached first, as it would if
+/// that member's importer ran first.
+///
+/// The root module of a chunk runs last, after modules it doesn't import as well, like
+/// those a smaller chunk merged into it brought along, and even if a module of the
+/// chunk imports it back. The chunk of an entry thus runs its entry module once
+/// everything else is in place.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn order_modules<M: ModuleInfo>(chunk_graph: &mut ChunkGraph, g: &ModuleGraph<M>) {
+    let roots: HashSet<ModuleId> = chunk_graph.chunk_roots.keys().copied().collect();
+    finish_chunks(
+        chunk_graph,
+        |chunk| module_order(chunk, g, &roots),
+        |chunk, order| chunk.modules = order,
+    );
+}
+
+/// The modules of `chunk` in the order they run, see [`order_modules`]. Modules of
+/// `roots`, the chunk root modules, run last.
+fn module_order<M: ModuleInfo>(
+    chunk: &Chunk,
+    g: &ModuleGraph<M>,
+    roots: &HashSet<ModuleId>,
+) -> Vec<ModuleId> {
+    let in_chunk: HashSet<ModuleId> = chunk.modules.iter().copied().collect();
+    let imports = |module_id: ModuleId| {
+        let mut imports: Vec<ModuleId> = g
+            .edges(module_id)
+            .filter(|edge| !edge.weight().is_async && !edge.weight().is_worker)
+            .map(|edge| edge.target())
+            .filter(|importee| *importee != module_id && in_chunk.contains(importee))
+            .collect();
+        imports.sort_unstable();
+        imports.dedup();
+        imports
+    };
+    let mut by_id = chunk.modules.clone();
+    by_id.sort_unstable();
+    let imported: HashSet<ModuleId> = by_id.iter().flat_map(|m| imports(*m)).collect();
+    let starts = by_id
+        .iter()
+        .filter(|module_id| !imported.contains(module_id))
+        .chain(&by_id);
+
+    let mut visited = HashSet::new();
+    let mut order = Vec::with_capacity(by_id.len());
+    let mut deferred_roots = vec![];
+    for start in starts {
+        if !visited.insert(*start) {
+            continue;
+        }
+        let mut stack = vec![(*start, imports(*start), 0)];
+        while let Some((module_id, imports_of_module, next)) = stack.last_mut() {
+            match imports_of_module.get(*next) {
+                Some(importee) => {
+                    *next += 1;
+                    let importee = *importee;
+                    if visited.insert(importee) {
+                        stack.push((importee, imports(importee), 0));
+                    }
+                }
+                None => {
+                    if roots.contains(module_id) {
+                        deferred_roots.push(*module_id);
+                    } else {
+                        order.push(*module_id);
+                    }
+                    stack.pop();
+                }
+            }
+        }
+    }
+    order.extend(deferred_roots);
+    order
+}
+
+/// Sets the [`Chunk::concat_groups`] of every chunk: the largest trees of its modules
+/// in which every module but the first is imported by exactly one module of the chunk,
+/// its parent in the tree, and only by sync imports. A module can only be inlined into
+/// its importer's scope if nothing else needs it as a module of its own, so modules of
+/// other chunks may only import it if they have a copy of it in their chunk as well.
+/// Groups of a single module are left out.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn find_concat_groups<M: ModuleInfo>(chunk_graph: &mut ChunkGraph, g: &ModuleGraph<M>) {
+    let mut chunks_of_module: HashMap<ModuleId, BTreeSet<NodeIndex>> = HashMap::new();
+    for (chunk_id, chunk) in chunk_graph.chunks() {
+        for module_id in &chunk.modules {
+            chunks_of_module
+                .entry(*module_id)
+                .or_default()
+                .insert(chunk_id);
+        }
+    }
+    let no_chunks = BTreeSet::new();
+    let chunks_of = |module_id: ModuleId| chunks_of_module.get(&module_id).unwrap_or(&no_chunks);
+
+    let concat_groups_of = |chunk: &Chunk| {
+        let in_chunk: HashSet<ModuleId> = chunk.modules.iter().copied().collect();
+        // The modules that can join the scope of their importer, by importer.
+        let mut children: HashMap<ModuleId, Vec<ModuleId>> = HashMap::new();
+        let mut inlined: HashSet<ModuleId> = HashSet::new();
+        for module_id in &chunk.modules {
+            let mut importers_in_chunk = vec![];
+            let mut inlinable = true;
+            for edge in g.edges_directed(*module_id, Incoming) {
+                let importer = edge.source();
+                if importer == *module_id {
+                    continue;
+                }
+                if edge.weight().is_async || edge.weight().is_worker {
+                    inlinable = false;
+                } else if in_chunk.contains(&importer) {
+                    importers_in_chunk.push(importer);
+                } else if !chunks_of(importer).is_subset(chunks_of(*module_id)) {
+                    inlinable = false;
+                }
+            }
+            if inlinable && importers_in_chunk.len() == 1 {
+                children
+                    .entry(importers_in_chunk[0])
+                    .or_default()
+                    .push(*module_id);
+                inlined.insert(*module_id);
+            }
+        }
+
+        let mut concat_groups = vec![];
+        for root in chunk
+            .modules
+            .iter()
+            .filter(|module_id| !inlined.contains(module_id))
+        {
+            let mut group = vec![];
+            let mut stack = vec![*root];
+            while let Some(module_id) = stack.pop() {
+                group.push(module_id);
+                if let Some(children) = children.get(&module_id) {
+                    stack.extend(children.iter().rev());
+                }
+            }
+            if group.len() > 1 {
+                concat_groups.push(group);
+            }
+        }
+        concat_groups
+    };
+    finish_chunks(chunk_graph, concat_groups_of, |chunk, concat_groups| {
+        chunk.concat_groups = concat_groups
+    });
+}
+
+/// Renames every chunk `options.chunk_names` gives a name to.
+pub fn name_chunks<M: ModuleInfo>(
+    chunk_graph: &mut ChunkGraph,
+    g: &ModuleGraph<M>,
+    options: &SplitOptions,
+) {
+    let chunk_names = match &options.chunk_names {
+        Some(chunk_names) => chunk_names,
+        None => return,
+    };
+    let name_of = |chunk: &Chunk| {
+        let modules: Vec<&dyn ModuleInfo> = chunk
+            .modules
+            .iter()
+            .map(|module_id| &g[*module_id] as &dyn ModuleInfo)
+            .collect();
+        chunk_names.name_of(chunk, &modules)
+    };
+    finish_chunks(chunk_graph, name_of, |chunk, name| {
+        if let Some(name) = name {
+            chunk.name = name;
+        }
+    });
+}
+
+/// Sets what `finish` works out from each chunk alone with `apply`, in chunk id order.
+/// No chunk depends on what `finish` gives another, so with the `rayon` feature the
+/// chunks are finished in parallel and collected by chunk id afterwards, and the chunk
+/// graph ends up the same either way.
+fn finish_chunks<T: Send>(
+    chunk_graph: &mut ChunkGraph,
+    finish: impl Fn(&Chunk) -> T + Sync,
+    apply: impl Fn(&mut Chunk, T),
+) {
+    let chunks: Vec<(NodeIndex, &Chunk)> = chunk_graph.chunks().collect();
+    #[cfg(feature = "rayon")]
+    let finished: BTreeMap<NodeIndex, T> = {
+        use rayon::prelude::*;
+        chunks
+            .into_par_iter()
+            .map(|(chunk_id, chunk)| (chunk_id, finish(chunk)))
+            .collect()
+    };
+    #[cfg(not(feature = "rayon"))]
+    let finished: BTreeMap<NodeIndex, T> = chunks
+        .into_iter()
+        .map(|(chunk_id, chunk)| (chunk_id, finish(chunk)))
+        .collect();
+    for (chunk_id, finished) in finished {
+        apply(&mut chunk_graph.graph[chunk_id], finished);
+    }
+}
+
+/// Splits every chunk larger than `max_size` into parts of at most `max_size` bytes.
+/// Returns each split chunk with its new parts.
+///
+/// Modules are packed in name order, so modules from the same directory tend to stay
+/// together and the result doesn't depend on placement order. A part is closed when the
+/// next module doesn't fit, and a single module larger than `max_size` gets a part of
+/// its own. The first part stays in the original chunk, together with the chunk's root
+/// module if it has one. The other parts are named after the chunk with a `-<n>` suffix.
+///
+/// Parts of a shared chunk get an edge from every chunk group loading the chunk. Parts
+/// of a root chunk are loaded by the root's own chunk group. The last part may end up
+/// smaller than `min_size`. Manual chunks are never split.
+pub fn split_large_chunks<M: ModuleInfo>(
+    chunk_graph: &mut ChunkGraph,
+    g: &ModuleGraph<M>,
+    max_size: usize,
+    trace: &mut DecisionTrace,
+) -> Vec<(NodeIndex, Vec<NodeIndex>)> {
+    let groups = chunk_graph.groups();
+    let mut splits = vec![];
+
+    for chunk_id in chunk_graph.graph.node_indices().collect::<Vec<_>>() {
+        let chunk = &chunk_graph.graph[chunk_id];
+        if chunk.size <= max_size || chunk.kind == ChunkKind::Manual {
+            continue;
+        }
+        let (pinned, mut movable): (Vec<ModuleId>, Vec<ModuleId>) = chunk
+            .modules
+            .iter()
+            .partition(|module_id| chunk_graph.chunk_roots.contains_key(module_id));
+        movable.sort_by_key(|module_id| g[*module_id].name());
+
+        let mut bin_size: usize = pinned.iter().map(|module_id| g[*module_id].size()).sum();
+        let mut bins = vec![pinned];
+        for module_id in movable {
+            let size = g[module_id].size();
+            if !bins.last().unwrap().is_empty() && bin_size + size > max_size {
+                bins.push(vec![]);
+                bin_size = 0;
+            }
+            bins.last_mut().unwrap().push(module_id);
+            bin_size += size;
+        }
+        if bins.len() == 1 {
+            continue;
+        }
+
+        let (parents, source_bundles) = if groups.contains(&chunk_id) {
+            (vec![chunk_id], vec![chunk_id])
+        } else {
+            let parents = chunk_graph
+                .graph
+                .neighbors_directed(chunk_id, Incoming)
+                .collect();
+            (parents, chunk.source_bundles.clone())
+        };
+        let name = chunk.name.clone();
+        let module_type = chunk.module_type;
+        let layer = chunk.layer.clone();
+        let cache_group = chunk.cache_group.clone();
+        let size_of =
+            |modules: &[ModuleId]| modules.iter().map(|module_id| g[*module_id].size()).sum();
+
+        let mut bins = bins.into_iter();
+        let first = bins.next().unwrap();
+        let chunk = &mut chunk_graph.graph[chunk_id];
+        chunk.size = size_of(&first);
+        chunk.modules = first;
+
+        let mut parts = vec![];
+        for (part_index, bin) in bins.enumerate() {
+            let part_id = chunk_graph.add_chunk(Chunk {
+                name: format!("{}-{}", name, part_index + 1),
+                kind: ChunkKind::Shared,
+                module_type,
+                layer: layer.clone(),
+                size: size_of(&bin),
+                modules: bin,
+                source_bundles: source_bundles.clone(),
+                cache_group: cache_group.clone(),
+                ..Default::default()
+            });
+            for parent_id in &parents {
+                chunk_graph.graph.add_edge(*parent_id, part_id, 0);
+            }
+            parts.push(part_id);
+        }
+        trace.record(|| trace::Decision::ChunkSplit {
+            chunk: chunk_id,
+            parts: parts.clone(),
+            max_size,
+        });
+        splits.push((chunk_id, parts));
+    }
+    splits
+}
+
+/// Webpack's "remove available modules" optimization.
+///
+/// A chunk group is an entry or async chunk root together with the shared chunks it
+/// loads. A module is available in a chunk group if it is loaded by every possible
+/// parent chunk group, either directly or because it was available there in turn.
+/// For entries, the groups of the entries they depend on are the parents, and workers
+/// have none. Such modules are removed from the chunks of that group, except from
+/// manual chunks.
+/// Returns the removed `(chunk, module)` pairs.
+pub fn remove_available_modules<M: ModuleInfo>(
+    chunk_graph: &mut ChunkGraph,
+    entries: &[ModuleId],
+    g: &ModuleGraph<M>,
+) -> Vec<(NodeIndex, ModuleId)> {
+    let groups = chunk_graph.groups();
+    // Workers start out with nothing available either, whatever starts them.
+    let entry_groups: HashSet<NodeIndex> = entries
+        .iter()
+        .map(|entry| chunk_graph.chunk_roots[entry].1)
+        .chain(
+            groups
+                .iter()
+                .copied()
+                .filter(|group_id| chunk_graph.graph[*group_id].kind == ChunkKind::Worker),
+        )
+        .collect();
+
+    // Chunk groups each chunk belongs to. A group root belongs to its own group, a
+    // shared chunk belongs to every group that has an edge to it.
+    let mut groups_of_chunk: BTreeMap<NodeIndex, Vec<NodeIndex>> = BTreeMap::new();
+    let mut parent_groups: HashMap<NodeIndex, Vec<NodeIndex>> = HashMap::new();
+    for group_id in &groups {
+        groups_of_chunk
+            .entry(*group_id)
+            .or_default()
+            .push(*group_id);
+        for child_id in chunk_graph.graph.neighbors(*group_id) {
+            if groups.contains(&child_id) {
+                parent_groups.entry(child_id).or_default().push(*group_id);
+            } else {
+                groups_of_chunk.entry(child_id).or_default().push(*group_id);
+            }
+        }
+    }
+
+    let modules_of_group = chunk_graph.modules_of_groups();
+
+    // `None` means "not computed yet", which acts as the universal set when intersecting.
+    // Entries start out with the modules of the entries they depend on.
+    let mut available: HashMap<NodeIndex, Option<HashSet<ModuleId>>> = groups
+        .iter()
+        .map(|group_id| {
+            let initial = if entry_groups.contains(group_id) {
+                Some(
+                    chunk_graph
+                        .dependencies_of(*group_id)
+                        .iter()
+                        .flat_map(|dependency| modules_of_group[dependency].iter().copied())
+                        .collect(),
+                )
+            } else {
+                None
+            };
+            (*group_id, initial)
+        })
+        .collect();
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for group_id in &groups {
+            if entry_groups.contains(group_id) {
+                continue;
+            }
+            let mut next: Option<HashSet<ModuleId>> = None;
+            for parent_id in parent_groups.get(group_id).into_iter().flatten() {
+                let parent_available = match &available[parent_id] {
+                    Some(modules) => modules,
+                    None => continue,
+                };
+                let mut loaded_by_parent = parent_available.clone();
+                loaded_by_parent.extend(modules_of_group[parent_id].iter().copied());
+                next = Some(match next {
+                    Some(modules) => modules.intersection(&loaded_by_parent).copied().collect(),
+                    None => loaded_by_parent,
+                });
+            }
+            if next.is_some() && next != available[group_id] {
+                available.insert(*group_id, next);
+                changed = true;
+            }
+        }
+    }
+
+    let mut removed = vec![];
+    for (chunk_id, group_ids) in &groups_of_chunk {
+        let chunk = &mut chunk_graph.graph[*chunk_id];
+        if chunk.kind == ChunkKind::Manual {
+            continue;
+        }
+        let removable: Vec<ModuleId> = chunk
+            .modules
+            .iter()
+            .copied()
+            .filter(|module_id| !chunk_graph.chunk_roots.contains_key(module_id))
+            .filter(|module_id| {
+                group_ids.iter().all(|group_id| match &available[group_id] {
+                    Some(modules) => modules.contains(module_id),
+                    None => false,
+                })
+            })
+            .collect();
+        for module_id in removable {
+            chunk.modules.retain(|id| *id != module_id);
+            chunk.size -= g[module_id].size();
+            removed.push((*chunk_id, module_id));
+        }
+    }
+    removed
+}
+
+/// What a [`collect_garbage`] run cleaned up.
+#[derive(Debug, Default)]
+pub struct GcSummary {
+    /// Indices (before compaction) of the empty chunks that were removed.
+    pub removed_chunks: Vec<NodeIndex>,
+    /// Edges that duplicated an existing edge between the same two chunks.
+    pub deduplicated_edges: usize,
+    /// Edges that pointed to or from a removed chunk, or from a chunk to itself.
+    pub dropped_edges: usize,
+    /// `source_bundles` entries that referenced removed or repeated chunks.
+    pub dropped_source_bundles: usize,
+}
+
+/// Rebuilds the chunk graph without empty chunks, parallel edges, self edges or edges
+/// to removed chunks, and remaps every chunk index held in `chunk_roots`, `chunks` and
+/// `Chunk::source_bundles` to the compacted graph. The chunks keep their handles, which
+/// find their new ids, see [`ChunkGraph::update_handles`].
+///
+/// Chunk roots are kept even when empty since they anchor their chunk group.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn collect_garbage(
+    chunk_graph: &mut ChunkGraph,
+    chunks: &mut Vec<(Combination, NodeIndex)>,
+) -> GcSummary {
+    let mut summary = GcSummary::default();
+    let root_chunks: HashSet<NodeIndex> = chunk_graph
+        .chunk_roots
+        .values()
+        .map(|(chunk_id, _)| *chunk_id)
+        .collect();
+    let (nodes, edges) = std::mem::take(&mut chunk_graph.graph).into_nodes_edges();
+    let graph = &mut chunk_graph.graph;
+
+    let mut new_index: HashMap<NodeIndex, NodeIndex> = HashMap::new();
+    let mut kept_chunks = vec![];
+    for (index, node) in nodes.into_iter().enumerate() {
+        let old_id = NodeIndex::new(index);
+        if node.weight.modules.is_empty() && !root_chunks.contains(&old_id) {
+            summary.removed_chunks.push(old_id);
+        } else {
+            new_index.insert(old_id, graph.add_node(Chunk::default()));
+            kept_chunks.push((old_id, node.weight));
+        }
+    }
+
+    for (old_id, mut chunk) in kept_chunks {
+        let mut source_bundles = vec![];
+        for source_id in &chunk.source_bundles {
+            match new_index.get(source_id) {
+                Some(new_id) if !source_bundles.contains(new_id) => source_bundles.push(*new_id),
+                _ => summary.dropped_source_bundles += 1,
+            }
+        }
+        chunk.source_bundles = source_bundles;
+        graph[new_index[&old_id]] = chunk;
+    }
+
+    let mut seen_edges = HashSet::new();
+    for edge in edges {
+        match (new_index.get(&edge.source()), new_index.get(&edge.target())) {
+            (Some(from), Some(to)) if from != to => {
+                if seen_edges.insert((*from, *to)) {
+                    graph.add_edge(*from, *to, edge.weight);
+                } else {
+                    summary.deduplicated_edges += 1;
+                }
+            }
+            _ => summary.dropped_edges += 1,
+        }
+    }
+
+    for (chunk_id, group_id) in chunk_graph.chunk_roots.values_mut() {
+        *chunk_id = new_index[chunk_id];
+        *group_id = new_index[group_id];
+    }
+    chunks.retain_mut(|(_, chunk_id)| match new_index.get(chunk_id) {
+        Some(new_id) => {
+            *chunk_id = *new_id;
+            true
+        }
+        None => false,
+    });
+    chunk_graph.update_handles();
+
+    tracing::debug!(
+        removed_chunks = summary.removed_chunks.len(),
+        deduplicated_edges = summary.deduplicated_edges,
+        dropped_edges = summary.dropped_edges,
+        "collected garbage"
+    );
+    summary
+}
+
+/// Sets the weight of every chunk graph edge to the number of import sites from the
+/// modules of the source chunk group into the modules of the target chunk. Imports
+/// between modules of the target chunk don't cross the edge and are not counted.
+pub fn weigh_chunk_edges<M: ModuleInfo>(chunk_graph: &mut ChunkGraph, g: &ModuleGraph<M>) {
+    let modules_of_group = chunk_graph.modules_of_groups();
+    let graph = &mut chunk_graph.graph;
+
+    for edge_id in graph.edge_indices() {
+        let (from, to) = graph.edge_endpoints(edge_id).unwrap();
+        let importers = match modules_of_group.get(&from) {
+            Some(modules) => modules,
+            None => continue,
+        };
+        let own_modules: HashSet<ModuleId> = graph[to].modules.iter().copied().collect();
+        let import_count = own_modules
+            .iter()
+            .flat_map(|importee| g.edges_directed(*importee, Incoming))
+            .filter(|edge| {
+                importers.contains(&edge.source()) && !own_modules.contains(&edge.source())
+            })
+            .map(|edge| edge.weight().import_count)
+            .sum();
+        graph[edge_id] = import_count;
+    }
+}
+
+/// Average number of import sites per chunk group loading `chunk_id`, at least 1.
+pub fn coupling_of(chunk_graph: &Graph<Chunk, usize>, chunk_id: NodeIndex) -> usize {
+    let weights: Vec<usize> = chunk_graph
+        .edges_directed(chunk_id, Incoming)
+        .map(|edge| *edge.weight())
+        .collect();
+    if weights.is_empty() {
+        return 1;
+    }
+    (weights.iter().sum::<usize>() / weights.len()).max(1)
+}
+
+/// Step 4: Removes shared bundles whose expected size is smaller than `options.min_size`
+/// and adds their modules to the source bundles they were referenced from, or to one of
+/// them, or keeps them, as [`SplitOptions::small_chunk_policy`] says. Returns the
+/// emptied bundles, which are left for [`collect_garbage`].
+///
+/// Bundles referenced from fewer than `options.min_source_chunks` source bundles are
+/// removed the same way whatever their size and policy, so a bundle left with one
+/// source bundle folds back into it.
+///
+/// Chunks of a cache group use the group's minimum size instead, and are never removed
+/// if the group is enforced.
+///
+/// The minimum size is scaled by the number of import sites per loading chunk group,
+/// see [`coupling_of`], to the power of `options.coupling_bias`, so with a positive bias
+/// strongly coupled shared modules tend to stay next to their importers. The size is
+/// weighted by how likely the source bundles are to load at all, since duplicating into
+/// a rarely loaded async chunk costs fewer expected bytes than a separate request.
+///
+/// The minimum size is also scaled by the heat of the hottest source bundle to the
+/// power of `options.hot_path_bias`, see [`group_heats`], so hot paths keep fewer and
+/// larger chunks and cold async routes are split further.
+///
+/// Duplicating modules with side effects into source bundles that can run on the same
+/// page runs them once per copy, so such bundles are traced or kept as
+/// `options.side_effect_duplication` says.
+pub fn remove_small_bundles<M: ModuleInfo>(
+    chunk_graph: &mut Graph<Chunk, usize>,
+    groups: &BTreeSet<NodeIndex>,
+    g: &ModuleGraph<M>,
+    likelihoods: &HashMap<NodeIndex, f64>,
+    heats: &HashMap<NodeIndex, f64>,
+    options: &SplitOptions,
+    trace: &mut DecisionTrace,
+) -> Vec<NodeIndex> {
+    let small_bundles: Vec<NodeIndex> = chunk_graph
+        .node_indices()
+        .filter(|bundle_id| {
+            let bundle = &chunk_graph[*bundle_id];
+            if bundle.source_bundles.is_empty()
+                || bundle.modules.is_empty()
+                || !fits_source_bundles(chunk_graph, *bundle_id)
+            {
+                return false;
+            }
+            let cache_group = options.cache_group_of_chunk(bundle);
+            if matches!(cache_group, Some(group) if group.enforce) {
+                return false;
+            }
+            if has_few_source_chunks(bundle, options) {
+                trace.record(|| trace::Decision::ChunkRemovedForFewSources {
+                    chunk: *bundle_id,
+                    source_chunks: bundle.source_bundles.len(),
+                    min_source_chunks: options.min_source_chunks,
+                });
+                return true;
+            }
+            let min_size = cache_group
+                .and_then(|group| group.min_size)
+                .unwrap_or(options.min_size);
+            let (expected_size, scale) =
+                expected_size_and_scale(chunk_graph, *bundle_id, likelihoods, heats, options);
+            let min_size = (min_size as f64 * scale).round() as usize;
+            if expected_size >= min_size as f64
+                || options.small_chunk_policy(bundle.size) == SmallChunkPolicy::Keep
+            {
+                return false;
+            }
+            tracing::trace!(
+                chunk = bundle_id.index(),
+                expected_size,
+                min_size,
+                "chunk under min_size"
+            );
+            trace.record(|| trace::Decision::ChunkRemoved {
+                chunk: *bundle_id,
+                expected_size,
+                min_size,
+            });
+            true
+        })
+        .collect();
+    let pages = pages(chunk_graph, groups);
+    let mut removed = Vec::with_capacity(small_bundles.len());
+    for bundle_id in &small_bundles {
+        let candidates = || parent_candidates(chunk_graph, groups, *bundle_id, &small_bundles);
+        let parent = match options.small_chunk_policy(chunk_graph[*bundle_id].size) {
+            SmallChunkPolicy::MergeIntoLargestParent => candidates()
+                .into_iter()
+                .max_by_key(|parent_id| (chunk_graph[*parent_id].size, Reverse(*parent_id))),
+            SmallChunkPolicy::MergeIntoSmallestParent => candidates()
+                .into_iter()
+                .min_by_key(|parent_id| (chunk_graph[*parent_id].size, *parent_id)),
+            SmallChunkPolicy::DuplicateIntoAll | SmallChunkPolicy::Keep => None,
+        };
+        match parent {
+            Some(parent_id) => {
+                trace.record(|| trace::Decision::ChunkMergedIntoParent {
+                    chunk: *bundle_id,
+                    parent: parent_id,
+                });
+                move_bundle(g, chunk_graph, *bundle_id, parent_id);
+            }
+            None => {
+                let bundle = &chunk_graph[*bundle_id];
+                let side_effects: Vec<ModuleId> = bundle
+                    .modules
+                    .iter()
+                    .copied()
+                    .filter(|module_id| !g[*module_id].side_effect_free())
+                    .collect();
+                let on_same_page: BTreeSet<NodeIndex> = bundle
+                    .source_bundles
+                    .iter()
+                    .filter(|source_id| {
+                        bundle.source_bundles.iter().any(|other_id| {
+                            other_id != *source_id
+                                && pages
+                                    .get(*source_id)
+                                    .zip(pages.get(other_id))
+                                    .is_some_and(|(a, b)| !a.is_disjoint(b))
+                        })
+                    })
+                    .copied()
+                    .collect();
+                if !side_effects.is_empty() && !on_same_page.is_empty() {
+                    if options.side_effect_duplication == SideEffectDuplication::Refuse {
+                        trace.record(|| trace::Decision::DuplicationRefused {
+                            chunk: *bundle_id,
+                            modules: side_effects,
+                        });
+                        continue;
+                    }
+                    trace.record(|| trace::Decision::SideEffectsDuplicated {
+                        chunk: *bundle_id,
+                        modules: side_effects,
+                        chunks: on_same_page.into_iter().collect(),
+                    });
+                }
+                remove_bundle(g, chunk_graph, *bundle_id);
+            }
+        }
+        removed.push(*bundle_id);
+    }
+    removed
+}
+
+/// The size [`remove_small_bundles`] weighs `bundle_id` by, scaled by how likely its
+/// source bundles are to load, and the factor it scales the minimum size by for the
+/// chunk, from the chunk's coupling and the heat of its hottest source bundle.
+pub fn expected_size_and_scale(
+    chunk_graph: &Graph<Chunk, usize>,
+    bundle_id: NodeIndex,
+    likelihoods: &HashMap<NodeIndex, f64>,
+    heats: &HashMap<NodeIndex, f64>,
+    options: &SplitOptions,
+) -> (f64, f64) {
+    let bundle = &chunk_graph[bundle_id];
+    let expected_loads = bundle
+        .source_bundles
+        .iter()
+        .map(|source_id| likelihoods.get(source_id).copied().unwrap_or(1.0))
+        .sum::<f64>()
+        / bundle.source_bundles.len() as f64;
+    let heat = bundle
+        .source_bundles
+        .iter()
+        .map(|source_id| heats.get(source_id).copied().unwrap_or(1.0))
+        .fold(0.0, f64::max);
+    let coupling = coupling_of(chunk_graph, bundle_id) as f64;
+    (
+        bundle.size as f64 * expected_loads,
+        coupling.powf(options.coupling_bias) * heat.powf(options.hot_path_bias),
+    )
+}
+
+/// The pages every chunk can run on, keyed by chunk id: the root chunks of the entries
+/// and workers whose chunk groups, or the async chunk groups they import, load the
+/// chunk. A worker runs apart from the page that starts it, so it is a page of its own.
+fn pages(
+    chunk_graph: &Graph<Chunk, usize>,
+    groups: &BTreeSet<NodeIndex>,
+) -> HashMap<NodeIndex, BTreeSet<NodeIndex>> {
+    let mut pages: HashMap<NodeIndex, BTreeSet<NodeIndex>> = HashMap::new();
+    for page in groups.iter().filter(|group_id| {
+        matches!(
+            chunk_graph[**group_id].kind,
+            ChunkKind::Entry | ChunkKind::Worker
+        )
+    }) {
+        let mut stack = vec![*page];
+        let mut seen = BTreeSet::new();
+        while let Some(group_id) = stack.pop() {
+            if !seen.insert(group_id) {
+                continue;
+            }
+            pages.entry(group_id).or_default().insert(*page);
+            for chunk_id in chunk_graph.neighbors(group_id) {
+                if !groups.contains(&chunk_id) {
+                    pages.entry(chunk_id).or_default().insert(*page);
+                } else if chunk_graph[chunk_id].kind != ChunkKind::Worker {
+                    stack.push(chunk_id);
+                }
+            }
+        }
+    }
+    pages
+}
+
+/// The chunks `bundle_id` can be merged into without a module being duplicated or a
+/// request added: those of the same module type that every chunk group loading it
+/// loads as well. That is the root chunk of its chunk group if only one group loads
+/// it, and the shared chunks all of them load. Manual chunks, chunks of another cache
+/// group, empty chunks and the chunks in `removed` are left out.
+fn parent_candidates(
+    chunk_graph: &Graph<Chunk, usize>,
+    groups: &BTreeSet<NodeIndex>,
+    bundle_id: NodeIndex,
+    removed: &[NodeIndex],
+) -> BTreeSet<NodeIndex> {
+    let loaded_by = |group_id: NodeIndex| -> BTreeSet<NodeIndex> {
+        chunk_graph
+            .neighbors(group_id)
+            .filter(|chunk_id| !groups.contains(chunk_id))
+            .chain([group_id])
+            .collect()
+    };
+    let mut loading_groups = chunk_graph.neighbors_directed(bundle_id, Incoming);
+    let mut candidates = match loading_groups.next() {
+        Some(group_id) => loaded_by(group_id),
+        None => return BTreeSet::new(),
+    };
+    for group_id in loading_groups {
+        candidates = &candidates & &loaded_by(group_id);
+    }
+    let bundle = &chunk_graph[bundle_id];
+    candidates.retain(|chunk_id| {
+        let chunk = &chunk_graph[*chunk_id];
+        *chunk_id != bundle_id
+            && !removed.contains(chunk_id)
+            && chunk.module_type == bundle.module_type
+            && chunk.layer == bundle.layer
+            && chunk.kind != ChunkKind::Manual
+            && (chunk.cache_group.is_none() || chunk.cache_group == bundle.cache_group)
+            && !chunk.modules.is_empty()
+    });
+    candidates
+}
+
+/// Probability that each chunk group gets loaded, keyed by the group's chunk id.
+///
+/// Entries always load. An async chunk group loads with the likelihood of its most
+/// likely import, scaled by the likelihood of the most likely chunk group the importer
+/// is reachable from.
+pub fn group_likelihoods<M: ModuleInfo>(
+    g: &ModuleGraph<M>,
+    chunk_graph: &ChunkGraph,
+    entries: &[ModuleId],
+    reachable_modules: &BTreeMap<ModuleId, FixedBitSet>,
+) -> HashMap<NodeIndex, f64> {
+    along_hottest_imports(g, chunk_graph, entries, reachable_modules, |dependency| {
+        if dependency.is_async {
+            dependency.load_likelihood
+        } else {
+            1.0
+        }
+    })
+}
+
+/// How often each chunk group gets loaded relative to the entries, keyed by the group's
+/// chunk id: the product of the [`Dependency::frequency`] of the imports along its
+/// hottest import path. Without frequencies every group is at 1.
+pub fn group_heats<M: ModuleInfo>(
+    g: &ModuleGraph<M>,
+    chunk_graph: &ChunkGraph,
+    entries: &[ModuleId],
+    reachable_modules: &BTreeMap<ModuleId, FixedBitSet>,
+) -> HashMap<NodeIndex, f64> {
+    along_hottest_imports(g, chunk_graph, entries, reachable_modules, |dependency| {
+        if dependency.is_async || dependency.is_worker {
+            dependency.frequency.unwrap_or(1.0)
+        } else {
+            1.0
+        }
+    })
+}
+
+/// A value for every chunk group, keyed by the group's chunk id. Entries are at 1, and
+/// every other group at the highest product of `factor` over the imports of a path from
+/// an entry, scaled by the value of the group the importer is reachable from.
+///
+/// Values only grow. Since import cycles with factors above 1 would grow them forever,
+/// this stops after as many rounds as there are chunk roots, which is enough for every
+/// path that visits each root once.
+fn along_hottest_imports<M: ModuleInfo>(
+    g: &ModuleGraph<M>,
+    chunk_graph: &ChunkGraph,
+    entries: &[ModuleId],
+    reachable_modules: &BTreeMap<ModuleId, FixedBitSet>,
+    factor: impl Fn(&Dependency) -> f64,
+) -> HashMap<NodeIndex, f64> {
+    let chunk_roots = &chunk_graph.chunk_roots;
+    let mut value_of_root: HashMap<ModuleId, f64> = chunk_roots
+        .keys()
+        .map(|root| (*root, if entries.contains(root) { 1.0 } else { 0.0 }))
+        .collect();
+
+    let mut changed = true;
+    let mut rounds = 0;
+    while changed && rounds < chunk_roots.len() {
+        changed = false;
+        rounds += 1;
+        for root in chunk_roots.keys() {
+            if entries.contains(root) {
+                continue;
+            }
+            let mut best: f64 = 0.0;
+            for edge in g.edges_directed(*root, Incoming) {
+                let (importer, dependency) = (edge.source(), edge.weight());
+                let importer_value = reachable_modules
+                    .iter()
+                    .filter(|(_, reachable)| reachable.contains(importer.index()))
+                    .map(|(loading_root, _)| *loading_root)
+                    .chain(Some(importer))
+                    .filter_map(|loading_root| value_of_root.get(&loading_root))
+                    .fold(0.0, |a: f64, b| a.max(*b));
+                best = best.max(factor(dependency) * importer_value);
+            }
+            if best > value_of_root[root] {
+                value_of_root.insert(*root, best);
+                changed = true;
+            }
+        }
+    }
+
+    // Roots of another module type or layer share the chunk group of their importer,
+    // which takes the value of its own root.
+    value_of_root
+        .into_iter()
+        .filter(|(root, _)| chunk_roots[root].0 == chunk_roots[root].1)
+        .map(|(root, value)| (chunk_roots[&root].1, value))
+        .collect()
+}
+
+/// Whether every source bundle of `bundle_id` can hold its modules, being of the same
+/// module type and layer. A module reached from roots of another layer than its own
+/// keeps its shared chunk however small it is.
+pub fn fits_source_bundles(chunk_graph: &Graph<Chunk, usize>, bundle_id: NodeIndex) -> bool {
+    let bundle = &chunk_graph[bundle_id];
+    bundle.source_bundles.iter().all(|source_id| {
+        let source = &chunk_graph[*source_id];
+        source.module_type == bundle.module_type && source.layer == bundle.layer
+    })
+}
+
+/// Moves the modules of `bundle_id` into each of its source bundles. The emptied
+/// bundle is left in place for [`collect_garbage`] so that no chunk index moves.
+pub fn remove_bundle<M: ModuleInfo>(
+    g: &ModuleGraph<M>,
+    chunk_graph: &mut Graph<Chunk, usize>,
+    bundle_id: NodeIndex,
+) {
+    let bundle = &mut chunk_graph[bundle_id];
+    let module_ids = std::mem::take(&mut bundle.modules);
+    let source_bundles = bundle.source_bundles.clone();
+    bundle.size = 0;
+    for source_bundle_id in &source_bundles {
+        add_modules(g, &mut chunk_graph[*source_bundle_id], &module_ids);
+    }
+}
+
+/// Moves the modules of `bundle_id` into `parent_id`, a chunk loaded wherever the bundle
+/// is. The emptied bundle is left in place for [`collect_garbage`] like in
+/// [`remove_bundle`].
+pub fn move_bundle<M: ModuleInfo>(
+    g: &ModuleGraph<M>,
+    chunk_graph: &mut Graph<Chunk, usize>,
+    bundle_id: NodeIndex,
+    parent_id: NodeIndex,
+) {
+    let bundle = &mut chunk_graph[bundle_id];
+    let module_ids = std::mem::take(&mut bundle.modules);
+    bundle.size = 0;
+    add_modules(g, &mut chunk_graph[parent_id], &module_ids);
+}
+
+/// Adds the modules of `module_ids` that `chunk` doesn't hold yet to it. A chunk group
+/// can hold a module in two chunks, like the copy an isolated entry keeps next to the
+/// one shared by its roots of another module type, which then meet in one chunk.
+fn add_modules<M: ModuleInfo>(g: &ModuleGraph<M>, chunk: &mut Chunk, module_ids: &[ModuleId]) {
+    let held: HashSet<ModuleId> = chunk.modules.iter().copied().collect();
+    for module_id in module_ids {
+        if !held.contains(module_id) {
+            chunk.modules.push(*module_id);
+            chunk.size += g[*module_id].size();
+        }
+    }
+}
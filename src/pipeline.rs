@@ -0,0 +1,391 @@
+//! The algorithm as a pipeline of stages with explicit states in between, so a host can
+//! run its own passes between any two of them without copying the rest.
+//!
+//! [`create_roots`] turns the module graph into [`Roots`] (Step 1),
+//! [`compute_reachability`] adds what every root reaches for a [`Reachability`]
+//! (Step 2), and [`assign_modules`] places the modules into chunks for an
+//! [`AnalysisState`] (Step 3). Every later pass changes the `AnalysisState` in place.
+//! [`analysis::analyze_with`] runs the first three stages, [`Pipeline`] runs them all
+//! with the passes of a host in between.
+
+use crate::analysis::{self, AnalysisState, RootChunks, RootIndex};
+use crate::barrels::BarrelReport;
+use crate::cache::{self, AnalysisCache};
+use crate::chunk_graph::{ChunkGraph, ChunkId};
+use crate::entries::{self, Entry};
+use crate::error::SplitChunksError;
+use crate::module_graph::{ModuleGraph, ModuleId, ModuleInfo};
+use crate::options::SplitOptions;
+use crate::passes::{self, OptimizeReport};
+use crate::scc::Condensation;
+use crate::strategy::ChunkingStrategy;
+use crate::trace::DecisionTrace;
+use crate::verify;
+use fixedbitset::FixedBitSet;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The state after Step 1: the graph the algorithm runs on and its chunk roots.
+#[derive(Debug, Clone)]
+pub struct Roots<M> {
+    /// The module graph after [`analysis::prepare`].
+    pub module_graph: ModuleGraph<M>,
+    pub entries: Vec<ModuleId>,
+    /// `(depended-on entry, entry)` pairs, see [`entries::depends_on`].
+    pub depends_on: BTreeSet<(ModuleId, ModuleId)>,
+    /// The entry modules of [`Entry::isolated`] entries.
+    pub isolated: BTreeSet<ModuleId>,
+    pub barrels: Vec<BarrelReport>,
+    pub weak_imports: Vec<(ModuleId, ModuleId)>,
+    pub root_chunks: RootChunks,
+}
+
+/// The state after Step 2: [`Roots`] and the modules each chunk root reaches.
+#[derive(Debug, Clone)]
+pub struct Reachability<M> {
+    pub roots: Roots<M>,
+    /// The import cycles of the module graph, which the searches ran on.
+    pub condensation: Condensation,
+    /// See [`AnalysisState::reachable_modules`].
+    pub reachable_modules: BTreeMap<ModuleId, FixedBitSet>,
+}
+
+/// Step 1, after checking the input with [`analysis::validate`] and preparing the graph
+/// with [`analysis::prepare`].
+pub fn create_roots<M: ModuleInfo + Clone>(
+    g: &ModuleGraph<M>,
+    entries: &[Entry],
+    options: &SplitOptions,
+    trace: &mut DecisionTrace,
+) -> Result<Roots<M>, SplitChunksError> {
+    analysis::validate(g, entries)?;
+    let (module_graph, barrels, weak_imports) = analysis::prepare(g, entries, options);
+    let depends_on = entries::depends_on(&module_graph, entries)?;
+    let isolated = entries::isolated(entries);
+    let entries = entries::modules(entries);
+    let root_chunks =
+        analysis::create_root_chunks(&module_graph, &entries, options.max_async_depth, trace)?;
+    Ok(Roots {
+        module_graph,
+        entries,
+        depends_on,
+        isolated,
+        barrels,
+        weak_imports,
+        root_chunks,
+    })
+}
+
+/// Step 2 with `step_2`, which gets the graph, its import cycles and the chunk roots,
+/// and has to return what [`analysis::reachable_from_roots`] would.
+pub fn compute_reachability<M>(
+    roots: Roots<M>,
+    step_2: impl FnOnce(
+        &ModuleGraph<M>,
+        &Condensation,
+        &BTreeMap<ModuleId, (ChunkId, ChunkId)>,
+    ) -> BTreeMap<ModuleId, FixedBitSet>,
+) -> Reachability<M> {
+    // Import cycles are searched as a whole, so the search from each root runs on the
+    // acyclic graph of cycles and the modules of a cycle end up in the same chunk.
+    let condensation = Condensation::new(&roots.module_graph);
+    let reachable_modules = step_2(
+        &roots.module_graph,
+        &condensation,
+        &roots.root_chunks.chunk_roots,
+    );
+    Reachability {
+        roots,
+        condensation,
+        reachable_modules,
+    }
+}
+
+/// Step 3: places every module into a chunk by the chunk roots it is reachable from,
+/// with the package sizes `cache` holds.
+pub fn assign_modules<M: ModuleInfo>(
+    reachability: Reachability<M>,
+    options: &SplitOptions,
+    cache: &AnalysisCache,
+    trace: &mut DecisionTrace,
+) -> AnalysisState<M> {
+    let Reachability {
+        roots,
+        reachable_modules,
+        ..
+    } = reachability;
+    let RootChunks {
+        mut chunk_graph,
+        chunk_roots,
+    } = roots.root_chunks;
+    let g = roots.module_graph;
+    let reachable_chunks = analysis::parent_roots(
+        &g,
+        &chunk_graph,
+        &chunk_roots,
+        &roots.depends_on,
+        &reachable_modules,
+    );
+
+    // Find chunk entries reachable from each module, sorted so every module reachable
+    // from the same roots looks up the same combination.
+    let root_index = RootIndex::new(&chunk_roots, &reachable_chunks);
+    let assignments = analysis::assign_modules(g.node_count(), &reachable_modules, &root_index);
+
+    let chunks = analysis::place_modules(
+        &g,
+        &mut chunk_graph,
+        &chunk_roots,
+        &reachable_chunks,
+        &roots.isolated,
+        &assignments,
+        options,
+        cache,
+        trace,
+    );
+
+    AnalysisState {
+        module_graph: g,
+        entries: roots.entries,
+        barrels: roots.barrels,
+        reachable_chunks,
+        reachable_modules,
+        chunk_graph: ChunkGraph::new(chunk_graph, chunk_roots, roots.depends_on),
+        chunks: chunks.into_iter().collect(),
+        weak_imports: roots.weak_imports,
+    }
+}
+
+/// The stages of a [`Pipeline`] that work on an [`AnalysisState`], in the order they
+/// run. Each is one of the passes of [`passes::optimize`], except `AssignModules`,
+/// which is Step 3. This order is the only one: `passes::optimize` and the binary run
+/// the stages through [`Pipeline::optimize`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Stage {
+    AssignModules,
+    /// Step 3.5 and the edge weights, see [`passes::prune_available_modules`].
+    PruneAvailableModules,
+    /// Step 4, see [`passes::merge_small_chunks`].
+    PruneSmallChunks,
+    HoistSiblingDuplicates,
+    LimitRequests,
+    EnforceMaxSize,
+    AddEntryFacades,
+    ExtractRuntimeChunk,
+    /// See [`passes::order_modules`].
+    OrderModules,
+    FindConcatGroups,
+    NameChunks,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 11] = [
+        Stage::AssignModules,
+        Stage::PruneAvailableModules,
+        Stage::PruneSmallChunks,
+        Stage::HoistSiblingDuplicates,
+        Stage::LimitRequests,
+        Stage::EnforceMaxSize,
+        Stage::AddEntryFacades,
+        Stage::ExtractRuntimeChunk,
+        Stage::OrderModules,
+        Stage::FindConcatGroups,
+        Stage::NameChunks,
+    ];
+
+    /// Runs the stage's own pass on `state` and records what it changed in `report`.
+    /// `AssignModules` has none here.
+    fn run<M: ModuleInfo>(
+        self,
+        state: &mut AnalysisState<M>,
+        options: &SplitOptions,
+        trace: &mut DecisionTrace,
+        report: &mut OptimizeReport,
+    ) {
+        match self {
+            Stage::AssignModules => {}
+            Stage::PruneAvailableModules => {
+                (report.removed_available, report.gc_after_available) =
+                    passes::prune_available_modules(state, trace);
+                passes::weigh_chunk_edges(&mut state.chunk_graph, &state.module_graph);
+            }
+            Stage::PruneSmallChunks => {
+                (report.removed_small, report.gc_after_small) =
+                    passes::merge_small_chunks(state, options, trace);
+            }
+            Stage::HoistSiblingDuplicates => {
+                report.hoisted = passes::hoist_sibling_duplicates(state, options, trace);
+                Self::merge_chunks_of_few_sources(state, options, trace, report);
+            }
+            Stage::LimitRequests => {
+                (report.merged_for_requests, report.gc_after_requests) =
+                    passes::limit_requests(state, options, trace);
+                Self::merge_chunks_of_few_sources(state, options, trace, report);
+            }
+            Stage::EnforceMaxSize => {
+                report.split_large = passes::enforce_max_size(state, options, trace);
+            }
+            Stage::AddEntryFacades => {
+                report.facades = passes::add_entry_facades(state, options, trace);
+                Self::merge_chunks_of_few_sources(state, options, trace, report);
+            }
+            Stage::ExtractRuntimeChunk => {
+                report.runtime_chunk = passes::extract_runtime_chunk(state, options);
+            }
+            Stage::OrderModules => {
+                passes::order_modules(&mut state.chunk_graph, &state.module_graph);
+            }
+            Stage::FindConcatGroups => {
+                passes::find_concat_groups(&mut state.chunk_graph, &state.module_graph);
+            }
+            Stage::NameChunks => {
+                passes::name_chunks(&mut state.chunk_graph, &state.module_graph, options);
+            }
+        }
+    }
+
+    /// [`passes::merge_chunks_of_few_sources`] after a stage that merges or adds chunks.
+    fn merge_chunks_of_few_sources<M: ModuleInfo>(
+        state: &mut AnalysisState<M>,
+        options: &SplitOptions,
+        trace: &mut DecisionTrace,
+        report: &mut OptimizeReport,
+    ) {
+        let (merged, _) = passes::merge_chunks_of_few_sources(state, options, trace);
+        report.removed_few_sources.extend(merged);
+    }
+}
+
+/// A pass of a host, run on the state a stage left behind.
+pub type Pass<'a, S> = Box<dyn Fn(&mut S, &SplitOptions, &mut DecisionTrace) + 'a>;
+
+/// Every stage of the algorithm, with the passes of a host after any of them. Without
+/// passes of its own, it splits like [`split_chunks`].
+///
+/// [`split_chunks`]: crate::split_chunks
+pub struct Pipeline<'a, M> {
+    after_create_roots: Vec<Pass<'a, Roots<M>>>,
+    after_compute_reachability: Vec<Pass<'a, Reachability<M>>>,
+    after: BTreeMap<Stage, Vec<Pass<'a, AnalysisState<M>>>>,
+}
+
+impl<M> Default for Pipeline<'_, M> {
+    fn default() -> Self {
+        Pipeline {
+            after_create_roots: vec![],
+            after_compute_reachability: vec![],
+            after: BTreeMap::new(),
+        }
+    }
+}
+
+impl<M: ModuleInfo> Pipeline<'_, M> {
+    /// Runs every stage from `AssignModules` on, on a `state` Step 3 produced, and the
+    /// passes added after them. Every stage and pass is followed by
+    /// [`verify::check_pass`].
+    pub fn optimize(
+        &self,
+        state: &mut AnalysisState<M>,
+        options: &SplitOptions,
+        trace: &mut DecisionTrace,
+    ) -> OptimizeReport {
+        self.optimize_from(Stage::AssignModules, state, options, trace)
+    }
+
+    /// [`Pipeline::optimize`] from `first` on, on a `state` the stages before `first`
+    /// have run on already.
+    pub fn optimize_from(
+        &self,
+        first: Stage,
+        state: &mut AnalysisState<M>,
+        options: &SplitOptions,
+        trace: &mut DecisionTrace,
+    ) -> OptimizeReport {
+        let mut report = OptimizeReport::default();
+        for stage in Stage::ALL.into_iter().filter(|stage| *stage >= first) {
+            stage.run(state, options, trace, &mut report);
+            verify::check_pass(state, options, &format!("{:?}", stage));
+            for pass in self.after.get(&stage).into_iter().flatten() {
+                pass(state, options, trace);
+                verify::check_pass(state, options, &format!("a pass after {:?}", stage));
+            }
+        }
+        report
+    }
+}
+
+impl<'a, M: ModuleInfo + Clone> Pipeline<'a, M> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `pass` after Step 1, e.g. to add chunk roots of its own.
+    pub fn after_create_roots(
+        mut self,
+        pass: impl Fn(&mut Roots<M>, &SplitOptions, &mut DecisionTrace) + 'a,
+    ) -> Self {
+        self.after_create_roots.push(Box::new(pass));
+        self
+    }
+
+    /// Runs `pass` after Step 2, before any module is placed.
+    pub fn after_compute_reachability(
+        mut self,
+        pass: impl Fn(&mut Reachability<M>, &SplitOptions, &mut DecisionTrace) + 'a,
+    ) -> Self {
+        self.after_compute_reachability.push(Box::new(pass));
+        self
+    }
+
+    /// Runs `pass` after `stage`, and after the passes added after `stage` before it.
+    pub fn after(
+        mut self,
+        stage: Stage,
+        pass: impl Fn(&mut AnalysisState<M>, &SplitOptions, &mut DecisionTrace) + 'a,
+    ) -> Self {
+        self.after.entry(stage).or_default().push(Box::new(pass));
+        self
+    }
+
+    /// Runs every stage on `g` and the passes added between them. Step 2 reuses the
+    /// searches `cache` holds, like [`analysis::analyze`].
+    pub fn run(
+        &self,
+        g: &ModuleGraph<M>,
+        entries: &[Entry],
+        options: &SplitOptions,
+        cache: &AnalysisCache,
+        trace: &mut DecisionTrace,
+    ) -> Result<AnalysisState<M>, SplitChunksError> {
+        let mut roots = create_roots(g, entries, options, trace)?;
+        for pass in &self.after_create_roots {
+            pass(&mut roots, options, trace);
+        }
+        let mut reachability = compute_reachability(roots, |g, condensation, chunk_roots| {
+            let roots_fingerprint = cache::fingerprint(g, chunk_roots.keys().copied());
+            analysis::reachable_from_roots(condensation, chunk_roots, cache, roots_fingerprint)
+        });
+        for pass in &self.after_compute_reachability {
+            pass(&mut reachability, options, trace);
+        }
+        let mut state = assign_modules(reachability, options, cache, trace);
+        self.optimize(&mut state, options, trace);
+        Ok(state)
+    }
+}
+
+impl<M: ModuleInfo + Clone> ChunkingStrategy<M> for Pipeline<'_, M> {
+    fn name(&self) -> &str {
+        "pipeline"
+    }
+
+    fn split(
+        &self,
+        g: &ModuleGraph<M>,
+        entries: &[Entry],
+        options: &SplitOptions,
+    ) -> Result<ChunkGraph, SplitChunksError> {
+        let mut trace = DecisionTrace::new(false);
+        let state = self.run(g, entries, options, &AnalysisCache::default(), &mut trace)?;
+        Ok(state.chunk_graph)
+    }
+}
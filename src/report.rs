@@ -0,0 +1,230 @@
+//! A self-contained HTML page with a treemap of the final chunks and their modules, sized
+//! by bytes, like webpack-bundle-analyzer draws it. The layout is computed here, so the
+//! page needs neither scripts nor network access and can be attached to an issue.
+
+use crate::chunk_graph::{ChunkGraph, ChunkKind};
+use crate::html::escape;
+use crate::module_graph::{ModuleGraph, ModuleInfo};
+
+/// Size of the treemap in pixels.
+const WIDTH: f64 = 1200.0;
+const HEIGHT: f64 = 800.0;
+/// Height of the label above the modules of a chunk.
+const CHUNK_LABEL_HEIGHT: f64 = 18.0;
+
+/// A rectangle of the treemap, in pixels.
+#[derive(Debug, Clone, Copy)]
+struct Rect {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+/// The report of `chunk_graph`, whose modules are those of `g`. Every chunk is a
+/// rectangle whose area is its size, colored by its kind and split into one rectangle
+/// per module. Hovering a rectangle shows its name and size.
+pub fn treemap_html<M: ModuleInfo>(chunk_graph: &ChunkGraph, g: &ModuleGraph<M>) -> String {
+    let mut chunks: Vec<_> = chunk_graph.chunks().map(|(_, chunk)| chunk).collect();
+    chunks.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    let total: usize = chunks.iter().map(|chunk| chunk.size).sum();
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<title>Chunks</title>\n<style>\n");
+    html.push_str(STYLE);
+    html.push_str("</style>\n</head>\n<body>\n");
+    html.push_str(&format!(
+        "<h1>{} chunks, {} bytes</h1>\n<p class=\"legend\">",
+        chunks.len(),
+        total
+    ));
+    for kind in KINDS {
+        html.push_str(&format!(
+            "<span class=\"{}\">{}</span> ",
+            kind_class(kind),
+            kind_class(kind)
+        ));
+    }
+    html.push_str("</p>\n");
+    html.push_str(&format!(
+        "<div class=\"treemap\" style=\"width:{}px;height:{}px\">\n",
+        WIDTH, HEIGHT
+    ));
+
+    let sizes: Vec<usize> = chunks.iter().map(|chunk| chunk.size).collect();
+    let bounds = Rect {
+        x: 0.0,
+        y: 0.0,
+        width: WIDTH,
+        height: HEIGHT,
+    };
+    for (chunk, rect) in chunks.iter().zip(squarify(&sizes, bounds)) {
+        html.push_str(&format!(
+            "<div class=\"chunk {}\" style=\"{}\" title=\"{} ({:?}, {} bytes, {} modules)\">",
+            kind_class(chunk.kind),
+            position(rect),
+            escape(&chunk.name),
+            chunk.kind,
+            chunk.size,
+            chunk.modules.len()
+        ));
+        html.push_str(&format!("<span>{}</span>", escape(&chunk.name)));
+
+        let mut modules = chunk.modules.clone();
+        modules.sort_by(|a, b| g[*b].size().cmp(&g[*a].size()).then_with(|| a.cmp(b)));
+        let sizes: Vec<usize> = modules
+            .iter()
+            .map(|module_id| g[*module_id].size())
+            .collect();
+        let inside = Rect {
+            x: 0.0,
+            y: CHUNK_LABEL_HEIGHT,
+            width: rect.width,
+            height: (rect.height - CHUNK_LABEL_HEIGHT).max(0.0),
+        };
+        for (module_id, rect) in modules.iter().zip(squarify(&sizes, inside)) {
+            let module = &g[*module_id];
+            html.push_str(&format!(
+                "<div class=\"module\" style=\"{}\" title=\"{} ({} bytes)\"><span>{}</span></div>",
+                position(rect),
+                escape(module.name()),
+                module.size(),
+                escape(module.name())
+            ));
+        }
+        html.push_str("</div>\n");
+    }
+    html.push_str("</div>\n</body>\n</html>\n");
+    html
+}
+
+const KINDS: [ChunkKind; 6] = [
+    ChunkKind::Entry,
+    ChunkKind::Async,
+    ChunkKind::Worker,
+    ChunkKind::Shared,
+    ChunkKind::Manual,
+    ChunkKind::Runtime,
+];
+
+const STYLE: &str = "\
+body { font: 12px sans-serif; margin: 16px; }
+h1 { font-size: 16px; }
+.legend span { padding: 2px 6px; margin-right: 4px; }
+.treemap { position: relative; }
+.chunk, .module { position: absolute; box-sizing: border-box; overflow: hidden; }
+.chunk { border: 1px solid #555; }
+.chunk > span { display: block; height: 18px; line-height: 18px; padding: 0 4px; font-weight: bold; white-space: nowrap; }
+.module { border: 1px solid rgba(0, 0, 0, 0.25); background: rgba(255, 255, 255, 0.35); }
+.module span { display: block; padding: 2px 4px; white-space: nowrap; }
+.module:hover { background: rgba(255, 255, 255, 0.7); }
+.entry { background: #8ecae6; }
+.async { background: #b5e48c; }
+.worker { background: #cdb4db; }
+.shared { background: #ffd166; }
+.manual { background: #f4a261; }
+.runtime { background: #adb5bd; }
+";
+
+/// CSS class of chunks of `kind`.
+fn kind_class(kind: ChunkKind) -> &'static str {
+    match kind {
+        ChunkKind::Entry => "entry",
+        ChunkKind::Async => "async",
+        ChunkKind::Worker => "worker",
+        ChunkKind::Shared => "shared",
+        ChunkKind::Manual => "manual",
+        ChunkKind::Runtime => "runtime",
+    }
+}
+
+fn position(rect: Rect) -> String {
+    format!(
+        "left:{:.1}px;top:{:.1}px;width:{:.1}px;height:{:.1}px",
+        rect.x, rect.y, rect.width, rect.height
+    )
+}
+
+/// Splits `bounds` into one rectangle per size with areas in proportion to the sizes,
+/// as close to squares as the squarified layout of Bruls, Huizing and van Wijk gets.
+/// The sizes should be sorted largest first. Empty modules still get a sliver.
+fn squarify(sizes: &[usize], bounds: Rect) -> Vec<Rect> {
+    let weights: Vec<f64> = sizes.iter().map(|size| (*size).max(1) as f64).collect();
+    let total: f64 = weights.iter().sum();
+    let scale = bounds.width * bounds.height / total;
+    let areas: Vec<f64> = weights.iter().map(|weight| weight * scale).collect();
+
+    let mut rects = Vec::with_capacity(areas.len());
+    let mut free = bounds;
+    let mut start = 0;
+    while start < areas.len() {
+        // Grow the row along the shorter side of the free space for as long as that
+        // makes its worst aspect ratio better.
+        let side = free.width.min(free.height);
+        let mut end = start + 1;
+        let mut worst = worst_ratio(&areas[start..end], side);
+        while end < areas.len() {
+            let next = worst_ratio(&areas[start..=end], side);
+            if next > worst {
+                break;
+            }
+            worst = next;
+            end += 1;
+        }
+
+        let row_area: f64 = areas[start..end].iter().sum();
+        if free.width >= free.height {
+            let width = if free.height > 0.0 {
+                row_area / free.height
+            } else {
+                0.0
+            };
+            let mut y = free.y;
+            for area in &areas[start..end] {
+                let height = if width > 0.0 { area / width } else { 0.0 };
+                rects.push(Rect {
+                    x: free.x,
+                    y,
+                    width,
+                    height,
+                });
+                y += height;
+            }
+            free.x += width;
+            free.width -= width;
+        } else {
+            let height = if free.width > 0.0 {
+                row_area / free.width
+            } else {
+                0.0
+            };
+            let mut x = free.x;
+            for area in &areas[start..end] {
+                let width = if height > 0.0 { area / height } else { 0.0 };
+                rects.push(Rect {
+                    x,
+                    y: free.y,
+                    width,
+                    height,
+                });
+                x += width;
+            }
+            free.y += height;
+            free.height -= height;
+        }
+        start = end;
+    }
+    rects
+}
+
+/// The largest aspect ratio among rectangles of `areas` laid out in a row along a side
+/// of length `side`.
+fn worst_ratio(areas: &[f64], side: f64) -> f64 {
+    let sum: f64 = areas.iter().sum();
+    let max = areas.iter().copied().fold(0.0, f64::max);
+    let min = areas.iter().copied().fold(f64::INFINITY, f64::min);
+    let side = side * side;
+    let sum = sum * sum;
+    (side * max / sum).max(sum / (side * min))
+}
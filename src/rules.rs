@@ -0,0 +1,128 @@
+//! Which placement rule claims a module in Step 3 when several match it.
+//!
+//! A module can be named by [`SplitOptions::manual_chunks`] and match any number of
+//! [`SplitOptions::cache_groups`]. Every rule has a priority, and the highest wins; on
+//! a tie the manual chunk wins, then the cache group listed first, so the same options
+//! always place a module the same way. Modules no rule claims are placed by the chunk
+//! roots they are reachable from alone.
+//!
+//! Enforced rules skip the size and request limits: manual chunks are never merged or
+//! split, and neither are the chunks of a cache group with [`CacheGroup::enforce`] set,
+//! whatever `min_size`, `max_async_requests` and `max_initial_requests` say.
+//!
+//! [`CacheGroup::enforce`]: crate::options::CacheGroup::enforce
+
+use crate::module_graph::ModuleInfo;
+use crate::options::SplitOptions;
+use std::cmp::Reverse;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rule {
+    /// The manual chunk [`SplitOptions::manual_chunks`] named.
+    ManualChunk { name: String },
+    /// The cache group at `index` in [`SplitOptions::cache_groups`].
+    CacheGroup { index: usize, name: String },
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rule::ManualChunk { name } => write!(f, "manual chunk {}", name),
+            Rule::CacheGroup { name, .. } => write!(f, "cache group {}", name),
+        }
+    }
+}
+
+/// A rule matching a module, with the priority it has.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleMatch {
+    pub rule: Rule,
+    pub priority: i32,
+    /// Whether the rule's chunks skip the size and request limits.
+    pub enforce: bool,
+}
+
+impl fmt::Display for RuleMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (priority {}", self.rule, self.priority)?;
+        if self.enforce {
+            f.write_str(", enforced")?;
+        }
+        f.write_str(")")
+    }
+}
+
+/// Every rule matching a module, the one claiming it first.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Resolution {
+    pub matches: Vec<RuleMatch>,
+}
+
+impl Resolution {
+    /// The rule claiming the module, `None` if no rule matches.
+    pub fn winner(&self) -> Option<&RuleMatch> {
+        self.matches.first()
+    }
+
+    /// The matching rules that lost to the winner.
+    pub fn outranked(&self) -> &[RuleMatch] {
+        self.matches.get(1..).unwrap_or_default()
+    }
+
+    /// The name of the manual chunk claiming the module, if a manual chunk won.
+    pub fn manual_chunk(&self) -> Option<&str> {
+        match &self.winner()?.rule {
+            Rule::ManualChunk { name } => Some(name),
+            Rule::CacheGroup { .. } => None,
+        }
+    }
+
+    /// The index of the cache group claiming the module, if a cache group won.
+    pub fn cache_group(&self) -> Option<usize> {
+        match &self.winner()?.rule {
+            Rule::CacheGroup { index, .. } => Some(*index),
+            Rule::ManualChunk { .. } => None,
+        }
+    }
+}
+
+/// The rules of `options` matching `module`, ordered by priority, highest first, then
+/// manual chunk first, then by the order the cache groups are listed in.
+pub fn resolve(options: &SplitOptions, module: &dyn ModuleInfo) -> Resolution {
+    let mut matches = vec![];
+    if let (Some(manual_chunks), Some(name)) =
+        (&options.manual_chunks, options.manual_chunk_of(module))
+    {
+        matches.push((
+            0,
+            RuleMatch {
+                rule: Rule::ManualChunk { name },
+                priority: manual_chunks.priority(),
+                enforce: true,
+            },
+        ));
+    }
+    for (index, group) in options.cache_groups.iter().enumerate() {
+        if group.matches(module.name()) {
+            matches.push((
+                index + 1,
+                RuleMatch {
+                    rule: Rule::CacheGroup {
+                        index,
+                        name: group.name.clone(),
+                    },
+                    priority: group.priority,
+                    enforce: group.enforce,
+                },
+            ));
+        }
+    }
+    matches.sort_by_key(|(order, rule_match)| (Reverse(rule_match.priority), *order));
+    Resolution {
+        matches: matches
+            .into_iter()
+            .map(|(_, rule_match)| rule_match)
+            .collect(),
+    }
+}
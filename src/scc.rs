@@ -0,0 +1,65 @@
+//! Import cycles collapsed into single units, so reachability treats every module of a
+//! cycle alike instead of depending on where a search happens to enter it.
+
+use crate::module_graph::{ModuleGraph, ModuleId};
+use petgraph::algo::tarjan_scc;
+use petgraph::visit::EdgeRef;
+
+/// The strongly connected components of a module graph and the acyclic graph between
+/// them. Modules outside any cycle have a component of their own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condensation {
+    /// The modules of each component, sorted.
+    pub components: Vec<Vec<ModuleId>>,
+    /// The component of each module, by module index.
+    pub component_of: Vec<usize>,
+    /// The components each component imports, sorted.
+    pub successors: Vec<Vec<usize>>,
+}
+
+impl Condensation {
+    /// Finds the components of `g` with Tarjan's algorithm.
+    pub fn new<M>(g: &ModuleGraph<M>) -> Self {
+        let mut components = tarjan_scc(g);
+        for members in &mut components {
+            members.sort_unstable();
+        }
+        let mut component_of = vec![0; g.node_count()];
+        for (component, members) in components.iter().enumerate() {
+            for module_id in members {
+                component_of[module_id.index()] = component;
+            }
+        }
+        let mut successors = vec![vec![]; components.len()];
+        for edge in g.edge_references() {
+            let (from, to) = (
+                component_of[edge.source().index()],
+                component_of[edge.target().index()],
+            );
+            if from != to {
+                successors[from].push(to);
+            }
+        }
+        for next in &mut successors {
+            next.sort_unstable();
+            next.dedup();
+        }
+        Condensation {
+            components,
+            component_of,
+            successors,
+        }
+    }
+
+    /// Adds a component for a new module without imports.
+    pub fn add_isolated(&mut self, module_id: ModuleId) {
+        self.component_of.push(self.components.len());
+        self.components.push(vec![module_id]);
+        self.successors.push(vec![]);
+    }
+
+    /// The modules in the same component as `module_id`, including itself.
+    pub fn members_with(&self, module_id: ModuleId) -> &[ModuleId] {
+        &self.components[self.component_of[module_id.index()]]
+    }
+}
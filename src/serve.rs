@@ -0,0 +1,358 @@
+//! A small HTTP server for exploring a split graph in the browser, for graphs too large
+//! to read as DOT.
+//!
+//! The page draws the chunk graph, or the module graph with every module colored by its
+//! chunk, with a force-directed layout it computes itself. Hovering a chunk lists its
+//! modules and their sizes, and async imports can be hidden. The server only answers
+//! `GET /` with the page and `GET /graph.json` with [`ExplorerGraph`], one connection
+//! at a time, which is all a single person exploring a graph needs. A client that
+//! sends nothing for [`READ_TIMEOUT`] is dropped, so it can't keep the others waiting.
+
+use crate::chunk_graph::{ChunkGraph, ChunkKind};
+use crate::module_graph::{ModuleGraph, ModuleInfo, ModuleType};
+use petgraph::visit::EdgeRef;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+/// What the page draws. Modules and chunks refer to each other by their index in
+/// `modules` and `chunks`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplorerGraph {
+    pub modules: Vec<ExplorerModule>,
+    pub imports: Vec<ExplorerImport>,
+    pub chunks: Vec<ExplorerChunk>,
+    /// `(parent, child)` pairs of the chunk graph.
+    pub chunk_edges: Vec<(usize, usize)>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplorerModule {
+    pub name: String,
+    pub size: usize,
+    #[serde(rename = "type")]
+    pub module_type: ModuleType,
+    /// The chunks holding the module, in id order. Empty if no entry reaches it.
+    pub chunks: Vec<usize>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplorerImport {
+    pub from: usize,
+    pub to: usize,
+    pub is_async: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplorerChunk {
+    pub name: String,
+    pub kind: ChunkKind,
+    pub size: usize,
+    pub modules: Vec<usize>,
+}
+
+impl ExplorerGraph {
+    /// The graph of `chunk_graph`, whose modules are those of `g`. Imports between the
+    /// same two modules are drawn once, async only if all of them are.
+    pub fn new<M: ModuleInfo>(chunk_graph: &ChunkGraph, g: &ModuleGraph<M>) -> Self {
+        let mut modules: Vec<ExplorerModule> = g
+            .node_weights()
+            .map(|module| ExplorerModule {
+                name: module.name().to_owned(),
+                size: module.size(),
+                module_type: module.module_type(),
+                chunks: vec![],
+            })
+            .collect();
+        let chunk_ids: Vec<_> = chunk_graph.graph.node_indices().collect();
+        let chunks = chunk_ids
+            .iter()
+            .enumerate()
+            .map(|(index, chunk_id)| {
+                let chunk = &chunk_graph.graph[*chunk_id];
+                for module_id in &chunk.modules {
+                    modules[module_id.index()].chunks.push(index);
+                }
+                ExplorerChunk {
+                    name: chunk.name.clone(),
+                    kind: chunk.kind,
+                    size: chunk.size,
+                    modules: chunk
+                        .modules
+                        .iter()
+                        .map(|module_id| module_id.index())
+                        .collect(),
+                }
+            })
+            .collect();
+        let mut sync_imports = BTreeSet::new();
+        let mut async_imports = BTreeSet::new();
+        for edge in g.edge_references() {
+            let pair = (edge.source().index(), edge.target().index());
+            if edge.weight().is_async || edge.weight().is_worker {
+                async_imports.insert(pair);
+            } else {
+                sync_imports.insert(pair);
+            }
+        }
+        let imports = sync_imports
+            .iter()
+            .map(|(from, to)| (*from, *to, false))
+            .chain(
+                async_imports
+                    .difference(&sync_imports)
+                    .map(|(from, to)| (*from, *to, true)),
+            )
+            .map(|(from, to, is_async)| ExplorerImport { from, to, is_async })
+            .collect();
+        let chunk_edges: BTreeSet<(usize, usize)> = chunk_graph
+            .graph
+            .edge_references()
+            .map(|edge| (edge.source().index(), edge.target().index()))
+            .filter(|(from, to)| from != to)
+            .collect();
+        ExplorerGraph {
+            modules,
+            imports,
+            chunks,
+            chunk_edges: chunk_edges.into_iter().collect(),
+        }
+    }
+}
+
+/// How long [`respond`] waits for each line of a request before giving up on it.
+pub const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Answers requests on `listener` until it fails, with the page and `graph`.
+pub fn serve(listener: &TcpListener, graph: &ExplorerGraph) -> io::Result<()> {
+    let graph_json = serde_json::to_string(graph)?;
+    for stream in listener.incoming() {
+        if let Err(error) = respond(stream?, &graph_json) {
+            tracing::warn!(%error, "failed to answer a request");
+        }
+    }
+    Ok(())
+}
+
+/// Answers the one request of `stream`, serving `graph_json` as `/graph.json`. Fails
+/// if the client sends nothing for [`READ_TIMEOUT`].
+pub fn respond(mut stream: TcpStream, graph_json: &str) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut request_line = String::new();
+    let mut reader = BufReader::new(&stream);
+    reader.read_line(&mut request_line)?;
+    // Drain the headers, so the browser doesn't see the connection reset.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+    let path = match request_line.split_whitespace().collect::<Vec<_>>()[..] {
+        ["GET", path, ..] => path,
+        _ => "",
+    };
+    let (status, content_type, body) = match path {
+        "/" | "/index.html" => ("200 OK", "text/html; charset=utf-8", PAGE),
+        "/graph.json" => ("200 OK", "application/json", graph_json),
+        _ => ("404 Not Found", "text/plain; charset=utf-8", "not found\n"),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body.as_bytes())?;
+    stream.flush()
+}
+
+/// The page, which fetches `/graph.json` and lays it out with a spring embedder: linked
+/// nodes pull together, nodes in neighboring cells of a grid push apart, so a step
+/// stays about linear in the size of the graph.
+const PAGE: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Chunk explorer</title>
+<style>
+body { font: 12px sans-serif; margin: 0; overflow: hidden; }
+#bar { position: fixed; top: 0; left: 0; right: 0; padding: 6px 10px; background: #f1f3f5; border-bottom: 1px solid #ced4da; }
+#bar label { margin-right: 12px; }
+#tip { position: fixed; display: none; max-width: 420px; max-height: 60vh; overflow: auto; padding: 6px 8px; background: #fff; border: 1px solid #868e96; box-shadow: 0 2px 6px rgba(0, 0, 0, 0.2); pointer-events: none; }
+#tip table { border-collapse: collapse; }
+#tip td { padding: 0 6px 0 0; white-space: nowrap; }
+#tip td:last-child { text-align: right; }
+canvas { display: block; }
+</style>
+</head>
+<body>
+<div id="bar">
+<label><input type="radio" name="view" value="chunks" checked> chunks</label>
+<label><input type="radio" name="view" value="modules"> modules</label>
+<label><input type="checkbox" id="async" checked> async edges</label>
+<span id="summary"></span>
+</div>
+<div id="tip"></div>
+<canvas id="canvas"></canvas>
+<script>
+const KIND_COLORS = { Entry: "#8ecae6", Async: "#b5e48c", Worker: "#cdb4db", Shared: "#ffd166", Manual: "#f4a261", Runtime: "#adb5bd" };
+const canvas = document.getElementById("canvas");
+const context = canvas.getContext("2d");
+const tip = document.getElementById("tip");
+let graph, view, nodes = [], links = [], frame = 0;
+let zoom = 1, panX = 0, panY = 0, drag = null, hovered = null;
+
+function chunkColor(index) {
+  return "hsl(" + (index * 137.508) % 360 + ", 60%, 65%)";
+}
+
+function escapeHtml(text) {
+  return text.replace(/[&<>"]/g, c => ({ "&": "&amp;", "<": "&lt;", ">": "&gt;", '"': "&quot;" })[c]);
+}
+
+function build() {
+  view = document.querySelector("input[name=view]:checked").value;
+  const showAsync = document.getElementById("async").checked;
+  const items = view === "chunks" ? graph.chunks : graph.modules;
+  const maxSize = Math.max(1, ...items.map(item => item.size));
+  nodes = items.map((item, index) => ({
+    item, index,
+    radius: 3 + 17 * Math.sqrt(item.size / maxSize),
+    color: view === "chunks" ? KIND_COLORS[item.kind] : (item.chunks.length ? chunkColor(item.chunks[0]) : "#dee2e6"),
+    x: Math.cos(index) * 10 * Math.sqrt(index + 1), y: Math.sin(index) * 10 * Math.sqrt(index + 1), vx: 0, vy: 0,
+  }));
+  links = view === "chunks"
+    ? graph.chunkEdges.map(([from, to]) => ({ from, to, isAsync: ["Async", "Worker"].includes(graph.chunks[to].kind) }))
+    : graph.imports.map(({ from, to, isAsync }) => ({ from, to, isAsync }));
+  links = links.filter(link => showAsync || !link.isAsync);
+  document.getElementById("summary").textContent =
+    graph.modules.length + " modules, " + graph.chunks.length + " chunks";
+  frame = 0;
+}
+
+function step() {
+  const cell = 60, grid = new Map();
+  for (const node of nodes) {
+    const key = Math.floor(node.x / cell) + "," + Math.floor(node.y / cell);
+    if (!grid.has(key)) grid.set(key, []);
+    grid.get(key).push(node);
+  }
+  for (const node of nodes) {
+    const cx = Math.floor(node.x / cell), cy = Math.floor(node.y / cell);
+    for (let dx = -1; dx <= 1; dx++) for (let dy = -1; dy <= 1; dy++) {
+      for (const other of grid.get((cx + dx) + "," + (cy + dy)) || []) {
+        if (other === node) continue;
+        let x = node.x - other.x, y = node.y - other.y, distance = Math.hypot(x, y) || 0.01;
+        const push = (node.radius + other.radius + 20) / distance;
+        if (push > 1) { node.vx += x / distance * push; node.vy += y / distance * push; }
+      }
+    }
+    node.vx -= node.x * 0.002; node.vy -= node.y * 0.002;
+  }
+  for (const link of links) {
+    const a = nodes[link.from], b = nodes[link.to];
+    const x = b.x - a.x, y = b.y - a.y, distance = Math.hypot(x, y) || 0.01;
+    const pull = (distance - (a.radius + b.radius + 30)) * 0.02 / distance;
+    a.vx += x * pull; a.vy += y * pull; b.vx -= x * pull; b.vy -= y * pull;
+  }
+  for (const node of nodes) {
+    if (node === drag) continue;
+    node.x += node.vx * 0.5; node.y += node.vy * 0.5; node.vx *= 0.6; node.vy *= 0.6;
+  }
+}
+
+function draw() {
+  canvas.width = innerWidth; canvas.height = innerHeight;
+  context.setTransform(zoom, 0, 0, zoom, canvas.width / 2 + panX, canvas.height / 2 + panY);
+  context.lineWidth = 1 / zoom;
+  for (const link of links) {
+    const a = nodes[link.from], b = nodes[link.to];
+    context.strokeStyle = link.isAsync ? "#adb5bd" : "#495057";
+    context.setLineDash(link.isAsync ? [4 / zoom, 4 / zoom] : []);
+    context.beginPath(); context.moveTo(a.x, a.y); context.lineTo(b.x, b.y); context.stroke();
+  }
+  context.setLineDash([]);
+  for (const node of nodes) {
+    context.fillStyle = node.color;
+    context.strokeStyle = node === hovered ? "#000" : "#495057";
+    context.beginPath(); context.arc(node.x, node.y, node.radius, 0, 2 * Math.PI); context.fill(); context.stroke();
+    if (view === "chunks" || node.radius * zoom > 8) {
+      context.fillStyle = "#212529"; context.font = 11 / zoom + "px sans-serif";
+      context.fillText(node.item.name, node.x + node.radius + 2 / zoom, node.y + 4 / zoom);
+    }
+  }
+}
+
+function tick() {
+  if (frame++ < 600) step();
+  draw();
+  requestAnimationFrame(tick);
+}
+
+function toGraph(event) {
+  return [(event.clientX - canvas.width / 2 - panX) / zoom, (event.clientY - canvas.height / 2 - panY) / zoom];
+}
+
+function nodeAt(event) {
+  const [x, y] = toGraph(event);
+  return nodes.find(node => Math.hypot(node.x - x, node.y - y) <= node.radius);
+}
+
+function describe(node) {
+  const item = node.item;
+  if (view === "chunks") {
+    const rows = item.modules
+      .map(index => graph.modules[index])
+      .sort((a, b) => b.size - a.size)
+      .map(module => "<tr><td>" + escapeHtml(module.name) + "</td><td>" + module.size + "</td></tr>")
+      .join("");
+    return "<b>" + escapeHtml(item.name) + "</b> (" + item.kind + ", " + item.size + " bytes, " +
+      item.modules.length + " modules)<table>" + rows + "</table>";
+  }
+  const chunks = item.chunks.map(index => escapeHtml(graph.chunks[index].name)).join(", ") || "no chunk";
+  return "<b>" + escapeHtml(item.name) + "</b> (" + item.type + ", " + item.size + " bytes)<br>in " + chunks;
+}
+
+canvas.addEventListener("mousedown", event => {
+  drag = nodeAt(event) || { pan: [event.clientX - panX, event.clientY - panY] };
+});
+addEventListener("mouseup", () => { drag = null; });
+canvas.addEventListener("mousemove", event => {
+  if (drag && drag.pan) {
+    panX = event.clientX - drag.pan[0]; panY = event.clientY - drag.pan[1];
+  } else if (drag) {
+    [drag.x, drag.y] = toGraph(event); frame = Math.min(frame, 500);
+  }
+  hovered = nodeAt(event);
+  if (hovered) {
+    tip.innerHTML = describe(hovered);
+    tip.style.display = "block";
+    tip.style.left = event.clientX + 14 + "px"; tip.style.top = event.clientY + 14 + "px";
+  } else {
+    tip.style.display = "none";
+  }
+});
+canvas.addEventListener("wheel", event => {
+  event.preventDefault();
+  const factor = Math.exp(-event.deltaY * 0.001);
+  const [x, y] = toGraph(event);
+  zoom *= factor;
+  panX = event.clientX - canvas.width / 2 - x * zoom; panY = event.clientY - canvas.height / 2 - y * zoom;
+}, { passive: false });
+for (const input of document.querySelectorAll("input")) input.addEventListener("change", build);
+
+fetch("/graph.json").then(response => response.json()).then(loaded => {
+  graph = loaded;
+  build();
+  tick();
+});
+</script>
+</body>
+</html>
+"##;
@@ -0,0 +1,160 @@
+//! Saving the analysis state to disk so later pipeline jobs can run passes, exporters
+//! and queries without recomputing Steps 1 to 3.
+//!
+//! [`save`] and [`load`] keep a state as JSON for other tools to read. [`analyze_warm`]
+//! keeps it in a compact binary cache file instead, with the [`graph_hash`] of what it
+//! was computed from, and reuses it as long as the graph and options hash the same, so
+//! a warm build skips the searches of Step 2 and the placement of Step 3.
+
+use crate::analysis::{self, AnalysisState};
+use crate::cache::AnalysisCache;
+use crate::entries::Entry;
+use crate::module_graph::{ModuleGraph, ModuleInfo};
+use crate::options::SplitOptions;
+use crate::trace::DecisionTrace;
+use petgraph::visit::EdgeRef;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::error::Error;
+use std::fs;
+use std::hash::{Hash, Hasher};
+
+pub fn save(state: &AnalysisState, path: &str) -> Result<(), Box<dyn Error>> {
+    fs::write(path, serde_json::to_string(state)?)?;
+    Ok(())
+}
+
+/// Reads a state written by [`save`].
+///
+/// Flag names are `&'static str`, so they borrow from the file contents, which are
+/// leaked for the rest of the process. Flags containing JSON escapes can't be borrowed
+/// and fail to load. Module names are copied into symbols of their own.
+pub fn load(path: &str) -> Result<AnalysisState, Box<dyn Error>> {
+    let contents: &'static str = Box::leak(fs::read_to_string(path)?.into_boxed_str());
+    Ok(serde_json::from_str(contents)?)
+}
+
+/// Changes whenever the layout of a cache file does, so older files are ignored.
+const CACHE_VERSION: u32 = 3;
+
+/// What a cache file of [`analyze_warm`] holds.
+#[derive(Serialize, Deserialize)]
+#[serde(bound(deserialize = "'de: 'static"))]
+struct CacheFile {
+    version: u32,
+    graph_hash: u64,
+    state: AnalysisState,
+}
+
+/// Whether [`analyze_warm`] reused its cache file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarmStart {
+    /// The cache file matched, and Steps 1 to 3 were skipped.
+    Hit,
+    /// There was no cache file, it was of another graph or options, or unreadable. The
+    /// analysis ran and the cache file was written.
+    Miss,
+}
+
+/// Hash of everything Steps 1 to 3 read: every module as [`ModuleInfo`] describes it,
+/// every import, the entries, and the options they use, including the manual chunk of
+/// every module. Equal graphs and options hash equal within the same build of this
+/// crate, which is all a cache file of [`analyze_warm`] is good for.
+pub fn graph_hash<M: ModuleInfo>(
+    g: &ModuleGraph<M>,
+    entries: &[Entry],
+    options: &SplitOptions,
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    g.node_count().hash(&mut hasher);
+    for module in g.node_weights() {
+        module.name().hash(&mut hasher);
+        module.size().hash(&mut hasher);
+        module.module_type().hash(&mut hasher);
+        module.reexports_only().hash(&mut hasher);
+        module.condition().hash(&mut hasher);
+        module.is_external().hash(&mut hasher);
+        module.layer().hash(&mut hasher);
+        module.side_effect_free().hash(&mut hasher);
+        options.manual_chunk_of(module).hash(&mut hasher);
+    }
+    // Repeated imports of a module combine in `analysis::prepare` in the order they
+    // were added, so the imports are hashed in that order too.
+    for edge in g.edge_references() {
+        let dependency = edge.weight();
+        (edge.source(), edge.target()).hash(&mut hasher);
+        dependency.is_async.hash(&mut hasher);
+        dependency.is_worker.hash(&mut hasher);
+        dependency.is_weak.hash(&mut hasher);
+        dependency.import_count.hash(&mut hasher);
+        dependency.load_likelihood.to_bits().hash(&mut hasher);
+        dependency.used_reexports.hash(&mut hasher);
+        dependency.condition.hash(&mut hasher);
+        dependency.hint.hash(&mut hasher);
+        dependency.frequency.map(f64::to_bits).hash(&mut hasher);
+    }
+    for entry in entries {
+        entry.module.hash(&mut hasher);
+        entry.prelude.hash(&mut hasher);
+        entry.depends_on.hash(&mut hasher);
+        entry.isolated.hash(&mut hasher);
+    }
+    let mut enabled_flags: Vec<&str> = options.enabled_flags.iter().copied().collect();
+    enabled_flags.sort_unstable();
+    enabled_flags.hash(&mut hasher);
+    options.parallel_imports.hash(&mut hasher);
+    options.max_async_depth.hash(&mut hasher);
+    options.min_chunks.hash(&mut hasher);
+    options.split_from.hash(&mut hasher);
+    options.cache_groups.hash(&mut hasher);
+    options.reuse_existing_chunk.hash(&mut hasher);
+    options
+        .manual_chunks
+        .as_ref()
+        .map(|manual_chunks| manual_chunks.priority())
+        .hash(&mut hasher);
+    hasher.finish()
+}
+
+/// [`analysis::analyze`], reusing the state saved at `path` if it was computed from a
+/// graph and options with the same [`graph_hash`], and saving the state there
+/// otherwise. On a hit `trace` records nothing of Steps 1 to 3.
+///
+/// Like [`load`], a reused state leaks the cache file's contents for the flag names it
+/// borrows.
+pub fn analyze_warm(
+    g: &ModuleGraph,
+    entries: &[Entry],
+    options: &SplitOptions,
+    cache: &AnalysisCache,
+    trace: &mut DecisionTrace,
+    path: &str,
+) -> Result<(AnalysisState, WarmStart), Box<dyn Error>> {
+    let graph_hash = graph_hash(g, entries, options);
+    if let Some(state) = load_cache_file(path, graph_hash) {
+        tracing::debug!(path, "reusing the saved analysis state");
+        return Ok((state, WarmStart::Hit));
+    }
+    let state = analysis::analyze(g, entries, options, cache, trace)?;
+    let cache_file = CacheFile {
+        version: CACHE_VERSION,
+        graph_hash,
+        state,
+    };
+    fs::write(path, bincode::serialize(&cache_file)?)?;
+    Ok((cache_file.state, WarmStart::Miss))
+}
+
+/// The state saved at `path` if it holds one computed from `graph_hash`. A missing or
+/// unreadable file is just a miss.
+fn load_cache_file(path: &str, graph_hash: u64) -> Option<AnalysisState> {
+    let contents = fs::read(path).ok()?;
+    // Check the header before leaking the contents of a file of another graph.
+    let (version, hash): (u32, u64) = bincode::deserialize(&contents).ok()?;
+    if version != CACHE_VERSION || hash != graph_hash {
+        return None;
+    }
+    let contents: &'static [u8] = Box::leak(contents.into_boxed_slice());
+    let cache_file: CacheFile = bincode::deserialize(contents).ok()?;
+    Some(cache_file.state)
+}
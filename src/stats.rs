@@ -0,0 +1,144 @@
+//! Sizes of the final chunks and how much of them is duplicated, as a table for the
+//! terminal or as JSON for dashboards.
+
+use crate::chunk_graph::{ChunkGraph, ChunkKind};
+use crate::module_graph::{ModuleGraph, ModuleId, ModuleInfo};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, Serialize)]
+pub struct SizeReport {
+    /// Bytes of all chunks together, counting duplicated modules once per chunk.
+    pub total_size: usize,
+    /// Bytes of the copies of duplicated modules beyond the first.
+    pub duplication_overhead: usize,
+    /// Every chunk, largest first.
+    pub chunks: Vec<ChunkSize>,
+    /// Modules placed in more than one chunk, by overhead, largest first.
+    pub duplicated_modules: Vec<ModuleSize>,
+    /// The largest modules of the graph that were placed in any chunk.
+    pub largest_modules: Vec<ModuleSize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChunkSize {
+    pub name: String,
+    pub kind: ChunkKind,
+    pub size: usize,
+    pub module_count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModuleSize {
+    pub name: String,
+    pub size: usize,
+    /// Names of the chunks holding the module.
+    pub chunks: Vec<String>,
+}
+
+/// Builds the report of `chunk_graph`, whose modules are those of `g`, listing the `top`
+/// largest modules.
+pub fn size_report<M: ModuleInfo>(
+    chunk_graph: &ChunkGraph,
+    g: &ModuleGraph<M>,
+    top: usize,
+) -> SizeReport {
+    let mut chunks_of_module: BTreeMap<ModuleId, Vec<String>> = BTreeMap::new();
+    let mut chunks = vec![];
+    for (_, chunk) in chunk_graph.chunks() {
+        for module_id in &chunk.modules {
+            chunks_of_module
+                .entry(*module_id)
+                .or_default()
+                .push(chunk.name.clone());
+        }
+        chunks.push(ChunkSize {
+            name: chunk.name.clone(),
+            kind: chunk.kind,
+            size: chunk.size,
+            module_count: chunk.modules.len(),
+        });
+    }
+    chunks.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+
+    let module_size = |(module_id, chunks): (&ModuleId, &Vec<String>)| ModuleSize {
+        name: g[*module_id].name().to_owned(),
+        size: g[*module_id].size(),
+        chunks: chunks.clone(),
+    };
+    let mut duplicated_modules: Vec<ModuleSize> = chunks_of_module
+        .iter()
+        .filter(|(_, chunks)| chunks.len() > 1)
+        .map(module_size)
+        .collect();
+    let overhead = |module: &ModuleSize| module.size * (module.chunks.len() - 1);
+    duplicated_modules.sort_by(|a, b| {
+        overhead(b)
+            .cmp(&overhead(a))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    let mut largest_modules: Vec<ModuleSize> = chunks_of_module.iter().map(module_size).collect();
+    largest_modules.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    largest_modules.truncate(top);
+
+    SizeReport {
+        total_size: chunks.iter().map(|chunk| chunk.size).sum(),
+        duplication_overhead: duplicated_modules.iter().map(overhead).sum(),
+        chunks,
+        duplicated_modules,
+        largest_modules,
+    }
+}
+
+impl SizeReport {
+    /// The report serialized as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+impl fmt::Display for SizeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "total {} bytes, {} bytes duplicated in {} modules",
+            self.total_size,
+            self.duplication_overhead,
+            self.duplicated_modules.len()
+        )?;
+        writeln!(
+            f,
+            "\n{:>10}  {:>7}  {:<8}  chunk",
+            "bytes", "modules", "kind"
+        )?;
+        for chunk in &self.chunks {
+            writeln!(
+                f,
+                "{:>10}  {:>7}  {:<8}  {}",
+                chunk.size,
+                chunk.module_count,
+                format!("{:?}", chunk.kind).to_lowercase(),
+                chunk.name
+            )?;
+        }
+        let modules = |f: &mut fmt::Formatter<'_>, title: &str, modules: &[ModuleSize]| {
+            if modules.is_empty() {
+                return Ok(());
+            }
+            writeln!(f, "\n{:>10}  {:>7}  {}", "bytes", "chunks", title)?;
+            for module in modules {
+                writeln!(
+                    f,
+                    "{:>10}  {:>7}  {}",
+                    module.size,
+                    module.chunks.len(),
+                    module.name
+                )?;
+            }
+            Ok(())
+        };
+        modules(f, "duplicated module", &self.duplicated_modules)?;
+        modules(f, "largest module", &self.largest_modules)
+    }
+}
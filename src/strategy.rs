@@ -0,0 +1,100 @@
+//! Chunking algorithms behind one interface, so different algorithms can split the same
+//! module graph and their chunk graphs can be compared.
+
+use crate::analysis;
+use crate::cache::AnalysisCache;
+use crate::chunk_graph::ChunkGraph;
+use crate::coarsen::{self, Grouping};
+use crate::dominators;
+use crate::entries::Entry;
+use crate::error::SplitChunksError;
+use crate::module_graph::{JsModule, ModuleGraph, ModuleInfo};
+use crate::options::SplitOptions;
+use crate::passes;
+use crate::trace::DecisionTrace;
+
+/// An algorithm that splits a module graph of `M` modules into chunks.
+pub trait ChunkingStrategy<M = JsModule> {
+    /// A short name to tell strategies apart in reports.
+    fn name(&self) -> &str;
+
+    /// Splits `g` into chunks, starting one entry chunk at each of `entries`. Options a
+    /// strategy has no use for are ignored.
+    fn split(
+        &self,
+        g: &ModuleGraph<M>,
+        entries: &[Entry],
+        options: &SplitOptions,
+    ) -> Result<ChunkGraph, SplitChunksError>;
+}
+
+/// The algorithm of this crate: chunks by the set of roots each module is reachable
+/// from, followed by every pass in [`passes::optimize`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ReachabilityStrategy;
+
+impl<M: ModuleInfo + Clone> ChunkingStrategy<M> for ReachabilityStrategy {
+    fn name(&self) -> &str {
+        "reachability"
+    }
+
+    fn split(
+        &self,
+        g: &ModuleGraph<M>,
+        entries: &[Entry],
+        options: &SplitOptions,
+    ) -> Result<ChunkGraph, SplitChunksError> {
+        let mut trace = DecisionTrace::new(false);
+        let mut state =
+            analysis::analyze(g, entries, options, &AnalysisCache::default(), &mut trace)?;
+        passes::optimize(&mut state, options, &mut trace);
+        Ok(state.chunk_graph)
+    }
+}
+
+/// The algorithm of this crate with Step 2 computed from a dominator tree, see
+/// [`dominators`]. Gives the same chunks as [`ReachabilityStrategy`], faster on graphs
+/// with many chunk roots.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DominatorStrategy;
+
+impl<M: ModuleInfo + Clone> ChunkingStrategy<M> for DominatorStrategy {
+    fn name(&self) -> &str {
+        "dominators"
+    }
+
+    fn split(
+        &self,
+        g: &ModuleGraph<M>,
+        entries: &[Entry],
+        options: &SplitOptions,
+    ) -> Result<ChunkGraph, SplitChunksError> {
+        let mut trace = DecisionTrace::new(false);
+        let mut state = dominators::analyze(g, entries, options, &mut trace)?;
+        passes::optimize(&mut state, options, &mut trace);
+        Ok(state.chunk_graph)
+    }
+}
+
+/// The algorithm of this crate on a graph of packages or directories instead of single
+/// modules, see [`coarsen`]. Faster on huge graphs, at the cost of keeping the modules
+/// of a package or directory together where they don't straddle chunk boundaries.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CoarseningStrategy {
+    pub grouping: Grouping,
+}
+
+impl<M: ModuleInfo + Clone> ChunkingStrategy<M> for CoarseningStrategy {
+    fn name(&self) -> &str {
+        "coarsening"
+    }
+
+    fn split(
+        &self,
+        g: &ModuleGraph<M>,
+        entries: &[Entry],
+        options: &SplitOptions,
+    ) -> Result<ChunkGraph, SplitChunksError> {
+        Ok(coarsen::split(g, entries, options, self.grouping)?.chunk_graph)
+    }
+}
@@ -0,0 +1,417 @@
+//! An opt-in log of the decisions the algorithm makes, for explaining surprising
+//! chunk assignments without adding prints to the source.
+
+use crate::module_graph::{ModuleGraph, ModuleId, ModuleInfo};
+use crate::options::SplitFrom;
+use crate::rules::Resolution;
+use petgraph::prelude::NodeIndex;
+use std::collections::BTreeSet;
+
+/// Why a module became the root of a chunk in Step 1.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RootReason {
+    Entry,
+    AsyncImport { importer: ModuleId },
+    WorkerImport { importer: ModuleId },
+    TypeChange { importer: ModuleId },
+    LayerChange { importer: ModuleId },
+}
+
+/// A chunk root dropped from a module's reachable roots in Step 3 because the module is
+/// already loaded by `available_from`, a parent of `root`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilteredRoot {
+    pub root: ModuleId,
+    pub available_from: ModuleId,
+}
+
+/// One decision of the algorithm. Chunk indices are the ones in effect when the
+/// decision was made; later garbage collection may renumber them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decision {
+    ChunkRootCreated {
+        root: ModuleId,
+        chunk: NodeIndex,
+        reason: RootReason,
+    },
+    ModulePlaced {
+        module: ModuleId,
+        chunk: NodeIndex,
+        reachable_from: Vec<ModuleId>,
+        filtered: Vec<FilteredRoot>,
+    },
+    ModuleDuplicated {
+        module: ModuleId,
+        chunks: Vec<NodeIndex>,
+        min_chunks: usize,
+    },
+    /// `module` stayed in the chunks of the roots reaching it, since `split_from` leaves
+    /// out the chunk groups of some of them.
+    ModuleNotSplit {
+        module: ModuleId,
+        chunks: Vec<NodeIndex>,
+        split_from: SplitFrom,
+    },
+    ChunkReused {
+        chunk: NodeIndex,
+        existing: NodeIndex,
+    },
+    ModuleRemovedAsAvailable {
+        module: ModuleId,
+        chunk: NodeIndex,
+    },
+    ChunkRemoved {
+        chunk: NodeIndex,
+        expected_size: f64,
+        min_size: usize,
+    },
+    /// The chunk was split from, or left with, fewer than `min_source_chunks` chunks.
+    ChunkRemovedForFewSources {
+        chunk: NodeIndex,
+        source_chunks: usize,
+        min_source_chunks: usize,
+    },
+    ChunkMergedIntoParent {
+        chunk: NodeIndex,
+        parent: NodeIndex,
+    },
+    /// A small chunk was duplicated although some of its modules have side effects and
+    /// `chunks`, which the modules were duplicated into, can run on the same page.
+    SideEffectsDuplicated {
+        chunk: NodeIndex,
+        modules: Vec<ModuleId>,
+        chunks: Vec<NodeIndex>,
+    },
+    /// A small chunk was kept, since duplicating it would have run `modules` twice on
+    /// one page.
+    DuplicationRefused {
+        chunk: NodeIndex,
+        modules: Vec<ModuleId>,
+    },
+    ChunkSplit {
+        chunk: NodeIndex,
+        parts: Vec<NodeIndex>,
+        max_size: usize,
+    },
+    ChunkMergedForRequests {
+        chunk: NodeIndex,
+        group: NodeIndex,
+        requests: usize,
+        max_requests: usize,
+    },
+    /// `modules`, copies of which were held by several of the sibling async chunks
+    /// `from`, were moved into `chunk`, which they all load.
+    DuplicatesHoisted {
+        chunk: NodeIndex,
+        modules: Vec<ModuleId>,
+        from: Vec<NodeIndex>,
+    },
+    /// `module` got no chunk group of its own, although `importer` imports it on demand,
+    /// since the importer's chunk group already is `max_async_depth` async imports deep.
+    AsyncImportInlined {
+        module: ModuleId,
+        importer: ModuleId,
+        max_async_depth: usize,
+    },
+    /// `module` got no chunk group of its own at the on-demand import from `importer`,
+    /// since it is already loaded wherever `importer` runs.
+    AsyncImportOfLoadedModule {
+        module: ModuleId,
+        importer: ModuleId,
+    },
+    /// The entry chunk `facade` kept only its entry module, since other chunks import
+    /// its other modules, which moved into `chunk`.
+    FacadeCreated {
+        facade: NodeIndex,
+        chunk: NodeIndex,
+    },
+    /// Placement rules matched `module`, and the first one of `resolution` claimed it.
+    RulesResolved {
+        module: ModuleId,
+        resolution: Resolution,
+    },
+}
+
+impl Decision {
+    /// One line explaining the decision, with module ids resolved to names in `g`.
+    pub fn describe<M: ModuleInfo>(&self, g: &ModuleGraph<M>) -> String {
+        match self {
+            Decision::ChunkRootCreated {
+                root,
+                chunk,
+                reason,
+            } => match reason {
+                RootReason::Entry => {
+                    format!(
+                        "chunk {} created for entry {}",
+                        chunk.index(),
+                        g[*root].name()
+                    )
+                }
+                RootReason::AsyncImport { importer } => format!(
+                    "chunk {} created for {} because of the async import from {}",
+                    chunk.index(),
+                    g[*root].name(),
+                    g[*importer].name()
+                ),
+                RootReason::WorkerImport { importer } => format!(
+                    "chunk {} created for {} because {} starts it as a worker",
+                    chunk.index(),
+                    g[*root].name(),
+                    g[*importer].name()
+                ),
+                RootReason::TypeChange { importer } => format!(
+                    "chunk {} created for {} because {} imports a different module type",
+                    chunk.index(),
+                    g[*root].name(),
+                    g[*importer].name()
+                ),
+                RootReason::LayerChange { importer } => format!(
+                    "chunk {} created for {} because {} imports a module of another layer",
+                    chunk.index(),
+                    g[*root].name(),
+                    g[*importer].name()
+                ),
+            },
+            Decision::ModulePlaced {
+                module,
+                chunk,
+                reachable_from,
+                filtered,
+            } => {
+                let mut line = format!(
+                    "{} placed in chunk {} because reachable from {{{}}}",
+                    g[*module].name(),
+                    chunk.index(),
+                    reachable_from
+                        .iter()
+                        .map(|root| g[*root].name())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+                for filtered_root in filtered {
+                    line.push_str(&format!(
+                        ", filtered {} since available from {}",
+                        g[filtered_root.root].name(), g[filtered_root.available_from].name()
+                    ));
+                }
+                line
+            }
+            Decision::ModuleDuplicated {
+                module,
+                chunks,
+                min_chunks,
+            } => format!(
+                "{} duplicated into chunks {{{}}} because fewer than min_chunks {} roots reach it",
+                g[*module].name(),
+                chunks
+                    .iter()
+                    .map(|chunk| chunk.index().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                min_chunks
+            ),
+            Decision::ModuleNotSplit {
+                module,
+                chunks,
+                split_from,
+            } => format!(
+                "{} kept in chunks {{{}}} because split_from {:?} leaves out some of the chunk groups reaching it",
+                g[*module].name(),
+                chunks
+                    .iter()
+                    .map(|chunk| chunk.index().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                split_from
+            ),
+            Decision::ChunkReused { chunk, existing } => format!(
+                "chunk {} replaced by chunk {} because both hold the same modules",
+                chunk.index(),
+                existing.index()
+            ),
+            Decision::ModuleRemovedAsAvailable { module, chunk } => format!(
+                "{} removed from chunk {} because every parent chunk group loads it",
+                g[*module].name(),
+                chunk.index()
+            ),
+            Decision::ChunkRemoved {
+                chunk,
+                expected_size,
+                min_size,
+            } => format!(
+                "chunk {} removed because expected size {} < min_size {}",
+                chunk.index(),
+                expected_size,
+                min_size
+            ),
+            Decision::ChunkRemovedForFewSources {
+                chunk,
+                source_chunks,
+                min_source_chunks,
+            } => format!(
+                "chunk {} removed because it was split from {} chunks < min_source_chunks {}",
+                chunk.index(),
+                source_chunks,
+                min_source_chunks
+            ),
+            Decision::ChunkMergedIntoParent { chunk, parent } => format!(
+                "chunk {} merged into chunk {}, which every chunk group loading it loads",
+                chunk.index(),
+                parent.index()
+            ),
+            Decision::SideEffectsDuplicated {
+                chunk,
+                modules,
+                chunks,
+            } => format!(
+                "chunk {} duplicated into chunks {{{}}}, which can run on the same page, running {} more than once",
+                chunk.index(),
+                chunks
+                    .iter()
+                    .map(|chunk| chunk.index().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                module_names(g, modules)
+            ),
+            Decision::DuplicationRefused { chunk, modules } => format!(
+                "chunk {} kept because duplicating it would run {} more than once on a page",
+                chunk.index(),
+                module_names(g, modules)
+            ),
+            Decision::ChunkSplit {
+                chunk,
+                parts,
+                max_size,
+            } => format!(
+                "chunk {} split into parts {{{}}} because it is larger than max_size {}",
+                chunk.index(),
+                parts
+                    .iter()
+                    .map(|part| part.index().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                max_size
+            ),
+            Decision::ChunkMergedForRequests {
+                chunk,
+                group,
+                requests,
+                max_requests,
+            } => format!(
+                "chunk {} merged into its source chunks because chunk group {} loads {} chunks > {}",
+                chunk.index(),
+                group.index(),
+                requests,
+                max_requests
+            ),
+            Decision::DuplicatesHoisted {
+                chunk,
+                modules,
+                from,
+            } => format!(
+                "{} hoisted from sibling async chunks {{{}}} into chunk {}",
+                module_names(g, modules),
+                from.iter()
+                    .map(|chunk| chunk.index().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                chunk.index()
+            ),
+            Decision::AsyncImportInlined {
+                module,
+                importer,
+                max_async_depth,
+            } => format!(
+                "{} not split off at the async import from {} because chunk groups nest at most max_async_depth {} deep",
+                g[*module].name(),
+                g[*importer].name(),
+                max_async_depth
+            ),
+            Decision::AsyncImportOfLoadedModule { module, importer } => format!(
+                "{} not split off at the async import from {} because it is already loaded wherever that module runs",
+                g[*module].name(),
+                g[*importer].name()
+            ),
+            Decision::FacadeCreated { facade, chunk } => format!(
+                "entry chunk {} reduced to a facade of its entry module because other chunks import its modules, which moved into chunk {}",
+                facade.index(),
+                chunk.index()
+            ),
+            Decision::RulesResolved { module, resolution } => {
+                let mut line = format!("{} claimed by", g[*module].name());
+                if let Some(winner) = resolution.winner() {
+                    line.push_str(&format!(" {}", winner));
+                }
+                if !resolution.outranked().is_empty() {
+                    let outranked: Vec<String> = resolution
+                        .outranked()
+                        .iter()
+                        .map(|rule_match| rule_match.to_string())
+                        .collect();
+                    line.push_str(&format!(" over {}", outranked.join(", ")));
+                }
+                line
+            }
+        }
+    }
+}
+
+/// Collects [`Decision`]s when enabled and ignores them otherwise. Decisions are built
+/// lazily so a disabled trace costs nothing.
+#[derive(Debug, Default)]
+pub struct DecisionTrace {
+    enabled: bool,
+    decisions: Vec<Decision>,
+}
+
+impl DecisionTrace {
+    pub fn new(enabled: bool) -> Self {
+        DecisionTrace {
+            enabled,
+            decisions: vec![],
+        }
+    }
+
+    pub fn record(&mut self, decision: impl FnOnce() -> Decision) {
+        if self.enabled {
+            self.decisions.push(decision());
+        }
+    }
+
+    /// Recorded decisions in the order they were made, for replaying.
+    pub fn decisions(&self) -> &[Decision] {
+        &self.decisions
+    }
+}
+
+/// The names of `modules` in `g`, separated by commas.
+fn module_names<M: ModuleInfo>(g: &ModuleGraph<M>, modules: &[ModuleId]) -> String {
+    modules
+        .iter()
+        .map(|module_id| g[*module_id].name())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The roots in `original` that Step 3's parent filter dropped, with the parent each
+/// one was available from.
+pub fn filtered_roots(
+    original: &[ModuleId],
+    kept: &[ModuleId],
+    reachable_chunks: &BTreeSet<(ModuleId, ModuleId)>,
+) -> Vec<FilteredRoot> {
+    original
+        .iter()
+        .filter(|root| !kept.contains(root))
+        .filter_map(|root| {
+            let available_from = original
+                .iter()
+                .find(|parent| reachable_chunks.contains(&(**parent, *root)))?;
+            Some(FilteredRoot {
+                root: *root,
+                available_from: *available_from,
+            })
+        })
+        .collect()
+}
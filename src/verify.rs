@@ -0,0 +1,202 @@
+//! Structural invariants of a chunk graph, checked between the passes so that a pass
+//! breaking one is caught right after it ran instead of by whatever reads its result.
+//!
+//! [`verify`] lists every violation. [`check_pass`] runs it after each pass of
+//! [`passes::optimize`] and of a [`Pipeline`] in debug builds, or in any build with
+//! [`SplitOptions::verify_passes`], and panics naming the pass.
+//!
+//! [`Pipeline`]: crate::pipeline::Pipeline
+
+use crate::analysis::AnalysisState;
+use crate::chunk_graph::{ChunkGraph, ChunkHandle, ChunkId};
+use crate::module_graph::{ModuleGraph, ModuleId, ModuleInfo};
+use crate::options::SplitOptions;
+use petgraph::visit::EdgeRef;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// A chunk edge starts or ends at a chunk the graph doesn't have.
+    DanglingEdge { from: ChunkId, to: ChunkId },
+    /// A chunk lists a source bundle the graph doesn't have.
+    DanglingSourceBundle { chunk: ChunkId, source: ChunkId },
+    /// The chunk or chunk group of a root in `chunk_roots` is not in the graph.
+    DanglingRoot { root: ModuleId, chunk: ChunkId },
+    /// A chunk holds a module the module graph doesn't have.
+    UnknownModule { chunk: ChunkId, module: ModuleId },
+    /// A chunk holds a module more than once.
+    RepeatedModule { chunk: ChunkId, module: ModuleId },
+    /// A chunk holds a module of another type than its own.
+    MixedModuleType { chunk: ChunkId, module: ModuleId },
+    /// A chunk's size is not the sum of the sizes of its modules.
+    WrongSize {
+        chunk: ChunkId,
+        size: usize,
+        expected: usize,
+    },
+    /// A chunk has the handle of a chunk before it, so it was added without
+    /// [`ChunkGraph::add_chunk`].
+    RepeatedHandle { chunk: ChunkId, handle: ChunkHandle },
+    /// [`ChunkGraph::chunk_by_handle`] doesn't find the chunk by its handle, so chunks
+    /// were renumbered without [`ChunkGraph::update_handles`].
+    LostHandle { chunk: ChunkId, handle: ChunkHandle },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::DanglingEdge { from, to } => write!(
+                f,
+                "edge from chunk {} to chunk {} leaves the graph",
+                from.index(),
+                to.index()
+            ),
+            Violation::DanglingSourceBundle { chunk, source } => write!(
+                f,
+                "chunk {} was split from chunk {}, which is not in the graph",
+                chunk.index(),
+                source.index()
+            ),
+            Violation::DanglingRoot { root, chunk } => write!(
+                f,
+                "root {} refers to chunk {}, which is not in the graph",
+                root.index(),
+                chunk.index()
+            ),
+            Violation::UnknownModule { chunk, module } => write!(
+                f,
+                "chunk {} holds module {}, which is not in the module graph",
+                chunk.index(),
+                module.index()
+            ),
+            Violation::RepeatedModule { chunk, module } => write!(
+                f,
+                "chunk {} holds module {} more than once",
+                chunk.index(),
+                module.index()
+            ),
+            Violation::MixedModuleType { chunk, module } => write!(
+                f,
+                "chunk {} holds module {} of another type",
+                chunk.index(),
+                module.index()
+            ),
+            Violation::WrongSize {
+                chunk,
+                size,
+                expected,
+            } => write!(
+                f,
+                "chunk {} has size {} but its modules add up to {}",
+                chunk.index(),
+                size,
+                expected
+            ),
+            Violation::RepeatedHandle { chunk, handle } => write!(
+                f,
+                "chunk {} has handle {}, which another chunk has too",
+                chunk.index(),
+                handle.index()
+            ),
+            Violation::LostHandle { chunk, handle } => write!(
+                f,
+                "chunk {} is not found by its handle {}",
+                chunk.index(),
+                handle.index()
+            ),
+        }
+    }
+}
+
+/// Every violation of the invariants in `chunk_graph`, whose modules are those of `g`.
+pub fn verify<M: ModuleInfo>(chunk_graph: &ChunkGraph, g: &ModuleGraph<M>) -> Vec<Violation> {
+    let graph = &chunk_graph.graph;
+    let exists = |chunk_id: ChunkId| chunk_id.index() < graph.node_count();
+    let mut violations = vec![];
+    for edge in graph.edge_references() {
+        if !exists(edge.source()) || !exists(edge.target()) {
+            violations.push(Violation::DanglingEdge {
+                from: edge.source(),
+                to: edge.target(),
+            });
+        }
+    }
+    for (root, (chunk_id, group_id)) in &chunk_graph.chunk_roots {
+        for chunk_id in [chunk_id, group_id] {
+            if !exists(*chunk_id) {
+                violations.push(Violation::DanglingRoot {
+                    root: *root,
+                    chunk: *chunk_id,
+                });
+            }
+        }
+    }
+    let mut handles: HashMap<ChunkHandle, ChunkId> = HashMap::new();
+    for (chunk_id, chunk) in chunk_graph.chunks() {
+        if handles.insert(chunk.handle, chunk_id).is_some() {
+            violations.push(Violation::RepeatedHandle {
+                chunk: chunk_id,
+                handle: chunk.handle,
+            });
+        } else if chunk_graph.chunk_by_handle(chunk.handle) != Some(chunk_id) {
+            violations.push(Violation::LostHandle {
+                chunk: chunk_id,
+                handle: chunk.handle,
+            });
+        }
+        for source_id in &chunk.source_bundles {
+            if !exists(*source_id) {
+                violations.push(Violation::DanglingSourceBundle {
+                    chunk: chunk_id,
+                    source: *source_id,
+                });
+            }
+        }
+        let mut seen = HashSet::new();
+        let mut expected = 0;
+        for module_id in &chunk.modules {
+            if module_id.index() >= g.node_count() {
+                violations.push(Violation::UnknownModule {
+                    chunk: chunk_id,
+                    module: *module_id,
+                });
+                continue;
+            }
+            if !seen.insert(*module_id) {
+                violations.push(Violation::RepeatedModule {
+                    chunk: chunk_id,
+                    module: *module_id,
+                });
+            }
+            if g[*module_id].module_type() != chunk.module_type {
+                violations.push(Violation::MixedModuleType {
+                    chunk: chunk_id,
+                    module: *module_id,
+                });
+            }
+            expected += g[*module_id].size();
+        }
+        if chunk.size != expected {
+            violations.push(Violation::WrongSize {
+                chunk: chunk_id,
+                size: chunk.size,
+                expected,
+            });
+        }
+    }
+    violations
+}
+
+/// Panics if the chunk graph of `state` violates an invariant after the pass named
+/// `pass`. Only checks in debug builds or with `options.verify_passes`.
+pub fn check_pass<M: ModuleInfo>(state: &AnalysisState<M>, options: &SplitOptions, pass: &str) {
+    if !cfg!(debug_assertions) && !options.verify_passes {
+        return;
+    }
+    let violations = verify(&state.chunk_graph, &state.module_graph);
+    if !violations.is_empty() {
+        let violations: Vec<String> = violations.iter().map(ToString::to_string).collect();
+        panic!("after {}: {}", pass, violations.join("; "));
+    }
+}
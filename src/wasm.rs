@@ -0,0 +1,27 @@
+//! WebAssembly bindings, so a bundler written in JS can call the algorithm directly.
+//! Built with the `wasm` feature, e.g. by `wasm-pack build --features wasm`.
+
+use crate::bindings::{self, JsSplitOptions};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// `splitChunks(graph, options)` from JS. `graph` is a graph description in the format
+/// of [`crate::loader`], either as an object or as a JSON string, and `options` an
+/// optional object of [`JsSplitOptions`]. Returns the [`bindings::ChunkAssignment`] as
+/// a plain object and throws if the graph is invalid.
+#[wasm_bindgen(js_name = splitChunks)]
+pub fn split_chunks(graph: JsValue, options: JsValue) -> Result<JsValue, JsError> {
+    let graph: serde_json::Value = match graph.as_string() {
+        Some(json) => serde_json::from_str(&json)?,
+        None => serde_wasm_bindgen::from_value(graph)?,
+    };
+    let options: JsSplitOptions = if options.is_undefined() || options.is_null() {
+        JsSplitOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options)?
+    };
+    let assignment = bindings::split_chunks(graph, &options)
+        .map_err(|error| JsError::new(&error.to_string()))?;
+    // Plain objects rather than `Map`s for the modules, like `JSON.parse` gives.
+    Ok(assignment.serialize(&serde_wasm_bindgen::Serializer::json_compatible())?)
+}
@@ -0,0 +1,117 @@
+//! How many rounds of requests each async import and worker waits for before its module
+//! runs, for weighing `min_size` against the number of requests.
+//!
+//! The chunks of a chunk group are fetched in parallel, but a nested async chunk group
+//! can only be requested once the group importing it runs, so every level of nesting
+//! adds a round trip.
+
+use crate::chunk_graph::{ChunkGraph, ChunkId, ChunkKind};
+use crate::chunk_groups::{ChunkGroupId, ChunkGroups};
+use serde::Serialize;
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+
+/// The requests before the root module of an async or worker chunk group runs, along
+/// the shortest chain of imports from an entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Waterfall {
+    /// Name of the root chunk of the chunk group.
+    pub chunk: String,
+    pub kind: ChunkKind,
+    /// Root chunks of the chunk groups loaded one after another, from the entry to the
+    /// chunk group itself.
+    pub chain: Vec<String>,
+    /// Rounds of requests, one per chunk group of the chain.
+    pub depth: usize,
+    /// Chunks fetched along the chain. Chunks an earlier group of the chain already
+    /// loaded are not fetched again, except by a worker, which loads its own.
+    pub requests: usize,
+    /// Bytes of the fetched chunks.
+    pub bytes: usize,
+}
+
+impl fmt::Display for Waterfall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} rounds, {} requests, {} bytes ({})",
+            self.chunk,
+            self.depth,
+            self.requests,
+            self.bytes,
+            self.chain.join(" > ")
+        )
+    }
+}
+
+/// The waterfall of every async and worker chunk group of `chunk_graph` that an entry
+/// reaches, deepest first, then by bytes, largest first.
+///
+/// The chain of a chunk group is the one with the fewest groups, found breadth-first
+/// from the entries in id order, so it is the least the group has to wait for.
+pub fn waterfalls(chunk_graph: &ChunkGraph) -> Vec<Waterfall> {
+    let chunk_groups = ChunkGroups::new(chunk_graph);
+    let mut importer_of: BTreeMap<ChunkGroupId, Option<ChunkGroupId>> = BTreeMap::new();
+    let mut queue: VecDeque<ChunkGroupId> = VecDeque::new();
+    for (group_id, _) in chunk_groups.entry_groups() {
+        importer_of.insert(group_id, None);
+        queue.push_back(group_id);
+    }
+    while let Some(group_id) = queue.pop_front() {
+        for child_id in chunk_groups.children(group_id) {
+            if let Entry::Vacant(importer) = importer_of.entry(child_id) {
+                importer.insert(Some(group_id));
+                queue.push_back(child_id);
+            }
+        }
+    }
+
+    let mut waterfalls: Vec<Waterfall> = importer_of
+        .iter()
+        .filter(|(_, importer)| importer.is_some())
+        .map(|(group_id, _)| {
+            let mut chain = vec![*group_id];
+            while let Some(importer) = importer_of[chain.last().unwrap()] {
+                chain.push(importer);
+            }
+            chain.reverse();
+
+            let mut loaded: BTreeSet<ChunkId> = BTreeSet::new();
+            let (mut requests, mut bytes) = (0, 0);
+            for link in &chain {
+                if chunk_groups.group(*link).kind == ChunkKind::Worker {
+                    loaded.clear();
+                }
+                for chunk_id in chunk_groups.initial_chunks(*link) {
+                    if loaded.insert(chunk_id) {
+                        requests += 1;
+                        bytes += chunk_graph.graph[chunk_id].size;
+                    }
+                }
+            }
+            let chunk = &chunk_graph.graph[chunk_groups.group(*group_id).root];
+            Waterfall {
+                chunk: chunk.name.clone(),
+                kind: chunk.kind,
+                depth: chain.len(),
+                chain: chain
+                    .iter()
+                    .map(|link| {
+                        chunk_graph.graph[chunk_groups.group(*link).root]
+                            .name
+                            .clone()
+                    })
+                    .collect(),
+                requests,
+                bytes,
+            }
+        })
+        .collect();
+    waterfalls.sort_by(|a, b| {
+        (b.depth, b.bytes)
+            .cmp(&(a.depth, a.bytes))
+            .then_with(|| a.chunk.cmp(&b.chunk))
+    });
+    waterfalls
+}
@@ -0,0 +1,463 @@
+//! The layer the JS bindings share: graphs and options in the shape JS passes them,
+//! and the chunk assignment they return.
+
+use split_chunks_algorithm::bindings::{self, JsSplitOptions};
+use split_chunks_algorithm::cache::AnalysisCache;
+use split_chunks_algorithm::{loader, ChunkKind};
+use std::fs;
+
+fn example_graph() -> serde_json::Value {
+    serde_json::from_str(&fs::read_to_string("examples/modules.json").unwrap()).unwrap()
+}
+
+#[test]
+fn assignment_lists_the_chunks_of_every_chunk_group_in_load_order() {
+    let options = JsSplitOptions {
+        vendors: true,
+        ..Default::default()
+    };
+    let assignment = bindings::split_chunks(example_graph(), &options).unwrap();
+    let groups: Vec<(&str, ChunkKind)> = assignment
+        .groups
+        .iter()
+        .map(|group| (group.name.as_str(), group.kind))
+        .collect();
+    assert_eq!(
+        groups,
+        [
+            ("entry-a", ChunkKind::Entry),
+            ("entry-b", ChunkKind::Entry),
+            ("asynced_a", ChunkKind::Async)
+        ]
+    );
+    let entry_a = &assignment.groups[0];
+    assert_eq!(
+        entry_a.chunks,
+        [
+            "styles/a",
+            "shared~entry-a~entry-b",
+            "vendors~entry-a~entry-b",
+            "entry-a"
+        ]
+    );
+    assert_eq!(entry_a.children, ["asynced_a"]);
+    assert_eq!(assignment.groups[2].chunks, ["asynced_a"]);
+}
+
+#[test]
+fn options_use_camel_case_names_and_default_like_split_options() {
+    let options: JsSplitOptions = serde_json::from_str(
+        r#"{ "minSize": 0, "maxInitialRequests": 3, "vendors": true, "flags": ["debug"] }"#,
+    )
+    .unwrap();
+    let options = options.to_split_options(&AnalysisCache::default());
+    assert_eq!(options.min_size, 0);
+    assert_eq!(options.max_initial_requests, 3);
+    assert_eq!(options.max_async_requests, 30);
+    assert_eq!(options.cache_groups.len(), 1);
+    assert!(options.enabled_flags.contains("debug"));
+}
+
+#[test]
+fn assignment_lists_the_chunks_of_split_chunks() {
+    let options = JsSplitOptions {
+        vendors: true,
+        ..Default::default()
+    };
+    let assignment = bindings::split_chunks(example_graph(), &options).unwrap();
+
+    let cache = AnalysisCache::default();
+    let (g, entries) = loader::from_value(example_graph(), &cache).unwrap();
+    let chunk_graph =
+        split_chunks_algorithm::split_chunks(&g, &entries, &options.to_split_options(&cache))
+            .unwrap();
+    let names: Vec<&str> = chunk_graph
+        .chunks()
+        .map(|(_, chunk)| chunk.name.as_str())
+        .collect();
+    let assigned: Vec<&str> = assignment
+        .chunks
+        .iter()
+        .map(|chunk| chunk.name.as_str())
+        .collect();
+    assert_eq!(assigned, names);
+
+    let lodash = &assignment.modules["node_modules/lodash/lodash.js"];
+    assert_eq!(lodash.len(), 1);
+    let vendors = assignment
+        .chunks
+        .iter()
+        .find(|chunk| chunk.name == lodash[0])
+        .unwrap();
+    assert_eq!(vendors.kind, ChunkKind::Shared);
+    assert!(!vendors.parents.is_empty());
+}
+
+#[test]
+fn invalid_graphs_are_errors() {
+    let graph = serde_json::json!({
+        "modules": [{ "name": "entry.js", "size": 1 }],
+        "entries": ["missing.js"]
+    });
+    let error = bindings::split_chunks(graph, &JsSplitOptions::default()).unwrap_err();
+    assert!(error.to_string().contains("missing.js"));
+}
+
+#[test]
+fn graphs_from_buffers_take_import_kinds_from_their_bits() {
+    let modules = serde_json::json!([
+        { "name": "entry.js", "size": 1000 },
+        { "name": "lazy.js", "size": 1000 },
+        { "name": "worker.js", "size": 1000 },
+        { "name": "util.js", "size": 1000 }
+    ]);
+    let entries = serde_json::json!(["entry.js"]);
+    let cache = AnalysisCache::default();
+    let edges = [0, 1, 0, 2, 1, 3, 2, 3];
+    let kinds = [bindings::ASYNC_IMPORT, bindings::WORKER_IMPORT, 0, 0];
+    let (g, entries) =
+        bindings::graph_from_buffers(modules.clone(), entries, &edges, Some(&kinds), &cache)
+            .unwrap();
+    let assignment =
+        bindings::split_loaded(&g, &entries, &JsSplitOptions::default(), &cache).unwrap();
+    let kind_of = |name: &str| {
+        assignment
+            .chunks
+            .iter()
+            .find(|chunk| chunk.name == name)
+            .unwrap()
+            .kind
+    };
+    assert_eq!(kind_of("lazy"), ChunkKind::Async);
+    assert_eq!(kind_of("worker"), ChunkKind::Worker);
+    // The worker can't use the page's chunks, so `util.js` is placed for both.
+    assert_eq!(assignment.modules["util.js"].len(), 2);
+
+    let entries = serde_json::json!(["entry.js"]);
+    let error =
+        bindings::graph_from_buffers(modules, entries, &[0, 1, 2], None, &cache).unwrap_err();
+    assert!(error.to_string().contains("pairs"));
+}
+
+#[test]
+fn page_and_workers_reuse_a_chunk_with_the_same_modules() {
+    let graph = serde_json::json!({
+        "modules": [
+            { "name": "a.js", "size": 1000 },
+            { "name": "b.js", "size": 1000 },
+            { "name": "worker-a.js", "size": 1000 },
+            { "name": "worker-b.js", "size": 1000 },
+            { "name": "utils.js", "size": 3000 }
+        ],
+        "edges": [
+            { "from": "a.js", "to": "utils.js" },
+            { "from": "b.js", "to": "utils.js" },
+            { "from": "a.js", "to": "worker-a.js", "is_worker": true },
+            { "from": "b.js", "to": "worker-b.js", "is_worker": true },
+            { "from": "worker-a.js", "to": "utils.js" },
+            { "from": "worker-b.js", "to": "utils.js" }
+        ],
+        "entries": ["a.js", "b.js"]
+    });
+    let split = |options: &str| {
+        bindings::split_chunks(graph.clone(), &serde_json::from_str(options).unwrap()).unwrap()
+    };
+
+    let assignment = split("{}");
+    assert_eq!(
+        assignment.modules["utils.js"],
+        ["shared~a~b", "shared~worker-a~worker-b"]
+    );
+
+    let assignment = split(r#"{ "reuseExistingChunk": true }"#);
+    assert_eq!(assignment.modules["utils.js"], ["shared~a~b"]);
+    let shared = assignment
+        .chunks
+        .iter()
+        .find(|chunk| chunk.name == "shared~a~b")
+        .unwrap();
+    assert_eq!(shared.parents, ["a", "b", "worker-a", "worker-b"]);
+}
+
+#[test]
+fn hot_chunk_groups_keep_fewer_chunks_than_cold_ones() {
+    let graph = serde_json::json!({
+        "modules": [
+            { "name": "entry.js", "size": 1000 },
+            { "name": "route-a.js", "size": 1000 },
+            { "name": "route-b.js", "size": 1000 },
+            { "name": "route-c.js", "size": 1000 },
+            { "name": "route-d.js", "size": 1000 },
+            { "name": "hot.js", "size": 4000 },
+            { "name": "cold.js", "size": 4000 }
+        ],
+        "edges": [
+            { "from": "entry.js", "to": "route-a.js", "is_async": true, "frequency": 3.0 },
+            { "from": "entry.js", "to": "route-b.js", "is_async": true, "frequency": 3.0 },
+            { "from": "entry.js", "to": "route-c.js", "is_async": true, "frequency": 0.2 },
+            { "from": "entry.js", "to": "route-d.js", "is_async": true, "frequency": 0.2 },
+            { "from": "route-a.js", "to": "hot.js" },
+            { "from": "route-b.js", "to": "hot.js" },
+            { "from": "route-c.js", "to": "cold.js" },
+            { "from": "route-d.js", "to": "cold.js" }
+        ],
+        "entries": ["entry.js"]
+    });
+    let split = |options: &str| {
+        bindings::split_chunks(graph.clone(), &serde_json::from_str(options).unwrap()).unwrap()
+    };
+
+    // Both shared chunks are above the minimum size while frequencies are ignored.
+    let assignment = split(r#"{ "minSize": 2000, "hotPathBias": 0 }"#);
+    assert_eq!(assignment.modules["hot.js"], ["shared~route-a~route-b"]);
+    assert_eq!(assignment.modules["cold.js"], ["shared~route-c~route-d"]);
+
+    // The hot routes would need 6000 bytes for a shared chunk, the cold ones only 400.
+    let assignment = split(r#"{ "minSize": 2000 }"#);
+    let mut hot = assignment.modules["hot.js"].clone();
+    hot.sort();
+    assert_eq!(hot, ["route-a", "route-b"]);
+    assert_eq!(assignment.modules["cold.js"], ["shared~route-c~route-d"]);
+}
+
+#[test]
+fn deterministic_chunk_ids_survive_unrelated_chunks() {
+    let ids = |graph: serde_json::Value, chunk_ids: &str| {
+        let options = serde_json::from_str(&format!(r#"{{ "chunkIds": "{}" }}"#, chunk_ids));
+        let assignment = bindings::split_chunks(graph, &options.unwrap()).unwrap();
+        assignment
+            .chunks
+            .into_iter()
+            .map(|chunk| (chunk.name, chunk.id.to_string()))
+            .collect::<std::collections::BTreeMap<_, _>>()
+    };
+    let mut graph = example_graph();
+    let before = ids(graph.clone(), "deterministic");
+    let natural_before = ids(graph.clone(), "natural");
+    assert!(before.values().all(|id| id.len() >= 3));
+
+    // A new async chunk shifts the natural ids of the chunks created after it.
+    graph["modules"]
+        .as_array_mut()
+        .unwrap()
+        .push(serde_json::json!({ "name": "0-lazy.js", "size": 1000 }));
+    let entry = graph["entries"][0].as_str().unwrap().to_owned();
+    graph["edges"]
+        .as_array_mut()
+        .unwrap()
+        .push(serde_json::json!({ "from": entry, "to": "0-lazy.js", "is_async": true }));
+    let natural_after = ids(graph.clone(), "natural");
+    assert!(natural_before
+        .iter()
+        .any(|(name, id)| &natural_after[name] != id));
+    let after = ids(graph.clone(), "deterministic");
+    assert_eq!(after.len(), before.len() + 1);
+    for (name, id) in &before {
+        assert_eq!(&after[name], id, "{}", name);
+    }
+
+    let named = ids(graph, "named");
+    assert!(named.iter().all(|(name, id)| name == id));
+}
+
+#[test]
+fn modules_with_side_effects_are_not_duplicated_onto_one_page_when_refused() {
+    let graph = serde_json::json!({
+        "modules": [
+            { "name": "entry.js", "size": 1000 },
+            { "name": "route-a.js", "size": 1000 },
+            { "name": "route-b.js", "size": 1000 },
+            { "name": "route-c.js", "size": 1000 },
+            { "name": "route-d.js", "size": 1000 },
+            { "name": "analytics.js", "size": 100 },
+            { "name": "format.js", "size": 100, "side_effect_free": true }
+        ],
+        "edges": [
+            { "from": "entry.js", "to": "route-a.js", "is_async": true },
+            { "from": "entry.js", "to": "route-b.js", "is_async": true },
+            { "from": "entry.js", "to": "route-c.js", "is_async": true },
+            { "from": "entry.js", "to": "route-d.js", "is_async": true },
+            { "from": "route-a.js", "to": "analytics.js" },
+            { "from": "route-b.js", "to": "analytics.js" },
+            { "from": "route-c.js", "to": "format.js" },
+            { "from": "route-d.js", "to": "format.js" }
+        ],
+        "entries": ["entry.js"]
+    });
+    let split = |options: &str| {
+        bindings::split_chunks(graph.clone(), &serde_json::from_str(options).unwrap()).unwrap()
+    };
+    let sorted = |mut chunks: Vec<String>| {
+        chunks.sort();
+        chunks
+    };
+
+    let assignment = split(r#"{ "minSize": 1000 }"#);
+    assert_eq!(
+        sorted(assignment.modules["analytics.js"].clone()),
+        ["route-a", "route-b"]
+    );
+
+    let assignment = split(r#"{ "minSize": 1000, "refuseSideEffectDuplication": true }"#);
+    assert_eq!(
+        assignment.modules["analytics.js"],
+        ["shared~route-a~route-b"]
+    );
+    assert_eq!(
+        sorted(assignment.modules["format.js"].clone()),
+        ["route-c", "route-d"]
+    );
+}
+
+#[test]
+fn granular_vendors_give_large_packages_chunks_of_their_own() {
+    let graph = serde_json::json!({
+        "modules": [
+            { "name": "a.js", "size": 1000 },
+            { "name": "b.js", "size": 1000 },
+            { "name": "node_modules/react-dom/index.js", "size": 100000 },
+            { "name": "node_modules/react-dom/server.js", "size": 30000 },
+            { "name": "node_modules/@scope/big/index.js", "size": 80000 },
+            { "name": "node_modules/tiny/index.js", "size": 500 },
+            { "name": "node_modules/small/index.js", "size": 700 }
+        ],
+        "edges": [
+            { "from": "a.js", "to": "node_modules/react-dom/index.js" },
+            { "from": "b.js", "to": "node_modules/react-dom/index.js" },
+            { "from": "node_modules/react-dom/index.js", "to": "node_modules/react-dom/server.js" },
+            { "from": "a.js", "to": "node_modules/@scope/big/index.js" },
+            { "from": "b.js", "to": "node_modules/@scope/big/index.js" },
+            { "from": "a.js", "to": "node_modules/tiny/index.js" },
+            { "from": "b.js", "to": "node_modules/tiny/index.js" },
+            { "from": "a.js", "to": "node_modules/small/index.js" },
+            { "from": "b.js", "to": "node_modules/small/index.js" }
+        ],
+        "entries": ["a.js", "b.js"]
+    });
+    let split = |options: &str| {
+        bindings::split_chunks(graph.clone(), &serde_json::from_str(options).unwrap()).unwrap()
+    };
+
+    let assignment = split(r#"{ "vendors": true }"#);
+    assert_eq!(
+        assignment.modules["node_modules/@scope/big/index.js"],
+        ["vendors~a~b"]
+    );
+    assert_eq!(
+        assignment.modules["node_modules/react-dom/index.js"],
+        ["vendors~a~b"]
+    );
+
+    let assignment = split(r#"{ "granularVendors": 50000 }"#);
+    for module in [
+        "node_modules/react-dom/index.js",
+        "node_modules/react-dom/server.js",
+    ] {
+        assert_eq!(assignment.modules[module], ["vendors~react-dom~a~b"]);
+    }
+    assert_eq!(
+        assignment.modules["node_modules/@scope/big/index.js"],
+        ["vendors~@scope/big~a~b"]
+    );
+    assert_eq!(
+        assignment.modules["node_modules/tiny/index.js"],
+        ["vendors~a~b"]
+    );
+    assert_eq!(
+        assignment.modules["node_modules/small/index.js"],
+        ["vendors~a~b"]
+    );
+}
+
+#[test]
+fn modules_sibling_async_chunks_duplicate_are_hoisted_into_a_shared_chunk() {
+    let graph = serde_json::json!({
+        "modules": [
+            { "name": "index.js", "size": 1000 },
+            { "name": "route-a.js", "size": 1000 },
+            { "name": "route-b.js", "size": 1000 },
+            { "name": "route-c.js", "size": 1000 },
+            { "name": "table.js", "size": 600 },
+            { "name": "chart.js", "size": 600 }
+        ],
+        "edges": [
+            { "from": "index.js", "to": "route-a.js", "is_async": true },
+            { "from": "index.js", "to": "route-b.js", "is_async": true },
+            { "from": "index.js", "to": "route-c.js", "is_async": true },
+            { "from": "route-a.js", "to": "table.js" },
+            { "from": "route-b.js", "to": "table.js" },
+            { "from": "route-b.js", "to": "chart.js" },
+            { "from": "route-c.js", "to": "chart.js" }
+        ],
+        "entries": ["index.js"]
+    });
+    let split = |options: &str| {
+        bindings::split_chunks(graph.clone(), &serde_json::from_str(options).unwrap()).unwrap()
+    };
+
+    // Each shared chunk is under the minimum size on its own, so both are duplicated.
+    let assignment = split(r#"{ "minSize": 1000 }"#);
+    assert_eq!(assignment.modules["table.js"].len(), 2);
+    assert_eq!(assignment.modules["chart.js"].len(), 2);
+
+    let assignment = split(r#"{ "minSize": 1000, "hoistSiblingDuplicates": true }"#);
+    let hoisted = "shared~route-a~route-b~route-c";
+    assert_eq!(assignment.modules["table.js"], [hoisted]);
+    assert_eq!(assignment.modules["chart.js"], [hoisted]);
+    let chunk = assignment
+        .chunks
+        .iter()
+        .find(|chunk| chunk.name == hoisted)
+        .unwrap();
+    assert_eq!(chunk.size, 1200);
+    let mut parents = chunk.parents.clone();
+    parents.sort();
+    assert_eq!(parents, ["route-a", "route-b", "route-c"]);
+
+    // Split from fewer chunks than the minimum, the hoisted chunk folds back into them.
+    let assignment =
+        split(r#"{ "minSize": 1000, "hoistSiblingDuplicates": true, "minSourceChunks": 4 }"#);
+    for module in ["table.js", "chart.js"] {
+        let mut chunks = assignment.modules[module].clone();
+        chunks.sort();
+        assert_eq!(chunks, ["route-a", "route-b", "route-c"]);
+    }
+}
+
+#[test]
+fn chunks_option_picks_the_chunk_groups_shared_modules_are_split_out_of() {
+    let graph = serde_json::json!({
+        "modules": [
+            { "name": "home.js", "size": 1000 },
+            { "name": "admin.js", "size": 1000 },
+            { "name": "framework.js", "size": 1000 },
+            { "name": "route-a.js", "size": 1000 },
+            { "name": "route-b.js", "size": 1000 },
+            { "name": "table.js", "size": 1000 }
+        ],
+        "edges": [
+            { "from": "home.js", "to": "framework.js" },
+            { "from": "admin.js", "to": "framework.js" },
+            { "from": "home.js", "to": "route-a.js", "is_async": true },
+            { "from": "home.js", "to": "route-b.js", "is_async": true },
+            { "from": "route-a.js", "to": "table.js" },
+            { "from": "route-b.js", "to": "table.js" }
+        ],
+        "entries": ["home.js", "admin.js"]
+    });
+    let split = |options: &str| {
+        bindings::split_chunks(graph.clone(), &serde_json::from_str(options).unwrap()).unwrap()
+    };
+
+    let assignment = split(r#"{ "minSize": 0 }"#);
+    assert_eq!(assignment.modules["framework.js"], ["shared~admin~home"]);
+    assert_eq!(assignment.modules["table.js"], ["shared~route-a~route-b"]);
+
+    let assignment = split(r#"{ "minSize": 0, "chunks": "initial" }"#);
+    assert_eq!(assignment.modules["framework.js"], ["shared~admin~home"]);
+    assert_eq!(assignment.modules["table.js"], ["route-b", "route-a"]);
+
+    let assignment = split(r#"{ "minSize": 0, "chunks": "async" }"#);
+    assert_eq!(assignment.modules["framework.js"], ["admin", "home"]);
+    assert_eq!(assignment.modules["table.js"], ["shared~route-a~route-b"]);
+}
@@ -0,0 +1,1464 @@
+//! Chunk assignments of the module graphs in `tests/fixtures`.
+//!
+//! Every fixture directory holds a `modules.json` graph description, see
+//! [`loader::parse`], and the `expected_chunks.json` it should split into after Step 3
+//! with the default options: the modules of each chunk by chunk name. Step 2 computed
+//! from the dominator tree has to give the same chunks. Run with `UPDATE_FIXTURES=1` to
+//! write the current assignments instead of comparing them. The same graphs described
+//! in the newline-delimited format have to load the same, and their stats documents
+//! have to read back unchanged.
+
+use split_chunks_algorithm::adapters;
+use split_chunks_algorithm::analysis::{self, AnalysisState};
+use split_chunks_algorithm::cache::AnalysisCache;
+use split_chunks_algorithm::coarsen::Grouping;
+use split_chunks_algorithm::dominators;
+use split_chunks_algorithm::duplicates::{self, Suggestion};
+use split_chunks_algorithm::emit::{self, EmitError, EmitOptions, FileNameTemplate};
+use split_chunks_algorithm::explain;
+use split_chunks_algorithm::graph_rules::{GraphRules, ImportPattern};
+use split_chunks_algorithm::hashing::NameAndSize;
+use split_chunks_algorithm::html;
+use split_chunks_algorithm::ids::ChunkIds;
+use split_chunks_algorithm::incremental::IncrementalChunker;
+use split_chunks_algorithm::lint::{self, Lint, Severity};
+use split_chunks_algorithm::manifest;
+use split_chunks_algorithm::options::{CacheGroup, EntrySignatures, ManualChunks, ParallelImports};
+use split_chunks_algorithm::output::{self, Stats};
+use split_chunks_algorithm::pareto::{self, SweepPoint};
+use split_chunks_algorithm::serve::{self, ExplorerGraph};
+use split_chunks_algorithm::snapshot::{self, WarmStart};
+use split_chunks_algorithm::trace::DecisionTrace;
+use split_chunks_algorithm::verify::{self, Violation};
+use split_chunks_algorithm::{diff, loader};
+use split_chunks_algorithm::{
+    split_chunks_with, ChunkGraph, ChunkGroups, ChunkHandle, ChunkId, ChunkKind, ChunkingStrategy,
+    CoarseningStrategy, Dependency, Entry, JsModule, ModuleGraph, ModuleId, ReachabilityStrategy,
+    SplitOptions,
+};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Module names by chunk name, each sorted.
+type Assignment = BTreeMap<String, Vec<String>>;
+
+/// The chunks after Step 3 of the graph described by `modules_json`, with Step 2
+/// computed by the search from every root and from the dominator tree.
+fn assignments(modules_json: &str) -> [Assignment; 2] {
+    let cache = AnalysisCache::default();
+    let (g, entries) = loader::parse(modules_json, &cache).unwrap();
+    let options = SplitOptions::default();
+    let by_search = analysis::analyze(
+        &g,
+        &entries,
+        &options,
+        &cache,
+        &mut DecisionTrace::new(false),
+    );
+    let by_dominators = dominators::analyze(&g, &entries, &options, &mut DecisionTrace::new(false));
+    [by_search, by_dominators].map(|state| assignment(&state.unwrap()))
+}
+
+fn assignment(state: &AnalysisState) -> Assignment {
+    state
+        .chunk_graph
+        .chunks()
+        .map(|(_, chunk)| {
+            let mut modules: Vec<String> = chunk
+                .modules
+                .iter()
+                .map(|module_id| state.module_graph[*module_id].name.to_string())
+                .collect();
+            modules.sort();
+            (chunk.name.clone(), modules)
+        })
+        .collect()
+}
+
+/// One line for every chunk whose modules differ between `expected` and `actual`.
+fn differences(expected: &Assignment, actual: &Assignment) -> Vec<String> {
+    let mut names: Vec<&String> = expected.keys().chain(actual.keys()).collect();
+    names.sort();
+    names.dedup();
+    names
+        .into_iter()
+        .filter_map(|name| match (expected.get(name), actual.get(name)) {
+            (Some(expected), Some(actual)) if expected == actual => None,
+            (Some(expected), Some(actual)) => Some(format!(
+                "  chunk {}: expected {:?}, got {:?}",
+                name, expected, actual
+            )),
+            (Some(expected), None) => Some(format!("  missing chunk {}: {:?}", name, expected)),
+            (None, Some(actual)) => Some(format!("  unexpected chunk {}: {:?}", name, actual)),
+            (None, None) => unreachable!(),
+        })
+        .collect()
+}
+
+/// Every fixture directory, sorted.
+fn fixture_dirs() -> Vec<PathBuf> {
+    let mut fixtures: Vec<_> =
+        fs::read_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.is_dir())
+            .collect();
+    fixtures.sort();
+    assert!(!fixtures.is_empty());
+    fixtures
+}
+
+#[test]
+fn fixtures() {
+    let update = std::env::var_os("UPDATE_FIXTURES").is_some();
+    let fixtures = fixture_dirs();
+
+    let mut failures = vec![];
+    for fixture in &fixtures {
+        let [actual, by_dominators] =
+            assignments(&fs::read_to_string(fixture.join("modules.json")).unwrap());
+        let mismatches = differences(&actual, &by_dominators);
+        if !mismatches.is_empty() {
+            failures.push(format!(
+                "{} with Step 2 from dominators:\n{}",
+                fixture.display(),
+                mismatches.join("\n")
+            ));
+        }
+        let expected_path = fixture.join("expected_chunks.json");
+        if update {
+            let json = serde_json::to_string_pretty(&actual).unwrap();
+            fs::write(&expected_path, json + "\n").unwrap();
+            continue;
+        }
+        let expected: Assignment =
+            serde_json::from_str(&fs::read_to_string(&expected_path).unwrap()).unwrap();
+        let differences = differences(&expected, &actual);
+        if !differences.is_empty() {
+            failures.push(format!(
+                "{}:\n{}",
+                fixture.display(),
+                differences.join("\n")
+            ));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "chunk assignments differ, rerun with UPDATE_FIXTURES=1 if expected_chunks.json \
+         should change\n{}",
+        failures.join("\n")
+    );
+}
+
+/// `graph` in the newline-delimited format, modules first, then imports and entries.
+fn to_records(graph: &serde_json::Value) -> String {
+    let record = |kind: &str, fields: &serde_json::Value| {
+        let mut record = match fields {
+            serde_json::Value::Object(fields) => fields.clone(),
+            name => serde_json::Map::from_iter([("name".to_owned(), name.clone())]),
+        };
+        record.insert("kind".to_owned(), kind.into());
+        serde_json::Value::Object(record).to_string() + "\n"
+    };
+    let list = |key: &str| graph[key].as_array().cloned().unwrap_or_default();
+    let mut records = String::new();
+    for module in list("modules") {
+        records += &record("module", &module);
+    }
+    for edge in list("edges") {
+        records += &record("edge", &edge);
+    }
+    records += "\n";
+    for entry in list("entries") {
+        records += &record("entry", &entry);
+    }
+    records
+}
+
+#[test]
+fn records_load_like_the_json_description() {
+    for fixture in fixture_dirs() {
+        let json = fs::read_to_string(fixture.join("modules.json")).unwrap();
+        let fixture = fixture.display();
+        let records = to_records(&serde_json::from_str(&json).unwrap());
+        let cache = AnalysisCache::default();
+        let (g, entries) = loader::parse(&json, &cache).unwrap();
+        let (streamed, streamed_entries) =
+            loader::parse_records(records.as_bytes(), &cache).unwrap();
+        assert_eq!(streamed_entries, entries, "{}", fixture);
+        assert_eq!(
+            streamed.node_weights().collect::<Vec<_>>(),
+            g.node_weights().collect::<Vec<_>>(),
+            "{}",
+            fixture
+        );
+        let imports = |g: &split_chunks_algorithm::ModuleGraph| {
+            g.raw_edges()
+                .iter()
+                .map(|edge| format!("{:?} {:?} {:?}", edge.source(), edge.target(), edge.weight))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(imports(&streamed), imports(&g), "{}", fixture);
+        // Both loads intern their names in the same cache, so they share the strings.
+        assert!(streamed
+            .node_weights()
+            .zip(g.node_weights())
+            .all(|(a, b)| a.name.ptr_eq(&b.name)));
+    }
+
+    let error = loader::parse_records(
+        "{ \"kind\": \"module\", \"name\": \"a.js\", \"size\": 1 }\n\
+         { \"kind\": \"edge\", \"from\": \"a.js\", \"to\": \"b.js\" }\n"
+            .as_bytes(),
+        &AnalysisCache::default(),
+    )
+    .unwrap_err();
+    assert_eq!(error.to_string(), "line 2: unknown module b.js");
+}
+
+#[test]
+fn stats_documents_diff_by_chunk_file() {
+    let stats = |json: &str, options: &SplitOptions| -> Stats {
+        let cache = AnalysisCache::default();
+        let (g, entries) = loader::parse(json, &cache).unwrap();
+        let chunk_graph = split_chunks_algorithm::split_chunks(&g, &entries, options).unwrap();
+        serde_json::from_str(&output::to_json(&chunk_graph, &g, ChunkIds::Natural)).unwrap()
+    };
+    for fixture in fixture_dirs() {
+        let json = fs::read_to_string(fixture.join("modules.json")).unwrap();
+        let stats = stats(&json, &SplitOptions::default());
+        let chunk_diff = diff::diff_chunk_graphs(&stats, &stats);
+        assert!(
+            chunk_diff.is_empty(),
+            "{}: {}",
+            fixture.display(),
+            chunk_diff
+        );
+    }
+
+    // Vendor chunks take lodash out of the chunk the entries shared.
+    let json = fs::read_to_string("examples/modules.json").unwrap();
+    let old = stats(&json, &SplitOptions::default());
+    let new = stats(
+        &json,
+        &SplitOptions {
+            cache_groups: vec![split_chunks_algorithm::options::CacheGroup::vendors()],
+            ..Default::default()
+        },
+    );
+    let chunk_diff = diff::diff_chunk_graphs(&old, &new);
+    assert_eq!(chunk_diff.added_chunks, ["vendors~entry-a~entry-b.js"]);
+    assert!(chunk_diff.removed_chunks.is_empty());
+    let shared = chunk_diff
+        .changed_chunks
+        .iter()
+        .find(|change| change.chunk == "shared~entry-a~entry-b.js")
+        .unwrap();
+    assert_eq!(shared.removed_modules, ["node_modules/lodash/lodash.js"]);
+    assert_eq!(shared.size_delta(), -5000);
+    assert!(shared.hash_changed);
+}
+
+#[test]
+fn module_graphs_with_modules_sharing_a_name_diff_by_name() {
+    let cache = AnalysisCache::default();
+    let json = fs::read_to_string("tests/fixtures/weak/modules.json").unwrap();
+    let (old, _) = loader::parse(&json, &cache).unwrap();
+    let mut new = old.clone();
+    let hot_reload_js = new
+        .node_indices()
+        .find(|id| new[*id].name == "hot-reload.js")
+        .unwrap();
+    let other_format_js = new.add_node(JsModule {
+        name: "format.js".into(),
+        size: 800,
+        ..Default::default()
+    });
+    new.add_edge(
+        hot_reload_js,
+        other_format_js,
+        Dependency {
+            is_async: true,
+            ..Default::default()
+        },
+    );
+
+    let graph_diff = diff::diff_module_graphs(&old, &new);
+    assert!(graph_diff.added_modules.is_empty());
+    assert_eq!(
+        graph_diff
+            .added_edges
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>(),
+        ["hot-reload.js --> format.js"]
+    );
+    assert!(graph_diff.removed_edges.is_empty());
+    assert!(diff::diff_module_graphs(&new, &new).is_empty());
+}
+
+#[test]
+fn parallel_imports_split_off_a_module_only_if_async_is_preferred() {
+    let cache = AnalysisCache::default();
+    let json = fs::read_to_string("tests/fixtures/parallel/modules.json").unwrap();
+    let (g, entries) = loader::parse(&json, &cache).unwrap();
+    let chunk_names = |parallel_imports| {
+        let options = SplitOptions {
+            parallel_imports,
+            ..Default::default()
+        };
+        let chunk_graph = split_chunks_algorithm::split_chunks(&g, &entries, &options).unwrap();
+        chunk_graph
+            .chunks()
+            .map(|(_, chunk)| chunk.name.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        chunk_names(ParallelImports::PreferSync),
+        ["index", "settings"]
+    );
+    assert_eq!(
+        chunk_names(ParallelImports::PreferAsync),
+        ["index", "settings", "editor"]
+    );
+}
+
+#[test]
+fn cache_groups_claim_modules_of_manual_chunks_only_with_a_higher_priority() {
+    let cache = AnalysisCache::default();
+    let json = fs::read_to_string("tests/fixtures/example/modules.json").unwrap();
+    let (g, entries) = loader::parse(&json, &cache).unwrap();
+    let chunk_of_lodash = |manual_chunk_priority| {
+        let options = SplitOptions {
+            cache_groups: vec![CacheGroup::vendors()],
+            manual_chunks: Some(
+                ManualChunks::new(|module| {
+                    module
+                        .name()
+                        .contains("lodash")
+                        .then(|| "lodash".to_owned())
+                })
+                .with_priority(manual_chunk_priority),
+            ),
+            ..Default::default()
+        };
+        let chunk_graph = split_chunks_algorithm::split_chunks(&g, &entries, &options).unwrap();
+        let (_, chunk) = chunk_graph
+            .chunks()
+            .find(|(_, chunk)| {
+                chunk
+                    .modules
+                    .iter()
+                    .any(|module_id| g[*module_id].name.contains("lodash"))
+            })
+            .unwrap();
+        chunk.name.clone()
+    };
+    // The vendors group has priority -10, and manual chunks win a tie.
+    assert_eq!(chunk_of_lodash(-11), "vendors~entry-a~entry-b");
+    assert_eq!(chunk_of_lodash(-10), "lodash");
+}
+
+#[test]
+fn explanation_tells_when_removing_small_chunks_duplicated_a_module() {
+    let cache = AnalysisCache::default();
+    let json = fs::read_to_string("tests/fixtures/example/modules.json").unwrap();
+    let (g, entries) = loader::parse(&json, &cache).unwrap();
+    let options = SplitOptions {
+        min_size: 6001,
+        ..Default::default()
+    };
+    let explanation = explain::explain(&g, &entries, &options, "shared.js")
+        .unwrap()
+        .unwrap();
+    let root_names: Vec<&str> = explanation
+        .assignment
+        .reachable_from
+        .iter()
+        .map(|root| &*g[*root].name)
+        .collect();
+    assert_eq!(root_names, ["entry-a.js", "entry-b.js"]);
+    assert!(explanation.filtered.is_empty());
+    assert_eq!(explanation.resolution.winner(), None);
+    assert_eq!(explanation.placed_in, ["shared~entry-a~entry-b"]);
+    assert!(explanation.duplicated_by_size_pass());
+    assert_eq!(explanation.chunks, ["entry-a", "entry-b"]);
+    assert_eq!(
+        explain::explain(&g, &entries, &options, "missing.js").unwrap(),
+        None
+    );
+}
+
+#[test]
+fn loader_tables_list_the_chunks_each_dynamic_import_of_a_chunk_loads() {
+    let cache = AnalysisCache::default();
+    let json = fs::read_to_string("tests/fixtures/worker/modules.json").unwrap();
+    let (g, entries) = loader::parse(&json, &cache).unwrap();
+    let options = SplitOptions::default();
+    let mut trace = DecisionTrace::new(false);
+    let mut state = analysis::analyze(&g, &entries, &options, &cache, &mut trace).unwrap();
+    split_chunks_algorithm::passes::optimize(&mut state, &options, &mut trace);
+    let table: serde_json::Value =
+        serde_json::from_str(&manifest::loader_table_json(&state, ChunkIds::Named)).unwrap();
+    assert_eq!(
+        table,
+        serde_json::json!({
+            "files": { "main": "main.js", "worker": "worker.js", "lazy": "lazy.js" },
+            "chunks": {
+                "main": { "main.js": { "worker.js": ["worker"] } },
+                "worker": { "worker.js": { "lazy.js": ["lazy"] } }
+            }
+        })
+    );
+}
+
+#[test]
+fn modules_of_different_layers_never_share_a_chunk() {
+    let cache = AnalysisCache::default();
+    let json = fs::read_to_string("tests/fixtures/layers/modules.json").unwrap();
+    let (g, entries) = loader::parse(&json, &cache).unwrap();
+    for min_size in [0, 1000, 10_000] {
+        let options = SplitOptions {
+            min_size,
+            ..Default::default()
+        };
+        let chunk_graph = split_chunks_algorithm::split_chunks(&g, &entries, &options).unwrap();
+        for (_, chunk) in chunk_graph.chunks() {
+            for module_id in &chunk.modules {
+                assert_eq!(
+                    g[*module_id].layer.as_deref(),
+                    chunk.layer.as_deref(),
+                    "{} in chunk {}",
+                    g[*module_id].name,
+                    chunk.name
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn lints_report_what_splits_into_chunks_the_graph_hardly_meant() {
+    let cache = AnalysisCache::default();
+    let json = fs::read_to_string("tests/fixtures/weak/modules.json").unwrap();
+    let (mut g, mut entries) = loader::parse(&json, &cache).unwrap();
+    assert_eq!(
+        lint::check(&g, &entries),
+        [Lint::Orphan {
+            module: "devtools.js".to_owned()
+        }
+        .into()]
+    );
+
+    let editor_js = g
+        .node_indices()
+        .find(|id| g[*id].name == "editor.js")
+        .unwrap();
+    let empty_js = g.add_node(JsModule {
+        name: "format.js".into(),
+        ..Default::default()
+    });
+    entries[0].prelude.push(empty_js);
+    entries.push(editor_js.into());
+    entries[1].prelude.push(ModuleId::new(99));
+    let diagnostics = lint::check(&g, &entries);
+    let lints: Vec<&Lint> = diagnostics.iter().map(|d| &d.lint).collect();
+    assert_eq!(
+        lints,
+        [
+            &Lint::DanglingReference {
+                referrer: "entry editor.js".to_owned(),
+                module: 99
+            },
+            &Lint::DuplicateName {
+                name: "format.js".to_owned(),
+                modules: vec![4, 5]
+            },
+            &Lint::AsyncImportOfEntry {
+                importer: "index.js".to_owned(),
+                entry: "editor.js".to_owned()
+            },
+            &Lint::Orphan {
+                module: "devtools.js".to_owned()
+            },
+            &Lint::ZeroSize {
+                module: "format.js".to_owned()
+            },
+        ]
+    );
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+    assert!(lint::has_errors(&diagnostics));
+    assert!(split_chunks_algorithm::split_chunks(&g, &entries, &SplitOptions::default()).is_err());
+}
+
+#[test]
+fn warm_caches_are_reused_only_for_the_same_graph_and_options() {
+    let cache = AnalysisCache::default();
+    let json = fs::read_to_string("tests/fixtures/example/modules.json").unwrap();
+    let (g, entries) = loader::parse(&json, &cache).unwrap();
+    let path = std::env::temp_dir().join(format!("warm-cache-{}.bin", std::process::id()));
+    let path = path.to_str().unwrap();
+    let analyze_warm = |options: &SplitOptions| {
+        let mut trace = DecisionTrace::new(false);
+        snapshot::analyze_warm(&g, &entries, options, &cache, &mut trace, path).unwrap()
+    };
+
+    let options = SplitOptions::default();
+    let (cold, warm_start) = analyze_warm(&options);
+    assert_eq!(warm_start, WarmStart::Miss);
+    let (warm, warm_start) = analyze_warm(&options);
+    assert_eq!(warm_start, WarmStart::Hit);
+    assert_eq!(assignment(&warm), assignment(&cold));
+    assert_eq!(warm.reachable_modules, cold.reachable_modules);
+
+    let options = SplitOptions {
+        min_chunks: 3,
+        ..Default::default()
+    };
+    let (_, warm_start) = analyze_warm(&options);
+    assert_eq!(warm_start, WarmStart::Miss);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn packages_get_vendor_chunks_by_their_size_when_the_cache_has_seen_the_graph() {
+    let cache = AnalysisCache::default();
+    let json = fs::read_to_string("examples/modules.json").unwrap();
+    let (g, entries) = loader::parse(&json, &cache).unwrap();
+    let options = SplitOptions {
+        cache_groups: vec![CacheGroup::granular_vendors(6000)],
+        ..Default::default()
+    };
+    let chunk_names = |g: &ModuleGraph| -> Vec<String> {
+        let mut trace = DecisionTrace::new(false);
+        let state = analysis::analyze(g, &entries, &options, &cache, &mut trace).unwrap();
+        state
+            .chunk_graph
+            .chunks()
+            .map(|(_, chunk)| chunk.name.clone())
+            .collect()
+    };
+    assert!(!chunk_names(&g).iter().any(|name| name.contains("lodash")));
+
+    let mut larger = g.clone();
+    let lodash_js = larger
+        .node_indices()
+        .find(|id| larger[*id].name == "node_modules/lodash/lodash.js")
+        .unwrap();
+    larger[lodash_js].size = 7000;
+    assert!(chunk_names(&larger)
+        .iter()
+        .any(|name| name.contains("lodash")));
+    assert_eq!(cache.package_sizes(&larger)["lodash"], 7000);
+    assert_eq!(cache.package_sizes(&g)["lodash"], 5000);
+}
+
+#[test]
+fn explorer_serves_every_chunk_with_its_modules() {
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+
+    let cache = AnalysisCache::default();
+    let json = fs::read_to_string("tests/fixtures/example/modules.json").unwrap();
+    let (g, entries) = loader::parse(&json, &cache).unwrap();
+    let chunk_graph =
+        split_chunks_algorithm::split_chunks(&g, &entries, &SplitOptions::default()).unwrap();
+    let graph = ExplorerGraph::new(&chunk_graph, &g);
+    for (index, chunk) in graph.chunks.iter().enumerate() {
+        for module in &chunk.modules {
+            assert!(graph.modules[*module].chunks.contains(&index));
+        }
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let graph_json = serde_json::to_string(&graph).unwrap();
+    let server = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        serve::respond(stream, &graph_json).unwrap();
+    });
+    let mut stream = TcpStream::connect(address).unwrap();
+    stream
+        .write_all(b"GET /graph.json HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    server.join().unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    assert!(head.starts_with("HTTP/1.1 200 OK"));
+    let served: serde_json::Value = serde_json::from_str(body).unwrap();
+    assert_eq!(
+        served["chunks"].as_array().unwrap().len(),
+        graph.chunks.len()
+    );
+}
+
+#[test]
+fn explorer_drops_a_client_that_sends_nothing() {
+    use std::net::{TcpListener, TcpStream};
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let idle = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (stream, _) = listener.accept().unwrap();
+    assert!(serve::respond(stream, "{}").is_err());
+    drop(idle);
+}
+
+#[test]
+fn duplicates_on_a_page_suggest_the_min_size_that_keeps_them_shared() {
+    let cache = AnalysisCache::default();
+    let json = fs::read_to_string("tests/fixtures/siblings/modules.json").unwrap();
+    let (g, entries) = loader::parse(&json, &cache).unwrap();
+    let options = SplitOptions {
+        min_size: 3000,
+        ..Default::default()
+    };
+    let report = duplicates::duplicates(&g, &entries, &options).unwrap();
+    assert_eq!(report.wasted_bytes, 2000);
+    let [page] = &report.pages[..] else {
+        panic!("{:?}", report.pages);
+    };
+    assert_eq!(page.entry, "index");
+    assert_eq!(page.modules[0].name, "form.js");
+    assert_eq!(page.modules[0].chunks, ["profile", "settings"]);
+    assert_eq!(
+        report.suggestion,
+        Some(Suggestion {
+            min_size: 2000,
+            min_chunks: 1
+        })
+    );
+
+    let options = SplitOptions {
+        min_size: 2000,
+        ..Default::default()
+    };
+    let report = duplicates::duplicates(&g, &entries, &options).unwrap();
+    assert_eq!(report.wasted_bytes, 0);
+    assert_eq!(report.suggestion, None);
+}
+
+#[test]
+fn bundler_dumps_load_like_the_graph_description_they_dump() {
+    let expected: Assignment = serde_json::from_str(
+        &fs::read_to_string("tests/fixtures/siblings/expected_chunks.json").unwrap(),
+    )
+    .unwrap();
+    let esbuild = r#"{
+        "inputs": {
+            "index.js": { "bytes": 1000, "imports": [
+                { "path": "settings.js", "kind": "dynamic-import" },
+                { "path": "profile.js", "kind": "dynamic-import" },
+                { "path": "react", "kind": "import-statement", "external": true }
+            ] },
+            "settings.js": { "bytes": 3000, "imports": [{ "path": "form.js", "kind": "import-statement" }] },
+            "profile.js": { "bytes": 3000, "imports": [{ "path": "form.js", "kind": "require-call" }] },
+            "form.js": { "bytes": 2000, "imports": [] }
+        },
+        "outputs": {
+            "out/index.js": { "entryPoint": "index.js" },
+            "out/settings.js": { "entryPoint": "settings.js" },
+            "out/profile.js": { "entryPoint": "profile.js" },
+            "out/form.js": {}
+        }
+    }"#;
+    let rollup = serde_json::json!([
+        { "id": "index.js", "isEntry": true, "code": "x".repeat(1000),
+          "importedIds": ["react"], "dynamicallyImportedIds": ["settings.js", "profile.js"] },
+        { "id": "settings.js", "code": "x".repeat(3000), "importedIds": ["form.js"] },
+        { "id": "profile.js", "code": "x".repeat(3000), "importedIds": ["form.js"] },
+        { "id": "form.js", "code": "x".repeat(2000) },
+    ])
+    .to_string();
+    let parcel = r#"{
+        "assets": [
+            { "id": "a1", "filePath": "index.js", "type": "js", "stats": { "size": 1000 } },
+            { "id": "a2", "filePath": "settings.js", "type": "js", "stats": { "size": 3000 } },
+            { "id": "a3", "filePath": "profile.js", "type": "js", "stats": { "size": 3000 } },
+            { "id": "a4", "filePath": "form.js", "type": "js", "stats": { "size": 2000 } }
+        ],
+        "dependencies": [
+            { "resolvedAssetId": "a1", "isEntry": true, "priority": "sync" },
+            { "sourceAssetId": "a1", "resolvedAssetId": "a2", "priority": "lazy" },
+            { "sourceAssetId": "a1", "resolvedAssetId": "a3", "priority": "lazy" },
+            { "sourceAssetId": "a1", "resolvedAssetId": null, "priority": "sync", "isOptional": true },
+            { "sourceAssetId": "a2", "resolvedAssetId": "a4", "priority": "sync" },
+            { "sourceAssetId": "a3", "resolvedAssetId": "a4", "priority": "sync" }
+        ]
+    }"#;
+
+    type Adapter =
+        fn(&str, &AnalysisCache) -> Result<(ModuleGraph, Vec<Entry>), Box<dyn std::error::Error>>;
+    let dumps: [(&str, Adapter, &str); 3] = [
+        ("esbuild", adapters::esbuild, esbuild),
+        ("rollup", adapters::rollup, &rollup),
+        ("parcel", adapters::parcel, parcel),
+    ];
+    for (bundler, adapter, dump) in dumps {
+        let cache = AnalysisCache::default();
+        let (g, entries) = adapter(dump, &cache).unwrap();
+        assert_eq!(entries.len(), 1, "{}", bundler);
+        let state = analysis::analyze(
+            &g,
+            &entries,
+            &SplitOptions::default(),
+            &cache,
+            &mut DecisionTrace::new(false),
+        )
+        .unwrap();
+        let differences = differences(&expected, &assignment(&state));
+        assert!(
+            differences.is_empty(),
+            "the {} dump splits differently:\n{}",
+            bundler,
+            differences.join("\n")
+        );
+    }
+
+    let cache = AnalysisCache::default();
+    let (g, _) = adapters::esbuild(esbuild, &cache).unwrap();
+    let react = g
+        .node_weights()
+        .find(|module| &*module.name == "react")
+        .unwrap();
+    assert!(react.is_external);
+    let unknown =
+        r#"{ "assets": [], "dependencies": [{ "sourceAssetId": "a1", "resolvedAssetId": "a2" }] }"#;
+    assert!(adapters::parcel(unknown, &cache).is_err());
+}
+
+#[test]
+fn strict_entry_signatures_reduce_entry_chunks_other_entries_import_from_to_facades() {
+    let json = r#"{
+        "modules": [
+            { "name": "a.js", "size": 100 },
+            { "name": "b.js", "size": 100 },
+            { "name": "lib.js", "size": 5000 },
+            { "name": "util.js", "size": 3000 }
+        ],
+        "edges": [
+            { "from": "a.js", "to": "lib.js" },
+            { "from": "a.js", "to": "util.js" },
+            { "from": "b.js", "to": "lib.js" }
+        ],
+        "entries": ["a.js", { "name": "b.js", "dependOn": ["a"] }]
+    }"#;
+    let cache = AnalysisCache::default();
+    let (g, entries) = loader::parse(json, &cache).unwrap();
+    let names = |chunk_graph: &ChunkGraph, chunk: &str| -> Vec<String> {
+        let chunk_id = chunk_graph.chunk_by_name(chunk).unwrap();
+        chunk_graph
+            .modules_of(chunk_id)
+            .iter()
+            .map(|module_id| g[*module_id].name.to_string())
+            .collect()
+    };
+
+    // b.js imports lib.js from the chunk of a, which it loads first.
+    let chunk_graph =
+        split_chunks_algorithm::split_chunks(&g, &entries, &SplitOptions::default()).unwrap();
+    assert_eq!(names(&chunk_graph, "a"), ["lib.js", "util.js", "a.js"]);
+    assert_eq!(names(&chunk_graph, "b"), ["b.js"]);
+
+    let options = SplitOptions {
+        preserve_entry_signatures: EntrySignatures::Strict,
+        ..SplitOptions::default()
+    };
+    let chunk_graph = split_chunks_algorithm::split_chunks(&g, &entries, &options).unwrap();
+    assert_eq!(names(&chunk_graph, "a"), ["a.js"]);
+    assert_eq!(names(&chunk_graph, "shared~a"), ["lib.js", "util.js"]);
+    assert_eq!(names(&chunk_graph, "b"), ["b.js"]);
+    let chunk_groups = ChunkGroups::new(&chunk_graph);
+    let b = chunk_groups
+        .group_of_root(chunk_graph.chunk_by_name("b").unwrap())
+        .unwrap();
+    let initial: Vec<&str> = chunk_groups
+        .initial_chunks(b)
+        .into_iter()
+        .map(|chunk_id| chunk_graph.chunk(chunk_id).name.as_str())
+        .collect();
+    assert_eq!(initial, ["shared~a", "a", "b"]);
+}
+
+#[test]
+fn async_imports_deeper_than_max_async_depth_stay_with_their_importer() {
+    let json = r#"{
+        "modules": [
+            { "name": "index.js", "size": 1000 },
+            { "name": "a.js", "size": 1000 },
+            { "name": "b.js", "size": 1000 },
+            { "name": "c.js", "size": 1000 },
+            { "name": "d.js", "size": 1000 }
+        ],
+        "edges": [
+            { "from": "index.js", "to": "a.js", "is_async": true },
+            { "from": "a.js", "to": "b.js", "is_async": true },
+            { "from": "b.js", "to": "c.js", "is_async": true },
+            { "from": "c.js", "to": "d.js" }
+        ],
+        "entries": ["index.js"]
+    }"#;
+    let cache = AnalysisCache::default();
+    let (g, entries) = loader::parse(json, &cache).unwrap();
+    let assignment_with = |max_async_depth| {
+        let options = SplitOptions {
+            max_async_depth,
+            ..SplitOptions::default()
+        };
+        let state = analysis::analyze(
+            &g,
+            &entries,
+            &options,
+            &cache,
+            &mut DecisionTrace::new(false),
+        )
+        .unwrap();
+        assignment(&state)
+    };
+    let chunks = |chunks: &[(&str, &[&str])]| -> Assignment {
+        chunks
+            .iter()
+            .map(|(chunk, modules)| {
+                let modules = modules.iter().map(|module| module.to_string()).collect();
+                (chunk.to_string(), modules)
+            })
+            .collect()
+    };
+
+    assert_eq!(assignment_with(None).len(), 4);
+    assert_eq!(
+        assignment_with(Some(1)),
+        chunks(&[
+            ("index", &["index.js"]),
+            ("a", &["a.js", "b.js", "c.js", "d.js"]),
+        ])
+    );
+    assert_eq!(
+        assignment_with(Some(0)),
+        chunks(&[("index", &["a.js", "b.js", "c.js", "d.js", "index.js"])])
+    );
+}
+
+#[test]
+fn graph_rules_ignore_alias_and_split_off_imports_before_splitting() {
+    let json = r#"{
+        "modules": [
+            { "name": "index.js", "size": 1000 },
+            { "name": "lodash.js", "size": 1000 },
+            { "name": "lodash-es.js", "size": 1000 },
+            { "name": "lodash-internal.js", "size": 1000 },
+            { "name": "devtools.js", "size": 1000 },
+            { "name": "editor.js", "size": 1000 }
+        ],
+        "edges": [
+            { "from": "index.js", "to": "lodash.js" },
+            { "from": "index.js", "to": "lodash-es.js" },
+            { "from": "lodash.js", "to": "lodash-internal.js" },
+            { "from": "index.js", "to": "devtools.js" },
+            { "from": "index.js", "to": "editor.js" }
+        ],
+        "entries": ["index.js"]
+    }"#;
+    let cache = AnalysisCache::default();
+    let (mut g, mut entries) = loader::parse(json, &cache).unwrap();
+    let rules = GraphRules {
+        aliases: vec![("lodash.js".to_owned(), "lodash-es.js".to_owned())],
+        ignored_imports: vec![ImportPattern::to("devtools")],
+        async_boundaries: vec!["editor".to_owned()],
+    };
+    let report = rules.apply(&mut g, &mut entries).unwrap();
+    assert_eq!(report.aliased.len(), 1);
+    assert_eq!(report.ignored_imports.len(), 1);
+    assert_eq!(report.async_imports.len(), 1);
+    assert_eq!(g.node_count(), 6);
+
+    let state = analysis::analyze(
+        &g,
+        &entries,
+        &SplitOptions::default(),
+        &cache,
+        &mut DecisionTrace::new(false),
+    )
+    .unwrap();
+    let modules = |modules: &[&str]| modules.iter().map(|module| module.to_string()).collect();
+    assert_eq!(
+        assignment(&state),
+        Assignment::from([
+            ("index".to_string(), modules(&["index.js", "lodash-es.js"])),
+            ("editor".to_string(), modules(&["editor.js"])),
+        ])
+    );
+
+    let unknown = GraphRules {
+        aliases: vec![("lodash.js".to_owned(), "underscore.js".to_owned())],
+        ..GraphRules::default()
+    };
+    assert!(unknown.apply(&mut g, &mut entries).is_err());
+}
+
+#[test]
+fn coarse_splits_of_the_fixtures_match_the_full_algorithm() {
+    let cache = AnalysisCache::default();
+    for fixture in fixture_dirs() {
+        let json = fs::read_to_string(fixture.join("modules.json")).unwrap();
+        let (g, entries) = loader::parse(&json, &cache).unwrap();
+        let chunks = |strategy: &dyn ChunkingStrategy| -> Assignment {
+            let chunk_graph =
+                split_chunks_with(strategy, &g, &entries, &SplitOptions::default()).unwrap();
+            chunk_graph
+                .chunks()
+                .map(|(_, chunk)| {
+                    let mut modules: Vec<String> = chunk
+                        .modules
+                        .iter()
+                        .map(|module_id| g[*module_id].name.to_string())
+                        .collect();
+                    modules.sort();
+                    (chunk.name.clone(), modules)
+                })
+                .collect()
+        };
+        let full = chunks(&ReachabilityStrategy);
+        for grouping in [Grouping::Package, Grouping::Directory] {
+            let coarse = chunks(&CoarseningStrategy { grouping });
+            let mismatches = differences(&full, &coarse);
+            assert!(
+                mismatches.is_empty(),
+                "{} grouped by {:?}:\n{}",
+                fixture.display(),
+                grouping,
+                mismatches.join("\n")
+            );
+        }
+    }
+}
+
+#[test]
+fn chunk_handles_find_the_same_chunks_after_passes_renumber_them() {
+    let mut renumbered = 0;
+    for fixture in fixture_dirs() {
+        let cache = AnalysisCache::default();
+        let (g, entries) = loader::parse(
+            &fs::read_to_string(fixture.join("modules.json")).unwrap(),
+            &cache,
+        )
+        .unwrap();
+        // Small shared chunks are removed, so the runtime chunk added after them moves up.
+        let options = SplitOptions {
+            runtime_chunk: true,
+            min_size: 1_000_000,
+            ..SplitOptions::default()
+        };
+        let mut trace = DecisionTrace::new(false);
+        let mut state = analysis::analyze(&g, &entries, &options, &cache, &mut trace).unwrap();
+        let before: Vec<(ChunkHandle, String)> = state
+            .chunk_graph
+            .chunks()
+            .map(|(chunk_id, chunk)| (state.chunk_graph.handle(chunk_id), chunk.name.clone()))
+            .collect();
+        split_chunks_algorithm::passes::optimize(&mut state, &options, &mut trace);
+
+        let chunk_graph = &state.chunk_graph;
+        for (handle, name) in &before {
+            match chunk_graph.chunk_by_handle(*handle) {
+                Some(chunk_id) => assert_eq!(&chunk_graph.graph[chunk_id].name, name),
+                None => assert!(chunk_graph.chunk_by_name(name).is_none()),
+            }
+        }
+        let runtime = chunk_graph.chunk_by_name("runtime").unwrap();
+        assert!(chunk_graph.handle(runtime).index() >= before.len());
+        if runtime.index() != chunk_graph.handle(runtime).index() {
+            renumbered += 1;
+        }
+        assert_eq!(
+            verify::verify(chunk_graph, &state.module_graph),
+            [],
+            "{}",
+            fixture.display()
+        );
+    }
+    assert!(renumbered > 0);
+}
+
+#[test]
+fn chunk_handles_survive_incremental_edits_and_are_never_given_out_twice() {
+    let cache = AnalysisCache::default();
+    let json = fs::read_to_string("tests/fixtures/example/modules.json").unwrap();
+    let (g, entries) = loader::parse(&json, &cache).unwrap();
+    let module = |name: &str| g.node_indices().find(|id| &*g[*id].name == name).unwrap();
+    let handles = |chunk_graph: &ChunkGraph| -> BTreeMap<String, ChunkHandle> {
+        chunk_graph
+            .chunks()
+            .map(|(chunk_id, chunk)| (chunk.name.clone(), chunk_graph.handle(chunk_id)))
+            .collect()
+    };
+    let mut chunker = IncrementalChunker::new(&g, &entries, SplitOptions::default()).unwrap();
+    let before = handles(&chunker.state().chunk_graph);
+    let shared = before["shared~entry-a~entry-b"];
+
+    // Without the import from entry-b, entry-a keeps shared.js to itself.
+    chunker
+        .update_edge(module("entry-b.js"), module("shared.js"), None)
+        .unwrap();
+    let without = handles(&chunker.state().chunk_graph);
+    assert!(!without.contains_key("shared~entry-a~entry-b"));
+    assert_eq!(chunker.state().chunk_graph.chunk_by_handle(shared), None);
+    chunker
+        .update_edge(
+            module("entry-b.js"),
+            module("shared.js"),
+            Some(Dependency::default()),
+        )
+        .unwrap();
+    let after = handles(&chunker.state().chunk_graph);
+    for (name, handle) in &after {
+        if name == "shared~entry-a~entry-b" {
+            assert!(!before.values().any(|before| before == handle));
+        } else {
+            assert_eq!(before[name], *handle, "{}", name);
+        }
+    }
+
+    // A chunk added to the graph directly gets a handle of its own once the handles are
+    // updated.
+    let mut chunk_graph = chunker.state().chunk_graph.clone();
+    let first = chunk_graph.chunks().next().unwrap().0;
+    let copy = chunk_graph.graph.add_node(chunk_graph.chunk(first).clone());
+    assert_eq!(chunk_graph.handle(copy), chunk_graph.handle(first));
+    chunk_graph.update_handles();
+    assert_ne!(chunk_graph.handle(copy), chunk_graph.handle(first));
+    assert_eq!(
+        chunk_graph.chunk_by_handle(chunk_graph.handle(first)),
+        Some(first)
+    );
+    assert_eq!(
+        chunk_graph.chunk_by_handle(chunk_graph.handle(copy)),
+        Some(copy)
+    );
+}
+
+#[test]
+fn pareto_fronts_keep_one_point_per_cost_with_the_smallest_min_size() {
+    let point = |min_size, initial_bytes, request_count| SweepPoint {
+        min_size,
+        initial_bytes,
+        request_count,
+        duplication_bytes: 0,
+    };
+    let front = pareto::pareto_front(&[
+        point(10, 18500, 6),
+        point(0, 18500, 6),
+        point(20, 18500, 6),
+        point(30, 19000, 5),
+        point(40, 19000, 7),
+    ]);
+    assert_eq!(front, [point(0, 18500, 6), point(30, 19000, 5)]);
+
+    let json = fs::read_to_string("tests/fixtures/example/modules.json").unwrap();
+    let cache = AnalysisCache::default();
+    let (g, entries) = loader::parse(&json, &cache).unwrap();
+    let options = SplitOptions::default();
+    let mut trace = DecisionTrace::new(false);
+    let mut state = analysis::analyze(&g, &entries, &options, &cache, &mut trace).unwrap();
+    split_chunks_algorithm::passes::prune_available_modules(&mut state, &mut trace);
+    split_chunks_algorithm::passes::weigh_chunk_edges(&mut state.chunk_graph, &state.module_graph);
+    let front = pareto::pareto_front(&pareto::sweep_min_size(&state, &options));
+    let mut costs: Vec<_> = front
+        .iter()
+        .map(|point| {
+            (
+                point.initial_bytes,
+                point.request_count,
+                point.duplication_bytes,
+            )
+        })
+        .collect();
+    costs.sort_unstable();
+    costs.dedup();
+    assert_eq!(costs.len(), front.len());
+    assert_eq!(front[0].min_size, 0);
+}
+
+#[test]
+fn min_size_sweeps_match_full_runs_at_the_scaled_thresholds() {
+    let json = fs::read_to_string("tests/fixtures/example/modules.json").unwrap();
+    let cache = AnalysisCache::default();
+    let (g, entries) = loader::parse(&json, &cache).unwrap();
+    let options = SplitOptions {
+        min_size: 4000,
+        coupling_bias: 1.0,
+        ..SplitOptions::default()
+    };
+    let run = |options: &SplitOptions| {
+        let mut trace = DecisionTrace::new(false);
+        let mut state = analysis::analyze(&g, &entries, options, &cache, &mut trace).unwrap();
+        split_chunks_algorithm::passes::optimize(&mut state, options, &mut trace);
+        pareto::measure(&state, options.min_size)
+    };
+
+    let mut trace = DecisionTrace::new(false);
+    let mut state = analysis::analyze(&g, &entries, &options, &cache, &mut trace).unwrap();
+    split_chunks_algorithm::passes::prune_available_modules(&mut state, &mut trace);
+    split_chunks_algorithm::passes::weigh_chunk_edges(&mut state.chunk_graph, &state.module_graph);
+    let sweep = pareto::sweep_min_size(&state, &options);
+    assert!(sweep.len() > 2);
+    for point in &sweep {
+        let options = SplitOptions {
+            min_size: point.min_size,
+            ..options.clone()
+        };
+        assert_eq!(*point, run(&options));
+    }
+    // Just under a threshold, Step 4 keeps what it merges at the threshold.
+    let changes = sweep.windows(2).filter(|pair| {
+        let below = SplitOptions {
+            min_size: pair[1].min_size - 1,
+            ..options.clone()
+        };
+        run(&below) != pair[1]
+    });
+    assert!(changes.count() > 0);
+}
+
+#[test]
+fn entry_html_hints_only_the_imports_asking_for_it_and_escapes_file_names() {
+    let cache = AnalysisCache::default();
+    let json = r#"{
+      "modules": [
+        { "name": "index&app.js", "size": 1000 },
+        { "name": "chart.js", "size": 30000 },
+        { "name": "settings.js", "size": 30000 },
+        { "name": "help.js", "size": 30000 }
+      ],
+      "edges": [
+        { "from": "index&app.js", "to": "chart.js", "is_async": true, "hint": "preload" },
+        { "from": "index&app.js", "to": "settings.js", "is_async": true, "hint": "prefetch" },
+        { "from": "index&app.js", "to": "help.js", "is_async": true }
+      ],
+      "entries": ["index&app.js"]
+    }"#;
+    let (g, entries) = loader::parse(json, &cache).unwrap();
+    let options = SplitOptions::default();
+    let mut trace = DecisionTrace::new(false);
+    let mut state = analysis::analyze(&g, &entries, &options, &cache, &mut trace).unwrap();
+    split_chunks_algorithm::passes::optimize(&mut state, &options, &mut trace);
+
+    let hints = html::import_hints(&state);
+    let entry_group = state.chunk_graph.chunk_roots[&state.entries[0]].1;
+    assert_eq!(
+        html::entry_html(&state.chunk_graph, &hints, entry_group),
+        "<link rel=\"modulepreload\" href=\"chart.js\">\n\
+         <link rel=\"prefetch\" href=\"settings.js\">\n\
+         <script type=\"module\" src=\"index&amp;app.js\"></script>\n"
+    );
+}
+
+#[test]
+fn emit_plans_name_every_chunk_file_by_its_template() {
+    let cache = AnalysisCache::default();
+    let json = fs::read_to_string("tests/fixtures/worker/modules.json").unwrap();
+    let (g, entries) = loader::parse(&json, &cache).unwrap();
+    let options = SplitOptions::default();
+    let mut trace = DecisionTrace::new(false);
+    let mut state = analysis::analyze(&g, &entries, &options, &cache, &mut trace).unwrap();
+    split_chunks_algorithm::passes::optimize(&mut state, &options, &mut trace);
+
+    let emit_options = EmitOptions {
+        entry_file_name: "[name].js".parse().unwrap(),
+        chunk_file_name: "chunks/[id].[contenthash:4].[ext]".parse().unwrap(),
+        chunk_ids: ChunkIds::Named,
+    };
+    let plan = emit::emit_plan(&state, &emit_options, &NameAndSize).unwrap();
+    for file in &plan.files {
+        let expected = if file.kind == ChunkKind::Entry {
+            format!("{}.js", file.chunk)
+        } else {
+            format!("chunks/{}.{}.js", file.id, &file.content_hash[..4])
+        };
+        assert_eq!(file.file_name, expected);
+    }
+    assert_eq!(plan.entries.len(), 1);
+    assert_eq!(plan.entries[0].files, ["main.js"]);
+    assert!(plan.entries[0].html.contains("src=\"main.js\""));
+
+    assert_eq!(
+        "[name].[hash].js".parse::<FileNameTemplate>(),
+        Err(EmitError::UnknownPlaceholder("[hash]".to_owned()))
+    );
+    let same_file = EmitOptions {
+        chunk_file_name: "chunk.js".parse().unwrap(),
+        ..EmitOptions::default()
+    };
+    assert!(matches!(
+        emit::emit_plan(&state, &same_file, &NameAndSize),
+        Err(EmitError::SameFileName { .. })
+    ));
+}
+
+#[test]
+fn entries_imported_from_another_layer_keep_their_entry_chunk() {
+    // The search from app-server.js, named first, reaches app.js before its own does.
+    let json = r#"{
+        "modules": [
+            { "name": "app.js", "size": 1000, "layer": "client" },
+            { "name": "app-server.js", "size": 1000, "layer": "ssr" },
+            { "name": "app/view.js", "size": 2000, "layer": "client" }
+        ],
+        "edges": [
+            { "from": "app-server.js", "to": "app.js" },
+            { "from": "app.js", "to": "app/view.js" }
+        ],
+        "entries": ["app.js", "app-server.js"]
+    }"#;
+    let cache = AnalysisCache::default();
+    let (g, entries) = loader::parse(json, &cache).unwrap();
+    let chunk_graph =
+        split_chunks_algorithm::split_chunks(&g, &entries, &SplitOptions::default()).unwrap();
+    let chunks: Vec<(&str, ChunkKind, Vec<&str>)> = chunk_graph
+        .chunks()
+        .map(|(_, chunk)| {
+            let modules = chunk
+                .modules
+                .iter()
+                .map(|module_id| g[*module_id].name.as_str())
+                .collect();
+            (chunk.name.as_str(), chunk.kind, modules)
+        })
+        .collect();
+    assert_eq!(
+        chunks,
+        [
+            ("app-server", ChunkKind::Entry, vec!["app-server.js"]),
+            ("app", ChunkKind::Entry, vec!["app/view.js", "app.js"]),
+        ]
+    );
+}
+
+#[test]
+fn imports_from_another_layer_start_a_chunk_when_reached_second() {
+    // The search from client.js, named first, reaches client/hydrate.js through the
+    // client layer before ssr/page.js imports it from the ssr layer.
+    let cache = AnalysisCache::default();
+    let json = fs::read_to_string("tests/fixtures/layers/modules.json").unwrap();
+    let (g, entries) = loader::parse(&json, &cache).unwrap();
+    let mut trace = DecisionTrace::new(false);
+    let state =
+        analysis::analyze(&g, &entries, &SplitOptions::default(), &cache, &mut trace).unwrap();
+    let module = |name: &str| {
+        g.node_indices()
+            .find(|module_id| g[*module_id].name.as_str() == name)
+            .unwrap()
+    };
+    let chunk_roots = &state.chunk_graph.chunk_roots;
+    let (server_chunk, _) = chunk_roots[&module("server.js")];
+    assert_eq!(chunk_roots[&module("client/hydrate.js")].1, server_chunk);
+}
+
+#[test]
+fn async_imports_of_a_module_a_parent_loaded_split_nothing_off() {
+    // a.js loads x.js before page.js can run, so page.js finds it loaded. b.js has
+    // nothing loaded and splits it off.
+    let json = |entries: &str| {
+        format!(
+            r#"{{
+                "modules": [
+                    {{ "name": "a.js", "size": 1000 }},
+                    {{ "name": "b.js", "size": 1000 }},
+                    {{ "name": "page.js", "size": 1000 }},
+                    {{ "name": "x.js", "size": 1000 }}
+                ],
+                "edges": [
+                    {{ "from": "a.js", "to": "x.js" }},
+                    {{ "from": "a.js", "to": "page.js", "is_async": true }},
+                    {{ "from": "page.js", "to": "x.js", "is_async": true }},
+                    {{ "from": "b.js", "to": "x.js", "is_async": true }}
+                ],
+                "entries": [{entries}]
+            }}"#
+        )
+    };
+    let cache = AnalysisCache::default();
+    let is_chunk_root = |entries: &str| {
+        let (g, entries) = loader::parse(&json(entries), &cache).unwrap();
+        let mut trace = DecisionTrace::new(false);
+        let state =
+            analysis::analyze(&g, &entries, &SplitOptions::default(), &cache, &mut trace).unwrap();
+        let x = g
+            .node_indices()
+            .find(|module_id| g[*module_id].name.as_str() == "x.js")
+            .unwrap();
+        state.chunk_graph.chunk_roots.contains_key(&x)
+    };
+    assert!(!is_chunk_root(r#""a.js""#));
+    assert!(is_chunk_root(r#""a.js", "b.js""#));
+}
+
+#[test]
+fn shared_chunks_of_fewer_source_chunks_than_min_source_chunks_are_removed() {
+    let json = r#"{
+        "modules": [
+            { "name": "a.js", "size": 100 },
+            { "name": "b.js", "size": 100 },
+            { "name": "c.js", "size": 100 },
+            { "name": "ab.js", "size": 50000 },
+            { "name": "abc.js", "size": 50000 }
+        ],
+        "edges": [
+            { "from": "a.js", "to": "ab.js" },
+            { "from": "b.js", "to": "ab.js" },
+            { "from": "a.js", "to": "abc.js" },
+            { "from": "b.js", "to": "abc.js" },
+            { "from": "c.js", "to": "abc.js" }
+        ],
+        "entries": ["a.js", "b.js", "c.js"]
+    }"#;
+    let cache = AnalysisCache::default();
+    let (g, entries) = loader::parse(json, &cache).unwrap();
+    let chunks = |min_source_chunks: usize| -> Vec<(String, Vec<String>)> {
+        let options = SplitOptions {
+            min_source_chunks,
+            ..SplitOptions::default()
+        };
+        let chunk_graph = split_chunks_algorithm::split_chunks(&g, &entries, &options).unwrap();
+        chunk_graph
+            .chunks()
+            .map(|(_, chunk)| {
+                let modules = chunk
+                    .modules
+                    .iter()
+                    .map(|module_id| g[*module_id].name.to_string())
+                    .collect();
+                (chunk.name.clone(), modules)
+            })
+            .collect()
+    };
+    let chunk = |name: &str, modules: &[&str]| {
+        (
+            name.to_owned(),
+            modules.iter().map(|name| name.to_string()).collect(),
+        )
+    };
+
+    assert_eq!(
+        chunks(2),
+        [
+            chunk("a", &["a.js"]),
+            chunk("b", &["b.js"]),
+            chunk("c", &["c.js"]),
+            chunk("shared~a~b", &["ab.js"]),
+            chunk("shared~a~b~c", &["abc.js"]),
+        ]
+    );
+    // However large, the chunk two entries share is copied into both.
+    assert_eq!(
+        chunks(3),
+        [
+            chunk("a", &["ab.js", "a.js"]),
+            chunk("b", &["ab.js", "b.js"]),
+            chunk("c", &["c.js"]),
+            chunk("shared~a~b~c", &["abc.js"]),
+        ]
+    );
+}
+
+#[test]
+fn verification_finds_nothing_in_the_fixtures_and_reports_corrupted_chunks() {
+    for fixture in fixture_dirs() {
+        let cache = AnalysisCache::default();
+        let (g, entries) = loader::parse(
+            &fs::read_to_string(fixture.join("modules.json")).unwrap(),
+            &cache,
+        )
+        .unwrap();
+        let options = SplitOptions {
+            verify_passes: true,
+            ..SplitOptions::default()
+        };
+        let chunk_graph = split_chunks_algorithm::split_chunks(&g, &entries, &options).unwrap();
+        assert_eq!(
+            verify::verify(&chunk_graph, &g),
+            [],
+            "{}",
+            fixture.display()
+        );
+    }
+
+    let json = fs::read_to_string("tests/fixtures/example/modules.json").unwrap();
+    let cache = AnalysisCache::default();
+    let (g, entries) = loader::parse(&json, &cache).unwrap();
+    let mut chunk_graph =
+        split_chunks_algorithm::split_chunks(&g, &entries, &SplitOptions::default()).unwrap();
+    let chunk_id = ChunkId::new(0);
+    let missing = ChunkId::new(chunk_graph.graph.node_count());
+    let chunk = &mut chunk_graph.graph[chunk_id];
+    let module_id = chunk.modules[0];
+    chunk.modules.push(module_id);
+    chunk.source_bundles.push(missing);
+    let size = chunk.size;
+    assert_eq!(
+        verify::verify(&chunk_graph, &g),
+        [
+            Violation::DanglingSourceBundle {
+                chunk: chunk_id,
+                source: missing
+            },
+            Violation::RepeatedModule {
+                chunk: chunk_id,
+                module: module_id
+            },
+            Violation::WrongSize {
+                chunk: chunk_id,
+                size,
+                expected: size + g[module_id].size
+            },
+        ]
+    );
+}
+
+#[test]
+fn shared_chunks_just_above_min_size_stay_however_their_modules_import_each_other() {
+    let mut modules = vec![
+        r#"{ "name": "a.js", "size": 100 }"#.to_owned(),
+        r#"{ "name": "b.js", "size": 100 }"#.to_owned(),
+    ];
+    let mut edges = vec![
+        r#"{ "from": "a.js", "to": "m1.js", "import_count": 3 }"#.to_owned(),
+        r#"{ "from": "b.js", "to": "m1.js", "import_count": 3 }"#.to_owned(),
+    ];
+    for i in 1..=7 {
+        modules.push(format!(r#"{{ "name": "m{}.js", "size": 3000 }}"#, i));
+        if i < 7 {
+            edges.push(format!(
+                r#"{{ "from": "m{}.js", "to": "m{}.js" }}"#,
+                i,
+                i + 1
+            ));
+        }
+    }
+    let json = format!(
+        r#"{{ "modules": [{}], "edges": [{}], "entries": ["a.js", "b.js"] }}"#,
+        modules.join(", "),
+        edges.join(", ")
+    );
+    let cache = AnalysisCache::default();
+    let (g, entries) = loader::parse(&json, &cache).unwrap();
+    let chunk_names = |coupling_bias: f64| -> Vec<String> {
+        let options = SplitOptions {
+            min_size: 20000,
+            coupling_bias,
+            ..SplitOptions::default()
+        };
+        let chunk_graph = split_chunks_algorithm::split_chunks(&g, &entries, &options).unwrap();
+        chunk_graph
+            .chunks()
+            .map(|(_, chunk)| format!("{} {}", chunk.name, chunk.size))
+            .collect()
+    };
+
+    // The 21000 bytes of the chain are above min_size, the imports inside it aside.
+    assert_eq!(chunk_names(0.0), ["a 100", "b 100", "shared~a~b 21000"]);
+    // Coupling only raises min_size if asked to: each entry imports the chain from
+    // three places, which needs three times min_size.
+    assert_eq!(chunk_names(1.0), ["a 21100", "b 21100"]);
+}
@@ -0,0 +1,697 @@
+//! Invariants of the algorithm on random module graphs.
+
+use petgraph::visit::EdgeRef;
+use proptest::prelude::*;
+use split_chunks_algorithm::analysis;
+use split_chunks_algorithm::budgets::{self, BudgetViolation, Budgets};
+use split_chunks_algorithm::cache::AnalysisCache;
+use split_chunks_algorithm::coarsen::{self, Grouping};
+use split_chunks_algorithm::dominators;
+use split_chunks_algorithm::hmr::HmrIndex;
+use split_chunks_algorithm::options::{EntrySignatures, SmallChunkPolicy};
+use split_chunks_algorithm::passes;
+use split_chunks_algorithm::pipeline::{Pipeline, Stage};
+use split_chunks_algorithm::scc::Condensation;
+use split_chunks_algorithm::trace::DecisionTrace;
+use split_chunks_algorithm::waterfall::{self, Waterfall};
+use split_chunks_algorithm::{
+    ChunkGraph, ChunkGroups, ChunkId, ChunkKind, Dependency, Entry, JsModule, ModuleGraph,
+    ModuleId, ModuleInfo, SplitOptions,
+};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// An acyclic graph of up to 40 modules whose first one to three modules are the
+/// entries. Every import goes from a module to one listed after it, and nothing imports
+/// an entry.
+fn dag() -> impl Strategy<Value = (ModuleGraph, Vec<Entry>)> {
+    (2..40usize)
+        .prop_flat_map(|module_count| {
+            (
+                prop::collection::vec(1..5000usize, module_count),
+                prop::collection::vec(
+                    (0..module_count, 0..module_count, prop::bool::weighted(0.2)),
+                    0..module_count * 3,
+                ),
+                1..=module_count.min(3),
+            )
+        })
+        .prop_map(|(sizes, imports, entry_count)| {
+            let mut g = ModuleGraph::new();
+            for (index, size) in sizes.into_iter().enumerate() {
+                g.add_node(JsModule {
+                    name: format!("m{}.js", index).as_str().into(),
+                    size,
+                    ..Default::default()
+                });
+            }
+            for (from, to, is_async) in imports {
+                if from < to && to >= entry_count {
+                    g.update_edge(
+                        ModuleId::new(from),
+                        ModuleId::new(to),
+                        Dependency {
+                            is_async,
+                            ..Default::default()
+                        },
+                    );
+                }
+            }
+            let entries = (0..entry_count)
+                .map(|index| ModuleId::new(index).into())
+                .collect();
+            (g, entries)
+        })
+}
+
+/// A module of a host with metadata of its own, which the algorithm never reads.
+#[derive(Debug, Clone)]
+struct HostModule {
+    path: String,
+    bytes: usize,
+    source_map: Option<String>,
+}
+
+impl ModuleInfo for HostModule {
+    fn name(&self) -> &str {
+        &self.path
+    }
+
+    fn size(&self) -> usize {
+        self.bytes
+    }
+
+    fn synthetic(name: &'static str, size: usize) -> Self {
+        HostModule {
+            path: name.to_owned(),
+            bytes: size,
+            source_map: None,
+        }
+    }
+}
+
+/// How many chunks each module was placed in.
+fn placements(chunk_graph: &ChunkGraph) -> BTreeMap<ModuleId, usize> {
+    let mut placements = BTreeMap::new();
+    for (_, chunk) in chunk_graph.chunks() {
+        for module_id in &chunk.modules {
+            *placements.entry(*module_id).or_insert(0) += 1;
+        }
+    }
+    placements
+}
+
+/// Whether `module_id` is reachable from any entry over any import.
+fn reachable_from_entries(g: &ModuleGraph, entries: &[Entry], module_id: ModuleId) -> bool {
+    entries
+        .iter()
+        .any(|entry| petgraph::algo::has_path_connecting(g, entry.module, module_id, None))
+}
+
+proptest! {
+    #[test]
+    fn step_3_places_every_reachable_module_exactly_once((g, entries) in dag()) {
+        let state = analysis::analyze(
+            &g,
+            &entries,
+            &SplitOptions::default(),
+            &AnalysisCache::default(),
+            &mut DecisionTrace::new(false),
+        )
+        .unwrap();
+        let placements = placements(&state.chunk_graph);
+        for module_id in g.node_indices() {
+            let expected = usize::from(reachable_from_entries(&g, &entries, module_id));
+            prop_assert_eq!(placements.get(&module_id).copied().unwrap_or(0), expected);
+        }
+    }
+
+    #[test]
+    fn step_3_loads_what_a_root_reaches_however_its_chunk_group_is_imported(
+        (g, entries) in dag(),
+    ) {
+        let state = analysis::analyze(
+            &g,
+            &entries,
+            &SplitOptions::default(),
+            &AnalysisCache::default(),
+            &mut DecisionTrace::new(false),
+        )
+        .unwrap();
+        let chunk_graph = &state.chunk_graph;
+        let modules_of_groups = chunk_graph.modules_of_groups();
+        // Every chain of imported chunk groups from an entry, by the root of the last
+        // group and what the groups before it loaded.
+        let mut chains: Vec<(ModuleId, BTreeSet<ModuleId>)> = entries
+            .iter()
+            .map(|entry| (entry.module, BTreeSet::new()))
+            .collect();
+        while let Some((root, mut loaded)) = chains.pop() {
+            loaded.extend(&modules_of_groups[&chunk_graph.chunk_roots[&root].1]);
+            for module_id in state.reachable_modules[&root].ones().map(ModuleId::new) {
+                if chunk_graph.chunk_roots.contains_key(&module_id) {
+                    chains.push((module_id, loaded.clone()));
+                } else {
+                    prop_assert!(
+                        loaded.contains(&module_id),
+                        "{:?} is not loaded with {:?}",
+                        module_id,
+                        root
+                    );
+                }
+            }
+        }
+
+        // Step 1 searches from the entries in name order, which must not change a chunk.
+        let mut renamed = g.clone();
+        for (index, entry) in entries.iter().enumerate() {
+            renamed[entry.module].name = format!("z{}.js", entries.len() - index).as_str().into();
+        }
+        let renamed_state = analysis::analyze(
+            &renamed,
+            &entries,
+            &SplitOptions::default(),
+            &AnalysisCache::default(),
+            &mut DecisionTrace::new(false),
+        )
+        .unwrap();
+        let chunks = |chunk_graph: &ChunkGraph| -> BTreeSet<(Vec<ModuleId>, String)> {
+            chunk_graph
+                .chunks()
+                .map(|(_, chunk)| {
+                    let mut modules = chunk.modules.clone();
+                    modules.sort();
+                    (modules, format!("{:?}", chunk.kind))
+                })
+                .collect()
+        };
+        prop_assert_eq!(chunks(chunk_graph), chunks(&renamed_state.chunk_graph));
+    }
+
+    #[test]
+    fn passes_keep_every_reachable_module_placed((g, entries) in dag(), min_size in 0..6000usize) {
+        let options = SplitOptions { min_size, ..Default::default() };
+        let mut trace = DecisionTrace::new(false);
+        let mut state =
+            analysis::analyze(&g, &entries, &options, &AnalysisCache::default(), &mut trace)
+                .unwrap();
+        passes::optimize(&mut state, &options, &mut trace);
+        let placements = placements(&state.chunk_graph);
+        for module_id in g.node_indices() {
+            let placed = placements.contains_key(&module_id);
+            prop_assert_eq!(placed, reachable_from_entries(&g, &entries, module_id));
+        }
+    }
+
+    #[test]
+    fn small_chunk_policies_keep_what_every_chunk_group_loads(
+        (g, entries) in dag(),
+        min_size in 0..6000usize,
+        policy in prop::sample::select(vec![
+            SmallChunkPolicy::DuplicateIntoAll,
+            SmallChunkPolicy::MergeIntoLargestParent,
+            SmallChunkPolicy::MergeIntoSmallestParent,
+            SmallChunkPolicy::Keep,
+        ]),
+    ) {
+        let options = SplitOptions {
+            min_size,
+            small_chunk_policies: vec![(usize::MAX, policy)],
+            ..Default::default()
+        };
+        let mut trace = DecisionTrace::new(false);
+        let mut state =
+            analysis::analyze(&g, &entries, &options, &AnalysisCache::default(), &mut trace)
+                .unwrap();
+        passes::prune_available_modules(&mut state, &mut trace);
+        passes::weigh_chunk_edges(&mut state.chunk_graph, &state.module_graph);
+        // Group ids change in the garbage collection, the roots stay.
+        let loaded_by_root = |chunk_graph: &ChunkGraph| {
+            let modules_of_groups = chunk_graph.modules_of_groups();
+            chunk_graph
+                .chunk_roots
+                .iter()
+                .map(|(root, (_, group_id))| (*root, modules_of_groups[group_id].clone()))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let before = loaded_by_root(&state.chunk_graph);
+        let (removed, _) = passes::merge_small_chunks(&mut state, &options, &mut trace);
+        if policy == SmallChunkPolicy::Keep {
+            prop_assert!(removed.is_empty());
+        }
+        let after = loaded_by_root(&state.chunk_graph);
+        for (root, modules) in before {
+            prop_assert!(modules.is_subset(&after[&root]), "group of {:?} lost modules", root);
+        }
+    }
+
+    #[test]
+    fn step_4_leaves_no_shared_chunk_split_from_fewer_than_min_source_chunks(
+        (g, entries) in dag(),
+        min_size in 0..6000usize,
+        min_source_chunks in 0..4usize,
+    ) {
+        let options = SplitOptions { min_size, min_source_chunks, ..Default::default() };
+        let mut trace = DecisionTrace::new(false);
+        let mut state =
+            analysis::analyze(&g, &entries, &options, &AnalysisCache::default(), &mut trace)
+                .unwrap();
+        passes::prune_available_modules(&mut state, &mut trace);
+        passes::weigh_chunk_edges(&mut state.chunk_graph, &state.module_graph);
+        passes::merge_small_chunks(&mut state, &options, &mut trace);
+        for (chunk_id, chunk) in state.chunk_graph.chunks() {
+            if !chunk.source_bundles.is_empty() {
+                prop_assert!(
+                    chunk.source_bundles.len() >= min_source_chunks,
+                    "chunk {:?} is split from {:?}",
+                    chunk_id,
+                    chunk.source_bundles
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn concat_groups_inline_modules_into_their_only_importer((g, entries) in dag()) {
+        let options = SplitOptions::default();
+        let mut trace = DecisionTrace::new(false);
+        let mut state =
+            analysis::analyze(&g, &entries, &options, &AnalysisCache::default(), &mut trace)
+                .unwrap();
+        passes::optimize(&mut state, &options, &mut trace);
+        let g = &state.module_graph;
+        for (_, chunk) in state.chunk_graph.chunks() {
+            let mut grouped = BTreeSet::new();
+            for group in &chunk.concat_groups {
+                prop_assert!(group.len() > 1);
+                for (position, module_id) in group.iter().enumerate() {
+                    prop_assert!(chunk.modules.contains(module_id));
+                    prop_assert!(grouped.insert(*module_id), "{:?} in two groups", module_id);
+                    if position == 0 {
+                        continue;
+                    }
+                    let imports: Vec<_> =
+                        g.edges_directed(*module_id, petgraph::Incoming).collect();
+                    prop_assert!(imports.iter().all(|edge| !edge.weight().is_async));
+                    let importers: Vec<ModuleId> = imports
+                        .iter()
+                        .map(|edge| edge.source())
+                        .filter(|importer| chunk.modules.contains(importer))
+                        .collect();
+                    prop_assert_eq!(importers.len(), 1);
+                    prop_assert!(group[..position].contains(&importers[0]));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn budgets_report_every_chunk_and_entry_over_them(
+        (g, entries) in dag(),
+        max_chunk_size in 0..20000usize,
+        max_initial_size in 0..40000usize,
+    ) {
+        let chunk_graph =
+            split_chunks_algorithm::split_chunks(&g, &entries, &SplitOptions::default()).unwrap();
+        let violations = budgets::check(
+            &chunk_graph,
+            &Budgets {
+                max_initial_size: Some(max_initial_size),
+                max_chunk_size: Some(max_chunk_size),
+            },
+        );
+        let mut over_initial = vec![];
+        let mut over_chunk = vec![];
+        for violation in violations {
+            match violation {
+                BudgetViolation::InitialSize { entry, size, .. } => {
+                    prop_assert!(size > max_initial_size);
+                    over_initial.push(entry);
+                }
+                BudgetViolation::ChunkSize { chunk, size, .. } => {
+                    prop_assert!(size > max_chunk_size);
+                    over_chunk.push(chunk);
+                }
+            }
+        }
+        let large_chunks: Vec<String> = chunk_graph
+            .chunks()
+            .filter(|(_, chunk)| chunk.size > max_chunk_size)
+            .map(|(_, chunk)| chunk.name.clone())
+            .collect();
+        prop_assert_eq!(over_chunk, large_chunks);
+        // An entry loads at least its own chunk.
+        for entry_group in chunk_graph.entry_chunks() {
+            let entry = &chunk_graph.graph[entry_group];
+            if entry.size > max_initial_size {
+                prop_assert!(over_initial.contains(&entry.name));
+            }
+        }
+    }
+
+    #[test]
+    fn waterfalls_extend_the_waterfall_of_their_importer((g, entries) in dag()) {
+        let chunk_graph =
+            split_chunks_algorithm::split_chunks(&g, &entries, &SplitOptions::default()).unwrap();
+        let waterfalls = waterfall::waterfalls(&chunk_graph);
+        let by_chunk: BTreeMap<&str, &Waterfall> = waterfalls
+            .iter()
+            .map(|waterfall| (waterfall.chunk.as_str(), waterfall))
+            .collect();
+        for waterfall in &waterfalls {
+            prop_assert_eq!(waterfall.depth, waterfall.chain.len());
+            prop_assert_eq!(waterfall.chain.last(), Some(&waterfall.chunk));
+            // Chunk groups right below an entry have none of their own.
+            if let [.., importer, _] = waterfall.chain.as_slice() {
+                if let Some(importer) = by_chunk.get(importer.as_str()) {
+                    prop_assert_eq!(&importer.chain[..], &waterfall.chain[..waterfall.depth - 1]);
+                    prop_assert!(importer.requests < waterfall.requests);
+                    prop_assert!(importer.bytes < waterfall.bytes);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn entry_chunks_contain_their_entry_module((g, entries) in dag()) {
+        let chunk_graph =
+            split_chunks_algorithm::split_chunks(&g, &entries, &SplitOptions::default()).unwrap();
+        for entry in &entries {
+            let (chunk_id, _) = chunk_graph.chunk_roots[&entry.module];
+            prop_assert_eq!(chunk_graph.chunk(chunk_id).kind, ChunkKind::Entry);
+            prop_assert!(chunk_graph.modules_of(chunk_id).contains(&entry.module));
+        }
+    }
+
+    #[test]
+    fn entry_modules_run_last_in_their_entry_chunk(
+        (g, entries) in dag(),
+        min_size in 0..6000usize,
+        merge_small_chunks: bool,
+        strict: bool,
+    ) {
+        let options = SplitOptions {
+            min_size,
+            small_chunk_policies: if merge_small_chunks {
+                vec![(usize::MAX, SmallChunkPolicy::MergeIntoLargestParent)]
+            } else {
+                vec![]
+            },
+            preserve_entry_signatures: if strict {
+                EntrySignatures::Strict
+            } else {
+                EntrySignatures::AllowExtension
+            },
+            ..Default::default()
+        };
+        let chunk_graph = split_chunks_algorithm::split_chunks(&g, &entries, &options).unwrap();
+        for entry in &entries {
+            let (chunk_id, _) = chunk_graph.chunk_roots[&entry.module];
+            prop_assert_eq!(chunk_graph.modules_of(chunk_id).last(), Some(&entry.module));
+        }
+        if strict {
+            // No entry chunk exports more than its entry module.
+            for chunk_id in chunk_graph.entry_chunks() {
+                for module_id in chunk_graph.modules_of(chunk_id) {
+                    if chunk_graph.chunk_roots.contains_key(module_id) {
+                        continue;
+                    }
+                    for edge in g.edges_directed(*module_id, petgraph::Incoming) {
+                        for importer_chunk in chunk_graph.chunks_of(edge.source()) {
+                            prop_assert!(
+                                importer_chunk == chunk_id
+                                    || chunk_graph.modules_of(importer_chunk).contains(module_id),
+                                "{:?} imports {:?} from entry chunk {:?}",
+                                edge.source(),
+                                module_id,
+                                chunk_id
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn async_chunk_groups_nest_at_most_max_async_depth_deep(
+        (g, entries) in dag(),
+        max_async_depth in 0..4usize,
+    ) {
+        let options = SplitOptions { max_async_depth: Some(max_async_depth), ..Default::default() };
+        let chunk_graph = split_chunks_algorithm::split_chunks(&g, &entries, &options).unwrap();
+        let chunk_groups = ChunkGroups::new(&chunk_graph);
+        let mut depths: BTreeMap<_, usize> =
+            chunk_groups.entry_groups().map(|(group_id, _)| (group_id, 0)).collect();
+        let mut queue: Vec<_> = depths.keys().copied().collect();
+        while !queue.is_empty() {
+            let mut next = vec![];
+            for group_id in queue {
+                for child_id in chunk_groups.children(group_id) {
+                    if !depths.contains_key(&child_id) {
+                        depths.insert(child_id, depths[&group_id] + 1);
+                        next.push(child_id);
+                    }
+                }
+            }
+            queue = next;
+        }
+        for (group_id, group) in chunk_groups.groups() {
+            prop_assert!(depths[&group_id] <= max_async_depth, "{:?} is nested too deep", group.root);
+        }
+        let placements = placements(&chunk_graph);
+        for module_id in g.node_indices() {
+            prop_assert_eq!(
+                placements.contains_key(&module_id),
+                reachable_from_entries(&g, &entries, module_id)
+            );
+        }
+    }
+
+    #[test]
+    fn coarse_splits_place_the_modules_the_full_algorithm_places(
+        (g, entries) in dag(),
+        directories in 1..5usize,
+        min_size in 0..6000usize,
+    ) {
+        let g = g.map(
+            |module_id, module| JsModule {
+                name: format!("d{}/{}", module_id.index() % directories, module.name).as_str().into(),
+                ..module.clone()
+            },
+            |_, dependency| dependency.clone(),
+        );
+        let options = SplitOptions { min_size, ..Default::default() };
+        let full = split_chunks_algorithm::split_chunks(&g, &entries, &options).unwrap();
+        let coarse = coarsen::split(&g, &entries, &options, Grouping::Directory).unwrap();
+        let placed = |chunk_graph: &ChunkGraph| -> BTreeSet<ModuleId> {
+            placements(chunk_graph).into_keys().collect()
+        };
+        prop_assert_eq!(placed(&coarse.chunk_graph), placed(&full));
+        for (_, chunk) in coarse.chunk_graph.chunks() {
+            let size: usize = chunk.modules.iter().map(|module_id| g[*module_id].size).sum();
+            prop_assert_eq!(chunk.size, size, "{}", chunk.name);
+        }
+        if min_size == 0 {
+            let chunks = |chunk_graph: &ChunkGraph| -> BTreeSet<BTreeSet<ModuleId>> {
+                chunk_graph.chunks().map(|(_, chunk)| chunk.modules.iter().copied().collect()).collect()
+            };
+            prop_assert_eq!(chunks(&coarse.chunk_graph), chunks(&full));
+        }
+    }
+
+    #[test]
+    fn hmr_boundaries_invalidate_every_chunk_holding_the_module(
+        (g, entries) in dag(),
+        min_size in 0..6000usize,
+    ) {
+        let options = SplitOptions { min_size, ..Default::default() };
+        let mut trace = DecisionTrace::new(false);
+        let mut state =
+            analysis::analyze(&g, &entries, &options, &AnalysisCache::default(), &mut trace)
+                .unwrap();
+        passes::optimize(&mut state, &options, &mut trace);
+        let index = HmrIndex::new(&state);
+        for module_id in g.node_indices() {
+            let boundary = index.boundary(module_id);
+            prop_assert_eq!(&boundary.chunks, &state.chunk_graph.chunks_of(module_id));
+            prop_assert_eq!(
+                !boundary.roots.is_empty(),
+                reachable_from_entries(&g, &entries, module_id)
+            );
+            for root in &boundary.roots {
+                prop_assert!(state.chunk_graph.chunk_roots.contains_key(root));
+                prop_assert!(petgraph::algo::has_path_connecting(&g, *root, module_id, None));
+            }
+        }
+    }
+
+    #[test]
+    fn no_chunk_is_empty((g, entries) in dag(), min_size in 0..6000usize) {
+        let options = SplitOptions { min_size, ..Default::default() };
+        let chunk_graph = split_chunks_algorithm::split_chunks(&g, &entries, &options).unwrap();
+        for (chunk_id, chunk) in chunk_graph.chunks() {
+            prop_assert!(!chunk.modules.is_empty(), "chunk {:?} is empty", chunk_id);
+        }
+    }
+
+    #[test]
+    fn chunk_groups_load_their_root_chunk_last((g, entries) in dag(), min_size in 0..6000usize) {
+        let options = SplitOptions { min_size, ..Default::default() };
+        let chunk_graph = split_chunks_algorithm::split_chunks(&g, &entries, &options).unwrap();
+        let chunk_groups = ChunkGroups::new(&chunk_graph);
+        let roots: Vec<ChunkId> = chunk_groups.groups().map(|(_, group)| group.root).collect();
+        prop_assert_eq!(roots, chunk_graph.groups().into_iter().collect::<Vec<_>>());
+        for (group_id, group) in chunk_groups.groups() {
+            prop_assert_eq!(group.chunks.last(), Some(&group.root));
+            prop_assert_eq!(chunk_groups.group_of_root(group.root), Some(group_id));
+            for child_id in chunk_groups.children(group_id) {
+                prop_assert!(chunk_groups.parents(child_id).contains(&group_id));
+                let child_root = chunk_groups.group(child_id).root;
+                prop_assert!(chunk_graph.graph.contains_edge(group.root, child_root));
+            }
+        }
+    }
+
+    #[test]
+    fn modules_run_after_what_they_import_from_their_chunk((g, entries) in dag(), min_size in 0..6000usize) {
+        let options = SplitOptions { min_size, ..Default::default() };
+        let chunk_graph = split_chunks_algorithm::split_chunks(&g, &entries, &options).unwrap();
+        for (_, chunk) in chunk_graph.chunks() {
+            let position = |module_id: ModuleId| chunk.modules.iter().position(|m| *m == module_id);
+            for edge in g.edge_references().filter(|edge| !edge.weight().is_async) {
+                if let (Some(importer), Some(importee)) =
+                    (position(edge.source()), position(edge.target()))
+                {
+                    prop_assert!(importee < importer, "{:?} runs before its import", edge.source());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn host_modules_split_like_js_modules((g, entries) in dag(), runtime_chunk: bool) {
+        let host = g.map(
+            |_, module| HostModule {
+                path: module.name.to_string(),
+                bytes: module.size,
+                source_map: Some(format!("{}.map", module.name)),
+            },
+            |_, dependency| dependency.clone(),
+        );
+        let options = SplitOptions { runtime_chunk, ..Default::default() };
+        let chunks = |chunk_graph: ChunkGraph| {
+            chunk_graph
+                .chunks()
+                .map(|(_, chunk)| (chunk.name.clone(), chunk.kind, chunk.modules.clone()))
+                .collect::<Vec<_>>()
+        };
+        let host_chunk_graph =
+            split_chunks_algorithm::split_chunks(&host, &entries, &options).unwrap();
+        // The chunks refer to the host's own modules, metadata included. The runtime
+        // module is only in the graph the passes work on.
+        for (_, chunk) in host_chunk_graph.chunks() {
+            for module_id in chunk.modules.iter().filter(|module_id| module_id.index() < host.node_count()) {
+                let module = &host[*module_id];
+                prop_assert_eq!(
+                    module.source_map.clone(),
+                    Some(format!("{}.map", module.path))
+                );
+            }
+        }
+        prop_assert_eq!(
+            chunks(host_chunk_graph),
+            chunks(split_chunks_algorithm::split_chunks(&g, &entries, &options).unwrap())
+        );
+    }
+
+    #[test]
+    fn edges_only_leave_chunk_group_roots((g, entries) in dag()) {
+        let chunk_graph =
+            split_chunks_algorithm::split_chunks(&g, &entries, &SplitOptions::default()).unwrap();
+        let groups = chunk_graph.groups();
+        for edge_id in chunk_graph.graph.edge_indices() {
+            let (from, to) = chunk_graph.graph.edge_endpoints(edge_id).unwrap();
+            prop_assert!(groups.contains(&from), "edge from {:?}, which loads nothing", from);
+            prop_assert_ne!(from, to);
+        }
+    }
+
+    #[test]
+    fn dominators_find_what_the_search_from_every_root_finds(
+        (mut g, entries) in dag(),
+        back_imports in prop::collection::vec((0..40usize, 0..40usize), 0..10),
+    ) {
+        // Imports back to earlier modules close import cycles.
+        for (from, to) in back_imports {
+            if from < g.node_count() && entries.len() <= to && to < from {
+                g.update_edge(ModuleId::new(from), ModuleId::new(to), Dependency::default());
+            }
+        }
+        let chunk_roots = analysis::create_root_chunks(
+            &g,
+            &split_chunks_algorithm::entries::modules(&entries),
+            None,
+            &mut DecisionTrace::new(false),
+        )
+        .unwrap()
+        .chunk_roots;
+        let condensation = Condensation::new(&g);
+        prop_assert_eq!(
+            dominators::reachable_from_roots(&condensation, &chunk_roots),
+            analysis::reachable_from_roots(&condensation, &chunk_roots, &AnalysisCache::default(), 0)
+        );
+    }
+
+    #[test]
+    fn pipelines_split_like_split_chunks_until_a_pass_changes_it(
+        (g, entries) in dag(),
+        min_size in 0..6000usize,
+    ) {
+        let options = SplitOptions { min_size, ..Default::default() };
+        let chunks = |chunk_graph: &ChunkGraph| {
+            chunk_graph
+                .chunks()
+                .map(|(_, chunk)| (chunk.name.clone(), chunk.kind, chunk.modules.clone()))
+                .collect::<Vec<_>>()
+        };
+        let expected = split_chunks_algorithm::split_chunks(&g, &entries, &options).unwrap();
+        let chunk_graph =
+            split_chunks_algorithm::split_chunks_with(&Pipeline::new(), &g, &entries, &options)
+                .unwrap();
+        prop_assert_eq!(chunks(&chunk_graph), chunks(&expected));
+
+        // A pass after Step 3 sees every module placed, and one after the last stage
+        // has the final say on the names.
+        let stages = std::cell::RefCell::new(vec![]);
+        let pipeline = Pipeline::new()
+            .after_create_roots(|roots, _, _| {
+                stages.borrow_mut().push(roots.root_chunks.chunk_roots.len())
+            })
+            .after(Stage::AssignModules, |state, _, _| {
+                stages.borrow_mut().push(placements(&state.chunk_graph).len())
+            })
+            .after(Stage::NameChunks, |state, _, _| {
+                for chunk in state.chunk_graph.graph.node_weights_mut() {
+                    chunk.name = format!("app-{}", chunk.name);
+                }
+            });
+        let state = pipeline
+            .run(&g, &entries, &options, &AnalysisCache::default(), &mut DecisionTrace::new(false))
+            .unwrap();
+        let expected_names: Vec<String> = expected
+            .chunks()
+            .map(|(_, chunk)| format!("app-{}", chunk.name))
+            .collect();
+        let names: Vec<String> =
+            state.chunk_graph.chunks().map(|(_, chunk)| chunk.name.clone()).collect();
+        prop_assert_eq!(names, expected_names);
+        let reachable = g
+            .node_indices()
+            .filter(|module_id| reachable_from_entries(&g, &entries, *module_id))
+            .count();
+        prop_assert_eq!(
+            stages.borrow().clone(),
+            vec![expected.chunk_roots.len(), reachable]
+        );
+    }
+}